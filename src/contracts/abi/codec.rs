@@ -0,0 +1,593 @@
+use super::AbiParam;
+use std::fmt;
+use web3::types::{Address, U256};
+
+/// Errors that can occur while encoding or decoding ABI values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodecError {
+    InvalidType(String),
+    TypeMismatch(String),
+    WrongArgumentCount { expected: usize, found: usize },
+    InvalidData(String),
+}
+
+/// A Solidity type as understood by the ABI encoder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AbiType {
+    Address,
+    Bool,
+    Int(usize),
+    Uint(usize),
+    FixedBytes(usize),
+    Bytes,
+    String,
+    Array(Box<AbiType>),
+    FixedArray(Box<AbiType>, usize),
+    Tuple(Vec<AbiType>),
+}
+
+/// A value that can be ABI-encoded, tagged with its Solidity type family.
+///
+/// Signed integers are stored as 256-bit two's complement, so `Int(U256::MAX)` is `-1`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AbiValue {
+    Address(Address),
+    Bool(bool),
+    Int(U256),
+    Uint(U256),
+    FixedBytes(Vec<u8>),
+    Bytes(Vec<u8>),
+    String(String),
+    Array(Vec<AbiValue>),
+    FixedArray(Vec<AbiValue>),
+    Tuple(Vec<AbiValue>),
+}
+
+impl AbiType {
+    /// Parses a canonical or shorthand type string such as `uint`, `bytes32[]` or `(address,uint256)[2]`.
+    ///
+    /// The bare `tuple` keyword cannot be resolved from a string alone; use `from_param` for ABI JSON.
+    pub fn parse(type_str: &str) -> Result<AbiType, CodecError> {
+        let type_str = type_str.trim();
+
+        if let Some(inner) = type_str.strip_suffix(']') {
+            let open = inner
+                .rfind('[')
+                .ok_or_else(|| CodecError::InvalidType(type_str.to_string()))?;
+            let element = AbiType::parse(&inner[..open])?;
+            return wrap_array(element, &inner[open + 1..], type_str);
+        }
+
+        if let Some(members) = type_str.strip_prefix('(').and_then(|s| s.strip_suffix(')')) {
+            let members = split_top_level(members)
+                .into_iter()
+                .map(AbiType::parse)
+                .collect::<Result<Vec<_>, _>>()?;
+            return Ok(AbiType::Tuple(members));
+        }
+
+        parse_elementary(type_str)
+    }
+
+    /// Resolves the type of an ABI JSON parameter, expanding `tuple` types from their `components`.
+    pub fn from_param(param: &AbiParam) -> Result<AbiType, CodecError> {
+        match param.kind.strip_prefix("tuple") {
+            Some(suffix) => {
+                let members = AbiType::from_params(&param.components)?;
+                let mut resolved = AbiType::Tuple(members);
+                let mut rest = suffix;
+                while let Some(dimension) = rest.strip_prefix('[') {
+                    let close = dimension
+                        .find(']')
+                        .ok_or_else(|| CodecError::InvalidType(param.kind.clone()))?;
+                    resolved = wrap_array(resolved, &dimension[..close], &param.kind)?;
+                    rest = &dimension[close + 1..];
+                }
+                if rest.is_empty() {
+                    Ok(resolved)
+                } else {
+                    Err(CodecError::InvalidType(param.kind.clone()))
+                }
+            }
+            None => AbiType::parse(&param.kind),
+        }
+    }
+
+    /// Resolves the types of a parameter list, e.g. the inputs of a function.
+    pub fn from_params(params: &[AbiParam]) -> Result<Vec<AbiType>, CodecError> {
+        params.iter().map(AbiType::from_param).collect()
+    }
+
+    /// Returns whether values of this type are encoded in the tail section.
+    pub fn is_dynamic(&self) -> bool {
+        match self {
+            AbiType::Bytes | AbiType::String | AbiType::Array(_) => true,
+            AbiType::FixedArray(element, _) => element.is_dynamic(),
+            AbiType::Tuple(members) => members.iter().any(AbiType::is_dynamic),
+            _ => false,
+        }
+    }
+
+    /// Returns the number of bytes this type occupies in the head section.
+    fn head_size(&self) -> usize {
+        match self {
+            _ if self.is_dynamic() => 32,
+            AbiType::FixedArray(element, len) => element.head_size() * len,
+            AbiType::Tuple(members) => members.iter().map(AbiType::head_size).sum(),
+            _ => 32,
+        }
+    }
+}
+
+impl fmt::Display for AbiType {
+    /// Formats the type in the canonical form used for signatures, e.g. `(uint256,address)[]`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AbiType::Address => write!(f, "address"),
+            AbiType::Bool => write!(f, "bool"),
+            AbiType::Int(size) => write!(f, "int{}", size),
+            AbiType::Uint(size) => write!(f, "uint{}", size),
+            AbiType::FixedBytes(size) => write!(f, "bytes{}", size),
+            AbiType::Bytes => write!(f, "bytes"),
+            AbiType::String => write!(f, "string"),
+            AbiType::Array(element) => write!(f, "{}[]", element),
+            AbiType::FixedArray(element, len) => write!(f, "{}[{}]", element, len),
+            AbiType::Tuple(members) => {
+                let members: Vec<String> = members.iter().map(ToString::to_string).collect();
+                write!(f, "({})", members.join(","))
+            }
+        }
+    }
+}
+
+fn wrap_array(element: AbiType, dimension: &str, type_str: &str) -> Result<AbiType, CodecError> {
+    if dimension.is_empty() {
+        return Ok(AbiType::Array(Box::new(element)));
+    }
+    match dimension.parse::<usize>() {
+        Ok(len) if len > 0 => Ok(AbiType::FixedArray(Box::new(element), len)),
+        _ => Err(CodecError::InvalidType(type_str.to_string())),
+    }
+}
+
+/// Splits a tuple's member list on commas that are not nested inside parentheses.
+pub(crate) fn split_top_level(members: &str) -> Vec<&str> {
+    if members.trim().is_empty() {
+        return Vec::new();
+    }
+
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in members.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(members[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(members[start..].trim());
+    parts
+}
+
+fn parse_elementary(type_str: &str) -> Result<AbiType, CodecError> {
+    let invalid = || CodecError::InvalidType(type_str.to_string());
+
+    let sized = |prefix: &str, default: usize| -> Option<Result<usize, CodecError>> {
+        let size = type_str.strip_prefix(prefix)?;
+        if size.is_empty() {
+            return Some(Ok(default));
+        }
+        Some(size.parse::<usize>().map_err(|_| invalid()))
+    };
+
+    match type_str {
+        "address" => return Ok(AbiType::Address),
+        "bool" => return Ok(AbiType::Bool),
+        "bytes" => return Ok(AbiType::Bytes),
+        "string" => return Ok(AbiType::String),
+        _ => {}
+    }
+
+    if let Some(size) = sized("uint", 256) {
+        let size = size?;
+        if size == 0 || size > 256 || size % 8 != 0 {
+            return Err(invalid());
+        }
+        return Ok(AbiType::Uint(size));
+    }
+    if let Some(size) = sized("int", 256) {
+        let size = size?;
+        if size == 0 || size > 256 || size % 8 != 0 {
+            return Err(invalid());
+        }
+        return Ok(AbiType::Int(size));
+    }
+    if let Some(size) = type_str.strip_prefix("bytes") {
+        return match size.parse::<usize>() {
+            Ok(size) if (1..=32).contains(&size) => Ok(AbiType::FixedBytes(size)),
+            _ => Err(invalid()),
+        };
+    }
+
+    Err(invalid())
+}
+
+impl AbiValue {
+    /// Builds an `Int` value from a signed integer, sign-extending it to 256 bits.
+    pub fn int(value: i128) -> AbiValue {
+        let magnitude = U256::from(value.unsigned_abs());
+        if value < 0 {
+            AbiValue::Int((!magnitude).overflowing_add(U256::one()).0)
+        } else {
+            AbiValue::Int(magnitude)
+        }
+    }
+
+    /// Returns whether this value is encoded in the tail section.
+    pub fn is_dynamic(&self) -> bool {
+        match self {
+            AbiValue::Bytes(_) | AbiValue::String(_) | AbiValue::Array(_) => true,
+            AbiValue::FixedArray(values) | AbiValue::Tuple(values) => {
+                values.iter().any(AbiValue::is_dynamic)
+            }
+            _ => false,
+        }
+    }
+
+    /// Checks that this value can be encoded as the given type, including integer ranges.
+    pub fn type_check(&self, ty: &AbiType) -> Result<(), CodecError> {
+        let mismatch = || CodecError::TypeMismatch(format!("expected {}, found {:?}", ty, self));
+
+        match (ty, self) {
+            (AbiType::Address, AbiValue::Address(_))
+            | (AbiType::Bool, AbiValue::Bool(_))
+            | (AbiType::Bytes, AbiValue::Bytes(_))
+            | (AbiType::String, AbiValue::String(_)) => Ok(()),
+            (AbiType::Uint(size), AbiValue::Uint(value)) if value.bits() <= *size => Ok(()),
+            (AbiType::Int(size), AbiValue::Int(value)) if fits_signed(*value, *size) => Ok(()),
+            (AbiType::FixedBytes(size), AbiValue::FixedBytes(bytes)) if bytes.len() == *size => Ok(()),
+            (AbiType::Array(element), AbiValue::Array(values)) => {
+                values.iter().try_for_each(|value| value.type_check(element))
+            }
+            (AbiType::FixedArray(element, len), AbiValue::FixedArray(values)) if values.len() == *len => {
+                values.iter().try_for_each(|value| value.type_check(element))
+            }
+            (AbiType::Tuple(members), AbiValue::Tuple(values)) if values.len() == members.len() => members
+                .iter()
+                .zip(values)
+                .try_for_each(|(member, value)| value.type_check(member)),
+            _ => Err(mismatch()),
+        }
+    }
+}
+
+impl From<Address> for AbiValue {
+    fn from(address: Address) -> Self {
+        AbiValue::Address(address)
+    }
+}
+
+impl From<bool> for AbiValue {
+    fn from(value: bool) -> Self {
+        AbiValue::Bool(value)
+    }
+}
+
+impl From<U256> for AbiValue {
+    fn from(value: U256) -> Self {
+        AbiValue::Uint(value)
+    }
+}
+
+impl From<&str> for AbiValue {
+    fn from(value: &str) -> Self {
+        AbiValue::String(value.to_string())
+    }
+}
+
+impl From<String> for AbiValue {
+    fn from(value: String) -> Self {
+        AbiValue::String(value)
+    }
+}
+
+/// Returns whether a two's complement value fits into a signed integer of `size` bits.
+fn fits_signed(value: U256, size: usize) -> bool {
+    if value.bit(255) {
+        (!value).bits() < size
+    } else {
+        value.bits() < size
+    }
+}
+
+/// ABI-encodes a list of values as a tuple, e.g. function arguments or return data.
+///
+/// # Arguments
+/// * `values` - The values to encode, in parameter order.
+///
+/// # Returns
+/// Vec<u8> - The encoded head and tail sections.
+pub fn encode(values: &[AbiValue]) -> Vec<u8> {
+    encode_sequence(values)
+}
+
+/// ABI-encodes a list of values after checking them against the expected parameter types.
+///
+/// # Arguments
+/// * `types` - The declared parameter types.
+/// * `values` - The values to encode, in parameter order.
+///
+/// # Returns
+/// Result<Vec<u8>, CodecError> - The encoded data, or an error if a value does not match its type.
+pub fn encode_params(types: &[AbiType], values: &[AbiValue]) -> Result<Vec<u8>, CodecError> {
+    if types.len() != values.len() {
+        return Err(CodecError::WrongArgumentCount { expected: types.len(), found: values.len() });
+    }
+    for (ty, value) in types.iter().zip(values) {
+        value.type_check(ty)?;
+    }
+    Ok(encode_sequence(values))
+}
+
+fn encode_sequence(values: &[AbiValue]) -> Vec<u8> {
+    let encoded: Vec<Vec<u8>> = values.iter().map(encode_value).collect();
+    let head_len: usize = values
+        .iter()
+        .zip(&encoded)
+        .map(|(value, data)| if value.is_dynamic() { 32 } else { data.len() })
+        .sum();
+
+    let mut head = Vec::with_capacity(head_len);
+    let mut tail = Vec::new();
+    for (value, data) in values.iter().zip(encoded) {
+        if value.is_dynamic() {
+            head.extend_from_slice(&word(U256::from(head_len + tail.len())));
+            tail.extend_from_slice(&data);
+        } else {
+            head.extend_from_slice(&data);
+        }
+    }
+
+    head.extend_from_slice(&tail);
+    head
+}
+
+fn encode_value(value: &AbiValue) -> Vec<u8> {
+    match value {
+        AbiValue::Address(address) => {
+            let mut out = vec![0u8; 12];
+            out.extend_from_slice(address.as_bytes());
+            out
+        }
+        AbiValue::Bool(value) => word(U256::from(*value as u8)).to_vec(),
+        AbiValue::Int(value) | AbiValue::Uint(value) => word(*value).to_vec(),
+        AbiValue::FixedBytes(bytes) => pad_right(bytes),
+        AbiValue::Bytes(bytes) => {
+            let mut out = word(U256::from(bytes.len())).to_vec();
+            out.extend_from_slice(&pad_right(bytes));
+            out
+        }
+        AbiValue::String(string) => encode_value(&AbiValue::Bytes(string.as_bytes().to_vec())),
+        AbiValue::Array(values) => {
+            let mut out = word(U256::from(values.len())).to_vec();
+            out.extend_from_slice(&encode_sequence(values));
+            out
+        }
+        AbiValue::FixedArray(values) | AbiValue::Tuple(values) => encode_sequence(values),
+    }
+}
+
+fn word(value: U256) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    value.to_big_endian(&mut out);
+    out
+}
+
+fn pad_right(bytes: &[u8]) -> Vec<u8> {
+    let mut out = bytes.to_vec();
+    let padded_len = bytes.len().div_ceil(32) * 32;
+    out.resize(padded_len, 0);
+    out
+}
+
+/// Decodes ABI-encoded data, e.g. function return data, into values of the given types.
+///
+/// # Arguments
+/// * `types` - The expected types, in parameter order.
+/// * `data` - The encoded data.
+///
+/// # Returns
+/// Result<Vec<AbiValue>, CodecError> - The decoded values, or an error if the data is malformed.
+pub fn decode(types: &[AbiType], data: &[u8]) -> Result<Vec<AbiValue>, CodecError> {
+    decode_sequence(types, data)
+}
+
+fn decode_sequence(types: &[AbiType], data: &[u8]) -> Result<Vec<AbiValue>, CodecError> {
+    let mut values = Vec::with_capacity(types.len());
+    let mut position = 0;
+
+    for ty in types {
+        let value = if ty.is_dynamic() {
+            let offset = read_usize(data, position)?;
+            decode_value(ty, slice_from(data, offset)?)?
+        } else {
+            decode_value(ty, slice_from(data, position)?)?
+        };
+        values.push(value);
+        position += ty.head_size();
+    }
+
+    Ok(values)
+}
+
+fn decode_value(ty: &AbiType, data: &[u8]) -> Result<AbiValue, CodecError> {
+    match ty {
+        AbiType::Address => {
+            let word = read_word(data, 0)?;
+            if word[..12].iter().any(|b| *b != 0) {
+                return Err(CodecError::InvalidData("dirty address padding".to_string()));
+            }
+            Ok(AbiValue::Address(Address::from_slice(&word[12..])))
+        }
+        AbiType::Bool => match U256::from_big_endian(read_word(data, 0)?) {
+            value if value.is_zero() => Ok(AbiValue::Bool(false)),
+            value if value == U256::one() => Ok(AbiValue::Bool(true)),
+            _ => Err(CodecError::InvalidData("invalid boolean".to_string())),
+        },
+        AbiType::Uint(size) => {
+            let value = U256::from_big_endian(read_word(data, 0)?);
+            if value.bits() > *size {
+                return Err(CodecError::InvalidData(format!("value out of range for uint{}", size)));
+            }
+            Ok(AbiValue::Uint(value))
+        }
+        AbiType::Int(size) => {
+            let value = U256::from_big_endian(read_word(data, 0)?);
+            if !fits_signed(value, *size) {
+                return Err(CodecError::InvalidData(format!("value out of range for int{}", size)));
+            }
+            Ok(AbiValue::Int(value))
+        }
+        AbiType::FixedBytes(size) => Ok(AbiValue::FixedBytes(read_word(data, 0)?[..*size].to_vec())),
+        AbiType::Bytes => Ok(AbiValue::Bytes(read_bytes(data)?)),
+        AbiType::String => Ok(AbiValue::String(String::from_utf8_lossy(&read_bytes(data)?).into_owned())),
+        AbiType::Array(element) => {
+            let len = read_usize(data, 0)?;
+            let tail = slice_from(data, 32)?;
+            // Every element occupies at least one word, which bounds allocations for hostile lengths.
+            if len > tail.len() / 32 {
+                return Err(CodecError::InvalidData("array length exceeds data".to_string()));
+            }
+            let types = vec![(**element).clone(); len];
+            Ok(AbiValue::Array(decode_sequence(&types, tail)?))
+        }
+        AbiType::FixedArray(element, len) => {
+            let types = vec![(**element).clone(); *len];
+            Ok(AbiValue::FixedArray(decode_sequence(&types, data)?))
+        }
+        AbiType::Tuple(members) => Ok(AbiValue::Tuple(decode_sequence(members, data)?)),
+    }
+}
+
+fn slice_from(data: &[u8], offset: usize) -> Result<&[u8], CodecError> {
+    data.get(offset..)
+        .ok_or_else(|| CodecError::InvalidData(format!("offset {} out of bounds", offset)))
+}
+
+fn read_word(data: &[u8], offset: usize) -> Result<&[u8], CodecError> {
+    offset
+        .checked_add(32)
+        .and_then(|end| data.get(offset..end))
+        .ok_or_else(|| CodecError::InvalidData("unexpected end of data".to_string()))
+}
+
+fn read_usize(data: &[u8], offset: usize) -> Result<usize, CodecError> {
+    let value = U256::from_big_endian(read_word(data, offset)?);
+    if value > U256::from(u32::MAX) {
+        return Err(CodecError::InvalidData("offset or length too large".to_string()));
+    }
+    Ok(value.as_usize())
+}
+
+fn read_bytes(data: &[u8]) -> Result<Vec<u8>, CodecError> {
+    let len = read_usize(data, 0)?;
+    data.get(32..32 + len)
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| CodecError::InvalidData("bytes length exceeds data".to_string()))
+}
+
+// Unit test example
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(data: &[u8]) -> String {
+        data.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn test_parse_types() {
+        assert_eq!(AbiType::parse("uint").unwrap(), AbiType::Uint(256));
+        assert_eq!(
+            AbiType::parse("(address,bytes32[2])[]").unwrap().to_string(),
+            "(address,bytes32[2])[]"
+        );
+        assert!(matches!(AbiType::parse("uint7"), Err(CodecError::InvalidType(_))));
+        assert!(matches!(AbiType::parse("bytes33"), Err(CodecError::InvalidType(_))));
+    }
+
+    #[test]
+    fn test_encode_static_values() {
+        // From the Solidity ABI specification: baz(uint32 69, bool true) without the selector.
+        let encoded = encode(&[AbiValue::Uint(U256::from(69)), AbiValue::Bool(true)]);
+        assert_eq!(
+            hex(&encoded),
+            "0000000000000000000000000000000000000000000000000000000000000045\
+             0000000000000000000000000000000000000000000000000000000000000001"
+        );
+    }
+
+    #[test]
+    fn test_encode_dynamic_values() {
+        // From the Solidity ABI specification: f(uint256,uint32[],bytes10,bytes)
+        // with (0x123, [0x456, 0x789], "1234567890", "Hello, world!").
+        let values = vec![
+            AbiValue::Uint(U256::from(0x123)),
+            AbiValue::Array(vec![AbiValue::Uint(U256::from(0x456)), AbiValue::Uint(U256::from(0x789))]),
+            AbiValue::FixedBytes(b"1234567890".to_vec()),
+            AbiValue::Bytes(b"Hello, world!".to_vec()),
+        ];
+        let types: Vec<AbiType> = ["uint256", "uint32[]", "bytes10", "bytes"]
+            .iter()
+            .map(|t| AbiType::parse(t).unwrap())
+            .collect();
+
+        let encoded = encode_params(&types, &values).unwrap();
+        assert_eq!(
+            hex(&encoded),
+            "0000000000000000000000000000000000000000000000000000000000000123\
+             0000000000000000000000000000000000000000000000000000000000000080\
+             3132333435363738393000000000000000000000000000000000000000000000\
+             00000000000000000000000000000000000000000000000000000000000000e0\
+             0000000000000000000000000000000000000000000000000000000000000002\
+             0000000000000000000000000000000000000000000000000000000000000456\
+             0000000000000000000000000000000000000000000000000000000000000789\
+             000000000000000000000000000000000000000000000000000000000000000d\
+             48656c6c6f2c20776f726c642100000000000000000000000000000000000000"
+        );
+        assert_eq!(decode(&types, &encoded).unwrap(), values);
+    }
+
+    #[test]
+    fn test_round_trip_nested_tuples() {
+        let types = vec![
+            AbiType::parse("(string,int8,address)[]").unwrap(),
+            AbiType::parse("uint8[2][]").unwrap(),
+        ];
+        let values = vec![
+            AbiValue::Array(vec![
+                AbiValue::Tuple(vec![AbiValue::from("alice"), AbiValue::int(-5), AbiValue::Address(Address::repeat_byte(0x11))]),
+                AbiValue::Tuple(vec![AbiValue::from("bob"), AbiValue::int(127), AbiValue::Address(Address::zero())]),
+            ]),
+            AbiValue::Array(vec![AbiValue::FixedArray(vec![AbiValue::Uint(U256::from(1)), AbiValue::Uint(U256::from(2))])]),
+        ];
+
+        let encoded = encode_params(&types, &values).unwrap();
+        assert_eq!(decode(&types, &encoded).unwrap(), values);
+    }
+
+    #[test]
+    fn test_type_and_data_errors() {
+        let uint8 = [AbiType::Uint(8)];
+        assert!(matches!(encode_params(&uint8, &[AbiValue::Uint(U256::from(256))]), Err(CodecError::TypeMismatch(_))));
+        assert!(matches!(encode_params(&uint8, &[]), Err(CodecError::WrongArgumentCount { expected: 1, found: 0 })));
+        assert!(matches!(encode_params(&[AbiType::Int(8)], &[AbiValue::int(-129)]), Err(CodecError::TypeMismatch(_))));
+        assert!(matches!(decode(&[AbiType::Bool], &[0u8; 31]), Err(CodecError::InvalidData(_))));
+        assert!(matches!(decode(&[AbiType::Bool], &word(U256::from(2))), Err(CodecError::InvalidData(_))));
+    }
+}
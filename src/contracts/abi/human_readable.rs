@@ -0,0 +1,258 @@
+use super::codec::split_top_level;
+use super::{Abi, AbiConstructor, AbiEntry, AbiError, AbiEvent, AbiFunction, AbiParam, AbiType, StateMutability};
+
+/// Parses a list of ethers-style human-readable fragments into an `Abi`.
+///
+/// Supported fragments look like the declarations of a Solidity interface, for example
+/// `function transfer(address to, uint256 amount) returns (bool)`,
+/// `event Transfer(address indexed from, address indexed to, uint256 value)`,
+/// `error Unauthorized(address caller)`, `constructor(string name) payable`,
+/// `fallback() external payable` and `receive() external payable`.
+/// Fragments without a keyword are treated as functions, and tuples may be written either as
+/// `tuple(uint256 a, address b)` or `(uint256 a, address b)`.
+///
+/// # Arguments
+/// * `fragments` - The fragments to parse, one declaration each.
+///
+/// # Returns
+/// `Result<Abi, String>` - A result containing either the parsed ABI or an error message naming the bad fragment.
+pub fn parse_human_readable_abi(fragments: &[&str]) -> Result<Abi, String> {
+    let entries = fragments
+        .iter()
+        .filter(|fragment| !fragment.trim().is_empty())
+        .map(|fragment| {
+            parse_fragment(fragment).map_err(|e| format!("Failed to parse ABI fragment '{}': {}", fragment.trim(), e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Abi { entries })
+}
+
+/// Parses a single human-readable fragment into an ABI entry.
+pub fn parse_fragment(fragment: &str) -> Result<AbiEntry, String> {
+    let fragment = fragment.trim().trim_end_matches(';').trim();
+    let (keyword, rest) = match fragment.split_once(char::is_whitespace) {
+        Some((keyword, rest)) if is_keyword(keyword) => (keyword, rest.trim()),
+        _ => match fragment.find('(') {
+            // `constructor(...)`, `fallback()` and `receive()` are usually written without a space
+            Some(open) if is_keyword(&fragment[..open]) => (&fragment[..open], &fragment[open..]),
+            _ => ("function", fragment),
+        },
+    };
+
+    let open = rest.find('(').ok_or("missing parameter list")?;
+    let name = rest[..open].trim().to_string();
+    let close = matching_paren(rest, open)?;
+    let params = &rest[open + 1..close];
+    let modifiers: Vec<&str> = rest[close + 1..].split_whitespace().collect();
+
+    if name.is_empty() && matches!(keyword, "function" | "event" | "error") {
+        return Err(format!("{} is missing a name", keyword));
+    }
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$') {
+        return Err(format!("invalid name '{}'", name));
+    }
+
+    match keyword {
+        "function" => {
+            let (modifiers, outputs) = split_returns(rest, close, &modifiers)?;
+            Ok(AbiEntry::Function(AbiFunction {
+                name,
+                inputs: parse_params(params, false)?,
+                outputs: parse_params(outputs, false)?,
+                state_mutability: mutability(&modifiers)?,
+            }))
+        }
+        "event" => Ok(AbiEntry::Event(AbiEvent {
+            name,
+            inputs: parse_params(params, true)?,
+            anonymous: modifiers.contains(&"anonymous"),
+        })),
+        "error" => Ok(AbiEntry::Error(AbiError {
+            name,
+            inputs: parse_params(params, false)?,
+        })),
+        "constructor" => Ok(AbiEntry::Constructor(AbiConstructor {
+            inputs: parse_params(params, false)?,
+            state_mutability: mutability(&modifiers)?,
+        })),
+        "fallback" => Ok(AbiEntry::Fallback {
+            state_mutability: mutability(&modifiers)?,
+        }),
+        "receive" => Ok(AbiEntry::Receive),
+        _ => unreachable!("keyword is validated by is_keyword"),
+    }
+}
+
+fn is_keyword(word: &str) -> bool {
+    matches!(word, "function" | "event" | "error" | "constructor" | "fallback" | "receive")
+}
+
+/// Returns the index of the parenthesis closing the one opened at `open`.
+fn matching_paren(s: &str, open: usize) -> Result<usize, String> {
+    let mut depth = 0usize;
+    for (i, c) in s[open..].char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(open + i);
+                }
+            }
+            _ => {}
+        }
+    }
+    Err("unbalanced parentheses".to_string())
+}
+
+/// Splits function modifiers from an optional `returns (...)` clause.
+fn split_returns<'a>(rest: &'a str, close: usize, modifiers: &[&'a str]) -> Result<(Vec<&'a str>, &'a str), String> {
+    let tail = &rest[close + 1..];
+    let returns = match tail.find("returns") {
+        Some(returns) => returns,
+        None => return Ok((modifiers.to_vec(), "")),
+    };
+
+    let open = tail[returns..].find('(').map(|i| returns + i).ok_or("missing return list")?;
+    let end = matching_paren(tail, open)?;
+    if !tail[end + 1..].trim().is_empty() {
+        return Err(format!("unexpected '{}' after return list", tail[end + 1..].trim()));
+    }
+
+    Ok((tail[..returns].split_whitespace().collect(), &tail[open + 1..end]))
+}
+
+fn mutability(modifiers: &[&str]) -> Result<StateMutability, String> {
+    let mut state_mutability = StateMutability::NonPayable;
+    for modifier in modifiers {
+        match *modifier {
+            "view" | "constant" => state_mutability = StateMutability::View,
+            "pure" => state_mutability = StateMutability::Pure,
+            "payable" => state_mutability = StateMutability::Payable,
+            "nonpayable" | "external" | "public" | "virtual" | "override" => {}
+            other => return Err(format!("unknown modifier '{}'", other)),
+        }
+    }
+    Ok(state_mutability)
+}
+
+fn parse_params(params: &str, allow_indexed: bool) -> Result<Vec<AbiParam>, String> {
+    split_top_level(params)
+        .into_iter()
+        .map(|param| parse_param(param, allow_indexed))
+        .collect()
+}
+
+fn parse_param(param: &str, allow_indexed: bool) -> Result<AbiParam, String> {
+    let param = param.trim();
+    if param.is_empty() {
+        return Err("empty parameter".to_string());
+    }
+
+    // Split off the type: either a (possibly `tuple`-prefixed) parenthesised list or the first word.
+    let tuple_start = param.strip_prefix("tuple").unwrap_or(param);
+    let (kind, components, rest) = if tuple_start.starts_with('(') {
+        let offset = param.len() - tuple_start.len();
+        let close = matching_paren(param, offset)?;
+        let components = parse_params(&param[offset + 1..close], false)?;
+        let rest = &param[close + 1..];
+        let suffix_len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let suffix = &rest[..suffix_len];
+        if !suffix.chars().all(|c| c == '[' || c == ']' || c.is_ascii_digit()) {
+            return Err(format!("invalid tuple array suffix '{}'", suffix));
+        }
+        (format!("tuple{}", suffix), components, &rest[suffix_len..])
+    } else {
+        let type_len = param.find(char::is_whitespace).unwrap_or(param.len());
+        let kind = AbiType::parse(&param[..type_len]).map_err(|_| format!("invalid type '{}'", &param[..type_len]))?;
+        (kind.to_string(), Vec::new(), &param[type_len..])
+    };
+
+    let mut indexed = false;
+    let mut name = String::new();
+    for word in rest.split_whitespace() {
+        match word {
+            "indexed" if allow_indexed => indexed = true,
+            "calldata" | "memory" | "storage" => {}
+            "payable" if kind == "address" => {}
+            _ if name.is_empty() => name = word.to_string(),
+            _ => return Err(format!("unexpected '{}' in parameter '{}'", word, param)),
+        }
+    }
+
+    Ok(AbiParam {
+        name,
+        kind,
+        internal_type: None,
+        indexed,
+        components,
+    })
+}
+
+// Unit test example
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_erc20_fragments() {
+        let abi = parse_human_readable_abi(&[
+            "function balanceOf(address owner) view returns (uint256)",
+            "function transfer(address to, uint amount) returns (bool)",
+            "event Transfer(address indexed from, address indexed to, uint256 value)",
+            "error InsufficientBalance(uint256 available, uint256 required)",
+            "constructor(string memory name, string memory symbol)",
+            "receive() external payable",
+        ])
+        .unwrap();
+
+        let balance_of = abi.function("balanceOf").unwrap();
+        assert_eq!(balance_of.state_mutability, StateMutability::View);
+        assert_eq!(balance_of.outputs[0].kind, "uint256");
+
+        let transfer = abi.function("transfer").unwrap();
+        assert_eq!(transfer.signature().unwrap(), "transfer(address,uint256)");
+        assert_eq!(transfer.selector().unwrap(), [0xa9, 0x05, 0x9c, 0xbb]);
+        assert_eq!(transfer.inputs[1].name, "amount");
+
+        let event = abi.event("Transfer").unwrap();
+        assert!(event.inputs[0].indexed && event.inputs[1].indexed && !event.inputs[2].indexed);
+        assert_eq!(event.inputs[0].name, "from");
+
+        assert_eq!(abi.errors().next().unwrap().signature().unwrap(), "InsufficientBalance(uint256,uint256)");
+        assert_eq!(abi.constructor().unwrap().inputs[1].name, "symbol");
+        assert!(abi.has_receive());
+    }
+
+    #[test]
+    fn test_parse_tuples() {
+        let abi = parse_human_readable_abi(&[
+            "function submit(tuple(address maker, uint128[2] amounts)[] calldata orders, (bool,bytes) extra) payable",
+        ])
+        .unwrap();
+
+        let submit = abi.function("submit").unwrap();
+        assert_eq!(submit.state_mutability, StateMutability::Payable);
+        assert_eq!(submit.inputs[0].kind, "tuple[]");
+        assert_eq!(submit.inputs[0].name, "orders");
+        assert_eq!(submit.inputs[0].components[1].name, "amounts");
+        assert_eq!(submit.inputs[1].kind, "tuple");
+        assert_eq!(submit.signature().unwrap(), "submit((address,uint128[2])[],(bool,bytes))");
+    }
+
+    #[test]
+    fn test_fragments_without_keyword_or_names() {
+        let abi = parse_human_readable_abi(&["approve(address,uint256)", "event Ping() anonymous;"]).unwrap();
+        assert_eq!(abi.function("approve").unwrap().selector().unwrap(), [0x09, 0x5e, 0xa7, 0xb3]);
+        assert!(abi.event("Ping").unwrap().anonymous);
+    }
+
+    #[test]
+    fn test_invalid_fragments() {
+        assert!(parse_human_readable_abi(&["function transfer(address to"]).is_err());
+        assert!(parse_human_readable_abi(&["function transfer(adress to)"]).is_err());
+        assert!(parse_human_readable_abi(&["function transfer(address to) mutable"]).is_err());
+        assert!(parse_human_readable_abi(&["function f(uint256 indexed x)"]).is_err());
+    }
+}
@@ -0,0 +1,430 @@
+//! Contract ABI model, JSON parsing and the ABI codec.
+
+pub mod codec;
+pub mod human_readable;
+pub mod revert;
+pub mod selector;
+
+pub use codec::{decode, encode, encode_params, AbiType, AbiValue, CodecError};
+pub use human_readable::parse_human_readable_abi;
+pub use revert::{decode_revert, RevertReason};
+pub use selector::ResolveError;
+
+use serde::Deserialize;
+
+/// A named parameter of a function, constructor, event or error, or a component of a tuple.
+///
+/// # Fields
+/// - `name`: The parameter name (empty for unnamed parameters).
+/// - `kind`: The Solidity type as written in the ABI, e.g. `uint256`, `address[]` or `tuple[2]`.
+/// - `internal_type`: The compiler's internal type, e.g. `contract IERC20` or `struct Pool.Key`.
+/// - `indexed`: Whether the parameter is an indexed event topic (always `false` outside events).
+/// - `components`: The members of a tuple type, empty for every other type.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct AbiParam {
+    #[serde(default)]
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(rename = "internalType", default)]
+    pub internal_type: Option<String>,
+    #[serde(default)]
+    pub indexed: bool,
+    #[serde(default)]
+    pub components: Vec<AbiParam>,
+}
+
+/// How a function interacts with the blockchain state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StateMutability {
+    Pure,
+    View,
+    NonPayable,
+    Payable,
+}
+
+/// A function entry of an ABI.
+///
+/// # Fields
+/// - `name`: The name of the function.
+/// - `inputs`: The input parameters of the function.
+/// - `outputs`: The return values of the function.
+/// - `state_mutability`: Whether the function reads, writes or accepts Ether.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AbiFunction {
+    pub name: String,
+    pub inputs: Vec<AbiParam>,
+    pub outputs: Vec<AbiParam>,
+    pub state_mutability: StateMutability,
+}
+
+impl AbiFunction {
+    /// Resolves the types of the function's input parameters.
+    pub fn input_types(&self) -> Result<Vec<AbiType>, CodecError> {
+        AbiType::from_params(&self.inputs)
+    }
+
+    /// Resolves the types of the function's return values.
+    pub fn output_types(&self) -> Result<Vec<AbiType>, CodecError> {
+        AbiType::from_params(&self.outputs)
+    }
+
+    /// ABI-encodes the arguments of a call to this function, without the selector.
+    pub fn encode_args(&self, args: &[AbiValue]) -> Result<Vec<u8>, CodecError> {
+        encode_params(&self.input_types()?, args)
+    }
+
+    /// Decodes the return data of a call to this function.
+    pub fn decode_output(&self, data: &[u8]) -> Result<Vec<AbiValue>, CodecError> {
+        decode(&self.output_types()?, data)
+    }
+}
+
+/// The constructor entry of an ABI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AbiConstructor {
+    pub inputs: Vec<AbiParam>,
+    pub state_mutability: StateMutability,
+}
+
+impl AbiConstructor {
+    /// Resolves the types of the constructor parameters.
+    pub fn input_types(&self) -> Result<Vec<AbiType>, CodecError> {
+        AbiType::from_params(&self.inputs)
+    }
+
+    /// ABI-encodes the constructor arguments that are appended to the creation bytecode.
+    pub fn encode_args(&self, args: &[AbiValue]) -> Result<Vec<u8>, CodecError> {
+        encode_params(&self.input_types()?, args)
+    }
+}
+
+/// An event entry of an ABI.
+///
+/// # Fields
+/// - `name`: The name of the event.
+/// - `inputs`: The event parameters, indexed ones are flagged with `indexed`.
+/// - `anonymous`: Whether the event is emitted without its signature as the first topic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AbiEvent {
+    pub name: String,
+    pub inputs: Vec<AbiParam>,
+    pub anonymous: bool,
+}
+
+/// A custom error entry of an ABI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AbiError {
+    pub name: String,
+    pub inputs: Vec<AbiParam>,
+}
+
+/// A single entry of an ABI, discriminated by its `type` field.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "RawAbiEntry")]
+pub enum AbiEntry {
+    Function(AbiFunction),
+    Constructor(AbiConstructor),
+    Event(AbiEvent),
+    Error(AbiError),
+    Fallback { state_mutability: StateMutability },
+    Receive,
+}
+
+/// A parsed contract ABI.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Abi {
+    pub entries: Vec<AbiEntry>,
+}
+
+impl Abi {
+    /// Returns all function entries in declaration order.
+    pub fn functions(&self) -> impl Iterator<Item = &AbiFunction> {
+        self.entries.iter().filter_map(|entry| match entry {
+            AbiEntry::Function(function) => Some(function),
+            _ => None,
+        })
+    }
+
+    /// Returns all event entries in declaration order.
+    pub fn events(&self) -> impl Iterator<Item = &AbiEvent> {
+        self.entries.iter().filter_map(|entry| match entry {
+            AbiEntry::Event(event) => Some(event),
+            _ => None,
+        })
+    }
+
+    /// Returns all custom error entries in declaration order.
+    pub fn errors(&self) -> impl Iterator<Item = &AbiError> {
+        self.entries.iter().filter_map(|entry| match entry {
+            AbiEntry::Error(error) => Some(error),
+            _ => None,
+        })
+    }
+
+    /// Returns the constructor entry, if the ABI declares one.
+    pub fn constructor(&self) -> Option<&AbiConstructor> {
+        self.entries.iter().find_map(|entry| match entry {
+            AbiEntry::Constructor(constructor) => Some(constructor),
+            _ => None,
+        })
+    }
+
+    /// Returns the first function with the given name.
+    pub fn function(&self, name: &str) -> Option<&AbiFunction> {
+        self.functions().find(|function| function.name == name)
+    }
+
+    /// Returns the first event with the given name.
+    pub fn event(&self, name: &str) -> Option<&AbiEvent> {
+        self.events().find(|event| event.name == name)
+    }
+
+    /// Returns whether the contract declares a `fallback` function.
+    pub fn has_fallback(&self) -> bool {
+        self.entries.iter().any(|entry| matches!(entry, AbiEntry::Fallback { .. }))
+    }
+
+    /// Returns whether the contract declares a `receive` function.
+    pub fn has_receive(&self) -> bool {
+        self.entries.iter().any(|entry| matches!(entry, AbiEntry::Receive))
+    }
+}
+
+/// The JSON shape shared by every ABI entry, before it is split by `type`.
+///
+/// Older compilers omit `type` for functions and describe mutability with the
+/// `constant`/`payable` flags instead of `stateMutability`, so all fields are optional here.
+#[derive(Deserialize)]
+struct RawAbiEntry {
+    #[serde(rename = "type", default)]
+    entry_type: Option<String>,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    inputs: Vec<AbiParam>,
+    #[serde(default)]
+    outputs: Vec<AbiParam>,
+    #[serde(rename = "stateMutability", default)]
+    state_mutability: Option<StateMutability>,
+    #[serde(default)]
+    payable: Option<bool>,
+    #[serde(default)]
+    constant: Option<bool>,
+    #[serde(default)]
+    anonymous: bool,
+}
+
+impl RawAbiEntry {
+    fn mutability(&self) -> StateMutability {
+        match (self.state_mutability, self.payable, self.constant) {
+            (Some(mutability), _, _) => mutability,
+            (None, Some(true), _) => StateMutability::Payable,
+            (None, _, Some(true)) => StateMutability::View,
+            _ => StateMutability::NonPayable,
+        }
+    }
+
+    fn required_name(&mut self, entry_type: &str) -> Result<String, String> {
+        self.name
+            .take()
+            .ok_or_else(|| format!("ABI {} entry is missing a name", entry_type))
+    }
+}
+
+impl TryFrom<RawAbiEntry> for AbiEntry {
+    type Error = String;
+
+    fn try_from(mut raw: RawAbiEntry) -> Result<Self, String> {
+        let state_mutability = raw.mutability();
+        let entry_type = raw.entry_type.take().unwrap_or_else(|| "function".to_string());
+
+        let entry = match entry_type.as_str() {
+            "function" => AbiEntry::Function(AbiFunction {
+                name: raw.required_name("function")?,
+                inputs: raw.inputs,
+                outputs: raw.outputs,
+                state_mutability,
+            }),
+            "constructor" => AbiEntry::Constructor(AbiConstructor {
+                inputs: raw.inputs,
+                state_mutability,
+            }),
+            "event" => AbiEntry::Event(AbiEvent {
+                name: raw.required_name("event")?,
+                inputs: raw.inputs,
+                anonymous: raw.anonymous,
+            }),
+            "error" => AbiEntry::Error(AbiError {
+                name: raw.required_name("error")?,
+                inputs: raw.inputs,
+            }),
+            "fallback" => AbiEntry::Fallback { state_mutability },
+            "receive" => AbiEntry::Receive,
+            other => return Err(format!("Unknown ABI entry type '{}'", other)),
+        };
+
+        Ok(entry)
+    }
+}
+
+/// Parses the given ABI JSON string into an `Abi`.
+///
+/// Accepts either the plain ABI array emitted by `solc --abi`, or a compiler artifact
+/// (Foundry, Hardhat, Truffle) that carries the array under an `abi` key.
+///
+/// # Arguments
+/// * `abi_json`: A string slice representing the ABI in JSON format.
+///
+/// # Returns
+/// `Result<Abi, String>` - A result containing either the parsed ABI or an error message.
+///
+/// # Errors
+/// - If the provided JSON is empty, it returns an error.
+/// - If the JSON is invalid, it returns a detailed parsing error.
+pub fn parse_abi(abi_json: &str) -> Result<Abi, String> {
+    if abi_json.trim().is_empty() {
+        return Err("ABI JSON cannot be empty.".to_string());
+    }
+
+    let mut value: serde_json::Value =
+        serde_json::from_str(abi_json).map_err(|e| format!("Failed to parse ABI: {}", e))?;
+
+    // Unwrap compiler artifacts so their output can be used as-is.
+    if let Some(abi) = value.get_mut("abi") {
+        value = abi.take();
+    }
+
+    // Attempt to parse the ABI array into its entries.
+    let entries: Vec<AbiEntry> =
+        serde_json::from_value(value).map_err(|e| format!("Failed to parse ABI: {}", e))?;
+
+    Ok(Abi { entries })
+}
+
+// Unit test example
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_abi_json() {
+        let result = parse_abi("");
+        assert!(matches!(result, Err(_)));
+    }
+
+    #[test]
+    fn test_invalid_abi_json() {
+        let result = parse_abi("invalid_json");
+        assert!(matches!(result, Err(_)));
+    }
+
+    #[test]
+    fn test_valid_abi_json() {
+        let abi_json = r#"
+        [
+            {
+                "inputs": [{ "internalType": "uint256", "name": "supply", "type": "uint256" }],
+                "stateMutability": "nonpayable",
+                "type": "constructor"
+            },
+            {
+                "inputs": [
+                    { "internalType": "address", "name": "to", "type": "address" },
+                    { "internalType": "uint256", "name": "amount", "type": "uint256" }
+                ],
+                "name": "transfer",
+                "outputs": [{ "internalType": "bool", "name": "", "type": "bool" }],
+                "stateMutability": "nonpayable",
+                "type": "function"
+            },
+            {
+                "anonymous": false,
+                "inputs": [
+                    { "indexed": true, "internalType": "address", "name": "from", "type": "address" },
+                    { "indexed": true, "internalType": "address", "name": "to", "type": "address" },
+                    { "indexed": false, "internalType": "uint256", "name": "value", "type": "uint256" }
+                ],
+                "name": "Transfer",
+                "type": "event"
+            },
+            {
+                "inputs": [{ "internalType": "uint256", "name": "needed", "type": "uint256" }],
+                "name": "InsufficientBalance",
+                "type": "error"
+            },
+            { "stateMutability": "payable", "type": "receive" }
+        ]
+        "#;
+
+        let abi = parse_abi(abi_json).unwrap();
+        assert_eq!(abi.entries.len(), 5);
+        assert_eq!(abi.constructor().unwrap().inputs[0].name, "supply");
+        assert!(abi.has_receive());
+
+        let transfer = abi.function("transfer").unwrap();
+        assert_eq!(transfer.inputs[0].kind, "address");
+        assert_eq!(transfer.inputs[1].internal_type.as_deref(), Some("uint256"));
+        assert_eq!(transfer.state_mutability, StateMutability::NonPayable);
+
+        let event = abi.event("Transfer").unwrap();
+        assert!(event.inputs[0].indexed && !event.inputs[2].indexed);
+        assert_eq!(abi.errors().next().unwrap().name, "InsufficientBalance");
+    }
+
+    #[test]
+    fn test_tuple_components_and_artifact() {
+        let artifact_json = r#"
+        {
+            "abi": [
+                {
+                    "inputs": [
+                        {
+                            "components": [
+                                { "internalType": "address", "name": "token", "type": "address" },
+                                { "internalType": "uint24[]", "name": "fees", "type": "uint24[]" }
+                            ],
+                            "internalType": "struct Router.Route[]",
+                            "name": "routes",
+                            "type": "tuple[]"
+                        }
+                    ],
+                    "name": "swap",
+                    "outputs": [],
+                    "stateMutability": "payable",
+                    "type": "function"
+                }
+            ],
+            "bytecode": { "object": "0x6080" }
+        }
+        "#;
+
+        let abi = parse_abi(artifact_json).unwrap();
+        let swap = abi.function("swap").unwrap();
+        assert_eq!(swap.state_mutability, StateMutability::Payable);
+        assert_eq!(swap.inputs[0].kind, "tuple[]");
+        assert_eq!(swap.inputs[0].components.len(), 2);
+        assert_eq!(swap.inputs[0].components[1].kind, "uint24[]");
+    }
+
+    #[test]
+    fn test_legacy_abi_json() {
+        let abi_json = r#"
+        [
+            {
+                "constant": true,
+                "inputs": [{ "name": "owner", "type": "address" }],
+                "name": "balanceOf",
+                "outputs": [{ "name": "", "type": "uint256" }],
+                "payable": false
+            },
+            { "payable": true, "type": "fallback" }
+        ]
+        "#;
+
+        let abi = parse_abi(abi_json).unwrap();
+        assert_eq!(abi.function("balanceOf").unwrap().state_mutability, StateMutability::View);
+        assert_eq!(abi.entries[1], AbiEntry::Fallback { state_mutability: StateMutability::Payable });
+    }
+}
@@ -0,0 +1,174 @@
+use super::{decode, Abi, AbiType, AbiValue};
+use std::fmt;
+use web3::types::U256;
+
+/// Selector of the built-in `Error(string)` emitted by `require` and `revert("...")`.
+pub const ERROR_STRING_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// Selector of the built-in `Panic(uint256)` emitted by failing assertions and checked arithmetic.
+pub const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// The decoded reason a call or transaction reverted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RevertReason {
+    /// `revert()` or `require(cond)` without a message.
+    Empty,
+    /// `revert("message")` or `require(cond, "message")`.
+    Error(String),
+    /// A compiler-inserted panic, with the meaning of its code.
+    Panic { code: U256, meaning: &'static str },
+    /// A custom error declared in the contract ABI, with its named arguments.
+    Custom { name: String, args: Vec<(String, AbiValue)> },
+    /// Revert data that matches neither a built-in nor an ABI-declared error.
+    Unknown(Vec<u8>),
+}
+
+/// Returns the documented meaning of a Solidity panic code.
+///
+/// # Arguments
+/// * `code` - The `uint256` argument of `Panic(uint256)`.
+pub fn panic_meaning(code: U256) -> &'static str {
+    if code > U256::from(0xff) {
+        return "unknown panic code";
+    }
+    match code.low_u32() {
+        0x00 => "generic compiler inserted panic",
+        0x01 => "assertion failed",
+        0x11 => "arithmetic overflow or underflow",
+        0x12 => "division or modulo by zero",
+        0x21 => "invalid enum value",
+        0x22 => "incorrectly encoded storage byte array",
+        0x31 => "pop on an empty array",
+        0x32 => "array index out of bounds",
+        0x41 => "out of memory",
+        0x51 => "call to a zero-initialized internal function",
+        _ => "unknown panic code",
+    }
+}
+
+/// Decodes the data returned by a reverted call.
+///
+/// Built-in `Error(string)` and `Panic(uint256)` reasons are always recognised; custom errors are
+/// looked up by selector in the given ABI.
+///
+/// # Arguments
+/// * `data` - The raw revert data returned by the node.
+/// * `abi` - The ABI of the called contract, if available.
+///
+/// # Returns
+/// RevertReason - The decoded reason, or `RevertReason::Unknown` carrying the raw data.
+pub fn decode_revert(data: &[u8], abi: Option<&Abi>) -> RevertReason {
+    if data.is_empty() {
+        return RevertReason::Empty;
+    }
+    if data.len() < 4 {
+        return RevertReason::Unknown(data.to_vec());
+    }
+
+    let selector = [data[0], data[1], data[2], data[3]];
+    let payload = &data[4..];
+
+    let decoded = match selector {
+        ERROR_STRING_SELECTOR => match decode(&[AbiType::String], payload) {
+            Ok(mut values) => match values.pop() {
+                Some(AbiValue::String(message)) => Some(RevertReason::Error(message)),
+                _ => None,
+            },
+            Err(_) => None,
+        },
+        PANIC_SELECTOR => match decode(&[AbiType::Uint(256)], payload) {
+            Ok(mut values) => match values.pop() {
+                Some(AbiValue::Uint(code)) => Some(RevertReason::Panic { code, meaning: panic_meaning(code) }),
+                _ => None,
+            },
+            Err(_) => None,
+        },
+        _ => abi.and_then(|abi| abi.error_by_selector(selector)).and_then(|error| {
+            let types = AbiType::from_params(&error.inputs).ok()?;
+            let values = decode(&types, payload).ok()?;
+            let names = error.inputs.iter().map(|input| input.name.clone());
+            Some(RevertReason::Custom {
+                name: error.name.clone(),
+                args: names.zip(values).collect(),
+            })
+        }),
+    };
+
+    decoded.unwrap_or_else(|| RevertReason::Unknown(data.to_vec()))
+}
+
+impl fmt::Display for RevertReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RevertReason::Empty => write!(f, "reverted without a reason"),
+            RevertReason::Error(message) => write!(f, "reverted: {}", message),
+            RevertReason::Panic { code, meaning } => write!(f, "panicked with code 0x{:02x}: {}", code, meaning),
+            RevertReason::Custom { name, args } => {
+                let args: Vec<String> = args
+                    .iter()
+                    .map(|(name, value)| if name.is_empty() { format!("{:?}", value) } else { format!("{}: {:?}", name, value) })
+                    .collect();
+                write!(f, "reverted with {}({})", name, args.join(", "))
+            }
+            RevertReason::Unknown(data) => {
+                let hex: String = data.iter().map(|b| format!("{:02x}", b)).collect();
+                write!(f, "reverted with unrecognised data 0x{}", hex)
+            }
+        }
+    }
+}
+
+// Unit test example
+#[cfg(test)]
+mod tests {
+    use super::super::{encode, parse_human_readable_abi};
+    use super::*;
+
+    #[test]
+    fn test_decode_error_string() {
+        let mut data = ERROR_STRING_SELECTOR.to_vec();
+        data.extend_from_slice(&encode(&[AbiValue::from("Not enough Ether provided.")]));
+        let reason = decode_revert(&data, None);
+        assert_eq!(reason, RevertReason::Error("Not enough Ether provided.".to_string()));
+        assert_eq!(reason.to_string(), "reverted: Not enough Ether provided.");
+    }
+
+    #[test]
+    fn test_decode_panic() {
+        let mut data = PANIC_SELECTOR.to_vec();
+        data.extend_from_slice(&encode(&[AbiValue::Uint(U256::from(0x11))]));
+        let reason = decode_revert(&data, None);
+        assert_eq!(reason, RevertReason::Panic { code: U256::from(0x11), meaning: "arithmetic overflow or underflow" });
+        assert_eq!(reason.to_string(), "panicked with code 0x11: arithmetic overflow or underflow");
+    }
+
+    #[test]
+    fn test_decode_custom_error() {
+        let abi = parse_human_readable_abi(&["error InsufficientBalance(uint256 available, uint256 required)"]).unwrap();
+        let error = abi.errors().next().unwrap();
+        let mut data = error.selector().unwrap().to_vec();
+        data.extend_from_slice(&encode(&[AbiValue::Uint(U256::from(5)), AbiValue::Uint(U256::from(10))]));
+
+        let reason = decode_revert(&data, Some(&abi));
+        assert_eq!(
+            reason,
+            RevertReason::Custom {
+                name: "InsufficientBalance".to_string(),
+                args: vec![
+                    ("available".to_string(), AbiValue::Uint(U256::from(5))),
+                    ("required".to_string(), AbiValue::Uint(U256::from(10))),
+                ],
+            }
+        );
+
+        // Without the ABI the selector cannot be resolved
+        assert_eq!(decode_revert(&data, None), RevertReason::Unknown(data.clone()));
+    }
+
+    #[test]
+    fn test_decode_empty_and_malformed() {
+        assert_eq!(decode_revert(&[], None), RevertReason::Empty);
+        assert_eq!(decode_revert(&[0x08, 0xc3], None), RevertReason::Unknown(vec![0x08, 0xc3]));
+        assert_eq!(decode_revert(&ERROR_STRING_SELECTOR, None), RevertReason::Unknown(ERROR_STRING_SELECTOR.to_vec()));
+    }
+}
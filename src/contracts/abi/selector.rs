@@ -0,0 +1,304 @@
+use super::{Abi, AbiError, AbiEvent, AbiFunction, AbiParam, AbiType, AbiValue, CodecError};
+use web3::signing::keccak256;
+use web3::types::H256;
+
+/// Errors that can occur while resolving a function, event or error against an ABI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolveError {
+    NotFound(String),
+    Ambiguous(Vec<String>),
+    InvalidSignature(CodecError),
+}
+
+impl From<CodecError> for ResolveError {
+    fn from(error: CodecError) -> Self {
+        ResolveError::InvalidSignature(error)
+    }
+}
+
+/// Builds a canonical signature such as `transfer(address,uint256)` from a name and parameters.
+fn canonical_signature(name: &str, params: &[AbiParam]) -> Result<String, CodecError> {
+    let types = AbiType::from_params(params)?;
+    let types: Vec<String> = types.iter().map(ToString::to_string).collect();
+    Ok(format!("{}({})", name, types.join(",")))
+}
+
+fn selector_of(signature: &str) -> [u8; 4] {
+    let hash = keccak256(signature.as_bytes());
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+/// Canonicalizes a user supplied signature, e.g. `transfer(address, uint)` to `transfer(address,uint256)`.
+///
+/// Returns `None` when the query is a bare name rather than a signature.
+fn canonicalize_query(query: &str) -> Result<Option<String>, CodecError> {
+    let open = match query.find('(') {
+        Some(open) => open,
+        None => return Ok(None),
+    };
+    let types = AbiType::parse(&query[open..])?;
+    Ok(Some(format!("{}{}", query[..open].trim(), types)))
+}
+
+fn parse_selector(query: &str) -> Option<[u8; 4]> {
+    let hex = query.strip_prefix("0x")?;
+    // Checking the digits first keeps the slices below on character boundaries
+    if hex.len() != 8 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+    let mut selector = [0u8; 4];
+    for (i, byte) in selector.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(selector)
+}
+
+impl AbiFunction {
+    /// Returns the canonical signature of the function, e.g. `transfer(address,uint256)`.
+    pub fn signature(&self) -> Result<String, CodecError> {
+        canonical_signature(&self.name, &self.inputs)
+    }
+
+    /// Returns the 4-byte selector that prefixes calldata for this function.
+    pub fn selector(&self) -> Result<[u8; 4], CodecError> {
+        Ok(selector_of(&self.signature()?))
+    }
+
+    /// Builds the calldata for a call to this function: the selector followed by the encoded arguments.
+    pub fn encode_call(&self, args: &[AbiValue]) -> Result<Vec<u8>, CodecError> {
+        let mut calldata = self.selector()?.to_vec();
+        calldata.extend_from_slice(&self.encode_args(args)?);
+        Ok(calldata)
+    }
+
+    fn accepts(&self, args: &[AbiValue]) -> bool {
+        match self.input_types() {
+            Ok(types) => {
+                types.len() == args.len()
+                    && types.iter().zip(args).all(|(ty, arg)| arg.type_check(ty).is_ok())
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+impl AbiEvent {
+    /// Returns the canonical signature of the event, e.g. `Transfer(address,address,uint256)`.
+    pub fn signature(&self) -> Result<String, CodecError> {
+        canonical_signature(&self.name, &self.inputs)
+    }
+
+    /// Returns the topic0 hash identifying this event in logs (not emitted for anonymous events).
+    pub fn topic(&self) -> Result<H256, CodecError> {
+        Ok(H256::from(keccak256(self.signature()?.as_bytes())))
+    }
+}
+
+impl AbiError {
+    /// Returns the canonical signature of the error, e.g. `InsufficientBalance(uint256,uint256)`.
+    pub fn signature(&self) -> Result<String, CodecError> {
+        canonical_signature(&self.name, &self.inputs)
+    }
+
+    /// Returns the 4-byte selector that prefixes revert data for this error.
+    pub fn selector(&self) -> Result<[u8; 4], CodecError> {
+        Ok(selector_of(&self.signature()?))
+    }
+}
+
+impl Abi {
+    /// Resolves a function by name, canonical signature or `0x`-prefixed selector.
+    ///
+    /// Overloaded names are disambiguated by the arguments that will be passed: only overloads whose
+    /// inputs accept `args` are considered. Passing a full signature always selects exactly one overload.
+    ///
+    /// # Arguments
+    /// * `query` - A name (`transfer`), signature (`transfer(address,uint256)`) or selector (`0xa9059cbb`).
+    /// * `args` - The arguments of the intended call.
+    ///
+    /// # Returns
+    /// Result<&AbiFunction, ResolveError> - The matching function, or an error if none or several match.
+    pub fn resolve_function(&self, query: &str, args: &[AbiValue]) -> Result<&AbiFunction, ResolveError> {
+        if let Some(selector) = parse_selector(query) {
+            return self
+                .function_by_selector(selector)
+                .ok_or_else(|| ResolveError::NotFound(query.to_string()));
+        }
+
+        if let Some(signature) = canonicalize_query(query)? {
+            return self
+                .functions()
+                .find(|function| function.signature().ok().as_deref() == Some(signature.as_str()))
+                .ok_or(ResolveError::NotFound(signature));
+        }
+
+        let candidates: Vec<&AbiFunction> = self.functions().filter(|function| function.name == query).collect();
+        let matching: Vec<&AbiFunction> = match candidates.len() {
+            0 => return Err(ResolveError::NotFound(query.to_string())),
+            1 => return Ok(candidates[0]),
+            _ => candidates.iter().copied().filter(|function| function.accepts(args)).collect(),
+        };
+
+        match matching.as_slice() {
+            [function] => Ok(function),
+            _ => Err(ResolveError::Ambiguous(
+                candidates.iter().filter_map(|function| function.signature().ok()).collect(),
+            )),
+        }
+    }
+
+    /// Resolves an event by name or canonical signature.
+    ///
+    /// # Arguments
+    /// * `query` - A name (`Transfer`) or signature (`Transfer(address,address,uint256)`).
+    ///
+    /// # Returns
+    /// Result<&AbiEvent, ResolveError> - The matching event, or an error if none or several match.
+    pub fn resolve_event(&self, query: &str) -> Result<&AbiEvent, ResolveError> {
+        if let Some(signature) = canonicalize_query(query)? {
+            return self
+                .events()
+                .find(|event| event.signature().ok().as_deref() == Some(signature.as_str()))
+                .ok_or(ResolveError::NotFound(signature));
+        }
+
+        let candidates: Vec<&AbiEvent> = self.events().filter(|event| event.name == query).collect();
+        match candidates.as_slice() {
+            [] => Err(ResolveError::NotFound(query.to_string())),
+            [event] => Ok(event),
+            _ => Err(ResolveError::Ambiguous(
+                candidates.iter().filter_map(|event| event.signature().ok()).collect(),
+            )),
+        }
+    }
+
+    /// Returns the function whose selector matches the first four bytes of some calldata.
+    pub fn function_by_selector(&self, selector: [u8; 4]) -> Option<&AbiFunction> {
+        self.functions().find(|function| function.selector().ok() == Some(selector))
+    }
+
+    /// Returns the non-anonymous event whose topic0 matches the given hash.
+    pub fn event_by_topic(&self, topic: H256) -> Option<&AbiEvent> {
+        self.events()
+            .find(|event| !event.anonymous && event.topic().ok() == Some(topic))
+    }
+
+    /// Returns the custom error whose selector matches the first four bytes of some revert data.
+    pub fn error_by_selector(&self, selector: [u8; 4]) -> Option<&AbiError> {
+        self.errors().find(|error| error.selector().ok() == Some(selector))
+    }
+}
+
+// Unit test example
+#[cfg(test)]
+mod tests {
+    use super::super::parse_abi;
+    use super::*;
+    use web3::types::{Address, U256};
+
+    const ERC20_ABI: &str = r#"
+    [
+        {
+            "type": "function", "name": "transfer", "stateMutability": "nonpayable",
+            "inputs": [{ "name": "to", "type": "address" }, { "name": "amount", "type": "uint256" }],
+            "outputs": [{ "name": "", "type": "bool" }]
+        },
+        {
+            "type": "function", "name": "safeTransferFrom", "stateMutability": "nonpayable",
+            "inputs": [{ "name": "from", "type": "address" }, { "name": "to", "type": "address" }, { "name": "id", "type": "uint256" }],
+            "outputs": []
+        },
+        {
+            "type": "function", "name": "safeTransferFrom", "stateMutability": "nonpayable",
+            "inputs": [{ "name": "from", "type": "address" }, { "name": "to", "type": "address" }, { "name": "id", "type": "uint256" }, { "name": "data", "type": "bytes" }],
+            "outputs": []
+        },
+        {
+            "type": "function", "name": "submit", "stateMutability": "nonpayable",
+            "inputs": [{
+                "name": "orders", "type": "tuple[]",
+                "components": [{ "name": "maker", "type": "address" }, { "name": "amounts", "type": "uint128[2]" }]
+            }],
+            "outputs": []
+        },
+        {
+            "type": "event", "name": "Transfer", "anonymous": false,
+            "inputs": [
+                { "name": "from", "type": "address", "indexed": true },
+                { "name": "to", "type": "address", "indexed": true },
+                { "name": "value", "type": "uint256", "indexed": false }
+            ]
+        },
+        {
+            "type": "error", "name": "InsufficientBalance",
+            "inputs": [{ "name": "available", "type": "uint256" }, { "name": "required", "type": "uint256" }]
+        }
+    ]
+    "#;
+
+    #[test]
+    fn test_signatures_and_selectors() {
+        let abi = parse_abi(ERC20_ABI).unwrap();
+        let transfer = abi.function("transfer").unwrap();
+        assert_eq!(transfer.signature().unwrap(), "transfer(address,uint256)");
+        assert_eq!(transfer.selector().unwrap(), [0xa9, 0x05, 0x9c, 0xbb]);
+
+        let submit = abi.function("submit").unwrap();
+        assert_eq!(submit.signature().unwrap(), "submit((address,uint128[2])[])");
+
+        let event = abi.event("Transfer").unwrap();
+        assert_eq!(
+            format!("{:x}", event.topic().unwrap()),
+            "ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"
+        );
+    }
+
+    #[test]
+    fn test_encode_call() {
+        let abi = parse_abi(ERC20_ABI).unwrap();
+        let calldata = abi
+            .function("transfer")
+            .unwrap()
+            .encode_call(&[AbiValue::Address(Address::repeat_byte(0x22)), AbiValue::Uint(U256::from(1000))])
+            .unwrap();
+        assert_eq!(calldata.len(), 4 + 64);
+        assert_eq!(&calldata[..4], &[0xa9, 0x05, 0x9c, 0xbb]);
+    }
+
+    #[test]
+    fn test_overload_resolution() {
+        let abi = parse_abi(ERC20_ABI).unwrap();
+        let from = AbiValue::Address(Address::repeat_byte(1));
+        let to = AbiValue::Address(Address::repeat_byte(2));
+        let id = AbiValue::Uint(U256::from(7));
+
+        let three = abi.resolve_function("safeTransferFrom", &[from.clone(), to.clone(), id.clone()]).unwrap();
+        assert_eq!(three.inputs.len(), 3);
+
+        let four = abi
+            .resolve_function("safeTransferFrom", &[from, to, id, AbiValue::Bytes(vec![])])
+            .unwrap();
+        assert_eq!(four.inputs.len(), 4);
+
+        let explicit = abi.resolve_function("safeTransferFrom(address, address, uint)", &[]).unwrap();
+        assert_eq!(explicit.signature().unwrap(), "safeTransferFrom(address,address,uint256)");
+
+        assert!(matches!(abi.resolve_function("safeTransferFrom", &[]), Err(ResolveError::Ambiguous(ref s)) if s.len() == 2));
+        assert!(matches!(abi.resolve_function("mint", &[]), Err(ResolveError::NotFound(_))));
+        assert_eq!(abi.resolve_function("0xa9059cbb", &[]).unwrap().name, "transfer");
+        assert_eq!(parse_selector("0xA9059CBB"), Some([0xa9, 0x05, 0x9c, 0xbb]));
+        assert_eq!(parse_selector("0x€€ab"), None);
+        assert_eq!(parse_selector("0x+f+f+f+f"), None);
+        assert!(matches!(abi.resolve_function("0x€€ab", &[]), Err(ResolveError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_error_and_event_lookup() {
+        let abi = parse_abi(ERC20_ABI).unwrap();
+        let error = abi.errors().next().unwrap();
+        assert_eq!(abi.error_by_selector(error.selector().unwrap()), Some(error));
+
+        let event = abi.resolve_event("Transfer").unwrap();
+        assert_eq!(abi.event_by_topic(event.topic().unwrap()), Some(event));
+    }
+}
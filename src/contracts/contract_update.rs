@@ -1,30 +1,55 @@
+use crate::contracts::abi::{parse_human_readable_abi, AbiValue};
+use crate::contracts::gas_report;
+use crate::contracts::provider::{Provider, ProviderError};
 use crate::framework::logging::{log_info, log_error};
-use web3::types::Address;
+use web3::types::{Address, Bytes, TransactionReceipt, TransactionRequest, U64};
+use web3::Transport;
 use std::str::FromStr;
 
 /// Errors that can occur during contract updates.
 #[derive(Debug)]
 pub enum UpdateError {
     InvalidAddress,
+    Provider(ProviderError),
     UpdateFailed,
 }
 
-/// Updates a smart contract with security checks and error handling.
-/// 
+/// Sends a transaction and waits for it to be mined successfully.
+async fn send_and_confirm<T: Transport>(
+    provider: &Provider<T>,
+    transaction: TransactionRequest,
+) -> Result<TransactionReceipt, UpdateError> {
+    let hash = provider.send_transaction(transaction).await.map_err(UpdateError::Provider)?;
+    let receipt = provider.wait_for_receipt(hash).await.map_err(UpdateError::Provider)?;
+    if receipt.status != Some(U64::one()) {
+        log_error(&format!("Update transaction {:?} reverted.", hash));
+        return Err(UpdateError::UpdateFailed);
+    }
+    Ok(receipt)
+}
+
+/// Updates an upgradeable (EIP-1967 proxy) smart contract with security checks and error handling.
+///
+/// The new bytecode is deployed as a fresh implementation contract, then the proxy is pointed
+/// at it by calling `upgradeTo(address)`.
+///
 /// # Arguments
-/// * `contract_address` - The address of the contract to update.
-/// * `new_code` - The new bytecode for the contract.
+/// * `provider` - The node to send the transactions through.
+/// * `contract_address` - The address of the proxy contract to update.
+/// * `new_code` - The deployment bytecode of the new implementation.
+/// * `sender_address` - The address authorised to upgrade the proxy.
 ///
 /// # Returns
-/// Result<(), UpdateError> - Returns Ok if the contract is updated successfully, otherwise returns an error.
-pub async fn update_contract(
+/// Result<Address, UpdateError> - The address of the new implementation, otherwise an error.
+pub async fn update_contract<T: Transport>(
+    provider: &Provider<T>,
     contract_address: &str,
     new_code: &[u8],
-) -> Result<(), UpdateError> {
-    // Input validation: ensure contract address is valid
-    if Address::from_str(contract_address).is_err() {
-        return Err(UpdateError::InvalidAddress);
-    }
+    sender_address: &str,
+) -> Result<Address, UpdateError> {
+    // Input validation: ensure contract and sender addresses are valid
+    let proxy = Address::from_str(contract_address).map_err(|_| UpdateError::InvalidAddress)?;
+    let sender = Address::from_str(sender_address).map_err(|_| UpdateError::InvalidAddress)?;
 
     // Input validation: ensure the new contract code is not empty
     if new_code.is_empty() {
@@ -33,16 +58,34 @@ pub async fn update_contract(
 
     log_info(&format!("Updating contract at address: {}", contract_address));
 
-    // Simulate contract update (real logic would go here)
-    let update_succeeded = true;  // Simulating a successful update
+    // Deploy the new implementation
+    let deployment = TransactionRequest {
+        from: sender,
+        data: Some(Bytes(new_code.to_vec())),
+        ..Default::default()
+    };
+    let receipt = send_and_confirm(provider, deployment).await?;
+    let implementation = receipt.contract_address.ok_or(UpdateError::UpdateFailed)?;
+    gas_report::record_deployment(new_code, implementation, receipt.gas_used.unwrap_or_default());
 
-    if update_succeeded {
-        log_info("Contract updated successfully."); // Using log_info instead of info!
-        Ok(())
-    } else {
-        log_error("Contract update failed."); // Using log_error instead of error!
-        Err(UpdateError::UpdateFailed)
-    }
+    // Point the proxy at the new implementation
+    let proxy_abi = parse_human_readable_abi(&["function upgradeTo(address newImplementation)"])
+        .expect("upgradeTo fragment is valid");
+    let calldata = proxy_abi
+        .function("upgradeTo")
+        .and_then(|function| function.encode_call(&[AbiValue::Address(implementation)]).ok())
+        .expect("upgradeTo arguments match its ABI");
+    let upgrade = TransactionRequest {
+        from: sender,
+        to: Some(proxy),
+        data: Some(Bytes(calldata)),
+        ..Default::default()
+    };
+    let receipt = send_and_confirm(provider, upgrade).await?;
+    gas_report::record_call(proxy, "upgradeTo(address)", receipt.gas_used.unwrap_or_default());
+
+    log_info(&format!("Contract updated successfully to implementation {:?}.", implementation)); // Using log_info instead of info!
+    Ok(implementation)
 }
 
 // Unit test example
@@ -50,21 +93,27 @@ pub async fn update_contract(
 mod tests {
     use super::*;
 
+    const SENDER: &str = "0x00000000000000000000000000000000000000aa";
+
+    fn offline_provider() -> Provider {
+        Provider::http("http://127.0.0.1:1").unwrap()
+    }
+
     #[tokio::test]
     async fn test_invalid_contract_address() {
-        let result = update_contract("invalid", &[0x60, 0x80, 0x60, 0x40]).await;
+        let result = update_contract(&offline_provider(), "invalid", &[0x60, 0x80, 0x60, 0x40], SENDER).await;
         assert!(matches!(result, Err(UpdateError::InvalidAddress)));
     }
 
     #[tokio::test]
     async fn test_empty_contract_code() {
-        let result = update_contract("0x1234567890abcdef1234567890abcdef12345678", &[]).await;
+        let result = update_contract(&offline_provider(), "0x1234567890abcdef1234567890abcdef12345678", &[], SENDER).await;
         assert!(matches!(result, Err(UpdateError::UpdateFailed)));
     }
 
     #[tokio::test]
-    async fn test_successful_update() {
-        let result = update_contract("0x1234567890abcdef1234567890abcdef12345678", &[0x60, 0x80, 0x60, 0x40]).await;
-        assert!(matches!(result, Ok(())));
+    async fn test_unreachable_node() {
+        let result = update_contract(&offline_provider(), "0x1234567890abcdef1234567890abcdef12345678", &[0x60, 0x80, 0x60, 0x40], SENDER).await;
+        assert!(matches!(result, Err(UpdateError::Provider(ProviderError::Transport(_)))));
     }
 }
@@ -1,76 +0,0 @@
-use crate::framework::logging::{log_info, log_warn, log_error};
-use web3::contract::{Contract, Options};
-use web3::types::{Address, U256};
-use std::str::FromStr;
-
-/// Errors that can occur during contract deployment.
-#[derive(Debug)]
-pub enum DeployError {
-    InvalidContractCode,
-    InvalidAddress,
-    DeploymentFailed,
-}
-
-/// Deploys a smart contract to the blockchain with input validation and enhanced error handling.
-/// 
-/// # Arguments
-/// * `contract_code` - The bytecode of the contract.
-/// * `gas_limit` - The maximum gas allowed for deployment.
-/// * `sender_address` - The address deploying the contract.
-/// 
-/// # Returns
-/// Result<(), DeployError> - Returns Ok if deployment succeeds, otherwise returns an error.
-pub async fn deploy_contract(
-    contract_code: &[u8],
-    gas_limit: U256,
-    sender_address: &str,
-) -> Result<(), DeployError> {
-    // Input validation: ensure contract code and sender address are valid
-    if contract_code.is_empty() {
-        return Err(DeployError::InvalidContractCode);
-    }
-    if Address::from_str(sender_address).is_err() {
-        return Err(DeployError::InvalidAddress);
-    }
-
-    // Log the deployment start
-    log_info(&format!("Deploying contract from address: {}", sender_address));
-
-    // Simulate contract deployment (real logic would go here)
-    let deployment_succeeded = true;
-    
-    if deployment_succeeded {
-        // Log success
-        log_info("Contract deployed successfully.");
-        Ok(())
-    } else {
-        // Log error
-        log_error("Contract deployment failed.");
-        Err(DeployError::DeploymentFailed)
-    }
-}
-
-// Unit test example
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use web3::types::U256;
-
-    #[tokio::test]
-    async fn test_invalid_contract_code() {
-        let result = deploy_contract(&[], U256::from(1), "0x123").await;
-        assert!(matches!(result, Err(DeployError::InvalidContractCode)));
-    }
-
-    #[tokio::test]
-    async fn test_invalid_address() {
-        let result = deploy_contract(&[0x60, 0x80, 0x60, 0x40], U256::from(1), "invalid").await;
-        assert!(matches!(result, Err(DeployError::InvalidAddress)));
-    }
-
-    #[tokio::test]
-    async fn test_successful_deployment() {
-        let result = deploy_contract(&[0x60, 0x80, 0x60, 0x40], U256::from(1), "0x1234567890abcdef1234567890abcdef12345678").await;
-        assert!(matches!(result, Ok(())));
-    }
-}
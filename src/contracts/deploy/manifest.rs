@@ -0,0 +1,107 @@
+use super::plan::PlanError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use web3::types::{Address, H256, U64};
+
+/// The record of the contracts a deployment plan has deployed on one chain.
+///
+/// Manifests are stored as `<chain_id>.json` in a manifest directory, so a single directory
+/// tracks every network a plan is deployed to.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub chain_id: u64,
+    /// Deployed contracts keyed by their name in the plan.
+    pub contracts: BTreeMap<String, ManifestEntry>,
+}
+
+/// A contract recorded in a manifest.
+///
+/// # Fields
+/// - `address`: The address the contract was deployed to.
+/// - `transaction_hash`: The hash of the deployment transaction.
+/// - `block_number`: The block the deployment was mined in.
+/// - `init_code_hash`: The Keccak-256 hash of the linked bytecode and constructor arguments,
+///   used to detect whether the contract must be redeployed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub address: Address,
+    pub transaction_hash: H256,
+    pub block_number: U64,
+    pub init_code_hash: H256,
+}
+
+impl Manifest {
+    /// Returns the path of the manifest of a chain inside a manifest directory.
+    pub fn path(dir: &Path, chain_id: u64) -> PathBuf {
+        dir.join(format!("{}.json", chain_id))
+    }
+
+    /// Loads the manifest of a chain, or an empty one if nothing was deployed there yet.
+    ///
+    /// # Arguments
+    /// * `dir` - The manifest directory.
+    /// * `chain_id` - The chain the manifest belongs to.
+    pub fn load(dir: &Path, chain_id: u64) -> Result<Manifest, PlanError> {
+        let path = Manifest::path(dir, chain_id);
+        if !path.exists() {
+            return Ok(Manifest { chain_id, contracts: BTreeMap::new() });
+        }
+        let contents = fs::read_to_string(&path).map_err(|e| PlanError::Io(format!("{}: {}", path.display(), e)))?;
+        serde_json::from_str(&contents).map_err(|e| PlanError::Parse(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Writes the manifest to `<dir>/<chain_id>.json`, creating the directory if needed.
+    ///
+    /// The manifest is written to a temporary file that is then renamed over the old one, so a
+    /// crash mid-write leaves the previous manifest intact.
+    pub fn save(&self, dir: &Path) -> Result<(), PlanError> {
+        let path = Manifest::path(dir, self.chain_id);
+        let io_error = |e: std::io::Error| PlanError::Io(format!("{}: {}", path.display(), e));
+        let contents = serde_json::to_string_pretty(self).map_err(|e| PlanError::Parse(e.to_string()))?;
+        fs::create_dir_all(dir).map_err(io_error)?;
+        let temporary = path.with_extension("json.tmp");
+        let mut file = File::create(&temporary).map_err(io_error)?;
+        writeln!(file, "{}", contents).and_then(|_| file.sync_all()).map_err(io_error)?;
+        fs::rename(&temporary, &path).map_err(io_error)
+    }
+
+    /// Returns the address a contract was deployed to, if it is recorded.
+    pub fn address(&self, name: &str) -> Option<Address> {
+        self.contracts.get(name).map(|entry| entry.address)
+    }
+}
+
+// Unit test example
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("wasmify-manifest-{}", std::process::id()));
+        assert_eq!(Manifest::load(&dir, 5).unwrap(), Manifest { chain_id: 5, contracts: BTreeMap::new() });
+
+        let mut manifest = Manifest { chain_id: 5, contracts: BTreeMap::new() };
+        let entry = ManifestEntry {
+            address: Address::repeat_byte(1),
+            transaction_hash: H256::repeat_byte(2),
+            block_number: U64::from(3),
+            init_code_hash: H256::repeat_byte(4),
+        };
+        manifest.contracts.insert("Token".to_string(), entry);
+        manifest.save(&dir).unwrap();
+
+        let loaded = Manifest::load(&dir, 5).unwrap();
+        assert_eq!(loaded, manifest);
+        assert_eq!(loaded.address("Token"), Some(Address::repeat_byte(1)));
+        assert!(Manifest::path(&dir, 5).ends_with("5.json"));
+        assert!(!dir.join("5.json.tmp").exists());
+
+        std::fs::write(Manifest::path(&dir, 6), "not json").unwrap();
+        assert!(matches!(Manifest::load(&dir, 6), Err(PlanError::Parse(_))));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
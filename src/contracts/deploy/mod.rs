@@ -0,0 +1,381 @@
+pub mod manifest;
+pub mod plan;
+
+pub use manifest::{Manifest, ManifestEntry};
+pub use plan::{execute_plan, DeploymentPlan, PlanError, PlanReport};
+
+use crate::contracts::abi::{Abi, AbiValue, CodecError};
+use crate::contracts::gas_report;
+use crate::contracts::linking::{link_bytecode, LinkError};
+use crate::contracts::provider::{Provider, ProviderError};
+use crate::evm::state::create2_address;
+use crate::framework::logging::{log_info, log_error};
+use web3::signing::keccak256;
+use web3::types::{Address, Bytes, TransactionReceipt, TransactionRequest, H160, H256, U256, U64};
+use web3::Transport;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Errors that can occur during contract deployment.
+#[derive(Debug)]
+pub enum DeployError {
+    InvalidContractCode,
+    InvalidAddress,
+    InvalidConstructorArguments(CodecError),
+    Linking(LinkError),
+    Provider(ProviderError),
+    DeploymentFailed,
+    FactoryNotDeployed(Address),
+    AlreadyDeployed(Address),
+}
+
+/// The deterministic deployment proxy, a `CREATE2` factory deployed at this address on most EVM chains
+/// by a pre-signed transaction. It takes `salt ++ init_code` as calldata.
+pub const DETERMINISTIC_DEPLOYMENT_PROXY: Address = H160([
+    0x4e, 0x59, 0xb4, 0x48, 0x47, 0xb3, 0x79, 0x57, 0x85, 0x88, 0x92, 0x0c, 0xa7, 0x8f, 0xbf, 0x26, 0xc0, 0xb4, 0x95, 0x6c,
+]);
+
+/// The outcome of a successful deployment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Deployment {
+    /// The address the contract was deployed to.
+    pub address: Address,
+    /// The hash of the deployment transaction.
+    pub transaction_hash: H256,
+    /// The block the deployment was mined in.
+    pub block_number: U64,
+    /// The gas used by the deployment transaction.
+    pub gas_used: U256,
+    /// The full receipt of the deployment transaction.
+    pub receipt: TransactionReceipt,
+}
+
+/// Deploys a smart contract to the blockchain with input validation and enhanced error handling.
+///
+/// The deployment transaction is sent with `Provider::send_transaction`, signed locally when the
+/// provider has a signer for `sender_address`, and the function waits until it has been mined and
+/// has the number of confirmations configured on the provider (see `Provider::with_confirmations`).
+///
+/// # Arguments
+/// * `provider` - The node to deploy through.
+/// * `contract_code` - The bytecode of the contract.
+/// * `gas_limit` - The maximum gas allowed for deployment.
+/// * `sender_address` - The address deploying the contract.
+///
+/// # Returns
+/// Result<Deployment, DeployError> - The address, transaction and receipt of the deployed contract, otherwise an error.
+pub async fn deploy_contract<T: Transport>(
+    provider: &Provider<T>,
+    contract_code: &[u8],
+    gas_limit: U256,
+    sender_address: &str,
+) -> Result<Deployment, DeployError> {
+    // Input validation: ensure contract code and sender address are valid
+    if contract_code.is_empty() {
+        return Err(DeployError::InvalidContractCode);
+    }
+    let sender = Address::from_str(sender_address).map_err(|_| DeployError::InvalidAddress)?;
+
+    // Log the deployment start
+    log_info(&format!("Deploying contract from address: {}", sender_address));
+
+    let transaction = TransactionRequest {
+        from: sender,
+        gas: Some(gas_limit),
+        data: Some(Bytes(contract_code.to_vec())),
+        ..Default::default()
+    };
+    let receipt = submit_deployment(provider, transaction).await?;
+    match receipt.contract_address {
+        Some(address) => Ok(deployment_at(address, contract_code, receipt)),
+        None => {
+            log_error(&format!("Contract deployment failed: no contract created by {:?}.", receipt.transaction_hash));
+            Err(DeployError::DeploymentFailed)
+        }
+    }
+}
+
+/// Sends a deployment transaction and waits for it to be confirmed.
+///
+/// # Returns
+/// Result<TransactionReceipt, DeployError> - The receipt of the mined transaction, or `DeploymentFailed` if it reverted.
+async fn submit_deployment<T: Transport>(
+    provider: &Provider<T>,
+    transaction: TransactionRequest,
+) -> Result<TransactionReceipt, DeployError> {
+    let hash = provider.send_transaction(transaction).await.map_err(|e| {
+        log_error(&format!("Contract deployment failed: {:?}", e));
+        DeployError::Provider(e)
+    })?;
+    let receipt = provider
+        .wait_for_confirmations(hash, provider.confirmations())
+        .await
+        .map_err(DeployError::Provider)?;
+
+    if receipt.status == Some(U64::one()) && receipt.block_number.is_some() {
+        Ok(receipt)
+    } else {
+        // Log error
+        log_error(&format!("Contract deployment failed: transaction {:?} reverted.", hash));
+        Err(DeployError::DeploymentFailed)
+    }
+}
+
+fn deployment_at(address: Address, init_code: &[u8], receipt: TransactionReceipt) -> Deployment {
+    // Log success
+    log_info(&format!("Contract deployed successfully at {:?} (tx {:?}).", address, receipt.transaction_hash));
+    gas_report::record_deployment(init_code, address, receipt.gas_used.unwrap_or_default());
+    Deployment {
+        address,
+        transaction_hash: receipt.transaction_hash,
+        block_number: receipt.block_number.unwrap_or_default(),
+        gas_used: receipt.gas_used.unwrap_or_default(),
+        receipt,
+    }
+}
+
+/// Predicts the address of a contract deployed with `CREATE2`:
+/// `keccak256(0xff ++ deployer ++ salt ++ init_code_hash)[12..]`.
+///
+/// # Arguments
+/// * `deployer` - The contract executing `CREATE2`, e.g. `DETERMINISTIC_DEPLOYMENT_PROXY`.
+/// * `salt` - The user chosen salt.
+/// * `init_code_hash` - The Keccak-256 hash of the creation bytecode, constructor arguments included.
+pub fn predict_create2_address(deployer: Address, salt: H256, init_code_hash: H256) -> Address {
+    create2_address(deployer, salt, init_code_hash)
+}
+
+/// Deploys a contract at a deterministic address through a `CREATE2` factory.
+///
+/// The factory is called with `salt ++ init_code` as calldata, the interface of the
+/// deterministic deployment proxy (`DETERMINISTIC_DEPLOYMENT_PROXY`). The same init code and salt
+/// therefore produce the same address on every chain the factory exists on.
+///
+/// # Arguments
+/// * `provider` - The node to deploy through.
+/// * `factory` - The address of the `CREATE2` factory.
+/// * `salt` - The salt that, with the init code, determines the address.
+/// * `init_code` - The creation bytecode of the contract, constructor arguments included.
+/// * `gas_limit` - The maximum gas allowed for deployment.
+/// * `sender_address` - The address sending the deployment transaction.
+///
+/// # Returns
+/// Result<Deployment, DeployError> - The deployment at the predicted address, or `AlreadyDeployed` if
+/// code already exists there.
+pub async fn deploy_contract_create2<T: Transport>(
+    provider: &Provider<T>,
+    factory: Address,
+    salt: H256,
+    init_code: &[u8],
+    gas_limit: U256,
+    sender_address: &str,
+) -> Result<Deployment, DeployError> {
+    if init_code.is_empty() {
+        return Err(DeployError::InvalidContractCode);
+    }
+    let sender = Address::from_str(sender_address).map_err(|_| DeployError::InvalidAddress)?;
+
+    if provider.code(factory).await.map_err(DeployError::Provider)?.0.is_empty() {
+        log_error(&format!("No CREATE2 factory deployed at {:?}.", factory));
+        return Err(DeployError::FactoryNotDeployed(factory));
+    }
+    let address = predict_create2_address(factory, salt, H256(keccak256(init_code)));
+    if !provider.code(address).await.map_err(DeployError::Provider)?.0.is_empty() {
+        log_info(&format!("Contract already deployed at {:?}.", address));
+        return Err(DeployError::AlreadyDeployed(address));
+    }
+
+    log_info(&format!("Deploying contract at {:?} through factory {:?} from address: {}", address, factory, sender_address));
+    let mut data = salt.as_bytes().to_vec();
+    data.extend_from_slice(init_code);
+    let transaction = TransactionRequest {
+        from: sender,
+        to: Some(factory),
+        gas: Some(gas_limit),
+        data: Some(Bytes(data)),
+        ..Default::default()
+    };
+    let receipt = submit_deployment(provider, transaction).await?;
+
+    // Factories may not revert when the creation fails, so check that the code landed
+    if provider.code(address).await.map_err(DeployError::Provider)?.0.is_empty() {
+        log_error(&format!("Contract deployment failed: no code at {:?} after {:?}.", address, receipt.transaction_hash));
+        return Err(DeployError::DeploymentFailed);
+    }
+    Ok(deployment_at(address, init_code, receipt))
+}
+
+/// Appends ABI-encoded constructor arguments to creation bytecode.
+///
+/// # Arguments
+/// * `contract_code` - The creation bytecode of the contract.
+/// * `abi` - The contract ABI; a contract without a constructor entry takes no arguments.
+/// * `args` - The constructor arguments, in parameter order.
+///
+/// # Returns
+/// Result<Vec<u8>, DeployError> - The deployment data, or an error if the arguments do not match the constructor.
+pub fn encode_constructor_args(contract_code: &[u8], abi: &Abi, args: &[AbiValue]) -> Result<Vec<u8>, DeployError> {
+    let encoded = match abi.constructor() {
+        Some(constructor) => constructor.encode_args(args),
+        None if args.is_empty() => Ok(Vec::new()),
+        None => Err(CodecError::WrongArgumentCount { expected: 0, found: args.len() }),
+    }
+    .map_err(DeployError::InvalidConstructorArguments)?;
+
+    let mut data = contract_code.to_vec();
+    data.extend_from_slice(&encoded);
+    Ok(data)
+}
+
+/// Deploys compiler output: links libraries into the bytecode, appends the constructor arguments and deploys it.
+///
+/// # Arguments
+/// * `provider` - The node to deploy through.
+/// * `bytecode` - The creation bytecode as hex, possibly containing library placeholders.
+/// * `libraries` - Deployed library addresses keyed by fully qualified name, e.g. `contracts/Math.sol:Math`.
+/// * `abi` - The contract ABI, used to encode the constructor arguments.
+/// * `args` - The constructor arguments, in parameter order.
+/// * `gas_limit` - The maximum gas allowed for deployment.
+/// * `sender_address` - The address deploying the contract.
+///
+/// # Returns
+/// Result<Deployment, DeployError> - The address, transaction and receipt of the deployed contract, otherwise an error.
+pub async fn deploy_contract_with_args<T: Transport>(
+    provider: &Provider<T>,
+    bytecode: &str,
+    libraries: &HashMap<String, Address>,
+    abi: &Abi,
+    args: &[AbiValue],
+    gas_limit: U256,
+    sender_address: &str,
+) -> Result<Deployment, DeployError> {
+    let contract_code = link_bytecode(bytecode, libraries).map_err(|e| {
+        log_error(&format!("Linking failed: {:?}", e));
+        DeployError::Linking(e)
+    })?;
+    if contract_code.is_empty() {
+        return Err(DeployError::InvalidContractCode);
+    }
+    let data = encode_constructor_args(&contract_code, abi, args)?;
+    deploy_contract(provider, &data, gas_limit, sender_address).await
+}
+
+// Unit test example
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use web3::types::U256;
+
+    fn offline_provider() -> Provider {
+        Provider::http("http://127.0.0.1:1").unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_invalid_contract_code() {
+        let result = deploy_contract(&offline_provider(), &[], U256::from(1), "0x123").await;
+        assert!(matches!(result, Err(DeployError::InvalidContractCode)));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_address() {
+        let result = deploy_contract(&offline_provider(), &[0x60, 0x80, 0x60, 0x40], U256::from(1), "invalid").await;
+        assert!(matches!(result, Err(DeployError::InvalidAddress)));
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_node() {
+        let result = deploy_contract(&offline_provider(), &[0x60, 0x80, 0x60, 0x40], U256::from(1), "0x1234567890abcdef1234567890abcdef12345678").await;
+        assert!(matches!(result, Err(DeployError::Provider(ProviderError::Transport(_)))));
+    }
+
+    #[tokio::test]
+    async fn test_deployment_result() {
+        let chain = crate::evm::LocalChain::new();
+        let provider = chain.provider();
+        let sender = chain.accounts()[0];
+        // Init code returning the single-byte runtime code `STOP`
+        let init_code = [0x60, 0x00, 0x5f, 0x53, 0x60, 0x01, 0x5f, 0xf3];
+
+        let deployment = deploy_contract(&provider, &init_code, U256::from(100_000), &format!("{:?}", sender)).await.unwrap();
+        assert_eq!(deployment.address, crate::evm::state::create_address(sender, 0));
+        assert_eq!(deployment.block_number, U64::one());
+        assert_eq!(deployment.receipt.transaction_hash, deployment.transaction_hash);
+        assert_eq!(Some(deployment.gas_used), deployment.receipt.gas_used);
+        assert_eq!(provider.code(deployment.address).await.unwrap().0, vec![0x00]);
+    }
+
+    #[test]
+    fn test_encode_constructor_args() {
+        let abi = crate::contracts::abi::parse_human_readable_abi(&["constructor(address owner, uint256 supply)"]).unwrap();
+        let owner = Address::repeat_byte(0xaa);
+        let data = encode_constructor_args(&[0x60, 0x80], &abi, &[owner.into(), U256::from(5).into()]).unwrap();
+        assert_eq!(data.len(), 2 + 64);
+        assert_eq!(&data[14..34], owner.as_bytes());
+        assert_eq!(data[65], 5);
+
+        let result = encode_constructor_args(&[0x60, 0x80], &abi, &[owner.into()]);
+        assert!(matches!(result, Err(DeployError::InvalidConstructorArguments(CodecError::WrongArgumentCount { expected: 2, found: 1 }))));
+        let result = encode_constructor_args(&[0x60, 0x80], &Abi::default(), &[owner.into()]);
+        assert!(matches!(result, Err(DeployError::InvalidConstructorArguments(_))));
+        assert_eq!(encode_constructor_args(&[0x60, 0x80], &Abi::default(), &[]).unwrap(), vec![0x60, 0x80]);
+    }
+
+    #[tokio::test]
+    async fn test_deploy_with_args_and_libraries() {
+        use crate::contracts::linking::library_placeholder;
+        let chain = crate::evm::LocalChain::new();
+        let provider = chain.provider();
+        let sender = format!("{:?}", chain.accounts()[0]);
+        let abi = crate::contracts::abi::parse_human_readable_abi(&["constructor(uint256 value)"]).unwrap();
+
+        // 33 bytes of init code pushing a linked library address, then storing the constructor
+        // argument that follows the code in slot 0 and returning empty runtime code
+        let library = library_placeholder("lib/Math.sol:Math");
+        let bytecode = format!("0x73{}50602060215f395f515f5500", library);
+        let mut libraries = HashMap::new();
+
+        let result = deploy_contract_with_args(&provider, &bytecode, &libraries, &abi, &[U256::from(7).into()], U256::from(100_000), &sender).await;
+        assert!(matches!(result, Err(DeployError::Linking(LinkError::UnresolvedLibraries(_)))));
+
+        libraries.insert("lib/Math.sol:Math".to_string(), Address::repeat_byte(0x11));
+        let deployment = deploy_contract_with_args(&provider, &bytecode, &libraries, &abi, &[U256::from(7).into()], U256::from(100_000), &sender).await.unwrap();
+        assert_eq!(chain.storage(&deployment.address, U256::zero()), U256::from(7));
+    }
+
+    #[test]
+    fn test_predict_create2_address() {
+        // Example 2 of EIP-1014
+        let deployer: Address = "0xdeadbeef00000000000000000000000000000000".parse().unwrap();
+        let salt: H256 = "0x000000000000000000000000feed000000000000000000000000000000000000".parse().unwrap();
+        let address = predict_create2_address(deployer, salt, H256(keccak256(&[0x00])));
+        assert_eq!(address, "0xD04116cDd17beBE565EB2422F2497E06cC1C9833".parse().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_deploy_contract_create2() {
+        let chain = crate::evm::LocalChain::new();
+        let provider = chain.provider();
+        let sender = format!("{:?}", chain.accounts()[0]);
+        // Init code returning the single-byte runtime code `STOP`
+        let init_code = [0x60, 0x00, 0x5f, 0x53, 0x60, 0x01, 0x5f, 0xf3];
+        let salt = H256::repeat_byte(0x42);
+
+        let result = deploy_contract_create2(&provider, DETERMINISTIC_DEPLOYMENT_PROXY, salt, &init_code, U256::from(200_000), &sender).await;
+        assert!(matches!(result, Err(DeployError::FactoryNotDeployed(_))));
+
+        // Runtime code of the deterministic deployment proxy
+        let proxy = crate::contracts::provider::decode_hex(
+            "7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffe03601600081602082378035828234f58015156039578182fd5b8082525050506014600cf3",
+        )
+        .unwrap();
+        chain.set_code(DETERMINISTIC_DEPLOYMENT_PROXY, proxy);
+
+        let deployment = deploy_contract_create2(&provider, DETERMINISTIC_DEPLOYMENT_PROXY, salt, &init_code, U256::from(200_000), &sender).await.unwrap();
+        assert_eq!(deployment.address, predict_create2_address(DETERMINISTIC_DEPLOYMENT_PROXY, salt, H256(keccak256(&init_code))));
+        assert_eq!(deployment.receipt.to, Some(DETERMINISTIC_DEPLOYMENT_PROXY));
+        assert_eq!(provider.code(deployment.address).await.unwrap().0, vec![0x00]);
+
+        let result = deploy_contract_create2(&provider, DETERMINISTIC_DEPLOYMENT_PROXY, salt, &init_code, U256::from(200_000), &sender).await;
+        assert!(matches!(result, Err(DeployError::AlreadyDeployed(address)) if address == deployment.address));
+    }
+}
@@ -0,0 +1,501 @@
+use super::manifest::{Manifest, ManifestEntry};
+use super::{deploy_contract, encode_constructor_args, DeployError};
+use crate::contracts::abi::{parse_abi, parse_human_readable_abi, Abi, AbiType, AbiValue};
+use crate::contracts::gas_report;
+use crate::contracts::linking::link_bytecode;
+use crate::contracts::provider::{decode_hex, Provider, ProviderError};
+use crate::framework::logging::{log_error, log_info};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use web3::signing::keccak256;
+use web3::types::{Address, H256, U256};
+use web3::Transport;
+
+/// The gas limit of a deployment that does not set one.
+pub const DEFAULT_GAS_LIMIT: u64 = 6_000_000;
+
+/// Errors that can occur while loading or executing a deployment plan.
+#[derive(Debug)]
+pub enum PlanError {
+    /// A plan, artifact or manifest file could not be read or written.
+    Io(String),
+    /// A plan, artifact or manifest file is malformed.
+    Parse(String),
+    /// The plan is inconsistent, e.g. a contract without bytecode or a duplicated name.
+    Invalid(String),
+    /// An argument or library refers to a contract that is not deployed before it.
+    UnresolvedReference { contract: String, reference: String },
+    /// A constructor argument cannot be converted to its declared type.
+    InvalidArgument { contract: String, message: String },
+    Deploy { contract: String, error: DeployError },
+    Provider(ProviderError),
+}
+
+/// A declarative list of contracts to deploy, in order.
+///
+/// Plans are written in TOML or JSON. Constructor arguments and library addresses may refer to
+/// a contract deployed earlier in the plan as `"${Name}"`.
+///
+/// ```toml
+/// [[contracts]]
+/// name = "Math"
+/// artifact = "out/Math.json"
+///
+/// [[contracts]]
+/// name = "Token"
+/// artifact = "out/Token.json"
+/// args = ["Token", "TKN", "1000000"]
+/// libraries = { "src/Math.sol:Math" = "${Math}" }
+///
+/// [networks.1.contracts.Token]
+/// args = ["Token", "TKN", "21000000"]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct DeploymentPlan {
+    pub contracts: Vec<ContractPlan>,
+    /// Per-network overrides keyed by chain id.
+    #[serde(default)]
+    pub networks: HashMap<String, NetworkOverrides>,
+    /// The directory artifact paths are relative to; the plan file's directory when loaded from a file.
+    #[serde(skip)]
+    pub base_dir: PathBuf,
+}
+
+/// A contract of a deployment plan.
+///
+/// The bytecode and ABI come either from a compiler artifact (Hardhat, Foundry or solc
+/// standard JSON output) or are given inline. The ABI may be JSON or human-readable fragments.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct ContractPlan {
+    pub name: String,
+    #[serde(default)]
+    pub artifact: Option<PathBuf>,
+    #[serde(default)]
+    pub bytecode: Option<String>,
+    #[serde(default)]
+    pub abi: Option<Value>,
+    #[serde(default)]
+    pub args: Vec<Value>,
+    /// Library addresses keyed by fully qualified name, e.g. `src/Math.sol:Math`.
+    #[serde(default)]
+    pub libraries: HashMap<String, String>,
+    #[serde(default)]
+    pub gas_limit: Option<u64>,
+}
+
+/// The overrides of a deployment plan for one network.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct NetworkOverrides {
+    #[serde(default)]
+    pub contracts: HashMap<String, ContractOverride>,
+}
+
+/// Replaces the settings of a contract on one network.
+///
+/// # Fields
+/// - `address`: Use an existing deployment instead of deploying the contract, e.g. a token that
+///   already exists on that network.
+/// - `args`, `libraries`, `gas_limit`: Replace the corresponding settings of the contract.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct ContractOverride {
+    #[serde(default)]
+    pub address: Option<String>,
+    #[serde(default)]
+    pub args: Option<Vec<Value>>,
+    #[serde(default)]
+    pub libraries: Option<HashMap<String, String>>,
+    #[serde(default)]
+    pub gas_limit: Option<u64>,
+}
+
+/// The outcome of executing a deployment plan.
+///
+/// # Fields
+/// - `manifest`: The manifest of the chain after execution.
+/// - `deployed`: The contracts deployed by this run.
+/// - `skipped`: The contracts left in place because their bytecode and arguments are unchanged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanReport {
+    pub manifest: Manifest,
+    pub deployed: Vec<String>,
+    pub skipped: Vec<String>,
+}
+
+impl DeploymentPlan {
+    /// Parses a plan written in TOML.
+    pub fn from_toml_str(contents: &str) -> Result<DeploymentPlan, PlanError> {
+        toml::from_str(contents).map_err(|e| PlanError::Parse(e.to_string()))
+    }
+
+    /// Parses a plan written in JSON.
+    pub fn from_json_str(contents: &str) -> Result<DeploymentPlan, PlanError> {
+        serde_json::from_str(contents).map_err(|e| PlanError::Parse(e.to_string()))
+    }
+
+    /// Loads a plan from a `.toml` or `.json` file; artifact paths are resolved against its directory.
+    pub fn load(path: &Path) -> Result<DeploymentPlan, PlanError> {
+        let contents = fs::read_to_string(path).map_err(|e| PlanError::Io(format!("{}: {}", path.display(), e)))?;
+        let mut plan = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => DeploymentPlan::from_toml_str(&contents)?,
+            _ => DeploymentPlan::from_json_str(&contents)?,
+        };
+        plan.base_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(plan)
+    }
+
+    /// Checks that contract names are unique and that every override targets a contract of the plan.
+    pub fn validate(&self) -> Result<(), PlanError> {
+        let mut names = HashSet::new();
+        for contract in &self.contracts {
+            if !names.insert(contract.name.as_str()) {
+                return Err(PlanError::Invalid(format!("contract {} is listed twice", contract.name)));
+            }
+        }
+        for (chain_id, overrides) in &self.networks {
+            if let Some(name) = overrides.contracts.keys().find(|name| !names.contains(name.as_str())) {
+                return Err(PlanError::Invalid(format!("network {} overrides unknown contract {}", chain_id, name)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the settings of a contract on a chain, with that chain's overrides applied.
+    fn resolve(&self, contract: &ContractPlan, chain_id: u64) -> (ContractPlan, Option<String>) {
+        let mut resolved = contract.clone();
+        let overrides = self
+            .networks
+            .get(&chain_id.to_string())
+            .and_then(|network| network.contracts.get(&contract.name));
+        let Some(overrides) = overrides else {
+            return (resolved, None);
+        };
+        if let Some(args) = &overrides.args {
+            resolved.args = args.clone();
+        }
+        if let Some(libraries) = &overrides.libraries {
+            resolved.libraries = libraries.clone();
+        }
+        if overrides.gas_limit.is_some() {
+            resolved.gas_limit = overrides.gas_limit;
+        }
+        (resolved, overrides.address.clone())
+    }
+}
+
+impl ContractPlan {
+    /// Returns the hex bytecode and the ABI of the contract, reading its artifact if it has one.
+    fn bytecode_and_abi(&self, base_dir: &Path) -> Result<(String, Abi), PlanError> {
+        let artifact = match &self.artifact {
+            Some(path) => {
+                let path = base_dir.join(path);
+                let contents = fs::read_to_string(&path).map_err(|e| PlanError::Io(format!("{}: {}", path.display(), e)))?;
+                serde_json::from_str(&contents).map_err(|e| PlanError::Parse(format!("{}: {}", path.display(), e)))?
+            }
+            None => Value::Null,
+        };
+
+        // Hardhat stores the bytecode as a string, Foundry and solc as `{ "object": ... }`
+        let artifact_bytecode = [&artifact["bytecode"], &artifact["bytecode"]["object"], &artifact["evm"]["bytecode"]["object"]]
+            .into_iter()
+            .find_map(|value| value.as_str().map(str::to_string));
+        let bytecode = self
+            .bytecode
+            .clone()
+            .or(artifact_bytecode)
+            .ok_or_else(|| PlanError::Invalid(format!("contract {} has no bytecode", self.name)))?;
+
+        let abi = match self.abi.as_ref().unwrap_or(&artifact["abi"]) {
+            Value::Null => Ok(Abi::default()),
+            Value::Array(entries) if entries.iter().all(Value::is_string) => {
+                let fragments: Vec<&str> = entries.iter().filter_map(Value::as_str).collect();
+                parse_human_readable_abi(&fragments)
+            }
+            abi => parse_abi(&abi.to_string()),
+        };
+        let abi = abi.map_err(|e| PlanError::Parse(format!("ABI of {}: {}", self.name, e)))?;
+        Ok((bytecode, abi))
+    }
+}
+
+/// Replaces a `"${Name}"` reference with the address of a contract deployed earlier in the plan.
+fn resolve_reference(value: &str, addresses: &HashMap<String, Address>, contract: &str) -> Result<Option<Address>, PlanError> {
+    let Some(reference) = value.strip_prefix("${").and_then(|rest| rest.strip_suffix('}')) else {
+        return Ok(None);
+    };
+    addresses.get(reference).copied().map(Some).ok_or_else(|| PlanError::UnresolvedReference {
+        contract: contract.to_string(),
+        reference: reference.to_string(),
+    })
+}
+
+fn parse_integer(value: &Value, signed: bool) -> Option<U256> {
+    let text = match value {
+        Value::Number(number) => number.to_string(),
+        Value::String(text) => text.trim().to_string(),
+        _ => return None,
+    };
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) if signed => (true, digits),
+        Some(_) => return None,
+        None => (false, text.as_str()),
+    };
+    let magnitude = match digits.strip_prefix("0x") {
+        Some(hex) => U256::from_str_radix(hex, 16).ok()?,
+        None => U256::from_dec_str(digits).ok()?,
+    };
+    Some(if negative { (!magnitude).overflowing_add(U256::one()).0 } else { magnitude })
+}
+
+/// Converts a constructor argument from the plan into a value of its declared type.
+///
+/// Integers may be numbers or decimal/hex strings (large values do not fit TOML or JSON numbers),
+/// bytes are hex strings, and arrays and tuples are arrays.
+fn to_abi_value(ty: &AbiType, value: &Value, addresses: &HashMap<String, Address>, contract: &str) -> Result<AbiValue, PlanError> {
+    let invalid = || PlanError::InvalidArgument { contract: contract.to_string(), message: format!("expected {}, found {}", ty, value) };
+    let elements = |types: &mut dyn Iterator<Item = &AbiType>, values: &[Value]| {
+        types
+            .zip(values)
+            .map(|(ty, value)| to_abi_value(ty, value, addresses, contract))
+            .collect::<Result<Vec<_>, _>>()
+    };
+
+    let converted = match (ty, value) {
+        (AbiType::Address, Value::String(text)) => match resolve_reference(text, addresses, contract)? {
+            Some(address) => AbiValue::Address(address),
+            None => AbiValue::Address(Address::from_str(text).map_err(|_| invalid())?),
+        },
+        (AbiType::Bool, Value::Bool(flag)) => AbiValue::Bool(*flag),
+        (AbiType::Uint(_), value) => AbiValue::Uint(parse_integer(value, false).ok_or_else(invalid)?),
+        (AbiType::Int(_), value) => AbiValue::Int(parse_integer(value, true).ok_or_else(invalid)?),
+        (AbiType::FixedBytes(_), Value::String(hex)) => AbiValue::FixedBytes(decode_hex(hex).ok_or_else(invalid)?),
+        (AbiType::Bytes, Value::String(hex)) => AbiValue::Bytes(decode_hex(hex).ok_or_else(invalid)?),
+        (AbiType::String, Value::String(text)) => AbiValue::String(text.clone()),
+        (AbiType::Array(element), Value::Array(values)) => {
+            AbiValue::Array(elements(&mut std::iter::repeat(element.as_ref()), values)?)
+        }
+        (AbiType::FixedArray(element, len), Value::Array(values)) if values.len() == *len => {
+            AbiValue::FixedArray(elements(&mut std::iter::repeat(element.as_ref()), values)?)
+        }
+        (AbiType::Tuple(members), Value::Array(values)) if values.len() == members.len() => {
+            AbiValue::Tuple(elements(&mut members.iter(), values)?)
+        }
+        _ => return Err(invalid()),
+    };
+    converted.type_check(ty).map_err(|e| PlanError::InvalidArgument { contract: contract.to_string(), message: format!("{:?}", e) })?;
+    Ok(converted)
+}
+
+/// Builds the deployment data of a contract: linked bytecode followed by the encoded constructor arguments.
+fn init_code(contract: &ContractPlan, base_dir: &Path, addresses: &HashMap<String, Address>) -> Result<Vec<u8>, PlanError> {
+    let name = contract.name.as_str();
+    let (bytecode, abi) = contract.bytecode_and_abi(base_dir)?;
+
+    let mut libraries = HashMap::new();
+    for (library, value) in &contract.libraries {
+        let address = match resolve_reference(value, addresses, name)? {
+            Some(address) => address,
+            None => Address::from_str(value)
+                .map_err(|_| PlanError::Invalid(format!("library {} of {} has an invalid address", library, name)))?,
+        };
+        libraries.insert(library.clone(), address);
+    }
+    let code = link_bytecode(&bytecode, &libraries)
+        .map_err(|e| PlanError::Deploy { contract: name.to_string(), error: DeployError::Linking(e) })?;
+
+    let types = match abi.constructor() {
+        Some(constructor) => constructor
+            .input_types()
+            .map_err(|e| PlanError::Parse(format!("constructor of {}: {:?}", name, e)))?,
+        None => Vec::new(),
+    };
+    if types.len() != contract.args.len() {
+        return Err(PlanError::InvalidArgument {
+            contract: name.to_string(),
+            message: format!("expected {} constructor arguments, found {}", types.len(), contract.args.len()),
+        });
+    }
+    let args = types
+        .iter()
+        .zip(&contract.args)
+        .map(|(ty, value)| to_abi_value(ty, value, addresses, name))
+        .collect::<Result<Vec<_>, _>>()?;
+    encode_constructor_args(&code, &abi, &args).map_err(|error| PlanError::Deploy { contract: name.to_string(), error })
+}
+
+/// Executes a deployment plan on the provider's chain, recording the results in the chain's manifest.
+///
+/// Contracts are deployed in plan order. A contract recorded in the manifest is skipped when its
+/// linked bytecode and constructor arguments are unchanged and its code is still on chain, so
+/// re-running a plan only deploys what changed. Contracts that depend on a redeployed contract
+/// are redeployed too, since the new address changes their arguments. The manifest is saved
+/// after every deployment, so an interrupted run resumes where it stopped.
+///
+/// # Arguments
+/// * `provider` - The node to deploy through.
+/// * `plan` - The plan to execute.
+/// * `manifest_dir` - The directory holding one `<chain_id>.json` manifest per chain.
+/// * `sender_address` - The address deploying the contracts.
+///
+/// # Returns
+/// Result<PlanReport, PlanError> - The updated manifest with the deployed and skipped contracts.
+pub async fn execute_plan<T: Transport>(
+    provider: &Provider<T>,
+    plan: &DeploymentPlan,
+    manifest_dir: &Path,
+    sender_address: &str,
+) -> Result<PlanReport, PlanError> {
+    plan.validate()?;
+    let chain_id = provider.chain_id().await.map_err(PlanError::Provider)?.as_u64();
+    let mut manifest = Manifest::load(manifest_dir, chain_id)?;
+    let mut addresses = HashMap::new();
+    let mut report = PlanReport { manifest: Manifest::default(), deployed: Vec::new(), skipped: Vec::new() };
+
+    for contract in &plan.contracts {
+        let (contract, existing) = plan.resolve(contract, chain_id);
+        let name = contract.name.clone();
+        if let Some(existing) = existing {
+            let address = match resolve_reference(&existing, &addresses, &name)? {
+                Some(address) => address,
+                None => Address::from_str(&existing)
+                    .map_err(|_| PlanError::Invalid(format!("{} has an invalid address on chain {}", name, chain_id)))?,
+            };
+            log_info(&format!("Using existing {} at {:?}.", name, address));
+            addresses.insert(name, address);
+            continue;
+        }
+
+        let data = init_code(&contract, &plan.base_dir, &addresses)?;
+        let init_code_hash = H256(keccak256(&data));
+        if let Some(entry) = manifest.contracts.get(&name).filter(|entry| entry.init_code_hash == init_code_hash) {
+            let code = provider.code(entry.address).await.map_err(PlanError::Provider)?;
+            if !code.0.is_empty() {
+                log_info(&format!("{} is unchanged at {:?}, skipping.", name, entry.address));
+                addresses.insert(name.clone(), entry.address);
+                report.skipped.push(name);
+                continue;
+            }
+        }
+
+        log_info(&format!("Deploying {} on chain {}.", name, chain_id));
+        gas_report::name_contract(&data, &name);
+        let gas_limit = U256::from(contract.gas_limit.unwrap_or(DEFAULT_GAS_LIMIT));
+        let deployment = deploy_contract(provider, &data, gas_limit, sender_address).await.map_err(|error| {
+            log_error(&format!("Deployment plan stopped at {}.", name));
+            PlanError::Deploy { contract: name.clone(), error }
+        })?;
+        manifest.contracts.insert(
+            name.clone(),
+            ManifestEntry {
+                address: deployment.address,
+                transaction_hash: deployment.transaction_hash,
+                block_number: deployment.block_number,
+                init_code_hash,
+            },
+        );
+        manifest.save(manifest_dir)?;
+        addresses.insert(name.clone(), deployment.address);
+        report.deployed.push(name);
+    }
+
+    report.manifest = manifest;
+    Ok(report)
+}
+
+// Unit test example
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::LocalChain;
+    use serde_json::json;
+
+    /// Init code storing its 32-byte constructor argument in slot 0 and returning `STOP` as runtime code.
+    const STORE_ARG: &str = "0x602060125f395f515f5560005f5360015ff3";
+
+    fn plan_toml(supply: &str) -> String {
+        format!(
+            r#"
+            [[contracts]]
+            name = "Registry"
+            bytecode = "{code}"
+            abi = ["constructor(uint256 supply)"]
+            args = ["{supply}"]
+
+            [[contracts]]
+            name = "Token"
+            bytecode = "{code}"
+            abi = ["constructor(address registry)"]
+            args = ["${{Registry}}"]
+
+            [networks.1.contracts.Registry]
+            address = "0x00000000000000000000000000000000000000aa"
+            "#,
+            code = STORE_ARG,
+            supply = supply
+        )
+    }
+
+    #[test]
+    fn test_parse_and_validate() {
+        let plan = DeploymentPlan::from_toml_str(&plan_toml("1000")).unwrap();
+        assert_eq!(plan.contracts.len(), 2);
+        assert_eq!(plan.contracts[1].args, vec![json!("${Registry}")]);
+        let (_, existing) = plan.resolve(&plan.contracts[0], 1);
+        assert_eq!(existing.as_deref(), Some("0x00000000000000000000000000000000000000aa"));
+        assert_eq!(plan.resolve(&plan.contracts[0], 31337).1, None);
+
+        let json = r#"{ "contracts": [{ "name": "A", "bytecode": "0x00" }, { "name": "A", "bytecode": "0x00" }] }"#;
+        let plan = DeploymentPlan::from_json_str(json).unwrap();
+        assert!(matches!(plan.validate(), Err(PlanError::Invalid(_))));
+        assert!(matches!(DeploymentPlan::from_toml_str("contracts = 1"), Err(PlanError::Parse(_))));
+    }
+
+    #[test]
+    fn test_to_abi_value() {
+        let addresses = HashMap::from([("Token".to_string(), Address::repeat_byte(1))]);
+        let convert = |ty: &str, value: Value| to_abi_value(&AbiType::parse(ty).unwrap(), &value, &addresses, "Test");
+
+        assert_eq!(convert("address", json!("${Token}")).unwrap(), AbiValue::Address(Address::repeat_byte(1)));
+        assert!(matches!(convert("address", json!("${Vault}")), Err(PlanError::UnresolvedReference { .. })));
+        assert_eq!(convert("uint256", json!("0x10")).unwrap(), AbiValue::Uint(U256::from(16)));
+        assert_eq!(convert("int8", json!(-1)).unwrap(), AbiValue::int(-1));
+        assert!(matches!(convert("uint8", json!(256)), Err(PlanError::InvalidArgument { .. })));
+        assert!(matches!(convert("uint8", json!("-1")), Err(PlanError::InvalidArgument { .. })));
+        assert_eq!(
+            convert("(bool,bytes2[])", json!([true, ["0xabcd"]])).unwrap(),
+            AbiValue::Tuple(vec![AbiValue::Bool(true), AbiValue::Array(vec![AbiValue::FixedBytes(vec![0xab, 0xcd])])])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_plan_is_idempotent() {
+        let chain = LocalChain::new();
+        let provider = chain.provider();
+        let sender = format!("{:?}", chain.accounts()[0]);
+        let dir = std::env::temp_dir().join(format!("wasmify-plan-{}", std::process::id()));
+
+        let plan = DeploymentPlan::from_toml_str(&plan_toml("1000")).unwrap();
+        let report = execute_plan(&provider, &plan, &dir, &sender).await.unwrap();
+        assert_eq!(report.deployed, vec!["Registry", "Token"]);
+        let registry = report.manifest.address("Registry").unwrap();
+        let token = report.manifest.address("Token").unwrap();
+        assert_eq!(chain.storage(&registry, U256::zero()), U256::from(1000));
+        assert_eq!(chain.storage(&token, U256::zero()), U256::from(registry.as_bytes()));
+        assert_eq!(Manifest::load(&dir, 31337).unwrap(), report.manifest);
+
+        // Nothing changed: both contracts are skipped
+        let report = execute_plan(&provider, &plan, &dir, &sender).await.unwrap();
+        assert_eq!(report.skipped, vec!["Registry", "Token"]);
+        assert!(report.deployed.is_empty());
+
+        // A changed argument redeploys the contract and everything that references it
+        let plan = DeploymentPlan::from_toml_str(&plan_toml("2000")).unwrap();
+        let report = execute_plan(&provider, &plan, &dir, &sender).await.unwrap();
+        assert_eq!(report.deployed, vec!["Registry", "Token"]);
+        assert_ne!(report.manifest.address("Registry"), Some(registry));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
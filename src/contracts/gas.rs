@@ -1,39 +1,112 @@
-use crate::framework::logging::{log_info, log_warn};
-use web3::types::U256;
+use super::abi::{decode_revert, Abi, RevertReason};
+use super::provider::{call_request, Provider, ProviderError};
+use crate::framework::logging::{log_debug, log_info, log_warn};
+use crate::signing::TransactionKind;
+use web3::types::{BlockId, BlockNumber, FeeHistory, TransactionRequest, U256};
+use web3::Transport;
 
 /// Errors that can occur during gas optimization.
 #[derive(Debug)]
 pub enum GasOptimizationError {
     InvalidGasLimit,
     GasCalculationFailed,
+    Provider(ProviderError),
+    /// The transaction reverts during estimation, with the decoded reason.
+    Reverted(RevertReason),
+    /// The transaction needs more gas than a block can hold.
+    ExceedsBlockGasLimit { required: U256, block_gas_limit: U256 },
 }
 
-/// Estimates the gas needed for a transaction with input validation.
-/// 
+impl From<ProviderError> for GasOptimizationError {
+    fn from(error: ProviderError) -> Self {
+        GasOptimizationError::Provider(error)
+    }
+}
+
+/// The default margin added on top of `eth_estimateGas`, in percent.
+///
+/// Estimates are exact for the state they ran against; the margin covers state changing
+/// between estimation and inclusion, e.g. a storage slot going from zero to non-zero.
+pub const DEFAULT_GAS_BUFFER_PERCENT: u64 = 20;
+
+/// The result of estimating a transaction against the node.
+///
+/// # Fields
+/// - `estimated`: The gas `eth_estimateGas` reported.
+/// - `gas_limit`: The estimate plus the buffer, capped at the block gas limit; use it as the transaction's gas.
+/// - `block_gas_limit`: The gas limit of the latest block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GasEstimate {
+    pub estimated: U256,
+    pub gas_limit: U256,
+    pub block_gas_limit: U256,
+}
+
+/// Estimates the gas a transaction needs by asking the node to execute it.
+///
 /// # Arguments
-/// * `gas_limit` - The maximum gas allowed for deployment.
+/// * `provider` - The node to estimate against.
+/// * `transaction` - The transaction as it will be sent: `from`, `value` and calldata, or for a
+///   deployment no `to` and the init code with constructor arguments as `data`.
+/// * `abi` - The ABI of the called contract, used to decode custom revert errors.
+/// * `buffer_percent` - The margin added to the estimate, e.g. `DEFAULT_GAS_BUFFER_PERCENT`.
 ///
 /// # Returns
-/// Result<U256, GasOptimizationError> - Returns the estimated gas or an error.
-pub fn estimate_gas(gas_limit: U256) -> Result<U256, GasOptimizationError> {
-    // Input validation: ensure gas limit is non-zero and valid
-    if gas_limit == U256::zero() {
-        return Err(GasOptimizationError::InvalidGasLimit);
+/// Result<GasEstimate, GasOptimizationError> - The estimate, `Reverted` with the decoded reason
+/// if the transaction would revert, or `ExceedsBlockGasLimit` if it cannot fit in a block.
+pub async fn estimate_gas<T: Transport>(
+    provider: &Provider<T>,
+    transaction: &TransactionRequest,
+    abi: Option<&Abi>,
+    buffer_percent: u64,
+) -> Result<GasEstimate, GasOptimizationError> {
+    let estimated = match provider.estimate_gas(call_request(transaction)).await {
+        Ok(estimated) => estimated,
+        Err(error) => {
+            return Err(match revert_reason(&error, abi) {
+                Some(reason) => {
+                    log_warn(&format!("Gas estimation failed: the transaction {}", reason));
+                    GasOptimizationError::Reverted(reason)
+                }
+                None => GasOptimizationError::Provider(error),
+            });
+        }
+    };
+    if estimated.is_zero() {
+        log_warn("Gas estimation failed: the node estimated zero gas.");
+        return Err(GasOptimizationError::GasCalculationFailed);
     }
 
-    // Simulate gas estimation logic (real logic would go here)
-    let estimated_gas = gas_limit / 2;
-
-    if estimated_gas.is_zero() {
-        log_warn("Gas estimation failed."); // Corrected log_warn usage
-        return Err(GasOptimizationError::GasCalculationFailed);
+    let latest = provider.block(BlockId::Number(BlockNumber::Latest)).await?;
+    let block_gas_limit = latest.map(|block| block.gas_limit).ok_or(GasOptimizationError::GasCalculationFailed)?;
+    if estimated > block_gas_limit {
+        return Err(GasOptimizationError::ExceedsBlockGasLimit { required: estimated, block_gas_limit });
     }
 
-    log_info(&format!("Estimated gas: {:?}", estimated_gas)); // Corrected log_info usage
-    Ok(estimated_gas)
+    let gas_limit = (estimated * (100 + buffer_percent) / 100).min(block_gas_limit);
+    log_info(&format!("Estimated gas: {} (limit {} with a {}% buffer)", estimated, gas_limit, buffer_percent));
+    Ok(GasEstimate { estimated, gas_limit, block_gas_limit })
+}
+
+/// Returns why a node error reports a reverted execution, if it does.
+fn revert_reason(error: &ProviderError, abi: Option<&Abi>) -> Option<RevertReason> {
+    if let Some(data) = error.revert_data() {
+        return Some(decode_revert(data, abi));
+    }
+    // Nodes that do not return revert data may still put the message in the error
+    match error {
+        ProviderError::Rpc { message, .. } => {
+            let reason = message.strip_prefix("execution reverted")?.trim_start_matches(':').trim();
+            Some(if reason.is_empty() { RevertReason::Empty } else { RevertReason::Error(reason.to_string()) })
+        }
+        _ => None,
+    }
 }
 
 /// Dynamically adjusts gas usage based on network conditions.
+///
+/// This scales the gas *limit* by a fixed threshold on the price; use `FeeOracle` to price
+/// transactions from the fees actually paid on chain.
 /// 
 /// # Arguments
 /// * `current_gas_price` - The current gas price in the network.
@@ -55,26 +128,274 @@ pub fn optimize_gas_dynamically(current_gas_price: U256, gas_limit: U256) -> U25
     optimized_gas
 }
 
-pub fn check_gas_limit(gas_limit: u64) -> bool {
-    gas_limit > 0
+/// Checks that a gas limit covers an estimate and still fits in a block.
+///
+/// # Arguments
+/// * `gas_limit` - The gas limit a transaction is about to be sent with.
+/// * `estimate` - The estimate from `estimate_gas` for the same transaction.
+pub fn check_gas_limit(gas_limit: U256, estimate: &GasEstimate) -> bool {
+    gas_limit >= estimate.estimated && gas_limit <= estimate.block_gas_limit
+}
+
+/// How urgently a transaction should be included, selecting the priority fee percentile paid in recent blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeeSpeed {
+    /// The 10th percentile: cheap, but may wait several blocks when blocks are full.
+    Slow,
+    /// The 50th percentile.
+    Normal,
+    /// The 90th percentile: outbids most transactions in recent blocks.
+    Fast,
+}
+
+impl FeeSpeed {
+    /// Returns the reward percentile passed to `eth_feeHistory`.
+    pub fn percentile(&self) -> f64 {
+        match self {
+            FeeSpeed::Slow => 10.0,
+            FeeSpeed::Normal => 50.0,
+            FeeSpeed::Fast => 90.0,
+        }
+    }
+}
+
+/// Suggested fees for a transaction.
+///
+/// # Fields
+/// - `max_fee_per_gas`: The most the sender pays per gas, base fee included.
+/// - `max_priority_fee_per_gas`: The tip per gas offered to the block producer.
+/// - `base_fee_per_gas`: The base fee of the next block, or `None` on chains without EIP-1559,
+///   where both fees equal the `eth_gasPrice` suggestion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeEstimate {
+    pub max_fee_per_gas: U256,
+    pub max_priority_fee_per_gas: U256,
+    pub base_fee_per_gas: Option<U256>,
+}
+
+impl FeeEstimate {
+    /// Returns the fee fields of a transaction paying these fees: EIP-1559 where the chain supports it, legacy otherwise.
+    pub fn kind(&self) -> TransactionKind {
+        match self.base_fee_per_gas {
+            Some(_) => TransactionKind::Eip1559 {
+                max_fee_per_gas: self.max_fee_per_gas,
+                max_priority_fee_per_gas: self.max_priority_fee_per_gas,
+            },
+            None => TransactionKind::Legacy { gas_price: self.max_fee_per_gas },
+        }
+    }
+
+    /// Returns the gas price a legacy transaction should offer: the next base fee plus the priority
+    /// fee, or the `eth_gasPrice` suggestion on chains without EIP-1559.
+    pub fn gas_price(&self) -> U256 {
+        match self.base_fee_per_gas {
+            Some(base_fee) => base_fee + self.max_priority_fee_per_gas,
+            None => self.max_fee_per_gas,
+        }
+    }
+}
+
+/// Fee estimates for every `FeeSpeed`, computed from a single `eth_feeHistory` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeePresets {
+    pub slow: FeeEstimate,
+    pub normal: FeeEstimate,
+    pub fast: FeeEstimate,
+}
+
+impl FeePresets {
+    /// Returns the estimate for a speed.
+    pub fn get(&self, speed: FeeSpeed) -> FeeEstimate {
+        match speed {
+            FeeSpeed::Slow => self.slow,
+            FeeSpeed::Normal => self.normal,
+            FeeSpeed::Fast => self.fast,
+        }
+    }
+}
+
+/// Suggests EIP-1559 fees from the priority fees paid in recent blocks.
+///
+/// The priority fee of a speed is the median, over the recent non-empty blocks, of the
+/// speed's percentile of the fees paid in each block. The max fee leaves room for the base fee
+/// to double: twice the next block's base fee plus the priority fee. Chains without a base fee,
+/// or nodes without `eth_feeHistory`, fall back to `eth_gasPrice`.
+#[derive(Debug, Clone)]
+pub struct FeeOracle {
+    block_count: u64,
+    base_fee_multiplier: u64,
+}
+
+impl Default for FeeOracle {
+    fn default() -> Self {
+        FeeOracle::new()
+    }
+}
+
+impl FeeOracle {
+    /// Creates an oracle looking at the last 10 blocks.
+    pub fn new() -> Self {
+        FeeOracle { block_count: 10, base_fee_multiplier: 2 }
+    }
+
+    /// Sets how many recent blocks the priority fees are taken from.
+    pub fn with_block_count(mut self, block_count: u64) -> Self {
+        self.block_count = block_count.max(1);
+        self
+    }
+
+    /// Sets how many times the next base fee the max fee covers; 2 stays valid through six full blocks.
+    pub fn with_base_fee_multiplier(mut self, base_fee_multiplier: u64) -> Self {
+        self.base_fee_multiplier = base_fee_multiplier.max(1);
+        self
+    }
+
+    /// Suggests fees for one speed.
+    ///
+    /// # Arguments
+    /// * `provider` - The node to read the fee history from.
+    /// * `speed` - How urgently the transaction should be included.
+    ///
+    /// # Returns
+    /// Result<FeeEstimate, GasOptimizationError> - The suggested fees.
+    pub async fn estimate<T: Transport>(&self, provider: &Provider<T>, speed: FeeSpeed) -> Result<FeeEstimate, GasOptimizationError> {
+        Ok(self.presets(provider).await?.get(speed))
+    }
+
+    /// Suggests fees for every speed.
+    pub async fn presets<T: Transport>(&self, provider: &Provider<T>) -> Result<FeePresets, GasOptimizationError> {
+        let speeds = [FeeSpeed::Slow, FeeSpeed::Normal, FeeSpeed::Fast];
+        let percentiles = speeds.map(|speed| speed.percentile());
+        let history = match provider.fee_history(self.block_count, BlockNumber::Latest, &percentiles).await {
+            Ok(history) => Some(history),
+            // Nodes predating London do not know the method
+            Err(ProviderError::Rpc { code: -32601, message, .. }) => {
+                log_debug(&format!("eth_feeHistory is unavailable ({}); using eth_gasPrice", message));
+                None
+            }
+            Err(error) => return Err(error.into()),
+        };
+        let base_fee = history.as_ref().and_then(|history| history.base_fee_per_gas.last().copied()).filter(|fee| !fee.is_zero());
+
+        let (Some(history), Some(base_fee)) = (history, base_fee) else {
+            let gas_price = provider.gas_price().await?;
+            let legacy = FeeEstimate { max_fee_per_gas: gas_price, max_priority_fee_per_gas: gas_price, base_fee_per_gas: None };
+            return Ok(FeePresets { slow: legacy, normal: legacy, fast: legacy });
+        };
+
+        let mut estimates = Vec::with_capacity(speeds.len());
+        for index in 0..speeds.len() {
+            let max_priority_fee_per_gas = match median_reward(&history, index) {
+                Some(fee) => fee,
+                // Only empty blocks: nobody had to bid, so ask the node
+                None => provider.request("eth_maxPriorityFeePerGas", vec![]).await?,
+            };
+            estimates.push(FeeEstimate {
+                max_fee_per_gas: base_fee * self.base_fee_multiplier + max_priority_fee_per_gas,
+                max_priority_fee_per_gas,
+                base_fee_per_gas: Some(base_fee),
+            });
+        }
+        log_info(&format!("Fee estimates over {} blocks with base fee {}: {:?}", self.block_count, base_fee, estimates));
+        Ok(FeePresets { slow: estimates[0], normal: estimates[1], fast: estimates[2] })
+    }
+}
+
+/// Returns the median of one reward percentile over the blocks that had transactions in them.
+fn median_reward(history: &FeeHistory, percentile_index: usize) -> Option<U256> {
+    let rewards = history.reward.as_ref()?;
+    let mut fees: Vec<U256> = rewards
+        .iter()
+        .zip(history.gas_used_ratio.iter())
+        .filter(|(_, ratio)| **ratio > 0.0)
+        .filter_map(|(block, _)| block.get(percentile_index).copied())
+        .collect();
+    if fees.is_empty() {
+        return None;
+    }
+    fees.sort();
+    Some(fees[fees.len() / 2])
+}
+
+/// The smallest fee increase, in percent, that geth and most other clients accept for a
+/// transaction replacing a pending one with the same nonce.
+pub const MIN_REPLACEMENT_BUMP_PERCENT: u64 = 10;
+
+/// Raises a fee by a percentage, rounding up so a replacement never falls just short of the
+/// node's threshold.
+///
+/// # Arguments
+/// * `fee` - The gas price, max fee or priority fee of the pending transaction.
+/// * `percent` - The increase, e.g. `MIN_REPLACEMENT_BUMP_PERCENT`.
+///
+/// # Returns
+/// U256 - The bumped fee.
+pub fn bump_fee(fee: U256, percent: u64) -> U256 {
+    (fee * (100 + percent) + 99) / 100
 }
 
 // Unit test example
 #[cfg(test)]
 mod tests {
     use super::*;
-    use web3::types::U256;
+    use crate::contracts::abi::parse_human_readable_abi;
+    use crate::testing::MockNode;
+    use serde_json::json;
+    use web3::types::{Address, Bytes};
 
-    #[test]
-    fn test_invalid_gas_limit() {
-        let result = estimate_gas(U256::zero());
-        assert!(matches!(result, Err(GasOptimizationError::InvalidGasLimit)));
+    #[tokio::test]
+    async fn test_estimate_gas() {
+        let node = MockNode::start().await;
+        node.respond("eth_estimateGas", json!("0x186a0"));
+        let provider = node.provider();
+        let transaction = TransactionRequest {
+            from: Address::repeat_byte(1),
+            to: Some(Address::repeat_byte(2)),
+            value: Some(U256::from(5)),
+            data: Some(Bytes(vec![0xa9, 0x05, 0x9c, 0xbb])),
+            ..Default::default()
+        };
+        let estimate = estimate_gas(&provider, &transaction, None, DEFAULT_GAS_BUFFER_PERCENT).await.unwrap();
+        assert_eq!(estimate.estimated, U256::from(100_000));
+        assert_eq!(estimate.gas_limit, U256::from(120_000));
+        assert!(check_gas_limit(estimate.gas_limit, &estimate));
+        assert!(!check_gas_limit(U256::from(99_999), &estimate));
+        assert!(!check_gas_limit(estimate.block_gas_limit + 1, &estimate));
+
+        let call = &node.requests_for("eth_estimateGas")[0][0];
+        assert_eq!((&call["value"], &call["data"]), (&json!("0x5"), &json!("0xa9059cbb")));
+
+        // The buffer never pushes the limit past the block gas limit
+        node.respond("eth_estimateGas", json!(estimate.block_gas_limit - 1));
+        let estimate = estimate_gas(&provider, &transaction, None, 50).await.unwrap();
+        assert_eq!(estimate.gas_limit, estimate.block_gas_limit);
+        node.respond("eth_estimateGas", json!(estimate.block_gas_limit + 1));
+        let result = estimate_gas(&provider, &transaction, None, 50).await;
+        assert!(matches!(result, Err(GasOptimizationError::ExceedsBlockGasLimit { .. })));
     }
 
-    #[test]
-    fn test_successful_gas_estimation() {
-        let result = estimate_gas(U256::from(10000));
-        assert!(matches!(result, Ok(_)));
+    #[tokio::test]
+    async fn test_estimate_gas_reverts() {
+        let node = MockNode::start().await;
+        let provider = node.provider();
+        let abi = parse_human_readable_abi(&["error Unauthorized(address caller)"]).unwrap();
+        let mut data = abi.errors().next().unwrap().selector().unwrap().to_vec();
+        data.extend_from_slice(&[0u8; 12]);
+        data.extend_from_slice(&[0x11; 20]);
+        let hex: String = data.iter().map(|byte| format!("{:02x}", byte)).collect();
+        node.fail("eth_estimateGas", 3, "execution reverted", Some(json!(format!("0x{}", hex))));
+
+        let transaction = TransactionRequest { from: Address::repeat_byte(1), ..Default::default() };
+        let result = estimate_gas(&provider, &transaction, Some(&abi), DEFAULT_GAS_BUFFER_PERCENT).await;
+        assert!(matches!(result, Err(GasOptimizationError::Reverted(RevertReason::Custom { ref name, .. })) if name == "Unauthorized"));
+
+        node.fail("eth_estimateGas", -32000, "execution reverted: not owner", None);
+        let result = estimate_gas(&provider, &transaction, None, DEFAULT_GAS_BUFFER_PERCENT).await;
+        assert!(matches!(result, Err(GasOptimizationError::Reverted(RevertReason::Error(ref message))) if message == "not owner"));
+
+        node.fail("eth_estimateGas", -32000, "insufficient funds for transfer", None);
+        let result = estimate_gas(&provider, &transaction, None, DEFAULT_GAS_BUFFER_PERCENT).await;
+        assert!(matches!(result, Err(GasOptimizationError::Provider(ProviderError::Rpc { .. }))));
     }
 
     #[test]
@@ -88,4 +409,62 @@ mod tests {
         let optimized_gas = optimize_gas_dynamically(U256::from(50), U256::from(10000));
         assert!(optimized_gas > U256::from(10000));
     }
+
+    #[tokio::test]
+    async fn test_fee_oracle() {
+        let node = MockNode::start().await;
+        let gwei = |fee: u64| json!(format!("{:#x}", fee * 1_000_000_000));
+        node.respond(
+            "eth_feeHistory",
+            json!({
+                "oldestBlock": "0x1",
+                "baseFeePerGas": [gwei(10), gwei(11), gwei(12), gwei(20)],
+                "gasUsedRatio": [0.9, 0.0, 0.8],
+                "reward": [[gwei(1), gwei(2), gwei(5)], [gwei(0), gwei(0), gwei(0)], [gwei(1), gwei(3), gwei(4)]],
+            }),
+        );
+        let provider = node.provider();
+        let presets = FeeOracle::new().with_block_count(3).presets(&provider).await.unwrap();
+        // The empty block is ignored; the median of two values is the upper one
+        assert_eq!(presets.slow.max_priority_fee_per_gas, U256::from(1_000_000_000u64));
+        assert_eq!(presets.normal.max_priority_fee_per_gas, U256::from(3_000_000_000u64));
+        assert_eq!(presets.fast.max_fee_per_gas, U256::from(45_000_000_000u64));
+        assert_eq!(presets.fast.base_fee_per_gas, Some(U256::from(20_000_000_000u64)));
+        assert!(matches!(presets.normal.kind(), TransactionKind::Eip1559 { .. }));
+        assert_eq!(presets.normal.gas_price(), U256::from(23_000_000_000u64));
+
+        let request = &node.requests_for("eth_feeHistory")[0];
+        assert_eq!(request, &json!(["0x3", "latest", [10.0, 50.0, 90.0]]));
+    }
+
+    #[tokio::test]
+    async fn test_fee_oracle_legacy_fallback() {
+        let node = MockNode::start().await;
+        node.respond("eth_gasPrice", json!("0x4a817c800"));
+        node.fail("eth_feeHistory", -32601, "the method eth_feeHistory does not exist/is not available", None);
+        let provider = node.provider();
+        let estimate = FeeOracle::new().estimate(&provider, FeeSpeed::Fast).await.unwrap();
+        let gas_price = U256::from(20_000_000_000u64);
+        assert_eq!(estimate, FeeEstimate { max_fee_per_gas: gas_price, max_priority_fee_per_gas: gas_price, base_fee_per_gas: None });
+        assert_eq!(estimate.kind(), TransactionKind::Legacy { gas_price });
+        assert_eq!(estimate.gas_price(), gas_price);
+
+        // Pre-London blocks report a zero base fee
+        node.respond("eth_feeHistory", json!({ "oldestBlock": "0x0", "baseFeePerGas": ["0x0", "0x0"], "gasUsedRatio": [0.5], "reward": [["0x1", "0x2", "0x3"]] }));
+        assert_eq!(FeeOracle::new().estimate(&provider, FeeSpeed::Slow).await.unwrap().base_fee_per_gas, None);
+
+        // Other errors, such as rate limits, are returned instead of falling back to eth_gasPrice
+        node.fail("eth_feeHistory", -32005, "rate limit exceeded", None);
+        let result = FeeOracle::new().presets(&provider).await;
+        assert!(matches!(result, Err(GasOptimizationError::Provider(ProviderError::Rpc { code: -32005, .. }))));
+        assert_eq!(node.requests_for("eth_gasPrice").len(), 2);
+    }
+
+    #[test]
+    fn test_bump_fee() {
+        assert_eq!(bump_fee(U256::from(1_000_000_000u64), MIN_REPLACEMENT_BUMP_PERCENT), U256::from(1_100_000_000u64));
+        // Rounded up: 10% of 15 is 1.5
+        assert_eq!(bump_fee(U256::from(15), 10), U256::from(17));
+        assert_eq!(bump_fee(U256::zero(), 10), U256::zero());
+    }
 }
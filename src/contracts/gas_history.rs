@@ -0,0 +1,512 @@
+use super::provider::{Provider, ProviderError};
+use crate::framework::logging::{log_debug, log_warn};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use web3::types::{BlockId, BlockNumber, U256};
+use web3::Transport;
+
+/// The most blocks a single `eth_feeHistory` request may cover on geth and most other clients.
+const FEE_HISTORY_MAX_BLOCKS: u64 = 1024;
+
+/// Errors that can occur while storing or collecting gas price history.
+#[derive(Debug)]
+pub enum GasHistoryError {
+    /// The history file could not be read or written.
+    Io(String),
+    Provider(ProviderError),
+}
+
+impl From<ProviderError> for GasHistoryError {
+    fn from(error: ProviderError) -> Self {
+        GasHistoryError::Provider(error)
+    }
+}
+
+/// The fees paid in one block.
+///
+/// # Fields
+/// - `block_number`: The block the sample was taken from.
+/// - `timestamp`: The block timestamp, in seconds since the Unix epoch.
+/// - `base_fee_per_gas`: The base fee of the block; zero on chains without EIP-1559.
+/// - `priority_fee_per_gas`: The median priority fee paid in the block, or the node's gas price
+///   suggestion on chains without EIP-1559.
+/// - `gas_used_ratio`: The share of the block gas limit that was used.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GasSample {
+    pub block_number: u64,
+    pub timestamp: u64,
+    pub base_fee_per_gas: U256,
+    pub priority_fee_per_gas: U256,
+    pub gas_used_ratio: f64,
+}
+
+impl GasSample {
+    /// Returns the price per gas a typical transaction paid in the block: base fee plus priority fee.
+    pub fn gas_price(&self) -> U256 {
+        self.base_fee_per_gas + self.priority_fee_per_gas
+    }
+}
+
+/// The fee a gas history query is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GasMetric {
+    BaseFee,
+    PriorityFee,
+    /// Base fee plus priority fee.
+    GasPrice,
+}
+
+/// A rolling history of per-block fees, persisted as an append-only file of JSON lines.
+///
+/// Samples older than the retention period, measured from the newest sample, are dropped; the
+/// file is rewritten once it holds more dropped samples than live ones. Time windows in queries
+/// also end at the newest sample, so a history that has not been updated for a while still
+/// answers from its most recent data.
+#[derive(Debug)]
+pub struct GasHistory {
+    path: PathBuf,
+    retention: Duration,
+    samples: Vec<GasSample>,
+    /// Lines in the file that no longer belong to a live sample.
+    stale_lines: usize,
+}
+
+impl GasHistory {
+    /// Opens a history file, creating an empty history if it does not exist.
+    ///
+    /// # Arguments
+    /// * `path` - The file the samples are stored in.
+    /// * `retention` - How much history to keep.
+    ///
+    /// A torn last line, as left by a crash while appending, is cut off so that later samples are
+    /// appended on a line of their own.
+    pub fn open(path: &Path, retention: Duration) -> Result<Self, GasHistoryError> {
+        let mut history = GasHistory { path: path.to_path_buf(), retention, samples: Vec::new(), stale_lines: 0 };
+        if !path.exists() {
+            return Ok(history);
+        }
+        let io_error = |e: std::io::Error| GasHistoryError::Io(format!("{}: {}", path.display(), e));
+        let mut contents = fs::read_to_string(path).map_err(io_error)?;
+        if !contents.is_empty() && !contents.ends_with('\n') {
+            let complete = contents.rfind('\n').map_or(0, |end| end + 1);
+            log_warn(&format!("Truncating a torn last line of {}", path.display()));
+            OpenOptions::new().write(true).open(path).and_then(|file| file.set_len(complete as u64)).map_err(io_error)?;
+            contents.truncate(complete);
+        }
+        for (index, line) in contents.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            match serde_json::from_str::<GasSample>(line) {
+                Ok(sample) => history.samples.push(sample),
+                Err(e) => {
+                    log_warn(&format!("Skipping line {} of {}: {}", index + 1, path.display(), e));
+                    history.stale_lines += 1;
+                }
+            }
+        }
+        let lines = history.samples.len();
+        history.samples.sort_by_key(|sample| sample.block_number);
+        history.samples.dedup_by_key(|sample| sample.block_number);
+        history.stale_lines += lines - history.samples.len();
+        history.prune();
+        Ok(history)
+    }
+
+    /// Appends samples for blocks newer than the newest recorded one and drops expired samples.
+    ///
+    /// # Returns
+    /// Result<usize, GasHistoryError> - The number of samples added.
+    pub fn record(&mut self, samples: &[GasSample]) -> Result<usize, GasHistoryError> {
+        let latest = self.latest_block();
+        let mut new: Vec<GasSample> = samples.iter().filter(|sample| Some(sample.block_number) > latest).copied().collect();
+        new.sort_by_key(|sample| sample.block_number);
+        new.dedup_by_key(|sample| sample.block_number);
+        if new.is_empty() {
+            return Ok(0);
+        }
+
+        let mut lines = String::new();
+        for sample in &new {
+            lines += &serde_json::to_string(sample).map_err(|e| GasHistoryError::Io(e.to_string()))?;
+            lines.push('\n');
+        }
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(|e| GasHistoryError::Io(format!("{}: {}", dir.display(), e)))?;
+        }
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(lines.as_bytes()))
+            .map_err(|e| GasHistoryError::Io(format!("{}: {}", self.path.display(), e)))?;
+
+        let added = new.len();
+        self.samples.extend(new);
+        self.prune();
+        if self.stale_lines > self.samples.len() {
+            self.compact()?;
+        }
+        Ok(added)
+    }
+
+    /// Rewrites the file with only the live samples.
+    pub fn compact(&mut self) -> Result<(), GasHistoryError> {
+        let temporary = self.path.with_extension("tmp");
+        let io_error = |e: std::io::Error| GasHistoryError::Io(format!("{}: {}", self.path.display(), e));
+        let mut file = File::create(&temporary).map_err(io_error)?;
+        for sample in &self.samples {
+            let line = serde_json::to_string(sample).map_err(|e| GasHistoryError::Io(e.to_string()))?;
+            writeln!(file, "{}", line).map_err(io_error)?;
+        }
+        file.sync_all().map_err(io_error)?;
+        fs::rename(&temporary, &self.path).map_err(io_error)?;
+        log_debug(&format!("Compacted {} to {} samples", self.path.display(), self.samples.len()));
+        self.stale_lines = 0;
+        Ok(())
+    }
+
+    /// Returns every live sample, oldest first.
+    pub fn samples(&self) -> &[GasSample] {
+        &self.samples
+    }
+
+    /// Returns the number of the newest recorded block.
+    pub fn latest_block(&self) -> Option<u64> {
+        self.samples.last().map(|sample| sample.block_number)
+    }
+
+    /// Returns the samples of the blocks within `window` of the newest sample.
+    pub fn window(&self, window: Duration) -> &[GasSample] {
+        let Some(newest) = self.samples.last() else {
+            return &[];
+        };
+        let since = newest.timestamp.saturating_sub(window.as_secs());
+        let start = self.samples.partition_point(|sample| sample.timestamp < since);
+        &self.samples[start..]
+    }
+
+    /// Returns one fee of every sample within a window, oldest first.
+    pub fn values(&self, metric: GasMetric, window: Duration) -> Vec<U256> {
+        self.window(window)
+            .iter()
+            .map(|sample| match metric {
+                GasMetric::BaseFee => sample.base_fee_per_gas,
+                GasMetric::PriorityFee => sample.priority_fee_per_gas,
+                GasMetric::GasPrice => sample.gas_price(),
+            })
+            .collect()
+    }
+
+    /// Returns a percentile of a fee over a window, using the nearest-rank method.
+    ///
+    /// # Arguments
+    /// * `metric` - The fee to look at.
+    /// * `percentile` - Between 0 and 100; 50 is the median.
+    /// * `window` - How far back from the newest sample to look.
+    ///
+    /// # Returns
+    /// Option<U256> - The percentile, or `None` if the window holds no samples.
+    pub fn percentile(&self, metric: GasMetric, percentile: f64, window: Duration) -> Option<U256> {
+        let mut values = self.values(metric, window);
+        if values.is_empty() {
+            return None;
+        }
+        values.sort();
+        let rank = (percentile.clamp(0.0, 100.0) / 100.0 * values.len() as f64).ceil() as usize;
+        Some(values[rank.saturating_sub(1)])
+    }
+
+    fn prune(&mut self) {
+        let Some(newest) = self.samples.last() else {
+            return;
+        };
+        let cutoff = newest.timestamp.saturating_sub(self.retention.as_secs());
+        let expired = self.samples.partition_point(|sample| sample.timestamp < cutoff);
+        self.samples.drain(..expired);
+        self.stale_lines += expired;
+    }
+}
+
+/// Samples the fees of new blocks into a `GasHistory`.
+///
+/// Base fees and timestamps come from block headers, priority fees from the median reward of
+/// `eth_feeHistory`. On chains without EIP-1559, and on nodes that do not know `eth_feeHistory`,
+/// the part of the node's `eth_gasPrice` above the base fee is recorded as the priority fee
+/// instead.
+#[derive(Debug, Clone)]
+pub struct GasHistoryCollector {
+    backfill_blocks: u64,
+}
+
+impl Default for GasHistoryCollector {
+    fn default() -> Self {
+        GasHistoryCollector::new()
+    }
+}
+
+impl GasHistoryCollector {
+    /// Creates a collector that backfills the last 100 blocks into an empty history.
+    pub fn new() -> Self {
+        GasHistoryCollector { backfill_blocks: 100 }
+    }
+
+    /// Sets how many past blocks are sampled when the history is empty or has fallen further behind.
+    pub fn with_backfill_blocks(mut self, backfill_blocks: u64) -> Self {
+        self.backfill_blocks = backfill_blocks.max(1);
+        self
+    }
+
+    /// Samples every block mined since the newest recorded one, going back at most the backfill
+    /// window from the head.
+    ///
+    /// # Arguments
+    /// * `provider` - The node to read blocks and fee history from.
+    /// * `history` - The history to record into; it is not locked while waiting for the node.
+    ///
+    /// # Returns
+    /// Result<usize, GasHistoryError> - The number of samples recorded.
+    pub async fn collect<T: Transport>(&self, provider: &Provider<T>, history: &Mutex<GasHistory>) -> Result<usize, GasHistoryError> {
+        let latest = provider.block_number().await?.as_u64();
+        let recorded = history.lock().unwrap().latest_block();
+        let window_start = latest.saturating_sub(self.backfill_blocks - 1);
+        let first = recorded.map_or(window_start, |block| (block + 1).max(window_start));
+
+        // Walk back from the head, since the node may cover fewer blocks than asked for
+        let mut samples = Vec::new();
+        let mut legacy_price = None;
+        let mut end = latest;
+        while end >= first {
+            let count = (end - first + 1).min(FEE_HISTORY_MAX_BLOCKS);
+            let fee_history = match provider.fee_history(count, BlockNumber::Number(end.into()), &[50.0]).await {
+                Ok(history) => Some(history),
+                // Nodes predating London do not know the method
+                Err(ProviderError::Rpc { code: -32601, .. }) => None,
+                Err(error) => return Err(error.into()),
+            };
+            let oldest = match fee_history.as_ref().map(|history| history.oldest_block) {
+                Some(BlockNumber::Number(oldest)) => oldest.as_u64(),
+                Some(other) => return Err(ProviderError::InvalidResponse(format!("eth_feeHistory oldest block {:?}", other)).into()),
+                None => end + 1 - count,
+            };
+            if oldest > end {
+                return Err(ProviderError::InvalidResponse(format!("eth_feeHistory covers no block up to {}", end)).into());
+            }
+
+            for number in oldest.max(first)..=end {
+                let Some(block) = provider.block(BlockId::Number(BlockNumber::Number(number.into()))).await? else {
+                    continue;
+                };
+                let reward = fee_history
+                    .as_ref()
+                    .and_then(|history| history.reward.as_ref())
+                    .and_then(|rewards| rewards.get((number - oldest) as usize))
+                    .and_then(|reward| reward.first().copied());
+                let priority_fee_per_gas = match (block.base_fee_per_gas, reward, &fee_history) {
+                    (Some(_), Some(reward), _) => reward,
+                    (Some(_), None, Some(_)) => {
+                        return Err(ProviderError::InvalidResponse(format!("eth_feeHistory has no reward for block {}", number)).into());
+                    }
+                    // Without fee history the priority fee is what the gas price adds to the base fee
+                    (base_fee, _, _) => {
+                        let price = match legacy_price {
+                            Some(price) => price,
+                            None => *legacy_price.insert(provider.gas_price().await?),
+                        };
+                        price.saturating_sub(base_fee.unwrap_or_default())
+                    }
+                };
+                let gas_used_ratio = match block.gas_limit.is_zero() {
+                    true => 0.0,
+                    false => block.gas_used.as_u128() as f64 / block.gas_limit.as_u128() as f64,
+                };
+                samples.push(GasSample {
+                    block_number: number,
+                    timestamp: block.timestamp.as_u64(),
+                    base_fee_per_gas: block.base_fee_per_gas.unwrap_or_default(),
+                    priority_fee_per_gas,
+                    gas_used_ratio,
+                });
+            }
+            match oldest.checked_sub(1) {
+                Some(previous) => end = previous,
+                None => break,
+            }
+        }
+        samples.sort_by_key(|sample| sample.block_number);
+        history.lock().unwrap().record(&samples)
+    }
+
+    /// Runs `collect` in a background task every `interval` until the returned handle is aborted.
+    ///
+    /// Failures are logged and retried on the next tick.
+    pub fn spawn<T>(self, provider: Provider<T>, history: Arc<Mutex<GasHistory>>, interval: Duration) -> JoinHandle<()>
+    where
+        T: Transport + Send + Sync + 'static,
+        T::Out: Send,
+    {
+        tokio::spawn(async move {
+            loop {
+                match self.collect(&provider, &history).await {
+                    Ok(count) => log_debug(&format!("Recorded gas prices of {} block(s)", count)),
+                    Err(error) => log_warn(&format!("Gas price collection failed: {:?}", error)),
+                }
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+}
+
+// Unit test example
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockNode;
+    use serde_json::json;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("wasmify-gas-history-{}-{}", name, std::process::id())).join("gas.jsonl")
+    }
+
+    fn sample(block_number: u64, base_fee: u64, priority_fee: u64) -> GasSample {
+        GasSample {
+            block_number,
+            timestamp: 1_000 + block_number * 12,
+            base_fee_per_gas: U256::from(base_fee),
+            priority_fee_per_gas: U256::from(priority_fee),
+            gas_used_ratio: 0.5,
+        }
+    }
+
+    #[test]
+    fn test_record_and_query() {
+        let path = temp_path("query");
+        let mut history = GasHistory::open(&path, Duration::from_secs(3600)).unwrap();
+        let samples: Vec<_> = (1..=10).map(|block| sample(block, block * 10, 2)).collect();
+        assert_eq!(history.record(&samples).unwrap(), 10);
+        // Blocks already recorded are ignored
+        assert_eq!(history.record(&samples[5..]).unwrap(), 0);
+
+        let minute = Duration::from_secs(60);
+        assert_eq!(history.window(minute).len(), 6);
+        assert_eq!(history.percentile(GasMetric::BaseFee, 50.0, minute), Some(U256::from(70)));
+        assert_eq!(history.percentile(GasMetric::GasPrice, 100.0, minute), Some(U256::from(102)));
+        assert_eq!(history.percentile(GasMetric::PriorityFee, 0.0, Duration::from_secs(3600)), Some(U256::from(2)));
+
+        // The samples survive reopening, and a torn last line is skipped
+        OpenOptions::new().append(true).open(&path).unwrap().write_all(b"{\"block_number\":11,").unwrap();
+        let reopened = GasHistory::open(&path, Duration::from_secs(3600)).unwrap();
+        assert_eq!(reopened.samples(), history.samples());
+        assert_eq!(reopened.latest_block(), Some(10));
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_record_after_torn_line() {
+        let path = temp_path("torn");
+        let mut history = GasHistory::open(&path, Duration::from_secs(3600)).unwrap();
+        history.record(&[sample(1, 1, 1), sample(2, 1, 1)]).unwrap();
+        OpenOptions::new().append(true).open(&path).unwrap().write_all(b"{\"block_number\":3,").unwrap();
+
+        let mut history = GasHistory::open(&path, Duration::from_secs(3600)).unwrap();
+        history.record(&[sample(3, 1, 1), sample(4, 1, 1)]).unwrap();
+        let reopened = GasHistory::open(&path, Duration::from_secs(3600)).unwrap();
+        let blocks: Vec<u64> = reopened.samples().iter().map(|sample| sample.block_number).collect();
+        assert_eq!(blocks, vec![1, 2, 3, 4]);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_retention_and_compaction() {
+        let path = temp_path("retention");
+        // Keep two minutes: 10 blocks of 12 seconds
+        let mut history = GasHistory::open(&path, Duration::from_secs(120)).unwrap();
+        history.record(&(1..=10).map(|block| sample(block, 1, 1)).collect::<Vec<_>>()).unwrap();
+        assert_eq!(history.samples().len(), 10);
+        history.record(&(11..=25).map(|block| sample(block, 1, 1)).collect::<Vec<_>>()).unwrap();
+        assert_eq!(history.samples().first().unwrap().block_number, 15);
+
+        // 14 expired lines outnumbered the 11 live ones, so the file was rewritten
+        let lines = fs::read_to_string(&path).unwrap().lines().count();
+        assert_eq!(lines, 11);
+        assert_eq!(GasHistory::open(&path, Duration::from_secs(120)).unwrap().samples(), history.samples());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    /// Answers `eth_feeHistory` with rewards of 100 plus the block number, covering at most `limit` blocks.
+    fn fee_history(params: &serde_json::Value, limit: u64) -> Result<serde_json::Value, serde_json::Value> {
+        let parse = |value: &serde_json::Value| u64::from_str_radix(value.as_str().unwrap().trim_start_matches("0x"), 16).unwrap();
+        let (newest, count) = (parse(&params[1]), parse(&params[0]).min(limit));
+        let oldest = newest + 1 - count;
+        let reward: Vec<_> = (oldest..=newest).map(|block| json!([format!("{:#x}", 100 + block)])).collect();
+        Ok(json!({
+            "oldestBlock": format!("{:#x}", oldest),
+            "baseFeePerGas": vec!["0x3b9aca00"; count as usize + 1],
+            "gasUsedRatio": vec![0.0; count as usize],
+            "reward": reward,
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_collect_from_node() {
+        let node = MockNode::start().await;
+        node.mine_blocks(5);
+        node.respond_with("eth_feeHistory", |params| fee_history(params, 10));
+        let provider = node.provider();
+        let path = temp_path("collect");
+        let history = Mutex::new(GasHistory::open(&path, Duration::from_secs(86_400)).unwrap());
+
+        let collector = GasHistoryCollector::new().with_backfill_blocks(3);
+        assert_eq!(collector.collect(&provider, &history).await.unwrap(), 3);
+        let recorded = history.lock().unwrap().samples().to_vec();
+        assert_eq!(recorded.iter().map(|sample| sample.block_number).collect::<Vec<_>>(), vec![3, 4, 5]);
+        assert_eq!(recorded[0].base_fee_per_gas, U256::from(1_000_000_000u64));
+        assert_eq!(recorded[2].priority_fee_per_gas, U256::from(105));
+        assert_eq!(recorded[1].timestamp, recorded[0].timestamp + 12);
+
+        // Later runs only sample new blocks
+        node.mine_blocks(2);
+        assert_eq!(collector.collect(&provider, &history).await.unwrap(), 2);
+        assert_eq!(collector.collect(&provider, &history).await.unwrap(), 0);
+        assert_eq!(history.lock().unwrap().latest_block(), Some(7));
+
+        // After a long pause only the backfill window is sampled
+        node.mine_blocks(50);
+        assert_eq!(collector.collect(&provider, &history).await.unwrap(), 3);
+        assert_eq!(node.requests_for("eth_getBlockByNumber").len(), 8);
+        assert_eq!(history.lock().unwrap().latest_block(), Some(57));
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_collect_partial_and_missing_fee_history() {
+        let node = MockNode::start().await;
+        node.mine_blocks(6);
+        let provider = node.provider();
+        let path = temp_path("partial");
+        let history = Mutex::new(GasHistory::open(&path, Duration::from_secs(86_400)).unwrap());
+        let collector = GasHistoryCollector::new().with_backfill_blocks(5);
+
+        // Other errors are not mistaken for a pre-London node
+        node.fail_once("eth_feeHistory", 429, "rate limited", None);
+        assert!(matches!(collector.collect(&provider, &history).await, Err(GasHistoryError::Provider(ProviderError::Rpc { code: 429, .. }))));
+        assert!(history.lock().unwrap().samples().is_empty());
+
+        // A node covering only two blocks per request is asked again for the older ones
+        node.respond_with("eth_feeHistory", |params| fee_history(params, 2));
+        assert_eq!(collector.collect(&provider, &history).await.unwrap(), 5);
+        let recorded = history.lock().unwrap().samples().to_vec();
+        let fees: Vec<(u64, u64)> = recorded.iter().map(|sample| (sample.block_number, sample.priority_fee_per_gas.as_u64())).collect();
+        assert_eq!(fees, vec![(2, 102), (3, 103), (4, 104), (5, 105), (6, 106)]);
+
+        // Without eth_feeHistory the base fee is taken out of the gas price
+        node.reset("eth_feeHistory");
+        node.respond("eth_gasPrice", json!("0x3b9aca07"));
+        node.mine_blocks(1);
+        assert_eq!(collector.collect(&provider, &history).await.unwrap(), 1);
+        assert_eq!(history.lock().unwrap().samples()[5].priority_fee_per_gas, U256::from(7));
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,367 @@
+use crate::framework::logging::log_warn;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use web3::signing::keccak256;
+use web3::types::{Address, H256, U256};
+
+/// Setting this environment variable to a file path turns on gas reporting for the whole
+/// process and appends every measurement to that file, so the test binaries of one session can
+/// be combined with `GasReport::load`.
+pub const GAS_REPORT_ENV: &str = "WASMIFY_GAS_REPORT";
+
+/// The function name deployments are reported under.
+pub const DEPLOYMENT: &str = "(deployment)";
+
+/// Errors that can occur while reading or writing gas reports.
+#[derive(Debug)]
+pub enum GasReportError {
+    Io(String),
+    InvalidReport(String),
+}
+
+/// The gas used by a successful deployment or contract call; one line of a gas report file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Entry {
+    contract: String,
+    function: String,
+    gas_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct Recorder {
+    entries: Vec<Entry>,
+    /// Contract names by init code hash.
+    code_names: HashMap<[u8; 32], String>,
+    /// Contract names by address, learned from deployments or set explicitly.
+    address_names: HashMap<Address, String>,
+    file: Option<PathBuf>,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static RECORDER: OnceLock<Mutex<Recorder>> = OnceLock::new();
+
+fn recorder() -> &'static Mutex<Recorder> {
+    RECORDER.get_or_init(|| {
+        let file = std::env::var_os(GAS_REPORT_ENV).filter(|path| !path.is_empty()).map(PathBuf::from);
+        if file.is_some() {
+            ENABLED.store(true, Ordering::Relaxed);
+        }
+        Mutex::new(Recorder { file, ..Default::default() })
+    })
+}
+
+/// Starts recording the gas used by deployments and contract calls made through this crate.
+///
+/// Recording is also on when `WASMIFY_GAS_REPORT` is set.
+pub fn enable() {
+    recorder();
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Stops recording gas usage.
+pub fn disable() {
+    recorder();
+    ENABLED.store(false, Ordering::Relaxed);
+}
+
+/// Returns whether gas usage is being recorded.
+pub fn is_enabled() -> bool {
+    recorder();
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Names the contract deployed with some init code, so its deployments and calls are reported
+/// under that name instead of a hash of the code.
+///
+/// # Arguments
+/// * `init_code` - The deployment data, constructor arguments included.
+/// * `name` - The name to report the contract under.
+pub fn name_contract(init_code: &[u8], name: &str) {
+    recorder().lock().unwrap().code_names.insert(keccak256(init_code), name.to_string());
+}
+
+/// Names a contract that was not deployed in this session, so calls to it are reported under
+/// that name instead of its address.
+pub fn name_address(address: Address, name: &str) {
+    recorder().lock().unwrap().address_names.insert(address, name.to_string());
+}
+
+/// Records a successful deployment; called by the deploy functions.
+pub(crate) fn record_deployment(init_code: &[u8], address: Address, gas_used: U256) {
+    if !is_enabled() {
+        return;
+    }
+    let mut recorder = recorder().lock().unwrap();
+    let hash = keccak256(init_code);
+    let contract = recorder
+        .code_names
+        .get(&hash)
+        .cloned()
+        .unwrap_or_else(|| format!("{:?}", H256(hash))[..10].to_string());
+    recorder.address_names.insert(address, contract.clone());
+    recorder.push(Entry { contract, function: DEPLOYMENT.to_string(), gas_used: gas_used.low_u64() });
+}
+
+/// Records a successful contract call; called by the interaction functions.
+pub(crate) fn record_call(address: Address, function: &str, gas_used: U256) {
+    if !is_enabled() {
+        return;
+    }
+    let mut recorder = recorder().lock().unwrap();
+    let contract = recorder.address_names.get(&address).cloned().unwrap_or_else(|| format!("{:?}", address));
+    recorder.push(Entry { contract, function: function.to_string(), gas_used: gas_used.low_u64() });
+}
+
+impl Recorder {
+    fn push(&mut self, entry: Entry) {
+        if let Some(path) = &self.file {
+            let line = serde_json::to_string(&entry).expect("gas entries serialize") + "\n";
+            let written = OpenOptions::new().create(true).append(true).open(path).and_then(|mut file| file.write_all(line.as_bytes()));
+            if let Err(e) = written {
+                log_warn(&format!("Could not append to gas report {}: {}", path.display(), e));
+            }
+        }
+        self.entries.push(entry);
+    }
+}
+
+/// Returns the report of everything recorded by this process so far.
+pub fn report() -> GasReport {
+    GasReport::from_entries(&recorder().lock().unwrap().entries)
+}
+
+/// Forgets everything recorded by this process so far; contract names are kept.
+pub fn reset() {
+    recorder().lock().unwrap().entries.clear();
+}
+
+/// Gas usage statistics of one contract function.
+///
+/// # Fields
+/// - `calls`: How many successful calls were measured.
+/// - `min`, `avg`, `median`, `max`: The gas used by those calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GasStats {
+    pub calls: u64,
+    pub min: u64,
+    pub avg: u64,
+    pub median: u64,
+    pub max: u64,
+}
+
+impl GasStats {
+    fn from_samples(mut samples: Vec<u64>) -> Self {
+        samples.sort_unstable();
+        let count = samples.len();
+        let middle = count / 2;
+        let median = match count.is_multiple_of(2) {
+            true => (samples[middle - 1] + samples[middle]) / 2,
+            false => samples[middle],
+        };
+        GasStats {
+            calls: count as u64,
+            min: samples[0],
+            avg: samples.iter().sum::<u64>() / count as u64,
+            median,
+            max: samples[count - 1],
+        }
+    }
+}
+
+/// Gas usage per contract and function, e.g. collected over a test session.
+///
+/// Serializes to `{"contracts": {"<contract>": {"<function>": {"calls": .., "min": .., ...}}}}`
+/// with sorted keys, so reports of two runs can be diffed directly.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GasReport {
+    pub contracts: BTreeMap<String, BTreeMap<String, GasStats>>,
+}
+
+/// A function whose average gas usage differs from the baseline.
+///
+/// # Fields
+/// - `contract`, `function`: The function that changed.
+/// - `baseline`: The average gas of the baseline, `None` for a new function.
+/// - `current`: The average gas of this report, `None` for a function that is no longer called.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GasDiff {
+    pub contract: String,
+    pub function: String,
+    pub baseline: Option<u64>,
+    pub current: Option<u64>,
+}
+
+impl GasDiff {
+    /// Returns the change in average gas relative to the baseline, in percent.
+    pub fn change_percent(&self) -> Option<f64> {
+        match (self.baseline, self.current) {
+            (Some(baseline), Some(current)) if baseline > 0 => Some((current as f64 - baseline as f64) * 100.0 / baseline as f64),
+            _ => None,
+        }
+    }
+}
+
+impl GasReport {
+    fn from_entries(entries: &[Entry]) -> Self {
+        let mut samples: BTreeMap<String, BTreeMap<String, Vec<u64>>> = BTreeMap::new();
+        for Entry { contract, function, gas_used } in entries {
+            samples.entry(contract.clone()).or_default().entry(function.clone()).or_default().push(*gas_used);
+        }
+        let contracts = samples
+            .into_iter()
+            .map(|(contract, functions)| {
+                let stats = functions.into_iter().map(|(function, gas)| (function, GasStats::from_samples(gas))).collect();
+                (contract, stats)
+            })
+            .collect();
+        GasReport { contracts }
+    }
+
+    /// Builds a report from the file written through `WASMIFY_GAS_REPORT`.
+    ///
+    /// # Arguments
+    /// * `path` - The file every process of the session appended its measurements to.
+    pub fn load(path: &Path) -> Result<GasReport, GasReportError> {
+        let contents = fs::read_to_string(path).map_err(|e| GasReportError::Io(format!("{}: {}", path.display(), e)))?;
+        let entries = contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(|e| GasReportError::InvalidReport(format!("{}: {}", path.display(), e))))
+            .collect::<Result<Vec<Entry>, _>>()?;
+        Ok(GasReport::from_entries(&entries))
+    }
+
+    /// Parses a report saved with `to_json`, e.g. a committed baseline.
+    pub fn from_json(json: &str) -> Result<GasReport, GasReportError> {
+        serde_json::from_str(json).map_err(|e| GasReportError::InvalidReport(e.to_string()))
+    }
+
+    /// Serializes the report for CI artifacts and baselines.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("gas reports serialize")
+    }
+
+    /// Returns the functions whose average gas changed since a baseline, including functions that
+    /// were added or are no longer called.
+    pub fn diff(&self, baseline: &GasReport) -> Vec<GasDiff> {
+        let mut keys: Vec<(&String, &String)> = self
+            .contracts
+            .iter()
+            .chain(&baseline.contracts)
+            .flat_map(|(contract, functions)| functions.keys().map(move |function| (contract, function)))
+            .collect();
+        keys.sort();
+        keys.dedup();
+
+        let average = |report: &GasReport, contract: &String, function: &String| {
+            report.contracts.get(contract).and_then(|functions| functions.get(function)).map(|stats| stats.avg)
+        };
+        keys.into_iter()
+            .filter_map(|(contract, function)| {
+                let (baseline, current) = (average(baseline, contract, function), average(self, contract, function));
+                (baseline != current).then(|| GasDiff { contract: contract.clone(), function: function.clone(), baseline, current })
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for GasReport {
+    /// Formats the report as one table per contract.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let headers = ["Function", "Min", "Avg", "Median", "Max", "Calls"];
+        for (contract, functions) in &self.contracts {
+            let rows: Vec<[String; 6]> = functions
+                .iter()
+                .map(|(function, stats)| {
+                    [function.clone(), stats.min.to_string(), stats.avg.to_string(), stats.median.to_string(), stats.max.to_string(), stats.calls.to_string()]
+                })
+                .collect();
+            let widths: Vec<usize> = (0..headers.len())
+                .map(|column| rows.iter().map(|row| row[column].len()).chain([headers[column].len()]).max().unwrap_or(0))
+                .collect();
+            let separator: String = widths.iter().map(|width| format!("+{}", "-".repeat(width + 2))).collect::<String>() + "+";
+
+            writeln!(f, "{}", contract)?;
+            writeln!(f, "{}", separator)?;
+            let header: Vec<String> = headers.iter().map(|header| header.to_string()).collect();
+            for (index, row) in std::iter::once(header.as_slice()).chain(rows.iter().map(|row| row.as_slice())).enumerate() {
+                for (column, cell) in row.iter().enumerate() {
+                    match column {
+                        0 => write!(f, "| {:<width$} ", cell, width = widths[column])?,
+                        _ => write!(f, "| {:>width$} ", cell, width = widths[column])?,
+                    }
+                }
+                writeln!(f, "|")?;
+                if index == 0 {
+                    writeln!(f, "{}", separator)?;
+                }
+            }
+            writeln!(f, "{}", separator)?;
+        }
+        Ok(())
+    }
+}
+
+// Unit test example
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gas(contract: &str, function: &str, gas_used: u64) -> Entry {
+        Entry { contract: contract.to_string(), function: function.to_string(), gas_used }
+    }
+
+    #[test]
+    fn test_statistics_and_table() {
+        let report = GasReport::from_entries(&[
+            gas("Token", "transfer(address,uint256)", 51_000),
+            gas("Token", "transfer(address,uint256)", 34_000),
+            gas("Token", "transfer(address,uint256)", 34_500),
+            gas("Token", "transfer(address,uint256)", 29_000),
+            gas("Token", DEPLOYMENT, 1_200_000),
+        ]);
+        let stats = report.contracts["Token"]["transfer(address,uint256)"];
+        assert_eq!(stats, GasStats { calls: 4, min: 29_000, avg: 37_125, median: 34_250, max: 51_000 });
+
+        let table = report.to_string();
+        assert!(table.starts_with("Token\n+"));
+        assert!(table.contains("| (deployment)              | 1200000 | 1200000 | 1200000 | 1200000 |     1 |\n"));
+        assert!(table.contains("| transfer(address,uint256) |   29000 |   37125 |   34250 |   51000 |     4 |\n"));
+        assert_eq!(GasReport::from_json(&report.to_json()).unwrap(), report);
+    }
+
+    #[test]
+    fn test_diff_against_baseline() {
+        let baseline = GasReport::from_entries(&[gas("Token", "transfer", 50_000), gas("Token", "approve", 46_000), gas("Vault", "deposit", 80_000)]);
+        let current = GasReport::from_entries(&[gas("Token", "transfer", 45_000), gas("Token", "approve", 46_000), gas("Token", "permit", 70_000)]);
+        let diff = current.diff(&baseline);
+        assert_eq!(diff.len(), 3);
+        assert_eq!((diff[0].function.as_str(), diff[0].baseline, diff[0].current), ("permit", None, Some(70_000)));
+        assert_eq!((diff[1].function.as_str(), diff[1].change_percent()), ("transfer", Some(-10.0)));
+        assert_eq!((diff[2].contract.as_str(), diff[2].current), ("Vault", None));
+        assert!(current.diff(&current).is_empty());
+    }
+
+    #[test]
+    fn test_recording() {
+        enable();
+        let init_code = b"gas report test contract";
+        name_contract(init_code, "GasReportTest");
+        let address = Address::repeat_byte(0x6a);
+        record_deployment(init_code, address, U256::from(100_000));
+        record_call(address, "store(uint256)", U256::from(43_000));
+        record_call(address, "store(uint256)", U256::from(26_000));
+
+        // Other tests may record concurrently, so only look at this contract
+        let functions = &report().contracts["GasReportTest"];
+        assert_eq!(functions[DEPLOYMENT].max, 100_000);
+        assert_eq!(functions["store(uint256)"].calls, 2);
+        assert_eq!(functions["store(uint256)"].median, 34_500);
+    }
+}
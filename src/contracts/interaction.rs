@@ -1,76 +1,261 @@
+use crate::contracts::abi::{decode_revert, Abi, AbiFunction, AbiValue, CodecError, ResolveError, RevertReason};
+use crate::contracts::gas_report;
+use crate::contracts::provider::{call_request, Provider, ProviderError};
 use crate::framework::logging::{log_info, log_error};
-use web3::contract::{Contract, Options};
-use web3::types::{Address, U256};
+use web3::types::{Address, BlockNumber, Bytes, CallRequest, TransactionReceipt, TransactionRequest, U64};
+use web3::Transport;
 use std::str::FromStr;
 
 /// Errors that can occur during contract interactions.
 #[derive(Debug)]
 pub enum InteractionError {
     InvalidAddress,
+    UnknownFunction(ResolveError),
+    InvalidArguments(CodecError),
+    InvalidReturnData(CodecError),
+    Provider(ProviderError),
     FunctionCallFailed,
+    Reverted(RevertReason),
+}
+
+impl InteractionError {
+    /// Builds a `Reverted` error from the revert data of a failed call, decoding custom errors with the ABI.
+    ///
+    /// # Arguments
+    /// * `revert_data` - The raw data returned by the reverted call.
+    /// * `abi` - The ABI of the called contract.
+    pub fn from_revert_data(revert_data: &[u8], abi: &Abi) -> Self {
+        let reason = decode_revert(revert_data, Some(abi));
+        log_error(&format!("Contract call {}", reason));
+        InteractionError::Reverted(reason)
+    }
+
+    /// Maps a node error, decoding the revert reason when the node attached revert data.
+    fn from_provider(error: ProviderError, abi: &Abi) -> Self {
+        match error.revert_data() {
+            Some(data) => InteractionError::from_revert_data(data, abi),
+            None => InteractionError::Provider(error),
+        }
+    }
+}
+
+/// Validates the contract address and builds the calldata for a call to one of its functions.
+fn prepare_call<'a>(
+    contract_address: &str,
+    abi: &'a Abi,
+    function_name: &str,
+    params: &[AbiValue],
+) -> Result<(Address, &'a AbiFunction, Vec<u8>), InteractionError> {
+    // Input validation: ensure contract address is valid
+    let contract = Address::from_str(contract_address).map_err(|_| InteractionError::InvalidAddress)?;
+
+    // Resolve the function against the ABI (disambiguating overloads by the arguments) and build the calldata
+    let function = abi
+        .resolve_function(function_name, params)
+        .map_err(InteractionError::UnknownFunction)?;
+    let calldata = function
+        .encode_call(params)
+        .map_err(InteractionError::InvalidArguments)?;
+
+    Ok((contract, function, calldata))
 }
 
 /// Calls a function of a smart contract with security checks and error handling.
-/// 
+///
+/// The call is sent as a transaction with `Provider::send_transaction`, signed locally when the
+/// provider has a signer for `sender_address`, and the function waits until it has been mined.
+/// When the mined transaction reverted, the call is replayed with `eth_call` against the state
+/// before its block to recover the revert reason.
+///
 /// # Arguments
+/// * `provider` - The node to send the transaction through.
 /// * `contract_address` - The address of the contract.
-/// * `function_name` - The name of the function to call.
+/// * `abi` - The ABI of the contract, used to resolve the function and encode its arguments.
+/// * `function_name` - The name, signature or selector of the function to call.
 /// * `params` - Parameters to pass to the function.
+/// * `sender_address` - The address sending the transaction.
 ///
 /// # Returns
-/// Result<(), InteractionError> - Returns Ok if the function call succeeds, otherwise returns an error.
-pub async fn call_contract_function(
+/// Result<TransactionReceipt, InteractionError> - The receipt of the mined transaction, otherwise an error.
+pub async fn call_contract_function<T: Transport>(
+    provider: &Provider<T>,
     contract_address: &str,
+    abi: &Abi,
     function_name: &str,
-    params: Vec<U256>,
-) -> Result<(), InteractionError> {
-    // Input validation: ensure contract address is valid
-    if Address::from_str(contract_address).is_err() {
-        return Err(InteractionError::InvalidAddress);
-    }
+    params: Vec<AbiValue>,
+    sender_address: &str,
+) -> Result<TransactionReceipt, InteractionError> {
+    let (contract, function, calldata) = prepare_call(contract_address, abi, function_name, &params)?;
+    let sender = Address::from_str(sender_address).map_err(|_| InteractionError::InvalidAddress)?;
 
     // Correct log_info usage with formatted message
-    log_info(&format!("Calling contract function: {}", function_name));
+    log_info(&format!(
+        "Calling contract function: {} ({} bytes of calldata)",
+        function.name,
+        calldata.len()
+    ));
 
-    // Simulate contract function call (real logic would go here)
-    let function_call_succeeded = function_name != "failFunction"; // Simulate failure for certain function names
+    let transaction = TransactionRequest {
+        from: sender,
+        to: Some(contract),
+        data: Some(Bytes(calldata)),
+        ..Default::default()
+    };
+    let replay = call_request(&transaction);
+    let hash = provider
+        .send_transaction(transaction)
+        .await
+        .map_err(|e| InteractionError::from_provider(e, abi))?;
+    let receipt = provider.wait_for_receipt(hash).await.map_err(InteractionError::Provider)?;
 
-    if function_call_succeeded {
+    if receipt.status == Some(U64::one()) {
         // Correct log_info usage
-        log_info(&format!("Function call to {} succeeded.", function_name));
-        Ok(())
+        log_info(&format!("Function call to {} succeeded.", function.name));
+        let signature = function.signature().unwrap_or_else(|_| function.name.clone());
+        gas_report::record_call(contract, &signature, receipt.gas_used.unwrap_or_default());
+        Ok(receipt)
     } else {
         // Correct log_error usage
-        log_error(&format!("Function call to {} failed.", function_name));
-        Err(InteractionError::FunctionCallFailed)
+        log_error(&format!("Function call to {} failed in transaction {:?}.", function.name, hash));
+        Err(replay_revert(provider, replay, &receipt, abi).await)
     }
 }
 
-pub fn fetch_contract_data() {
-    // Contract data fetching logic would go here
+/// Replays a reverted transaction with `eth_call` on top of its parent block and decodes the
+/// revert reason.
+///
+/// Falls back to `FunctionCallFailed` when the replay does not revert with data, for example
+/// because the node no longer has the state of the parent block.
+async fn replay_revert<T: Transport>(
+    provider: &Provider<T>,
+    call: CallRequest,
+    receipt: &TransactionReceipt,
+    abi: &Abi,
+) -> InteractionError {
+    let Some(block) = receipt.block_number else {
+        return InteractionError::FunctionCallFailed;
+    };
+    let parent = block.saturating_sub(U64::one());
+    match provider.call_at(call, BlockNumber::Number(parent)).await {
+        Err(error) => match error.revert_data() {
+            Some(data) => InteractionError::from_revert_data(data, abi),
+            None => InteractionError::FunctionCallFailed,
+        },
+        Ok(_) => InteractionError::FunctionCallFailed,
+    }
+}
+
+/// Reads data from a smart contract by executing one of its functions with `eth_call`.
+///
+/// # Arguments
+/// * `provider` - The node to query.
+/// * `contract_address` - The address of the contract.
+/// * `abi` - The ABI of the contract, used to resolve the function and decode its return values.
+/// * `function_name` - The name, signature or selector of the function to call.
+/// * `params` - Parameters to pass to the function.
+///
+/// # Returns
+/// Result<Vec<AbiValue>, InteractionError> - The decoded return values, otherwise an error.
+pub async fn fetch_contract_data<T: Transport>(
+    provider: &Provider<T>,
+    contract_address: &str,
+    abi: &Abi,
+    function_name: &str,
+    params: Vec<AbiValue>,
+) -> Result<Vec<AbiValue>, InteractionError> {
+    let (contract, function, calldata) = prepare_call(contract_address, abi, function_name, &params)?;
+
+    let call = CallRequest {
+        to: Some(contract),
+        data: Some(Bytes(calldata)),
+        ..Default::default()
+    };
+    let output = provider
+        .call(call)
+        .await
+        .map_err(|e| InteractionError::from_provider(e, abi))?;
+
+    function
+        .decode_output(&output.0)
+        .map_err(InteractionError::InvalidReturnData)
 }
 
 // Unit test example
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::contracts::abi::parse_abi;
+    use crate::testing::MockNode;
+    use serde_json::json;
     use web3::types::U256;
 
+    const SENDER: &str = "0x00000000000000000000000000000000000000aa";
+
+    fn test_abi() -> Abi {
+        parse_abi(r#"[
+            { "type": "function", "name": "testFunction", "inputs": [{ "name": "x", "type": "uint256" }], "outputs": [], "stateMutability": "nonpayable" },
+            { "type": "function", "name": "failFunction", "inputs": [{ "name": "x", "type": "uint256" }], "outputs": [], "stateMutability": "nonpayable" }
+        ]"#).unwrap()
+    }
+
+    fn offline_provider() -> Provider {
+        Provider::http("http://127.0.0.1:1").unwrap()
+    }
+
     #[tokio::test]
     async fn test_invalid_contract_address() {
-        let result = call_contract_function("invalid", "testFunction", vec![U256::from(1)]).await;
+        let result = call_contract_function(&offline_provider(), "invalid", &test_abi(), "testFunction", vec![AbiValue::Uint(U256::from(1))], SENDER).await;
         assert!(matches!(result, Err(InteractionError::InvalidAddress)));
     }
 
+    #[test]
+    fn test_revert_error_decoding() {
+        let abi = parse_abi(r#"[{ "type": "error", "name": "Unauthorized", "inputs": [] }]"#).unwrap();
+        let selector = abi.errors().next().unwrap().selector().unwrap();
+        let error = InteractionError::from_revert_data(&selector, &abi);
+        assert!(matches!(error, InteractionError::Reverted(RevertReason::Custom { ref name, .. }) if name == "Unauthorized"));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_function_and_bad_arguments() {
+        let address = "0x1234567890abcdef1234567890abcdef12345678";
+        let result = call_contract_function(&offline_provider(), address, &test_abi(), "missingFunction", vec![], SENDER).await;
+        assert!(matches!(result, Err(InteractionError::UnknownFunction(ResolveError::NotFound(_)))));
+
+        let result = fetch_contract_data(&offline_provider(), address, &test_abi(), "testFunction", vec![AbiValue::Bool(true)]).await;
+        assert!(matches!(result, Err(InteractionError::InvalidArguments(_))));
+    }
+
     #[tokio::test]
-    async fn test_successful_function_call() {
-        let result = call_contract_function("0x1234567890abcdef1234567890abcdef12345678", "testFunction", vec![U256::from(1)]).await;
-        assert!(matches!(result, Ok(())));
+    async fn test_mined_revert_is_replayed() {
+        let abi = parse_abi(r#"[
+            { "type": "function", "name": "testFunction", "inputs": [{ "name": "x", "type": "uint256" }], "outputs": [], "stateMutability": "nonpayable" },
+            { "type": "error", "name": "Unauthorized", "inputs": [] }
+        ]"#).unwrap();
+        let selector = abi.errors().next().unwrap().selector().unwrap();
+        let hash = format!("0x{}", "11".repeat(32));
+        let node = MockNode::start().await;
+        node.respond("eth_sendTransaction", json!(hash));
+        node.respond("eth_getTransactionReceipt", json!({
+            "transactionHash": hash, "transactionIndex": "0x0", "blockHash": format!("0x{}", "22".repeat(32)),
+            "blockNumber": "0x7", "cumulativeGasUsed": "0x5208", "gasUsed": "0x5208", "logs": [],
+            "logsBloom": format!("0x{}", "00".repeat(256)), "status": "0x0"
+        }));
+        node.fail("eth_call", 3, "execution reverted", Some(json!(Bytes(selector.to_vec()))));
+
+        let address = "0x1234567890abcdef1234567890abcdef12345678";
+        let result = call_contract_function(&node.provider(), address, &abi, "testFunction", vec![AbiValue::Uint(U256::from(1))], SENDER).await;
+        assert!(matches!(result, Err(InteractionError::Reverted(RevertReason::Custom { ref name, .. })) if name == "Unauthorized"));
+
+        let replay = &node.requests_for("eth_call")[0];
+        assert_eq!(replay[0]["from"], json!(SENDER));
+        assert_eq!(replay[1], json!("0x6"));
     }
 
     #[tokio::test]
-    async fn test_function_call_failure() {
-        let result = call_contract_function("0x1234567890abcdef1234567890abcdef12345678", "failFunction", vec![U256::from(1)]).await;
-        assert!(matches!(result, Err(InteractionError::FunctionCallFailed)));
+    async fn test_unreachable_node() {
+        let address = "0x1234567890abcdef1234567890abcdef12345678";
+        let result = call_contract_function(&offline_provider(), address, &test_abi(), "failFunction", vec![AbiValue::Uint(U256::from(1))], SENDER).await;
+        assert!(matches!(result, Err(InteractionError::Provider(ProviderError::Transport(_)))));
     }
 }
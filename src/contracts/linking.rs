@@ -0,0 +1,125 @@
+use crate::contracts::provider::decode_hex;
+use std::collections::HashMap;
+use web3::signing::keccak256;
+use web3::types::Address;
+
+/// The length in hex characters of a library placeholder, the size of the address it stands for.
+const PLACEHOLDER_LEN: usize = 40;
+
+/// Errors that can occur while linking libraries into bytecode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkError {
+    /// Placeholders left in the bytecode after linking, as they appear in it.
+    UnresolvedLibraries(Vec<String>),
+    /// The bytecode is not valid hex once every placeholder has been replaced.
+    InvalidBytecode,
+}
+
+/// Returns the placeholder solc (>= 0.5) emits for a library: `__$` + the first 34 hex characters
+/// of `keccak256(fully_qualified_name)` + `$__`.
+///
+/// # Arguments
+/// * `fully_qualified_name` - The source path and library name, e.g. `contracts/Math.sol:Math`.
+pub fn library_placeholder(fully_qualified_name: &str) -> String {
+    let hash = keccak256(fully_qualified_name.as_bytes());
+    let hex: String = hash[..17].iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("__${}$__", hex)
+}
+
+/// Returns the placeholder solc < 0.5 emits for a library: `__` + the name truncated to 36
+/// characters, padded with underscores to 40 characters.
+fn legacy_placeholder(fully_qualified_name: &str) -> String {
+    let name: String = fully_qualified_name.chars().take(PLACEHOLDER_LEN - 4).collect();
+    format!("__{:_<width$}", name, width = PLACEHOLDER_LEN - 2)
+}
+
+/// Lists the distinct library placeholders left in a hex bytecode string, in order of appearance.
+///
+/// # Arguments
+/// * `bytecode` - The bytecode as emitted by the compiler, with or without a `0x` prefix.
+pub fn unlinked_placeholders(bytecode: &str) -> Vec<String> {
+    let mut placeholders: Vec<String> = Vec::new();
+    let mut rest = bytecode;
+    while let Some(start) = rest.find("__") {
+        let candidate = rest.get(start..start + PLACEHOLDER_LEN);
+        match candidate {
+            Some(candidate) => {
+                if !placeholders.iter().any(|known| known == candidate) {
+                    placeholders.push(candidate.to_string());
+                }
+                rest = &rest[start + PLACEHOLDER_LEN..];
+            }
+            None => {
+                placeholders.push(rest[start..].to_string());
+                break;
+            }
+        }
+    }
+    placeholders
+}
+
+/// Replaces the library placeholders of compiler output with deployed library addresses and decodes the result.
+///
+/// Both the hashed placeholders of current solc releases and the name-based ones of solc < 0.5
+/// are recognised. Libraries that the bytecode does not reference are ignored.
+///
+/// # Arguments
+/// * `bytecode` - The hex bytecode as emitted by the compiler, with or without a `0x` prefix.
+/// * `libraries` - Deployed library addresses keyed by fully qualified name, e.g. `contracts/Math.sol:Math`.
+///
+/// # Returns
+/// Result<Vec<u8>, LinkError> - The linked bytecode, or the placeholders no address was given for.
+pub fn link_bytecode(bytecode: &str, libraries: &HashMap<String, Address>) -> Result<Vec<u8>, LinkError> {
+    let mut linked = bytecode.trim().trim_start_matches("0x").to_string();
+    for (name, address) in libraries {
+        let hex: String = address.as_bytes().iter().map(|byte| format!("{:02x}", byte)).collect();
+        linked = linked.replace(&library_placeholder(name), &hex).replace(&legacy_placeholder(name), &hex);
+    }
+
+    let unresolved = unlinked_placeholders(&linked);
+    if !unresolved.is_empty() {
+        return Err(LinkError::UnresolvedLibraries(unresolved));
+    }
+    decode_hex(&linked).ok_or(LinkError::InvalidBytecode)
+}
+
+// Unit test example
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MATH: &str = "contracts/Math.sol:Math";
+
+    #[test]
+    fn test_placeholders() {
+        let placeholder = library_placeholder(MATH);
+        assert_eq!(placeholder.len(), PLACEHOLDER_LEN);
+        assert!(placeholder.starts_with("__$") && placeholder.ends_with("$__"));
+        assert_eq!(legacy_placeholder("Math"), format!("__Math{}", "_".repeat(34)));
+        assert_eq!(legacy_placeholder(MATH).len(), PLACEHOLDER_LEN);
+
+        let bytecode = format!("0x73{}3014{}73{}", placeholder, placeholder, legacy_placeholder("Other"));
+        assert_eq!(unlinked_placeholders(&bytecode), vec![placeholder, legacy_placeholder("Other")]);
+    }
+
+    #[test]
+    fn test_link_bytecode() {
+        let bytecode = format!("0x6080{}5073{}", library_placeholder(MATH), legacy_placeholder("Strings"));
+        let mut libraries = HashMap::new();
+        libraries.insert(MATH.to_string(), Address::repeat_byte(0x11));
+
+        let result = link_bytecode(&bytecode, &libraries);
+        assert_eq!(result, Err(LinkError::UnresolvedLibraries(vec![legacy_placeholder("Strings")])));
+
+        libraries.insert("Strings".to_string(), Address::repeat_byte(0x22));
+        libraries.insert("contracts/Unused.sol:Unused".to_string(), Address::repeat_byte(0x33));
+        let linked = link_bytecode(&bytecode, &libraries).unwrap();
+        let mut expected = vec![0x60, 0x80];
+        expected.extend_from_slice(&[0x11; 20]);
+        expected.extend_from_slice(&[0x50, 0x73]);
+        expected.extend_from_slice(&[0x22; 20]);
+        assert_eq!(linked, expected);
+
+        assert_eq!(link_bytecode("0x60zz", &HashMap::new()), Err(LinkError::InvalidBytecode));
+    }
+}
@@ -1,12 +1,21 @@
 //! This module provides functionalities for managing and interacting with smart contracts.
-//! It includes deployment, interaction, updating, gas management, ABI parsing, 
-//! event watching, and contract monitoring features.
+//! It includes deployment, library linking, interaction, updating, gas management, ABI parsing, 
+//! event watching, and contract monitoring features, all talking to a node through a `Provider`,
+//! which signs locally and allocates nonces for concurrent senders, and a tracker that speeds up
+//! or cancels stuck transactions. Gas prices can be sampled into a persistent history for
+//! percentile queries, and the gas used by deployments and calls can be reported per function.
 
 // Module declarations
 pub mod deploy;
+pub mod linking;
 pub mod interaction;
 pub mod contract_update;
 pub mod gas;
+pub mod gas_history;
+pub mod gas_report;
 pub mod abi;
 pub mod watch;
-pub mod monitor;
\ No newline at end of file
+pub mod monitor;
+pub mod provider;
+pub mod nonce;
+pub mod tracker;
\ No newline at end of file
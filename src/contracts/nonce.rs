@@ -0,0 +1,204 @@
+use super::provider::{Provider, ProviderError};
+use crate::framework::logging::log_debug;
+use std::collections::{BTreeSet, HashMap};
+use tokio::sync::Mutex;
+use web3::types::{Address, BlockNumber, U256};
+use web3::Transport;
+
+/// Hands out sequential nonces for accounts that send many transactions concurrently.
+///
+/// The pending nonce of an account is fetched from the node once; after that nonces are
+/// allocated locally, so parallel senders never race on `eth_getTransactionCount`. A nonce whose
+/// transaction failed before reaching the node is `release`d and handed out again, so no gap is
+/// left. When the node rejects a nonce as already used, or a broadcast failed in transit so the
+/// transaction may be pending after all, the account is `resync`ed and the next allocation asks
+/// the node again.
+///
+/// Every `Provider` owns one, shared by its clones, and uses it for transactions it signs
+/// locally; share one between providers with `Provider::with_nonce_manager`.
+#[derive(Debug, Default)]
+pub struct NonceManager {
+    accounts: Mutex<HashMap<Address, AccountNonces>>,
+}
+
+/// The allocation state of one account.
+#[derive(Debug)]
+struct AccountNonces {
+    /// The nonce after the highest one allocated.
+    next: U256,
+    /// Nonces below `next` that were released and are handed out again first.
+    released: BTreeSet<U256>,
+}
+
+impl NonceManager {
+    /// Creates a manager that has not fetched any nonce yet.
+    pub fn new() -> Self {
+        NonceManager::default()
+    }
+
+    /// Allocates the next nonce of an account.
+    ///
+    /// # Arguments
+    /// * `provider` - The provider used to fetch the pending nonce on first use or after a resync.
+    /// * `address` - The sending account.
+    ///
+    /// # Returns
+    /// Result<U256, ProviderError> - A nonce no other caller of this manager has been given.
+    pub async fn next<T: Transport>(&self, provider: &Provider<T>, address: Address) -> Result<U256, ProviderError> {
+        // Holding the lock while fetching makes concurrent first uses wait for one request
+        let mut accounts = self.accounts.lock().await;
+        if let Some(account) = accounts.get_mut(&address) {
+            let nonce = account.released.pop_first().unwrap_or(account.next);
+            if nonce == account.next {
+                account.next = nonce + 1;
+            }
+            return Ok(nonce);
+        }
+        let nonce = provider.transaction_count(address, BlockNumber::Pending).await?;
+        log_debug(&format!("Fetched pending nonce {} of {:?}", nonce, address));
+        accounts.insert(address, AccountNonces { next: nonce + 1, released: BTreeSet::new() });
+        Ok(nonce)
+    }
+
+    /// Returns the nonce the next allocation for an account will use, if it is known.
+    pub async fn peek(&self, address: Address) -> Option<U256> {
+        let accounts = self.accounts.lock().await;
+        let account = accounts.get(&address)?;
+        Some(account.released.first().copied().unwrap_or(account.next))
+    }
+
+    /// Hands back a nonce whose transaction never reached the node, so it is allocated again.
+    ///
+    /// Releasing the highest allocated nonce rewinds the counter; any other is reused by the next
+    /// allocation, so that the transactions already holding the nonces above it stay valid.
+    pub async fn release(&self, address: Address, nonce: U256) {
+        let mut accounts = self.accounts.lock().await;
+        let Some(account) = accounts.get_mut(&address) else {
+            return;
+        };
+        if nonce >= account.next {
+            return;
+        }
+        log_debug(&format!("Released nonce {} of {:?}", nonce, address));
+        account.released.insert(nonce);
+        while account.released.last().is_some_and(|last| *last + 1 == account.next) {
+            account.released.pop_last();
+            account.next -= U256::one();
+        }
+    }
+
+    /// Forgets the nonces of an account, so the next allocation fetches it from the node again.
+    pub async fn resync(&self, address: Address) {
+        if self.accounts.lock().await.remove(&address).is_some() {
+            log_debug(&format!("Resyncing nonce of {:?}", address));
+        }
+    }
+}
+
+/// Returns whether the node rejected a transaction because its nonce was already used.
+///
+/// Matches the messages of geth and its forks ("nonce too low"), Nethermind and OpenEthereum
+/// ("nonce is too low"), Besu ("NONCE_TOO_LOW") and Hardhat/Anvil ("Nonce too low").
+pub fn is_nonce_too_low(error: &ProviderError) -> bool {
+    rpc_message(error).is_some_and(|message| {
+        ["nonce too low", "nonce is too low", "nonce_too_low"].iter().any(|pattern| message.contains(pattern))
+    })
+}
+
+/// Returns whether the node rejected a transaction because it already has it in its pool.
+///
+/// Matches geth's "already known" and the older "known transaction", and OpenEthereum's
+/// "already imported".
+pub fn is_already_known(error: &ProviderError) -> bool {
+    rpc_message(error).is_some_and(|message| {
+        ["already known", "known transaction", "already imported"].iter().any(|pattern| message.contains(pattern))
+    })
+}
+
+fn rpc_message(error: &ProviderError) -> Option<String> {
+    match error {
+        ProviderError::Rpc { message, .. } => Some(message.to_lowercase()),
+        _ => None,
+    }
+}
+
+// Unit test example
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockNode;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn rpc(message: &str) -> ProviderError {
+        ProviderError::Rpc { code: -32000, message: message.to_string(), data: None }
+    }
+
+    #[test]
+    fn test_error_classification() {
+        assert!(is_nonce_too_low(&rpc("nonce too low: next nonce 5, tx nonce 3")));
+        assert!(is_nonce_too_low(&rpc("Nonce too low. Expected nonce to be 5 but got 3.")));
+        assert!(is_nonce_too_low(&rpc("Transaction nonce is too low. Try incrementing the nonce.")));
+        assert!(is_already_known(&rpc("already known")));
+        assert!(is_already_known(&rpc("known transaction: 0x1234")));
+        assert!(!is_nonce_too_low(&rpc("insufficient funds for gas * price + value")));
+        assert!(!is_already_known(&ProviderError::Transport("already known".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_allocation() {
+        let node = MockNode::start().await;
+        node.respond("eth_getTransactionCount", json!("0x5"));
+        let provider = node.provider();
+        let manager = Arc::new(NonceManager::new());
+        let address = Address::repeat_byte(1);
+
+        let tasks: Vec<_> = (0..10)
+            .map(|_| {
+                let (manager, provider) = (manager.clone(), provider.clone());
+                tokio::spawn(async move { manager.next(&provider, address).await.unwrap() })
+            })
+            .collect();
+        let mut nonces = Vec::new();
+        for task in tasks {
+            nonces.push(task.await.unwrap().as_u64());
+        }
+        nonces.sort();
+        assert_eq!(nonces, (5..15).collect::<Vec<_>>());
+        assert_eq!(node.requests_for("eth_getTransactionCount").len(), 1);
+        assert_eq!(manager.peek(address).await, Some(U256::from(15)));
+
+        // After a resync the node is asked again
+        node.respond("eth_getTransactionCount", json!("0x9"));
+        manager.resync(address).await;
+        assert_eq!(manager.next(&provider, address).await.unwrap(), U256::from(9));
+        assert_eq!(manager.next(&provider, Address::repeat_byte(2)).await.unwrap(), U256::from(9));
+        assert_eq!(node.requests_for("eth_getTransactionCount").len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_release() {
+        let node = MockNode::start().await;
+        node.respond("eth_getTransactionCount", json!("0x5"));
+        let provider = node.provider();
+        let manager = NonceManager::new();
+        let address = Address::repeat_byte(1);
+        for _ in 0..3 {
+            manager.next(&provider, address).await.unwrap();
+        }
+
+        // A released nonce below others in flight is reused first, the last one rewinds the counter
+        manager.release(address, U256::from(5)).await;
+        assert_eq!(manager.peek(address).await, Some(U256::from(5)));
+        manager.release(address, U256::from(7)).await;
+        assert_eq!(manager.next(&provider, address).await.unwrap(), U256::from(5));
+        assert_eq!(manager.next(&provider, address).await.unwrap(), U256::from(7));
+        assert_eq!(manager.next(&provider, address).await.unwrap(), U256::from(8));
+
+        manager.release(address, U256::from(8)).await;
+        manager.release(address, U256::from(6)).await;
+        manager.release(address, U256::from(7)).await;
+        assert_eq!(manager.peek(address).await, Some(U256::from(6)));
+        assert_eq!(node.requests_for("eth_getTransactionCount").len(), 1);
+    }
+}
@@ -0,0 +1,612 @@
+use super::nonce::{is_already_known, is_nonce_too_low, NonceManager};
+use crate::framework::logging::{log_debug, log_warn};
+use crate::signing::{Signer, TransactionKind, UnsignedTransaction};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use web3::transports::Http;
+use web3::types::{
+    Address, Block, BlockId, BlockNumber, Bytes, CallRequest, FeeHistory, Filter, Log, SignedTransaction, TransactionReceipt,
+    TransactionRequest, H256, U256, U64,
+};
+use web3::Transport;
+
+/// Errors that can occur while talking to an Ethereum node.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProviderError {
+    /// The endpoint URL could not be used to build a transport.
+    InvalidEndpoint(String),
+    /// The node could not be reached or the connection failed.
+    Transport(String),
+    /// The node answered with a JSON-RPC error; `data` carries revert data when the node provides it.
+    Rpc { code: i64, message: String, data: Option<Vec<u8>> },
+    /// The node answered with a result that could not be decoded.
+    InvalidResponse(String),
+    /// A transaction was not mined, or not confirmed, before the deadline.
+    Timeout(H256),
+    /// A local signer failed to sign a transaction.
+    Signing(String),
+}
+
+impl ProviderError {
+    /// Returns the revert data attached to an `eth_call`/`eth_estimateGas` failure, if any.
+    pub fn revert_data(&self) -> Option<&[u8]> {
+        match self {
+            ProviderError::Rpc { data: Some(data), .. } => Some(data),
+            _ => None,
+        }
+    }
+
+    fn from_web3(error: web3::Error) -> Self {
+        match error {
+            web3::Error::Rpc(rpc) => ProviderError::Rpc {
+                code: rpc.code.code(),
+                message: rpc.message.clone(),
+                data: rpc.data.as_ref().and_then(extract_revert_data),
+            },
+            web3::Error::Decoder(message) | web3::Error::InvalidResponse(message) => ProviderError::InvalidResponse(message),
+            other => ProviderError::Transport(other.to_string()),
+        }
+    }
+}
+
+/// Finds hex revert data in the `data` field of a JSON-RPC error.
+///
+/// Geth returns the data as a plain hex string, while Hardhat, Anvil and some gateways nest it
+/// inside an object, so both shapes are accepted.
+fn extract_revert_data(data: &Value) -> Option<Vec<u8>> {
+    match data {
+        Value::String(hex) => decode_hex(hex),
+        Value::Object(object) => object.get("data").and_then(extract_revert_data),
+        _ => None,
+    }
+}
+
+pub(crate) fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    let hex = hex.strip_prefix("0x").unwrap_or(hex).as_bytes();
+    if !hex.len().is_multiple_of(2) || !hex.iter().all(u8::is_ascii_hexdigit) {
+        return None;
+    }
+    let digit = |byte: u8| (byte as char).to_digit(16).expect("checked to be a hex digit") as u8;
+    Some(hex.chunks(2).map(|pair| digit(pair[0]) << 4 | digit(pair[1])).collect())
+}
+
+/// Builds the `eth_call`/`eth_estimateGas` request matching a transaction request.
+pub(crate) fn call_request(transaction: &TransactionRequest) -> CallRequest {
+    CallRequest {
+        from: Some(transaction.from),
+        to: transaction.to,
+        gas: transaction.gas,
+        gas_price: transaction.gas_price,
+        value: transaction.value,
+        data: transaction.data.clone(),
+        transaction_type: transaction.transaction_type,
+        access_list: transaction.access_list.clone(),
+        max_fee_per_gas: transaction.max_fee_per_gas,
+        max_priority_fee_per_gas: transaction.max_priority_fee_per_gas,
+    }
+}
+
+fn to_param<P: Serialize>(param: P) -> Result<Value, ProviderError> {
+    serde_json::to_value(param).map_err(|e| ProviderError::InvalidResponse(e.to_string()))
+}
+
+/// A connection to an Ethereum node, issuing typed JSON-RPC requests over any `web3` transport.
+///
+/// The transport is the extension point: `Provider::http` talks to a node over HTTP, any other
+/// `web3::Transport` (WebSocket, IPC, or an in-process backend) can be wrapped with `Provider::new`.
+///
+/// Transactions from an address with an attached signer (`with_signer`) are signed locally and
+/// sent with `eth_sendRawTransaction`, with nonces allocated by the provider's `NonceManager`;
+/// transactions from other addresses are left to the node's account manager.
+#[derive(Debug, Clone)]
+pub struct Provider<T: Transport = Http> {
+    transport: T,
+    poll_interval: Duration,
+    receipt_timeout: Duration,
+    confirmations: u64,
+    signers: Vec<Arc<dyn Signer>>,
+    nonce_manager: Arc<NonceManager>,
+}
+
+/// How often a locally signed transaction is re-signed with a fresh nonce after "nonce too low".
+const NONCE_RETRIES: usize = 3;
+
+impl Provider<Http> {
+    /// Connects to a node over HTTP.
+    ///
+    /// # Arguments
+    /// * `url` - The JSON-RPC endpoint, e.g. `http://localhost:8545`.
+    pub fn http(url: &str) -> Result<Self, ProviderError> {
+        let transport = Http::new(url).map_err(|e| ProviderError::InvalidEndpoint(e.to_string()))?;
+        Ok(Provider::new(transport))
+    }
+}
+
+impl<T: Transport> Provider<T> {
+    /// Wraps an existing transport.
+    pub fn new(transport: T) -> Self {
+        Provider {
+            transport,
+            poll_interval: Duration::from_secs(1),
+            receipt_timeout: Duration::from_secs(300),
+            confirmations: 1,
+            signers: Vec::new(),
+            nonce_manager: Arc::new(NonceManager::new()),
+        }
+    }
+
+    /// Sets how often pending transactions and logs are polled.
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Sets how long to wait for a transaction to be mined before giving up.
+    pub fn with_receipt_timeout(mut self, receipt_timeout: Duration) -> Self {
+        self.receipt_timeout = receipt_timeout;
+        self
+    }
+
+    /// Sets how many confirmations deployments wait for; 1 returns as soon as the transaction is mined.
+    pub fn with_confirmations(mut self, confirmations: u64) -> Self {
+        self.confirmations = confirmations;
+        self
+    }
+
+    /// Attaches a signer; transactions sent from its address are signed locally.
+    pub fn with_signer<S: Signer + 'static>(mut self, signer: S) -> Self {
+        self.signers.push(Arc::new(signer));
+        self
+    }
+
+    /// Returns the signer attached for an address, if any.
+    pub fn signer(&self, address: &Address) -> Option<&Arc<dyn Signer>> {
+        self.signers.iter().find(|signer| signer.address() == *address)
+    }
+
+    /// Replaces the nonce manager, e.g. to share one between providers that send from the same accounts.
+    pub fn with_nonce_manager(mut self, nonce_manager: Arc<NonceManager>) -> Self {
+        self.nonce_manager = nonce_manager;
+        self
+    }
+
+    /// Returns the nonce manager allocating nonces for locally signed transactions.
+    pub fn nonce_manager(&self) -> &Arc<NonceManager> {
+        &self.nonce_manager
+    }
+
+    /// Returns the underlying transport.
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// Returns the interval used when polling the node.
+    pub fn poll_interval(&self) -> Duration {
+        self.poll_interval
+    }
+
+    /// Returns the number of confirmations deployments wait for.
+    pub fn confirmations(&self) -> u64 {
+        self.confirmations
+    }
+
+    /// Sends a raw JSON-RPC request and decodes its result.
+    ///
+    /// # Arguments
+    /// * `method` - The JSON-RPC method, e.g. `eth_blockNumber`.
+    /// * `params` - The positional parameters of the method.
+    ///
+    /// # Returns
+    /// Result<R, ProviderError> - The decoded result, or the error reported by the node.
+    pub async fn request<R: DeserializeOwned>(&self, method: &str, params: Vec<Value>) -> Result<R, ProviderError> {
+        log_debug(&format!("JSON-RPC request: {} {:?}", method, params));
+        let result = self.transport.execute(method, params).await.map_err(ProviderError::from_web3)?;
+        serde_json::from_value(result).map_err(|e| ProviderError::InvalidResponse(format!("{}: {}", method, e)))
+    }
+
+    /// Returns the chain id reported by `eth_chainId`.
+    pub async fn chain_id(&self) -> Result<U256, ProviderError> {
+        self.request("eth_chainId", vec![]).await
+    }
+
+    /// Returns the number of the most recent block.
+    pub async fn block_number(&self) -> Result<U64, ProviderError> {
+        self.request("eth_blockNumber", vec![]).await
+    }
+
+    /// Returns the current gas price reported by `eth_gasPrice`.
+    pub async fn gas_price(&self) -> Result<U256, ProviderError> {
+        self.request("eth_gasPrice", vec![]).await
+    }
+
+    /// Returns base fees, gas usage and priority fee percentiles of recent blocks via `eth_feeHistory`.
+    ///
+    /// # Arguments
+    /// * `block_count` - The number of blocks to report on, ending at `newest_block`.
+    /// * `newest_block` - The last block of the range.
+    /// * `reward_percentiles` - Percentiles (0-100, ascending) of the priority fees paid in each block.
+    pub async fn fee_history(&self, block_count: u64, newest_block: BlockNumber, reward_percentiles: &[f64]) -> Result<FeeHistory, ProviderError> {
+        let params = vec![to_param(U256::from(block_count))?, to_param(newest_block)?, to_param(reward_percentiles)?];
+        self.request("eth_feeHistory", params).await
+    }
+
+    /// Returns the number of transactions sent from an address, including pending ones if requested.
+    pub async fn transaction_count(&self, address: Address, block: BlockNumber) -> Result<U256, ProviderError> {
+        self.request("eth_getTransactionCount", vec![to_param(address)?, to_param(block)?]).await
+    }
+
+    /// Returns the runtime bytecode deployed at an address.
+    pub async fn code(&self, address: Address) -> Result<Bytes, ProviderError> {
+        self.request("eth_getCode", vec![to_param(address)?, to_param(BlockNumber::Latest)?]).await
+    }
+
+    /// Returns the header of a block, with transaction hashes only, or `None` if it does not exist.
+    pub async fn block(&self, block: BlockId) -> Result<Option<Block<H256>>, ProviderError> {
+        match block {
+            BlockId::Number(number) => self.request("eth_getBlockByNumber", vec![to_param(number)?, Value::Bool(false)]).await,
+            BlockId::Hash(hash) => self.request("eth_getBlockByHash", vec![to_param(hash)?, Value::Bool(false)]).await,
+        }
+    }
+
+    /// Sends a transaction.
+    ///
+    /// If a signer is attached for `transaction.from`, the transaction is completed with
+    /// `fill_transaction`, signed locally and submitted with `eth_sendRawTransaction`. Otherwise it
+    /// is submitted with `eth_sendTransaction` for the node's account manager to sign.
+    ///
+    /// Locally signed transactions without an explicit nonce take the next one from the nonce
+    /// manager, which is settled as `send_allocated` describes when sending fails. If the node
+    /// answers "nonce too low" the transaction is re-signed with a fresh nonce; "already known"
+    /// means the node already has the transaction, so its hash is returned.
+    pub async fn send_transaction(&self, mut transaction: TransactionRequest) -> Result<H256, ProviderError> {
+        let Some(signer) = self.signer(&transaction.from) else {
+            return self.request("eth_sendTransaction", vec![to_param(transaction)?]).await;
+        };
+        if transaction.nonce.is_some() {
+            let signed = self.sign(signer.as_ref(), &transaction).await?;
+            return self.broadcast(signed).await;
+        }
+
+        let from = transaction.from;
+        let mut attempt = 0;
+        loop {
+            transaction.nonce = Some(self.nonce_manager.next(self, from).await?);
+            let error = match self.send_allocated(signer.as_ref(), &transaction).await {
+                Ok(hash) => return Ok(hash),
+                Err(error) => error,
+            };
+            if !is_nonce_too_low(&error) {
+                return Err(error);
+            }
+            attempt += 1;
+            if attempt > NONCE_RETRIES {
+                return Err(error);
+            }
+            log_warn(&format!("Nonce {:?} of {:?} was already used; retrying with a fresh nonce", transaction.nonce, from));
+        }
+    }
+
+    /// Signs and broadcasts a transaction whose nonce was allocated by the nonce manager.
+    ///
+    /// When the transaction cannot have reached the node's pool, because it failed before the
+    /// broadcast or the node rejected it with a JSON-RPC error, the nonce is released. When the
+    /// node reports the nonce as used, or the broadcast failed in transit so that the transaction
+    /// may be pending after all, the account is resynced from the node instead.
+    pub(crate) async fn send_allocated(&self, signer: &dyn Signer, transaction: &TransactionRequest) -> Result<H256, ProviderError> {
+        let (from, nonce) = (transaction.from, transaction.nonce.unwrap_or_default());
+        let signed = match self.sign(signer, transaction).await {
+            Ok(signed) => signed,
+            Err(error) => {
+                self.nonce_manager.release(from, nonce).await;
+                return Err(error);
+            }
+        };
+        let result = self.broadcast(signed).await;
+        match &result {
+            Err(error @ ProviderError::Rpc { .. }) if !is_nonce_too_low(error) => self.nonce_manager.release(from, nonce).await,
+            Err(_) => self.nonce_manager.resync(from).await,
+            Ok(_) => {}
+        }
+        result
+    }
+
+    async fn sign(&self, signer: &dyn Signer, transaction: &TransactionRequest) -> Result<SignedTransaction, ProviderError> {
+        let unsigned = self.fill_transaction(transaction).await?;
+        let signed = signer.sign_transaction(&unsigned).map_err(|e| ProviderError::Signing(format!("{:?}", e)))?;
+        log_debug(&format!("Signed transaction {:?} from {:?} locally", signed.transaction_hash, transaction.from));
+        Ok(signed)
+    }
+
+    async fn broadcast(&self, signed: SignedTransaction) -> Result<H256, ProviderError> {
+        match self.send_raw_transaction(signed.raw_transaction).await {
+            Err(error) if is_already_known(&error) => Ok(signed.transaction_hash),
+            result => result,
+        }
+    }
+
+    /// Completes a transaction request so it can be signed locally.
+    ///
+    /// Missing fields are taken from the node: the chain id, the pending nonce of the sender, an
+    /// `eth_estimateGas` gas limit, and fees. The envelope is the one the request asks for with
+    /// `transaction_type`, otherwise it follows the fee fields that are set, and if none are,
+    /// EIP-1559 on chains with a base fee and legacy elsewhere. EIP-1559 fees default to the
+    /// node's priority fee on top of twice the latest base fee.
+    pub async fn fill_transaction(&self, request: &TransactionRequest) -> Result<UnsignedTransaction, ProviderError> {
+        let chain_id = self.chain_id().await?.as_u64();
+        let nonce = match request.nonce {
+            Some(nonce) => nonce,
+            None => self.transaction_count(request.from, BlockNumber::Pending).await?,
+        };
+        let gas = match request.gas {
+            Some(gas) => gas,
+            None => self.estimate_gas(call_request(request)).await?,
+        };
+
+        let type_id = match request.transaction_type {
+            Some(type_id) => type_id.as_u64(),
+            None if request.max_fee_per_gas.is_some() || request.max_priority_fee_per_gas.is_some() => 2,
+            None if request.gas_price.is_some() => u64::from(request.access_list.is_some()),
+            None => {
+                let latest = self.block(BlockId::Number(BlockNumber::Latest)).await?;
+                if latest.and_then(|block| block.base_fee_per_gas).is_some() { 2 } else { 0 }
+            }
+        };
+        let kind = match type_id {
+            0 | 1 => {
+                let gas_price = match request.gas_price {
+                    Some(gas_price) => gas_price,
+                    None => self.gas_price().await?,
+                };
+                if type_id == 0 {
+                    TransactionKind::Legacy { gas_price }
+                } else {
+                    TransactionKind::AccessList { gas_price }
+                }
+            }
+            2 => {
+                let max_priority_fee_per_gas = match request.max_priority_fee_per_gas {
+                    Some(fee) => fee,
+                    None => self.request("eth_maxPriorityFeePerGas", vec![]).await?,
+                };
+                let max_fee_per_gas = match request.max_fee_per_gas {
+                    Some(fee) => fee,
+                    None => {
+                        let latest = self.block(BlockId::Number(BlockNumber::Latest)).await?;
+                        let base_fee = latest.and_then(|block| block.base_fee_per_gas).unwrap_or_default();
+                        base_fee * 2 + max_priority_fee_per_gas
+                    }
+                };
+                TransactionKind::Eip1559 { max_fee_per_gas, max_priority_fee_per_gas }
+            }
+            other => return Err(ProviderError::Signing(format!("unsupported transaction type {}", other))),
+        };
+
+        Ok(UnsignedTransaction {
+            kind,
+            chain_id,
+            nonce,
+            to: request.to,
+            value: request.value.unwrap_or_default(),
+            data: request.data.clone().map(|data| data.0).unwrap_or_default(),
+            gas,
+            access_list: request.access_list.clone().unwrap_or_default(),
+        })
+    }
+
+    /// Submits an already signed transaction via `eth_sendRawTransaction`.
+    pub async fn send_raw_transaction(&self, raw: Bytes) -> Result<H256, ProviderError> {
+        self.request("eth_sendRawTransaction", vec![to_param(raw)?]).await
+    }
+
+    /// Executes a call against the latest state without creating a transaction.
+    pub async fn call(&self, call: CallRequest) -> Result<Bytes, ProviderError> {
+        self.call_at(call, BlockNumber::Latest).await
+    }
+
+    /// Executes a call against the state at the given block without creating a transaction.
+    pub async fn call_at(&self, call: CallRequest, block: BlockNumber) -> Result<Bytes, ProviderError> {
+        self.request("eth_call", vec![to_param(call)?, to_param(block)?]).await
+    }
+
+    /// Asks the node how much gas a transaction would use.
+    pub async fn estimate_gas(&self, call: CallRequest) -> Result<U256, ProviderError> {
+        self.request("eth_estimateGas", vec![to_param(call)?]).await
+    }
+
+    /// Returns the logs matching a filter.
+    pub async fn logs(&self, filter: Filter) -> Result<Vec<Log>, ProviderError> {
+        self.request("eth_getLogs", vec![to_param(filter)?]).await
+    }
+
+    /// Returns the receipt of a mined transaction, or `None` while it is pending.
+    pub async fn transaction_receipt(&self, hash: H256) -> Result<Option<TransactionReceipt>, ProviderError> {
+        self.request("eth_getTransactionReceipt", vec![to_param(hash)?]).await
+    }
+
+    /// Polls for a transaction receipt until the transaction is mined or the receipt timeout elapses.
+    ///
+    /// # Arguments
+    /// * `hash` - The hash of the submitted transaction.
+    ///
+    /// # Returns
+    /// Result<TransactionReceipt, ProviderError> - The receipt, or `ProviderError::Timeout`.
+    pub async fn wait_for_receipt(&self, hash: H256) -> Result<TransactionReceipt, ProviderError> {
+        self.wait_for_confirmations(hash, 1).await
+    }
+
+    /// Polls until a transaction has been mined and `confirmations` blocks, counting its own, are on top of it.
+    ///
+    /// The receipt is fetched again on every poll, so a transaction moved to another block by a
+    /// reorg is confirmed against its new block.
+    ///
+    /// # Arguments
+    /// * `hash` - The hash of the submitted transaction.
+    /// * `confirmations` - The number of blocks to wait for; 0 and 1 both return once the transaction is mined.
+    ///
+    /// # Returns
+    /// Result<TransactionReceipt, ProviderError> - The receipt, or `ProviderError::Timeout`.
+    pub async fn wait_for_confirmations(&self, hash: H256, confirmations: u64) -> Result<TransactionReceipt, ProviderError> {
+        let started = Instant::now();
+        loop {
+            if let Some(receipt) = self.transaction_receipt(hash).await? {
+                if let Some(block) = receipt.block_number {
+                    if confirmations <= 1 || self.block_number().await?.as_u64() + 1 >= block.as_u64() + confirmations {
+                        return Ok(receipt);
+                    }
+                }
+            }
+            if started.elapsed() >= self.receipt_timeout {
+                log_warn(&format!(
+                    "Transaction {:?} did not reach {} confirmation(s) within {:?}",
+                    hash, confirmations, self.receipt_timeout
+                ));
+                return Err(ProviderError::Timeout(hash));
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+}
+
+// Unit test example
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::{decode_signed_transaction, PrivateKeySigner};
+    use crate::testing::MockNode;
+    use serde_json::json;
+
+    #[test]
+    fn test_invalid_endpoint() {
+        let result = Provider::http("not a url");
+        assert!(matches!(result, Err(ProviderError::InvalidEndpoint(_))));
+    }
+
+    #[test]
+    fn test_extract_revert_data() {
+        assert_eq!(extract_revert_data(&json!("0x08c379a0")), Some(vec![0x08, 0xc3, 0x79, 0xa0]));
+        assert_eq!(extract_revert_data(&json!({ "message": "reverted", "data": "0x4e487b71" })), Some(vec![0x4e, 0x48, 0x7b, 0x71]));
+        assert_eq!(extract_revert_data(&json!("Reverted")), None);
+    }
+
+    #[test]
+    fn test_decode_hex() {
+        assert_eq!(decode_hex("0x00fFa1"), Some(vec![0x00, 0xff, 0xa1]));
+        assert_eq!(decode_hex(""), Some(vec![]));
+        assert_eq!(decode_hex("abc"), None);
+        assert_eq!(decode_hex("aé0"), None);
+        assert_eq!(decode_hex("+f"), None);
+    }
+
+    #[tokio::test]
+    async fn test_failed_send_releases_nonce() {
+        let node = MockNode::start().await;
+        node.respond("eth_getTransactionCount", json!("0x5"));
+        node.respond("eth_maxPriorityFeePerGas", json!("0x3b9aca00"));
+        node.respond_with("eth_estimateGas", |params| match params[0]["data"].as_str() {
+            Some("0x00") => Err(json!({ "code": 3, "message": "execution reverted" })),
+            _ => Ok(json!("0x5208")),
+        });
+        node.respond_with("eth_sendRawTransaction", |params| {
+            let raw: Bytes = serde_json::from_value(params[0].clone()).unwrap();
+            Ok(json!(decode_signed_transaction(&raw.0).unwrap().hash))
+        });
+        let signer = PrivateKeySigner::random();
+        let from = signer.address();
+        let provider = node.provider().with_signer(signer);
+
+        // One of the concurrent sends reverts in estimation while the others hold later nonces
+        let sends: Vec<_> = (0..6u8)
+            .map(|i| {
+                let provider = provider.clone();
+                let transaction = TransactionRequest { from, to: Some(Address::repeat_byte(1)), data: Some(vec![i].into()), ..Default::default() };
+                tokio::spawn(async move { provider.send_transaction(transaction).await })
+            })
+            .collect();
+        let mut failed = 0;
+        for send in sends {
+            failed += send.await.unwrap().is_err() as usize;
+        }
+        assert_eq!(failed, 1);
+        for i in 6..8u8 {
+            let transaction = TransactionRequest { from, to: Some(Address::repeat_byte(1)), data: Some(vec![i].into()), ..Default::default() };
+            provider.send_transaction(transaction).await.unwrap();
+        }
+
+        let mut nonces: Vec<u64> = node
+            .requests_for("eth_sendRawTransaction")
+            .iter()
+            .map(|params| {
+                let raw: Bytes = serde_json::from_value(params[0].clone()).unwrap();
+                decode_signed_transaction(&raw.0).unwrap().transaction.nonce.as_u64()
+            })
+            .collect();
+        nonces.sort();
+        assert_eq!(nonces, (5..12).collect::<Vec<_>>());
+        assert_eq!(node.requests_for("eth_getTransactionCount").len(), 1);
+    }
+
+    /// Forwards requests to a node but loses its answers to `eth_sendRawTransaction`.
+    #[derive(Debug, Clone)]
+    struct LossyTransport(Http);
+
+    impl Transport for LossyTransport {
+        type Out = futures::future::BoxFuture<'static, web3::Result<Value>>;
+
+        fn prepare(&self, method: &str, params: Vec<Value>) -> (web3::RequestId, jsonrpc_core::Call) {
+            self.0.prepare(method, params)
+        }
+
+        fn send(&self, id: web3::RequestId, request: jsonrpc_core::Call) -> Self::Out {
+            let lost = matches!(&request, jsonrpc_core::Call::MethodCall(call) if call.method == "eth_sendRawTransaction");
+            let response = self.0.send(id, request);
+            Box::pin(async move {
+                let result = response.await;
+                match lost {
+                    true => Err(web3::Error::Transport(web3::error::TransportError::Message("connection reset".into()))),
+                    false => result,
+                }
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lost_broadcast_resyncs_nonce() {
+        let node = MockNode::start().await;
+        node.respond("eth_getTransactionCount", json!("0x5"));
+        node.respond("eth_maxPriorityFeePerGas", json!("0x3b9aca00"));
+        node.respond("eth_estimateGas", json!("0x5208"));
+        node.respond("eth_sendRawTransaction", json!(H256::zero()));
+        let signer = PrivateKeySigner::random();
+        let from = signer.address();
+        let provider = Provider::new(LossyTransport(Http::new(&node.http_url()).unwrap())).with_signer(signer);
+
+        // The node received the transaction, so its nonce must not be handed out again
+        let transaction = TransactionRequest { from, to: Some(Address::repeat_byte(1)), ..Default::default() };
+        let result = provider.send_transaction(transaction.clone()).await;
+        assert!(matches!(result, Err(ProviderError::Transport(_))));
+        assert_eq!(provider.nonce_manager().peek(from).await, None);
+
+        node.respond("eth_getTransactionCount", json!("0x6"));
+        assert!(provider.send_transaction(transaction).await.is_err());
+        let nonces: Vec<u64> = node
+            .requests_for("eth_sendRawTransaction")
+            .iter()
+            .map(|params| {
+                let raw: Bytes = serde_json::from_value(params[0].clone()).unwrap();
+                decode_signed_transaction(&raw.0).unwrap().transaction.nonce.as_u64()
+            })
+            .collect();
+        assert_eq!(nonces, vec![5, 6]);
+        assert_eq!(node.requests_for("eth_getTransactionCount").len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_node() {
+        let provider = Provider::http("http://127.0.0.1:1").unwrap();
+        let result = provider.block_number().await;
+        assert!(matches!(result, Err(ProviderError::Transport(_))));
+    }
+}
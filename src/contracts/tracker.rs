@@ -0,0 +1,476 @@
+use super::gas::{bump_fee, FeeOracle, GasOptimizationError, FeeSpeed, MIN_REPLACEMENT_BUMP_PERCENT};
+use super::nonce::is_nonce_too_low;
+use super::provider::{Provider, ProviderError};
+use crate::framework::logging::{log_info, log_warn};
+use crate::signing::{TransactionKind, UnsignedTransaction};
+use std::time::{Duration, Instant};
+use web3::types::{AccessList, Address, Bytes, TransactionReceipt, TransactionRequest, H256, U256, U64};
+use web3::Transport;
+
+/// Errors that can occur while sending, replacing or waiting for a tracked transaction.
+#[derive(Debug)]
+pub enum TrackerError {
+    Provider(ProviderError),
+    /// Replacing the transaction would need a fee above the configured cap.
+    FeeCapExceeded { required: U256, cap: U256 },
+    /// The transaction was still not mined after the maximum number of replacements; holds the latest hash.
+    Stuck(H256),
+    /// The nonce was used by a transaction the tracker did not send.
+    NonceConsumed(U256),
+    /// The fee oracle could not suggest fees for a replacement.
+    FeeEstimation(GasOptimizationError),
+}
+
+impl From<ProviderError> for TrackerError {
+    fn from(error: ProviderError) -> Self {
+        TrackerError::Provider(error)
+    }
+}
+
+impl From<GasOptimizationError> for TrackerError {
+    fn from(error: GasOptimizationError) -> Self {
+        match error {
+            GasOptimizationError::Provider(error) => TrackerError::Provider(error),
+            other => TrackerError::FeeEstimation(other),
+        }
+    }
+}
+
+/// A transaction sent through a `TransactionTracker`, with every broadcast made for its nonce.
+///
+/// # Fields
+/// - `from`: The sending account.
+/// - `transaction`: The latest version of the transaction, with nonce, gas and fees filled in.
+/// - `hashes`: The hash of every broadcast, oldest first; any of them may end up mined.
+/// - `cancelled`: Whether the latest version is a cancelling self-transfer.
+#[derive(Debug, Clone)]
+pub struct TrackedTransaction {
+    pub from: Address,
+    pub transaction: UnsignedTransaction,
+    pub hashes: Vec<H256>,
+    pub cancelled: bool,
+    sent_at: Instant,
+}
+
+impl TrackedTransaction {
+    /// Returns the hash of the latest broadcast.
+    pub fn hash(&self) -> H256 {
+        *self.hashes.last().expect("a tracked transaction has been sent at least once")
+    }
+
+    /// Returns how many times the transaction has been replaced.
+    pub fn replacements(&self) -> usize {
+        self.hashes.len() - 1
+    }
+}
+
+/// Watches sent transactions and replaces those that are not mined in time.
+///
+/// A transaction still pending after the deadline is rebroadcast with the same nonce and fees
+/// bumped by at least the node's minimum replacement bump, and at least to the `FeeOracle`'s
+/// normal-speed suggestion. `cancel` replaces a transaction with a zero-value transfer to the sender instead.
+///
+/// Replacements go through `Provider::send_transaction` with an explicit nonce, so they are
+/// signed locally when the provider has a signer for the sender and by the node otherwise.
+#[derive(Debug, Clone)]
+pub struct TransactionTracker {
+    deadline: Duration,
+    bump_percent: u64,
+    max_replacements: usize,
+    max_fee_per_gas: Option<U256>,
+    fee_oracle: FeeOracle,
+}
+
+impl Default for TransactionTracker {
+    fn default() -> Self {
+        TransactionTracker::new()
+    }
+}
+
+/// How often a replacement the node rejects as underpriced is retried with a further bump.
+const UNDERPRICED_RETRIES: usize = 3;
+
+impl TransactionTracker {
+    /// Creates a tracker that replaces transactions pending for over three minutes, up to five times.
+    pub fn new() -> Self {
+        TransactionTracker {
+            deadline: Duration::from_secs(180),
+            bump_percent: MIN_REPLACEMENT_BUMP_PERCENT,
+            max_replacements: 5,
+            max_fee_per_gas: None,
+            fee_oracle: FeeOracle::new(),
+        }
+    }
+
+    /// Sets how long a broadcast may stay pending before it is replaced.
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Sets the fee increase per replacement in percent; values below the node's minimum of 10 are raised to it.
+    pub fn with_bump_percent(mut self, bump_percent: u64) -> Self {
+        self.bump_percent = bump_percent.max(MIN_REPLACEMENT_BUMP_PERCENT);
+        self
+    }
+
+    /// Sets how many times a transaction is replaced before `wait` gives up.
+    pub fn with_max_replacements(mut self, max_replacements: usize) -> Self {
+        self.max_replacements = max_replacements;
+        self
+    }
+
+    /// Caps the gas price or max fee per gas a replacement may offer.
+    pub fn with_max_fee_per_gas(mut self, max_fee_per_gas: U256) -> Self {
+        self.max_fee_per_gas = Some(max_fee_per_gas);
+        self
+    }
+
+    /// Sets the oracle whose suggestion replacement fees are raised to at least.
+    pub fn with_fee_oracle(mut self, fee_oracle: FeeOracle) -> Self {
+        self.fee_oracle = fee_oracle;
+        self
+    }
+
+    /// Fills in and sends a transaction so it can be tracked.
+    ///
+    /// # Arguments
+    /// * `provider` - The node to send the transaction through.
+    /// * `request` - The transaction; missing nonce, gas and fees are filled in as for local signing.
+    ///
+    /// # Returns
+    /// Result<TrackedTransaction, TrackerError> - The sent transaction, to pass to `wait`, `speed_up` or `cancel`.
+    pub async fn send<T: Transport>(&self, provider: &Provider<T>, mut request: TransactionRequest) -> Result<TrackedTransaction, TrackerError> {
+        let from = request.from;
+        let managed = request.nonce.is_none() && provider.signer(&from).is_some();
+        if managed {
+            request.nonce = Some(provider.nonce_manager().next(provider, from).await?);
+        }
+        let transaction = match provider.fill_transaction(&request).await {
+            Ok(transaction) => transaction,
+            Err(error) => {
+                if managed {
+                    provider.nonce_manager().release(from, request.nonce.unwrap_or_default()).await;
+                }
+                return Err(error.into());
+            }
+        };
+        // The provider settles an allocated nonce the same way when the broadcast fails
+        let hash = match provider.signer(&from) {
+            Some(signer) if managed => provider.send_allocated(signer.as_ref(), &filled_request(from, &transaction)).await?,
+            _ => provider.send_transaction(filled_request(from, &transaction)).await?,
+        };
+        Ok(TrackedTransaction { from, transaction, hashes: vec![hash], cancelled: false, sent_at: Instant::now() })
+    }
+
+    /// Rebroadcasts a transaction with the same nonce and bumped fees.
+    ///
+    /// # Returns
+    /// Result<H256, TrackerError> - The hash of the replacement.
+    pub async fn speed_up<T: Transport>(&self, provider: &Provider<T>, tracked: &mut TrackedTransaction) -> Result<H256, TrackerError> {
+        let transaction = tracked.transaction.clone();
+        self.replace(provider, tracked, transaction).await
+    }
+
+    /// Replaces a transaction with a zero-value transfer from the sender to itself with the same nonce.
+    ///
+    /// Once the self-transfer is mined the original can no longer be; `wait` then returns its receipt.
+    ///
+    /// # Returns
+    /// Result<H256, TrackerError> - The hash of the cancelling transaction.
+    pub async fn cancel<T: Transport>(&self, provider: &Provider<T>, tracked: &mut TrackedTransaction) -> Result<H256, TrackerError> {
+        let transaction = UnsignedTransaction {
+            to: Some(tracked.from),
+            value: U256::zero(),
+            data: Vec::new(),
+            gas: U256::from(21_000),
+            access_list: AccessList::new(),
+            ..tracked.transaction.clone()
+        };
+        let hash = self.replace(provider, tracked, transaction).await?;
+        tracked.cancelled = true;
+        Ok(hash)
+    }
+
+    /// Waits until one of the broadcasts of a transaction is mined, speeding it up each time the deadline passes.
+    ///
+    /// # Returns
+    /// Result<TransactionReceipt, TrackerError> - The receipt of whichever broadcast was mined,
+    /// `TrackerError::Stuck` once the replacements are used up, or `TrackerError::NonceConsumed`
+    /// if another transaction took the nonce.
+    pub async fn wait<T: Transport>(&self, provider: &Provider<T>, tracked: &mut TrackedTransaction) -> Result<TransactionReceipt, TrackerError> {
+        let mut nonce_used = false;
+        loop {
+            // Any broadcast may be the one that gets mined, not only the latest
+            for hash in tracked.hashes.iter().rev() {
+                if let Some(receipt) = provider.transaction_receipt(*hash).await? {
+                    if receipt.block_number.is_some() {
+                        return Ok(receipt);
+                    }
+                }
+            }
+
+            if tracked.sent_at.elapsed() >= self.deadline {
+                if nonce_used {
+                    return Err(TrackerError::NonceConsumed(tracked.transaction.nonce));
+                }
+                if tracked.replacements() >= self.max_replacements {
+                    log_warn(&format!("Transaction {:?} is still pending after {} replacements", tracked.hash(), tracked.replacements()));
+                    return Err(TrackerError::Stuck(tracked.hash()));
+                }
+                match self.speed_up(provider, tracked).await {
+                    Ok(_) => {}
+                    // One of the broadcasts, or another transaction, was mined meanwhile: give the
+                    // receipt one more deadline to show up
+                    Err(TrackerError::Provider(error)) if is_nonce_too_low(&error) => {
+                        nonce_used = true;
+                        tracked.sent_at = Instant::now();
+                    }
+                    Err(error) => return Err(error),
+                }
+            }
+            tokio::time::sleep(provider.poll_interval()).await;
+        }
+    }
+
+    /// Sends a transaction and waits for it, replacing it while it is stuck.
+    pub async fn send_and_wait<T: Transport>(&self, provider: &Provider<T>, request: TransactionRequest) -> Result<TransactionReceipt, TrackerError> {
+        let mut tracked = self.send(provider, request).await?;
+        self.wait(provider, &mut tracked).await
+    }
+
+    /// Broadcasts `transaction` with bumped fees in place of the tracked one.
+    async fn replace<T: Transport>(
+        &self,
+        provider: &Provider<T>,
+        tracked: &mut TrackedTransaction,
+        mut transaction: UnsignedTransaction,
+    ) -> Result<H256, TrackerError> {
+        let mut bump_percent = self.bump_percent;
+        for attempt in 0..=UNDERPRICED_RETRIES {
+            transaction.kind = self.bumped_fees(provider, &tracked.transaction.kind, bump_percent).await?;
+            match provider.send_transaction(filled_request(tracked.from, &transaction)).await {
+                Ok(hash) => {
+                    log_info(&format!(
+                        "Replaced transaction {:?} with {:?} paying up to {} per gas",
+                        tracked.hash(),
+                        hash,
+                        transaction.kind.max_gas_price()
+                    ));
+                    tracked.transaction = transaction;
+                    tracked.hashes.push(hash);
+                    tracked.sent_at = Instant::now();
+                    return Ok(hash);
+                }
+                // Some nodes require more than the usual bump
+                Err(ProviderError::Rpc { ref message, .. }) if message.contains("underpriced") && attempt < UNDERPRICED_RETRIES => {
+                    bump_percent += MIN_REPLACEMENT_BUMP_PERCENT;
+                }
+                Err(error) => return Err(error.into()),
+            }
+        }
+        unreachable!("the last attempt returns")
+    }
+
+    /// Returns fees that beat `previous` by `bump_percent` and at least match the oracle's suggestion.
+    async fn bumped_fees<T: Transport>(&self, provider: &Provider<T>, previous: &TransactionKind, bump_percent: u64) -> Result<TransactionKind, TrackerError> {
+        let suggested = self.fee_oracle.estimate(provider, FeeSpeed::Normal).await?;
+        let kind = match *previous {
+            TransactionKind::Legacy { gas_price } | TransactionKind::AccessList { gas_price } => {
+                let gas_price = bump_fee(gas_price, bump_percent).max(suggested.gas_price());
+                match previous {
+                    TransactionKind::Legacy { .. } => TransactionKind::Legacy { gas_price },
+                    _ => TransactionKind::AccessList { gas_price },
+                }
+            }
+            TransactionKind::Eip1559 { max_fee_per_gas, max_priority_fee_per_gas } => {
+                let max_priority_fee_per_gas = bump_fee(max_priority_fee_per_gas, bump_percent).max(suggested.max_priority_fee_per_gas);
+                let max_fee_per_gas = bump_fee(max_fee_per_gas, bump_percent).max(suggested.max_fee_per_gas);
+                TransactionKind::Eip1559 { max_fee_per_gas, max_priority_fee_per_gas }
+            }
+        };
+        match self.max_fee_per_gas {
+            Some(cap) if kind.max_gas_price() > cap => Err(TrackerError::FeeCapExceeded { required: kind.max_gas_price(), cap }),
+            _ => Ok(kind),
+        }
+    }
+}
+
+/// Turns a filled transaction back into a request with every field explicit.
+fn filled_request(from: Address, transaction: &UnsignedTransaction) -> TransactionRequest {
+    let mut request = TransactionRequest {
+        from,
+        to: transaction.to,
+        gas: Some(transaction.gas),
+        value: Some(transaction.value),
+        data: Some(Bytes(transaction.data.clone())),
+        nonce: Some(transaction.nonce),
+        transaction_type: Some(U64::from(transaction.kind.type_id())),
+        ..Default::default()
+    };
+    match transaction.kind {
+        TransactionKind::Legacy { gas_price } => request.gas_price = Some(gas_price),
+        TransactionKind::AccessList { gas_price } => {
+            request.gas_price = Some(gas_price);
+            request.access_list = Some(transaction.access_list.clone());
+        }
+        TransactionKind::Eip1559 { max_fee_per_gas, max_priority_fee_per_gas } => {
+            request.max_fee_per_gas = Some(max_fee_per_gas);
+            request.max_priority_fee_per_gas = Some(max_priority_fee_per_gas);
+            request.access_list = Some(transaction.access_list.clone());
+        }
+    }
+    request
+}
+
+// Unit test example
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::{decode_signed_transaction, DecodedTransaction, PrivateKeySigner, Signer};
+    use crate::testing::MockNode;
+    use serde_json::{json, Value};
+
+    const GWEI: u64 = 1_000_000_000;
+
+    /// A node that accepts raw transactions and mines only the ones `mined` selects.
+    fn node_mining<F: Fn(&DecodedTransaction) -> bool + Send + Sync + 'static>(node: &MockNode, mined: F) {
+        node.respond("eth_getTransactionCount", json!("0x4"));
+        node.respond("eth_estimateGas", json!("0xc350"));
+        node.respond("eth_maxPriorityFeePerGas", json!(format!("{:#x}", GWEI)));
+        fee_history(node, GWEI);
+        let sent = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let pool = sent.clone();
+        node.respond_with("eth_sendRawTransaction", move |params| {
+            let transaction = decode(&params[0]);
+            let hash = transaction.hash;
+            pool.lock().unwrap().push(transaction);
+            Ok(json!(hash))
+        });
+        let receipts = node.clone();
+        node.respond_with("eth_getTransactionReceipt", move |params| {
+            let hash: H256 = serde_json::from_value(params[0].clone()).unwrap();
+            let sent = sent.lock().unwrap();
+            Ok(match sent.iter().find(|transaction| transaction.hash == hash) {
+                Some(transaction) if mined(transaction) => receipts.receipt(hash, None, true),
+                _ => Value::Null,
+            })
+        });
+    }
+
+    /// Answers `eth_feeHistory` with a base fee of 1 gwei and `priority_fee` paid in every block.
+    fn fee_history(node: &MockNode, priority_fee: u64) {
+        let fee = |fee: u64| json!(format!("{:#x}", fee));
+        node.respond(
+            "eth_feeHistory",
+            json!({ "oldestBlock": "0x1", "baseFeePerGas": [fee(GWEI), fee(GWEI)], "gasUsedRatio": [0.5], "reward": [vec![fee(priority_fee); 3]] }),
+        );
+    }
+
+    fn decode(raw: &Value) -> DecodedTransaction {
+        let raw: Bytes = serde_json::from_value(raw.clone()).unwrap();
+        decode_signed_transaction(&raw.0).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_speed_up_until_mined() {
+        let node = MockNode::start().await;
+        // Only a transaction offering more than 3.5 gwei per gas is mined
+        node_mining(&node, |transaction| transaction.transaction.kind.max_gas_price() > U256::from(3_500_000_000u64));
+        let signer = PrivateKeySigner::random();
+        let provider = node.provider().with_signer(signer.clone());
+        let tracker = TransactionTracker::new().with_deadline(Duration::from_millis(50));
+
+        let request = TransactionRequest { from: signer.address(), to: Some(Address::repeat_byte(9)), ..Default::default() };
+        let mut tracked = tracker.send(&provider, request).await.unwrap();
+        let receipt = tracker.wait(&provider, &mut tracked).await.unwrap();
+        assert_eq!(tracked.replacements(), 2);
+        assert_eq!(receipt.transaction_hash, tracked.hash());
+
+        let sent: Vec<_> = node.requests_for("eth_sendRawTransaction").iter().map(|params| decode(&params[0])).collect();
+        assert!(sent.iter().all(|transaction| transaction.transaction.nonce == U256::from(4)));
+        let fees: Vec<_> = sent.iter().map(|transaction| transaction.transaction.kind).collect();
+        let eip1559 = |max_fee: u64, priority_fee: u64| TransactionKind::Eip1559 {
+            max_fee_per_gas: U256::from(max_fee),
+            max_priority_fee_per_gas: U256::from(priority_fee),
+        };
+        assert_eq!(fees, vec![eip1559(3 * GWEI, GWEI), eip1559(3_300_000_000, 1_100_000_000), eip1559(3_630_000_000, 1_210_000_000)]);
+    }
+
+    #[tokio::test]
+    async fn test_cancel() {
+        let node = MockNode::start().await;
+        node_mining(&node, |transaction| transaction.transaction.to == Some(transaction.from));
+        let signer = PrivateKeySigner::random();
+        let provider = node.provider().with_signer(signer.clone());
+        let tracker = TransactionTracker::new().with_deadline(Duration::from_secs(60));
+
+        let request = TransactionRequest {
+            from: signer.address(),
+            to: Some(Address::repeat_byte(9)),
+            value: Some(U256::from(1000)),
+            data: Some(Bytes(vec![1, 2, 3])),
+            ..Default::default()
+        };
+        let mut tracked = tracker.send(&provider, request).await.unwrap();
+        let hash = tracker.cancel(&provider, &mut tracked).await.unwrap();
+        assert!(tracked.cancelled);
+        assert_eq!(tracker.wait(&provider, &mut tracked).await.unwrap().transaction_hash, hash);
+
+        let cancel = decode(&node.requests_for("eth_sendRawTransaction")[1][0]).transaction;
+        assert_eq!((cancel.to, cancel.value, cancel.data.len(), cancel.gas), (Some(signer.address()), U256::zero(), 0, U256::from(21_000)));
+        assert_eq!(cancel.nonce, U256::from(4));
+    }
+
+    #[tokio::test]
+    async fn test_replacement_limits() {
+        let node = MockNode::start().await;
+        node_mining(&node, |_| false);
+        let signer = PrivateKeySigner::random();
+        let provider = node.provider().with_signer(signer.clone());
+        let request = TransactionRequest { from: signer.address(), to: Some(Address::repeat_byte(9)), ..Default::default() };
+
+        let tracker = TransactionTracker::new().with_deadline(Duration::from_millis(20)).with_max_replacements(1);
+        let mut tracked = tracker.send(&provider, request.clone()).await.unwrap();
+        assert!(matches!(tracker.wait(&provider, &mut tracked).await, Err(TrackerError::Stuck(hash)) if hash == tracked.hash()));
+
+        let capped = TransactionTracker::new().with_max_fee_per_gas(U256::from(3 * GWEI));
+        let mut tracked = capped.send(&provider, request).await.unwrap();
+        let result = capped.speed_up(&provider, &mut tracked).await;
+        assert!(matches!(result, Err(TrackerError::FeeCapExceeded { required, .. }) if required == U256::from(3_300_000_000u64)));
+
+        // A node demanding a larger bump gets a second, bigger one
+        node.fail_once("eth_sendRawTransaction", -32000, "replacement transaction underpriced", None);
+        let tracker = TransactionTracker::new();
+        tracker.speed_up(&provider, &mut tracked).await.unwrap();
+        assert_eq!(tracked.transaction.kind.max_gas_price(), U256::from(3_600_000_000u64));
+    }
+
+    #[tokio::test]
+    async fn test_replacement_follows_fee_oracle() {
+        let node = MockNode::start().await;
+        node_mining(&node, |_| false);
+        let signer = PrivateKeySigner::random();
+        let provider = node.provider().with_signer(signer.clone());
+        let tracker = TransactionTracker::new();
+        let request = TransactionRequest { from: signer.address(), to: Some(Address::repeat_byte(9)), ..Default::default() };
+        let mut tracked = tracker.send(&provider, request.clone()).await.unwrap();
+
+        // Recent blocks paid 2 gwei, more than a 10% bump of the 1 gwei priority fee
+        fee_history(&node, 2 * GWEI);
+        tracker.speed_up(&provider, &mut tracked).await.unwrap();
+        let expected = TransactionKind::Eip1559 { max_fee_per_gas: U256::from(4 * GWEI), max_priority_fee_per_gas: U256::from(2 * GWEI) };
+        assert_eq!(tracked.transaction.kind, expected);
+
+        // Legacy transactions are raised to the base fee plus the suggested priority fee
+        let legacy = TransactionRequest { gas_price: Some(GWEI.into()), ..request };
+        let mut tracked = tracker.send(&provider, legacy).await.unwrap();
+        tracker.speed_up(&provider, &mut tracked).await.unwrap();
+        assert_eq!(tracked.transaction.kind, TransactionKind::Legacy { gas_price: U256::from(3 * GWEI) });
+
+        // Errors other than an unsupported eth_feeHistory are returned
+        node.fail("eth_feeHistory", -32005, "rate limit exceeded", None);
+        let result = tracker.speed_up(&provider, &mut tracked).await;
+        assert!(matches!(result, Err(TrackerError::Provider(ProviderError::Rpc { code: -32005, .. }))));
+    }
+}
@@ -1,5 +1,22 @@
-use crate::framework::logging::{log_info, log_error};
-use web3::types::Address;
+use crate::contracts::abi::{decode, encode, Abi, AbiEvent, AbiType, AbiValue, CodecError, ResolveError};
+use crate::contracts::provider::{Provider, ProviderError};
+use crate::framework::logging::{log_info, log_debug, log_warn};
+use futures::channel::mpsc::UnboundedReceiver;
+use futures::stream::{self, BoxStream, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use web3::signing::keccak256;
+use web3::transports::{Either, Http, WebSocket};
+use web3::types::{Address, BlockHeader, BlockId, BlockNumber, Filter, FilterBuilder, Log, H256, U256, U64};
+use web3::{DuplexTransport, Transport};
 use std::str::FromStr;
 use std::time::Duration;
 
@@ -7,39 +24,1093 @@ use std::time::Duration;
 #[derive(Debug)]
 pub enum WatchError {
     InvalidAddress,
+    UnknownEvent(ResolveError),
+    /// An indexed-argument filter names no indexed parameter of the event, or its value does not fit.
+    InvalidFilter(String),
+    /// A log does not match the event it was fetched for.
+    InvalidLog(CodecError),
+    Provider(ProviderError),
     EventListeningFailed,
+    /// The connection of a subscription was lost and could not be re-established.
+    ConnectionLost(String),
+    /// A reorganization went deeper than the blocks an `EventWatcher` remembers; events delivered
+    /// before them may have been rolled back unnoticed. Carries the number of blocks rolled back.
+    ReorgTooDeep(u64),
+    /// A backfill checkpoint could not be read or written, or belongs to another event.
+    Checkpoint(String),
+}
+
+/// An event log decoded against the ABI.
+///
+/// # Fields
+/// - `name`: The name of the event.
+/// - `args`: The event parameters with their names, in declaration order. Indexed parameters of
+///   dynamic types (strings, bytes, arrays and tuples) are only logged as their Keccak-256 hash,
+///   which is returned as `AbiValue::FixedBytes`.
+/// - `address`: The contract that emitted the event.
+/// - `block`: The number of the block the log was included in.
+/// - `block_hash`: The hash of that block.
+/// - `tx_hash`: The transaction that emitted the event.
+/// - `log_index`: The position of the log in the block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedEvent {
+    pub name: String,
+    pub args: Vec<(String, AbiValue)>,
+    pub address: Address,
+    pub block: u64,
+    pub block_hash: Option<H256>,
+    pub tx_hash: H256,
+    pub log_index: u64,
+}
+
+impl DecodedEvent {
+    /// Returns the value of a parameter by name.
+    pub fn arg(&self, name: &str) -> Option<&AbiValue> {
+        self.args.iter().find(|(arg, _)| arg == name).map(|(_, value)| value)
+    }
+}
+
+/// The logs of one event of one contract, optionally narrowed down by indexed arguments.
+#[derive(Debug, Clone)]
+pub struct EventFilter {
+    address: Address,
+    event: AbiEvent,
+    /// The topic0 of logs of the event, unless it is anonymous.
+    signature: Option<H256>,
+    /// The wanted values of each topic position; `None` matches anything.
+    topics: Vec<Option<Vec<H256>>>,
+}
+
+impl EventFilter {
+    /// Builds the filter for an event.
+    ///
+    /// # Arguments
+    /// * `contract_address` - The address of the contract.
+    /// * `abi` - The ABI of the contract, used to resolve the event.
+    /// * `event_name` - The name or signature of the event.
+    /// * `indexed` - Values of indexed parameters, by parameter name. A log matches when every
+    ///   named parameter has one of the values given for it.
+    ///
+    /// # Returns
+    /// Result<EventFilter, WatchError> - The filter, otherwise an error.
+    pub fn new(contract_address: &str, abi: &Abi, event_name: &str, indexed: &[(&str, AbiValue)]) -> Result<Self, WatchError> {
+        // Input validation: ensure contract address is valid
+        let address = Address::from_str(contract_address).map_err(|_| WatchError::InvalidAddress)?;
+
+        // Resolve the event against the ABI to obtain the topic its logs are filtered by
+        let event = abi.resolve_event(event_name).map_err(WatchError::UnknownEvent)?.clone();
+        let signature = match event.anonymous {
+            true => None,
+            false => Some(event.topic().map_err(|e| WatchError::UnknownEvent(ResolveError::InvalidSignature(e)))?),
+        };
+        let mut topics: Vec<Option<Vec<H256>>> = signature.iter().map(|topic| Some(vec![*topic])).collect();
+        for param in event.inputs.iter().filter(|param| param.indexed) {
+            let values: Vec<H256> = indexed
+                .iter()
+                .filter(|(name, _)| *name == param.name)
+                .map(|(_, value)| {
+                    let ty = AbiType::from_param(param).and_then(|ty| value.type_check(&ty).map(|_| ty));
+                    ty.and_then(|ty| topic_for(&ty, value))
+                        .map_err(|e| WatchError::InvalidFilter(format!("{}: {:?}", param.name, e)))
+                })
+                .collect::<Result<_, _>>()?;
+            topics.push((!values.is_empty()).then_some(values));
+        }
+        if let Some((name, _)) = indexed.iter().find(|(name, _)| !event.inputs.iter().any(|param| param.indexed && param.name == *name)) {
+            return Err(WatchError::InvalidFilter(format!("{} is not an indexed parameter of {}", name, event.name)));
+        }
+        // Trailing wildcards are implied
+        while topics.last() == Some(&None) {
+            topics.pop();
+        }
+        Ok(EventFilter { address, event, signature, topics })
+    }
+
+    /// Returns the event the filter matches.
+    pub fn event(&self) -> &AbiEvent {
+        &self.event
+    }
+
+    /// Returns the contract the filter matches.
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    /// Returns the checkpoint of a backfill of the filter's event.
+    fn checkpoint(&self, next_block: u64) -> Result<Checkpoint, WatchError> {
+        let event = self.event.signature().map_err(WatchError::InvalidLog)?;
+        Ok(Checkpoint { address: self.address, event, next_block })
+    }
+
+    /// Builds the `eth_getLogs` filter for a range of blocks.
+    pub fn to_filter(&self, from_block: BlockNumber, to_block: BlockNumber) -> Filter {
+        self.builder().from_block(from_block).to_block(to_block).build()
+    }
+
+    /// Starts a filter on the address and topics, without a block range.
+    fn builder(&self) -> FilterBuilder {
+        let topic = |position: usize| self.topics.get(position).cloned().flatten();
+        FilterBuilder::default()
+            .address(vec![self.address])
+            .topics(topic(0), topic(1), topic(2), topic(3))
+    }
+
+    /// Decodes a log of the event.
+    ///
+    /// # Returns
+    /// Result<DecodedEvent, WatchError> - The event, or `InvalidLog` if the log does not match it.
+    pub fn decode(&self, log: &Log) -> Result<DecodedEvent, WatchError> {
+        let invalid = |message: &str| WatchError::InvalidLog(CodecError::InvalidData(message.to_string()));
+        let mut topics = log.topics.iter();
+        if self.signature.is_some() && topics.next() != self.signature.as_ref() {
+            return Err(invalid("the log is of another event"));
+        }
+
+        let types = AbiType::from_params(&self.event.inputs).map_err(WatchError::InvalidLog)?;
+        let data_types: Vec<AbiType> = self
+            .event
+            .inputs
+            .iter()
+            .zip(&types)
+            .filter(|(param, _)| !param.indexed)
+            .map(|(_, ty)| ty.clone())
+            .collect();
+        let mut data = decode(&data_types, &log.data.0).map_err(WatchError::InvalidLog)?.into_iter();
+
+        let mut args = Vec::with_capacity(types.len());
+        for (param, ty) in self.event.inputs.iter().zip(&types) {
+            let value = match param.indexed {
+                true => {
+                    let topic = topics.next().ok_or_else(|| invalid("the log has too few topics"))?;
+                    match ty.is_dynamic() || matches!(ty, AbiType::Tuple(_) | AbiType::FixedArray(..)) {
+                        true => AbiValue::FixedBytes(topic.as_bytes().to_vec()),
+                        false => decode(std::slice::from_ref(ty), topic.as_bytes()).map_err(WatchError::InvalidLog)?.remove(0),
+                    }
+                }
+                false => data.next().expect("one decoded value per data parameter"),
+            };
+            args.push((param.name.clone(), value));
+        }
+        if topics.next().is_some() {
+            return Err(invalid("the log has too many topics"));
+        }
+
+        Ok(DecodedEvent {
+            name: self.event.name.clone(),
+            args,
+            address: log.address,
+            block: log.block_number.unwrap_or_default().as_u64(),
+            block_hash: log.block_hash,
+            tx_hash: log.transaction_hash.unwrap_or_default(),
+            log_index: log.log_index.unwrap_or_default().as_u64(),
+        })
+    }
+}
+
+/// Returns the topic an indexed parameter value is logged as: the value itself for value types,
+/// and the Keccak-256 hash of the bytes for strings and byte arrays.
+pub fn topic_for(ty: &AbiType, value: &AbiValue) -> Result<H256, CodecError> {
+    match (ty, value) {
+        (AbiType::String, AbiValue::String(string)) => Ok(H256(keccak256(string.as_bytes()))),
+        (AbiType::Bytes, AbiValue::Bytes(bytes)) => Ok(H256(keccak256(bytes))),
+        (AbiType::Array(_) | AbiType::FixedArray(..) | AbiType::Tuple(_), _) => {
+            Err(CodecError::TypeMismatch("arrays and tuples cannot be filtered by value".to_string()))
+        }
+        _ => Ok(H256::from_slice(&encode(std::slice::from_ref(value)))),
+    }
+}
+
+/// The number of blocks of the first `eth_getLogs` request of a backfill.
+pub const DEFAULT_CHUNK_SIZE: u64 = 1_000;
+
+/// The largest number of blocks a backfill requests logs for at once.
+pub const DEFAULT_MAX_CHUNK_SIZE: u64 = 10_000;
+
+/// The confirmations, counting the block itself, a block needs before a backfill scans it.
+pub const DEFAULT_BACKFILL_CONFIRMATIONS: u64 = 12;
+
+/// Returns whether the node rejected an `eth_getLogs` request because its block range or result
+/// set was too large.
+///
+/// Matches error code -32005 and the messages of Infura ("query returned more than 10000
+/// results"), Alchemy ("Log response size exceeded"), QuickNode ("limited to a 10,000 range"),
+/// geth ("exceed maximum block range") and Erigon ("query exceeds max results").
+pub fn is_result_limit(error: &ProviderError) -> bool {
+    match error {
+        ProviderError::Rpc { code, message, .. } => {
+            let message = message.to_lowercase();
+            *code == -32005
+                || ["more than", "response size exceeded", "limited to", "block range", "max results"]
+                    .iter()
+                    .any(|pattern| message.contains(pattern))
+        }
+        _ => false,
+    }
+}
+
+/// Where a backfill resumes: every log of the event in blocks before `next_block` was delivered.
+///
+/// # Fields
+/// - `address`: The contract the checkpoint belongs to.
+/// - `event`: The canonical signature of the event the checkpoint belongs to.
+/// - `next_block`: The first block whose logs were not delivered yet.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub address: Address,
+    pub event: String,
+    pub next_block: u64,
+}
+
+impl Checkpoint {
+    /// Reads a checkpoint, if the file exists.
+    pub fn load(path: &Path) -> Result<Option<Checkpoint>, WatchError> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = fs::read_to_string(path).map_err(|e| WatchError::Checkpoint(format!("{}: {}", path.display(), e)))?;
+        serde_json::from_str(&contents)
+            .map(Some)
+            .map_err(|e| WatchError::Checkpoint(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Writes the checkpoint to a temporary file and renames it over `path`, so that a crash never
+    /// leaves a torn checkpoint behind.
+    pub fn save(&self, path: &Path) -> Result<(), WatchError> {
+        let io_error = |e: std::io::Error| WatchError::Checkpoint(format!("{}: {}", path.display(), e));
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(io_error)?;
+        }
+        let contents = serde_json::to_string(self).map_err(|e| WatchError::Checkpoint(e.to_string()))?;
+        let temporary = path.with_extension("tmp");
+        let mut file = File::create(&temporary).map_err(io_error)?;
+        writeln!(file, "{}", contents).and_then(|_| file.sync_all()).map_err(io_error)?;
+        fs::rename(&temporary, path).map_err(io_error)
+    }
+}
+
+/// Scans the history of an event before `watch_contract_events` switches to new blocks.
+///
+/// The history is fetched in chunks of blocks: a chunk is halved whenever the node rejects it for
+/// exceeding its result limits (see `is_result_limit`), and grows by half after every chunk that
+/// succeeds, up to the maximum. With a checkpoint file, the progress is saved once the events of a
+/// chunk were consumed, and a restarted backfill resumes after the last saved chunk.
+///
+/// Only blocks with the given number of confirmations are scanned, during the backfill and while
+/// following new blocks afterwards, so a checkpoint never covers a block that a reorganization
+/// shallower than that could still replace.
+#[derive(Debug, Clone)]
+pub struct Backfill {
+    from_block: u64,
+    checkpoint: Option<PathBuf>,
+    chunk_size: u64,
+    max_chunk_size: u64,
+    confirmations: u64,
+}
+
+impl Backfill {
+    /// Scans from a block on.
+    pub fn from_block(from_block: u64) -> Self {
+        Backfill {
+            from_block,
+            checkpoint: None,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            max_chunk_size: DEFAULT_MAX_CHUNK_SIZE,
+            confirmations: DEFAULT_BACKFILL_CONFIRMATIONS,
+        }
+    }
+
+    /// Saves the progress to a checkpoint file, and resumes from it if it exists.
+    pub fn with_checkpoint<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.checkpoint = Some(path.into());
+        self
+    }
+
+    /// Sets the number of blocks of the first request.
+    pub fn with_chunk_size(mut self, chunk_size: u64) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Sets the largest number of blocks requested at once.
+    pub fn with_max_chunk_size(mut self, max_chunk_size: u64) -> Self {
+        self.max_chunk_size = max_chunk_size.max(1);
+        self
+    }
+
+    /// Sets how many confirmations, counting the block itself, a block needs to be scanned.
+    pub fn with_confirmations(mut self, confirmations: u64) -> Self {
+        self.confirmations = confirmations.max(1);
+        self
+    }
+
+    /// Returns the block to scan from: the saved checkpoint if there is one, otherwise the start.
+    fn start(&self, filter: &EventFilter) -> Result<U64, WatchError> {
+        let path = match &self.checkpoint {
+            Some(path) => path,
+            None => return Ok(self.from_block.into()),
+        };
+        match Checkpoint::load(path)? {
+            Some(checkpoint) if checkpoint != filter.checkpoint(checkpoint.next_block)? => Err(WatchError::Checkpoint(format!(
+                "{} belongs to {} of {:?}",
+                path.display(),
+                checkpoint.event,
+                checkpoint.address
+            ))),
+            Some(checkpoint) => {
+                log_info(&format!("Resuming the backfill from {} at block {}", path.display(), checkpoint.next_block));
+                Ok(checkpoint.next_block.into())
+            }
+            None => Ok(self.from_block.into()),
+        }
+    }
+}
+
+/// Polls `eth_getLogs` for new blocks in chunks and queues the decoded events.
+struct LogPoller<'a, T: Transport> {
+    provider: &'a Provider<T>,
+    filter: EventFilter,
+    next_block: U64,
+    poll_interval: Duration,
+    pending: VecDeque<Result<DecodedEvent, WatchError>>,
+    chunk_size: u64,
+    max_chunk_size: u64,
+    /// How many confirmations a block needs to be scanned.
+    confirmations: u64,
+    /// The checkpoint file and the block it was last saved at.
+    checkpoint: Option<(PathBuf, U64)>,
+    /// Whether the last poll reached the head; until then chunks are fetched without waiting.
+    caught_up: bool,
+}
+
+impl<T: Transport> LogPoller<'_, T> {
+    async fn next(&mut self) -> Result<DecodedEvent, WatchError> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return event;
+            }
+            // Every event before the next block has been consumed
+            if let Some((path, saved)) = &mut self.checkpoint {
+                if *saved != self.next_block {
+                    self.filter.checkpoint(self.next_block.as_u64())?.save(path)?;
+                    *saved = self.next_block;
+                }
+            }
+            if self.caught_up {
+                tokio::time::sleep(self.poll_interval).await;
+            }
+            self.poll().await?;
+        }
+    }
+
+    async fn poll(&mut self) -> Result<(), WatchError> {
+        let head = self.provider.block_number().await.map_err(WatchError::Provider)?;
+        let head = head.saturating_sub(U64::from(self.confirmations - 1));
+        if head < self.next_block {
+            self.caught_up = true;
+            return Ok(());
+        }
+        let to = head.min(self.next_block + self.chunk_size - 1);
+        let filter = self.filter.to_filter(BlockNumber::Number(self.next_block), BlockNumber::Number(to));
+        let logs = match self.provider.logs(filter).await {
+            Ok(logs) => logs,
+            Err(e) if is_result_limit(&e) && self.chunk_size > 1 => {
+                self.chunk_size /= 2;
+                self.caught_up = false;
+                log_debug(&format!("eth_getLogs exceeded the node's limits, retrying with {} blocks", self.chunk_size));
+                return Ok(());
+            }
+            Err(e) => return Err(WatchError::Provider(e)),
+        };
+        if logs.is_empty() {
+            log_debug(&format!("No events found for contract: {:?} in blocks {} to {}", self.filter.address, self.next_block, to));
+        }
+        self.pending.extend(logs.iter().map(|log| self.filter.decode(log)));
+        self.next_block = to + 1;
+        self.caught_up = to == head;
+        self.chunk_size = (self.chunk_size + self.chunk_size.div_ceil(2)).min(self.max_chunk_size);
+        Ok(())
+    }
 }
 
 /// Watches for events from a smart contract with security checks and error handling.
-/// 
+///
+/// Starting at the current head, or at the start of the backfill, the node is polled with
+/// `eth_getLogs` for logs of the given event, and every log is decoded against the ABI.
+/// `Subscriber::watch_events` receives them over a WebSocket subscription instead, and
+/// `EventWatcher` follows reorganizations.
+///
 /// # Arguments
+/// * `provider` - The node to poll.
 /// * `contract_address` - The address of the contract.
-/// * `event_name` - The name of the event to watch for.
+/// * `abi` - The ABI of the contract, used to resolve the event and decode its logs.
+/// * `event_name` - The name or signature of the event to watch for.
+/// * `indexed` - Values of indexed parameters to filter by (see `EventFilter::new`).
 /// * `poll_interval` - How often to check for events.
+/// * `backfill` - The history to deliver first, up to the last confirmed block, before new blocks
+///   are watched (see `Backfill`).
 ///
 /// # Returns
-/// Result<(), WatchError> - Returns Ok if event watching succeeds, otherwise returns an error.
-pub async fn watch_contract_events(
+/// Result<impl Stream, WatchError> - An endless stream of decoded events, in chain order. A failed
+/// poll or a log that cannot be decoded is yielded as an error and watching continues.
+pub async fn watch_contract_events<'a, T: Transport>(
+    provider: &'a Provider<T>,
     contract_address: &str,
+    abi: &Abi,
     event_name: &str,
+    indexed: &[(&str, AbiValue)],
+    poll_interval: Duration,
+    backfill: Option<Backfill>,
+) -> Result<impl Stream<Item = Result<DecodedEvent, WatchError>> + 'a, WatchError> {
+    let filter = EventFilter::new(contract_address, abi, event_name, indexed)?;
+    log_info(&format!("Starting to watch events for contract: {} (event {})", contract_address, filter.event.name)); // Corrected log
+
+    let next_block = match &backfill {
+        Some(backfill) => backfill.start(&filter)?,
+        None => provider.block_number().await.map_err(WatchError::Provider)?,
+    };
+    let backfill = backfill.unwrap_or_else(|| Backfill::from_block(next_block.as_u64()).with_confirmations(1));
+    let poller = LogPoller {
+        provider,
+        filter,
+        next_block,
+        poll_interval,
+        pending: VecDeque::new(),
+        chunk_size: backfill.chunk_size,
+        max_chunk_size: backfill.max_chunk_size,
+        confirmations: backfill.confirmations,
+        checkpoint: backfill.checkpoint.map(|path| (path, next_block)),
+        caught_up: false,
+    };
+    Ok(stream::unfold(poller, |mut poller| async move {
+        let event = poller.next().await;
+        Some((event, poller))
+    }))
+}
+
+/// A change to the events on the canonical chain, as delivered by an `EventWatcher`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventUpdate {
+    /// An event on the canonical chain, with at least the requested number of confirmations.
+    Added(DecodedEvent),
+    /// An event delivered as `Added` before, whose block was dropped in a reorganization.
+    Removed(DecodedEvent),
+}
+
+/// A block the watcher has seen, with the events of the watched filter in it.
+#[derive(Debug, Clone)]
+struct TrackedBlock {
+    number: u64,
+    hash: H256,
+    events: Vec<DecodedEvent>,
+    delivered: bool,
+}
+
+/// The recent canonical chain as the watcher has seen it.
+///
+/// Blocks are remembered until they are both delivered and older than the history, so that a
+/// reorganization can be traced back to the last block the watcher and the node agree on.
+#[derive(Debug)]
+struct ChainTracker {
+    blocks: VecDeque<TrackedBlock>,
+    /// The number of the next block to fetch.
+    next: u64,
+    /// Whether delivered blocks were forgotten, so that the chain before the first one is unknown.
+    pruned: bool,
+    confirmations: u64,
+    history: usize,
+}
+
+impl ChainTracker {
+    fn new(next: u64, confirmations: u64, history: usize) -> Self {
+        ChainTracker { blocks: VecDeque::new(), next, pruned: false, confirmations: confirmations.max(1), history }
+    }
+
+    fn tip(&self) -> Option<&TrackedBlock> {
+        self.blocks.back()
+    }
+
+    /// Forgets the tip block after it was dropped from the canonical chain.
+    ///
+    /// # Returns
+    /// Vec<EventUpdate> - `Removed` for every event of the block that was delivered, last first.
+    fn pop(&mut self) -> Vec<EventUpdate> {
+        let block = match self.blocks.pop_back() {
+            Some(block) => block,
+            None => return Vec::new(),
+        };
+        self.next = block.number;
+        match block.delivered {
+            true => block.events.into_iter().rev().map(EventUpdate::Removed).collect(),
+            false => Vec::new(),
+        }
+    }
+
+    fn push(&mut self, block: TrackedBlock) {
+        self.next = block.number + 1;
+        self.blocks.push_back(block);
+    }
+
+    /// Delivers the events of the blocks that gained enough confirmations, and forgets blocks
+    /// beyond the history.
+    ///
+    /// # Returns
+    /// Vec<EventUpdate> - `Added` for every newly confirmed event, in chain order.
+    fn confirm(&mut self, head: u64) -> Vec<EventUpdate> {
+        let mut updates = Vec::new();
+        for block in self.blocks.iter_mut().filter(|block| !block.delivered) {
+            if block.number + self.confirmations > head + 1 {
+                break;
+            }
+            block.delivered = true;
+            updates.extend(block.events.iter().cloned().map(EventUpdate::Added));
+        }
+        while self.blocks.len() > self.history && self.blocks.front().is_some_and(|block| block.delivered) {
+            self.blocks.pop_front();
+            self.pruned = true;
+        }
+        updates
+    }
+}
+
+/// Watches the events of a contract across chain reorganizations.
+///
+/// The watcher remembers the hash of every block it processed. Before fetching new blocks it
+/// checks its latest block against the node, and walks back until both agree; the events of
+/// dropped blocks that were delivered are reported as `EventUpdate::Removed`. With a confirmation
+/// depth, events are only delivered once their block is that deep, so reorganizations shallower
+/// than it never surface. Applying every `Added` and `Removed` in order never counts an event
+/// twice, even when a reorganization re-includes its transaction in another block.
+#[derive(Debug)]
+pub struct EventWatcher<'a, T: Transport = Http> {
+    provider: &'a Provider<T>,
+    filter: EventFilter,
+    confirmations: u64,
     poll_interval: Duration,
-) -> Result<(), WatchError> {
-    // Input validation: ensure contract address is valid
-    if Address::from_str(contract_address).is_err() {
-        return Err(WatchError::InvalidAddress);
+    history: usize,
+}
+
+impl<'a, T: Transport> EventWatcher<'a, T> {
+    /// Creates a watcher that delivers events as soon as they are mined.
+    ///
+    /// # Arguments
+    /// * `provider` - The node to poll, at its poll interval.
+    /// * `filter` - The event and indexed argument values to watch for.
+    pub fn new(provider: &'a Provider<T>, filter: EventFilter) -> Self {
+        EventWatcher { provider, filter, confirmations: 1, poll_interval: provider.poll_interval(), history: 128 }
+    }
+
+    /// Sets how many confirmations, counting the block itself, an event needs to be delivered.
+    pub fn with_confirmations(mut self, confirmations: u64) -> Self {
+        self.confirmations = confirmations;
+        self
     }
 
-    log_info(&format!("Starting to watch events for contract: {}", contract_address)); // Corrected log
+    /// Sets how often the node is polled.
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Sets how many delivered blocks are remembered to trace reorganizations back.
+    pub fn with_history(mut self, history: usize) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// Starts watching at the current head.
+    ///
+    /// # Returns
+    /// Result<impl Stream, WatchError> - An endless stream of updates. A failed poll is yielded as
+    /// an error and retried, as is a reorganization deeper than the history (`ReorgTooDeep`).
+    pub async fn watch(self) -> Result<impl Stream<Item = Result<EventUpdate, WatchError>> + 'a, WatchError> {
+        let head = self.provider.block_number().await.map_err(WatchError::Provider)?;
+        log_info(&format!(
+            "Watching {} of {:?} from block {} with {} confirmation(s)",
+            self.filter.event.name,
+            self.filter.address,
+            head + 1,
+            self.confirmations
+        ));
+        let tracker = ChainTracker::new(head.as_u64() + 1, self.confirmations, self.history.max(1));
+        let state = (self, tracker, VecDeque::new(), false);
+        Ok(stream::unfold(state, |(watcher, mut tracker, mut pending, mut polled)| async move {
+            loop {
+                if let Some(update) = pending.pop_front() {
+                    return Some((update, (watcher, tracker, pending, polled)));
+                }
+                if std::mem::replace(&mut polled, true) {
+                    tokio::time::sleep(watcher.poll_interval).await;
+                }
+                if let Err(e) = watcher.poll(&mut tracker, &mut pending).await {
+                    pending.push_back(Err(e));
+                }
+            }
+        }))
+    }
+
+    async fn poll(&self, tracker: &mut ChainTracker, pending: &mut VecDeque<Result<EventUpdate, WatchError>>) -> Result<(), WatchError> {
+        let head = self.provider.block_number().await.map_err(WatchError::Provider)?.as_u64();
 
-    // Simulate event watching logic (real logic would go here)
-    let event_found = false;
+        // Walk back to the last block the node still has
+        let mut depth = 0;
+        while let Some(tip) = tracker.tip() {
+            if self.hash_of(tip.number).await? == Some(tip.hash) {
+                break;
+            }
+            pending.extend(tracker.pop().into_iter().map(Ok));
+            depth += 1;
+        }
+        if depth > 0 {
+            log_warn(&format!("Reorganization of {} block(s) detected, resuming at block {}", depth, tracker.next));
+            if tracker.tip().is_none() && tracker.pruned {
+                pending.push_back(Err(WatchError::ReorgTooDeep(depth)));
+            }
+        }
 
-    if event_found {
-        log_info(&format!("Event '{}' found for contract: {}", event_name, contract_address)); // Corrected log
+        if tracker.next <= head {
+            let blocks = self.fetch(tracker, head).await?;
+            blocks.into_iter().for_each(|block| tracker.push(block));
+        }
+        pending.extend(tracker.confirm(head).into_iter().map(Ok));
         Ok(())
-    } else {
-        log_error(&format!("No events found for contract: {}", contract_address)); // Corrected log
-        Err(WatchError::EventListeningFailed)
+    }
+
+    /// Fetches the blocks from `tracker.next` to `head` with their events.
+    ///
+    /// Blocks are only returned while they chain onto each other and their logs carry their hash;
+    /// the rest was reorganized while fetching and is left to the next poll.
+    async fn fetch(&self, tracker: &ChainTracker, head: u64) -> Result<Vec<TrackedBlock>, WatchError> {
+        let mut parent = tracker.tip().map(|tip| tip.hash);
+        let mut blocks = Vec::new();
+        for number in tracker.next..=head {
+            let block = self.provider.block(BlockId::Number(BlockNumber::Number(number.into()))).await.map_err(WatchError::Provider)?;
+            let block = match block {
+                Some(block) if parent.is_none_or(|parent| parent == block.parent_hash) => block,
+                _ => break,
+            };
+            let hash = block.hash.unwrap_or_default();
+            parent = Some(hash);
+            blocks.push(TrackedBlock { number, hash, events: Vec::new(), delivered: false });
+        }
+        let last = match blocks.last() {
+            Some(block) => block.number,
+            None => return Ok(blocks),
+        };
+
+        let filter = self.filter.to_filter(BlockNumber::Number(tracker.next.into()), BlockNumber::Number(last.into()));
+        let mut logs: BTreeMap<u64, Vec<Log>> = BTreeMap::new();
+        for log in self.provider.logs(filter).await.map_err(WatchError::Provider)? {
+            if log.removed != Some(true) {
+                logs.entry(log.block_number.unwrap_or_default().as_u64()).or_default().push(log);
+            }
+        }
+        for (index, block) in blocks.iter_mut().enumerate() {
+            let logs = logs.remove(&block.number).unwrap_or_default();
+            if logs.iter().any(|log| log.block_hash.is_some_and(|hash| hash != block.hash)) {
+                blocks.truncate(index);
+                break;
+            }
+            block.events = logs.iter().map(|log| self.filter.decode(log)).collect::<Result<_, _>>()?;
+        }
+        Ok(blocks)
+    }
+
+    async fn hash_of(&self, number: u64) -> Result<Option<H256>, WatchError> {
+        let block = self.provider.block(BlockId::Number(BlockNumber::Number(number.into()))).await.map_err(WatchError::Provider)?;
+        Ok(block.and_then(|block| block.hash))
+    }
+}
+
+/// The longest wait between two reconnect attempts.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// The transport a subscription talks to the node over.
+type SubscriptionTransport = Either<WebSocket, Http>;
+
+/// What a subscription delivers.
+#[derive(Debug, Clone)]
+pub enum SubscriptionKind {
+    /// The header of every new block.
+    NewHeads,
+    /// The logs matching an event filter.
+    Logs(EventFilter),
+    /// The hash of every transaction entering the node's pool.
+    NewPendingTransactions,
+}
+
+impl SubscriptionKind {
+    /// Returns the `eth_subscribe` parameters of the subscription.
+    fn params(&self) -> Vec<Value> {
+        match self {
+            SubscriptionKind::NewHeads => vec![json!("newHeads")],
+            SubscriptionKind::Logs(filter) => vec![json!("logs"), json!(filter.builder().build())],
+            SubscriptionKind::NewPendingTransactions => vec![json!("newPendingTransactions")],
+        }
+    }
+
+    /// Decodes the result of an `eth_subscription` notification.
+    fn parse(&self, value: Value) -> Result<Notification, WatchError> {
+        let notification = match self {
+            SubscriptionKind::NewHeads => serde_json::from_value(value).map(|header| Notification::NewHead(Box::new(header))),
+            SubscriptionKind::Logs(_) => serde_json::from_value(value).map(|log| Notification::Log(Box::new(log))),
+            SubscriptionKind::NewPendingTransactions => serde_json::from_value(value).map(Notification::PendingTransaction),
+        };
+        notification.map_err(|e| WatchError::Provider(ProviderError::InvalidResponse(format!("eth_subscription: {}", e))))
+    }
+}
+
+/// The block hash of a header, or the block hash and index of a log.
+type NotificationId = (H256, Option<U256>);
+
+/// A notification delivered by a subscription.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Notification {
+    NewHead(Box<BlockHeader>),
+    Log(Box<Log>),
+    PendingTransaction(H256),
+}
+
+impl Notification {
+    /// Returns the number of the block the notification belongs to; pending transactions have none.
+    pub fn block(&self) -> Option<U64> {
+        match self {
+            Notification::NewHead(header) => header.number,
+            Notification::Log(log) => log.block_number,
+            Notification::PendingTransaction(_) => None,
+        }
+    }
+
+    /// Identifies a header by its hash and a log by its block hash and index.
+    fn identity(&self) -> Option<NotificationId> {
+        match self {
+            Notification::NewHead(header) => header.hash.map(|hash| (hash, None)),
+            Notification::Log(log) => log.block_hash.map(|hash| (hash, log.log_index)),
+            Notification::PendingTransaction(_) => None,
+        }
+    }
+}
+
+/// Opens `eth_subscribe` subscriptions over WebSocket, reconnecting and resubscribing whenever
+/// the connection drops.
+///
+/// Endpoints that cannot push notifications, HTTP endpoints and WebSocket endpoints rejecting
+/// `eth_subscribe`, are polled instead: new blocks with `eth_getBlockByNumber` and `eth_getLogs`,
+/// pending transactions with an `eth_newPendingTransactionFilter` filter.
+#[derive(Debug, Clone)]
+pub struct Subscriber {
+    url: String,
+    poll_interval: Duration,
+    reconnect_delay: Duration,
+    max_reconnect_attempts: usize,
+}
+
+impl Subscriber {
+    /// Creates a subscriber for an endpoint.
+    ///
+    /// # Arguments
+    /// * `url` - A `ws://` or `wss://` endpoint, or an `http://` or `https://` endpoint to poll.
+    pub fn new(url: &str) -> Self {
+        Subscriber {
+            url: url.to_string(),
+            poll_interval: Duration::from_secs(1),
+            reconnect_delay: Duration::from_secs(1),
+            max_reconnect_attempts: 10,
+        }
+    }
+
+    /// Sets how often an endpoint without subscription support is polled.
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Sets the wait before the first reconnect attempt; it doubles after every failed attempt.
+    pub fn with_reconnect_delay(mut self, reconnect_delay: Duration) -> Self {
+        self.reconnect_delay = reconnect_delay;
+        self
+    }
+
+    /// Sets how many reconnect attempts are made before the subscription gives up.
+    pub fn with_max_reconnect_attempts(mut self, max_reconnect_attempts: usize) -> Self {
+        self.max_reconnect_attempts = max_reconnect_attempts;
+        self
+    }
+
+    /// Subscribes to notifications.
+    ///
+    /// Every subscription uses a connection of its own. After a reconnect, the headers and logs of
+    /// the blocks mined while disconnected are fetched and delivered before live notifications
+    /// resume, so none are missed; pending transactions announced meanwhile are lost.
+    ///
+    /// # Arguments
+    /// * `kind` - What to subscribe to.
+    ///
+    /// # Returns
+    /// Result<Subscription, WatchError> - The stream of notifications, or an error if the endpoint
+    /// cannot be reached. The stream ends with `ConnectionLost` once reconnecting gives up.
+    pub async fn subscribe(&self, kind: SubscriptionKind) -> Result<Subscription, WatchError> {
+        let (source, head) = connect(&self.url, &kind).await?;
+        let polling = Arc::new(AtomicBool::new(matches!(source, Source::Polling { .. })));
+        log_info(&format!("Subscribed to {:?} at {} from block {}", kind, self.url, head));
+
+        let state = SubscriptionState {
+            subscriber: self.clone(),
+            kind,
+            source,
+            polling: polling.clone(),
+            pending: VecDeque::new(),
+            last_block: head,
+            backfilled: None,
+        };
+        let notifications = stream::unfold(state, |mut state| async move {
+            let notification = state.next().await?;
+            Some((notification, state))
+        });
+        Ok(Subscription { polling, notifications: notifications.boxed() })
+    }
+
+    /// Subscribes to the logs of an event and decodes them against the ABI.
+    ///
+    /// # Arguments
+    /// * `filter` - The event and indexed argument values to watch for.
+    ///
+    /// # Returns
+    /// Result<impl Stream, WatchError> - The decoded events, in chain order.
+    pub async fn watch_events(&self, filter: EventFilter) -> Result<impl Stream<Item = Result<DecodedEvent, WatchError>>, WatchError> {
+        let subscription = self.subscribe(SubscriptionKind::Logs(filter.clone())).await?;
+        Ok(subscription.map(move |notification| match notification? {
+            Notification::Log(log) => filter.decode(&log),
+            other => Err(WatchError::Provider(ProviderError::InvalidResponse(format!("expected a log, got {:?}", other)))),
+        }))
+    }
+}
+
+/// A stream of the notifications of one subscription.
+pub struct Subscription {
+    polling: Arc<AtomicBool>,
+    notifications: BoxStream<'static, Result<Notification, WatchError>>,
+}
+
+impl Subscription {
+    /// Returns whether the endpoint is polled because it does not support subscriptions.
+    pub fn is_polling(&self) -> bool {
+        self.polling.load(Ordering::Relaxed)
+    }
+}
+
+impl Stream for Subscription {
+    type Item = Result<Notification, WatchError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.notifications.poll_next_unpin(cx)
+    }
+}
+
+/// Where the notifications of a subscription come from.
+enum Source {
+    /// Pushed by the node over a WebSocket subscription.
+    Live { provider: Provider<SubscriptionTransport>, id: String, notifications: UnboundedReceiver<Value> },
+    /// Pulled from the node every poll interval; `filter_id` is the pending-transaction filter.
+    Polling { provider: Provider<SubscriptionTransport>, filter_id: Option<U256>, polled: bool },
+    /// Reconnecting gave up.
+    Closed,
+}
+
+/// Connects to the endpoint and subscribes, falling back to polling if the endpoint cannot push
+/// notifications.
+///
+/// # Returns
+/// Result<(Source, U64), WatchError> - The source and the head block at the time of subscribing.
+async fn connect(url: &str, kind: &SubscriptionKind) -> Result<(Source, U64), WatchError> {
+    let transport_error = |e: web3::Error| WatchError::Provider(ProviderError::Transport(e.to_string()));
+    if url.starts_with("http://") || url.starts_with("https://") {
+        let transport = Http::new(url).map_err(|e| WatchError::Provider(ProviderError::InvalidEndpoint(e.to_string())))?;
+        return polling(Provider::new(Either::Right(transport)), kind).await;
+    }
+
+    let transport = WebSocket::new(url).await.map_err(transport_error)?;
+    let provider = Provider::new(Either::Left(transport.clone()));
+    match provider.request::<String>("eth_subscribe", kind.params()).await {
+        Ok(id) => {
+            let notifications = transport.subscribe(id.clone().into()).map_err(transport_error)?;
+            // Read after subscribing, so that every later block is announced
+            let head = provider.block_number().await.map_err(WatchError::Provider)?;
+            Ok((Source::Live { provider, id, notifications }, head))
+        }
+        Err(ProviderError::Rpc { message, .. }) => {
+            log_warn(&format!("{} does not support eth_subscribe ({}), polling every block instead", url, message));
+            polling(provider, kind).await
+        }
+        Err(e) => Err(WatchError::Provider(e)),
+    }
+}
+
+async fn polling(provider: Provider<SubscriptionTransport>, kind: &SubscriptionKind) -> Result<(Source, U64), WatchError> {
+    let head = provider.block_number().await.map_err(WatchError::Provider)?;
+    let filter_id = match kind {
+        SubscriptionKind::NewPendingTransactions => {
+            Some(provider.request("eth_newPendingTransactionFilter", vec![]).await.map_err(WatchError::Provider)?)
+        }
+        _ => None,
+    };
+    Ok((Source::Polling { provider, filter_id, polled: false }, head))
+}
+
+/// The state behind a `Subscription` stream.
+struct SubscriptionState {
+    subscriber: Subscriber,
+    kind: SubscriptionKind,
+    source: Source,
+    polling: Arc<AtomicBool>,
+    pending: VecDeque<Result<Notification, WatchError>>,
+    /// The last block whose header or logs were delivered, or the head when subscribing.
+    last_block: U64,
+    /// After a reconnect, the head the backfill reached and the headers and logs it queued; live
+    /// notifications repeating them are dropped until one above that head arrives.
+    backfilled: Option<(U64, HashSet<NotificationId>)>,
+}
+
+impl SubscriptionState {
+    async fn next(&mut self) -> Option<Result<Notification, WatchError>> {
+        loop {
+            if let Some(notification) = self.pending.pop_front() {
+                return Some(notification);
+            }
+            match &mut self.source {
+                Source::Closed => return None,
+                Source::Live { notifications, .. } => match notifications.next().await {
+                    Some(value) => self.deliver(value),
+                    None => {
+                        log_warn(&format!("Lost the subscription connection to {}", self.subscriber.url));
+                        self.reconnect().await;
+                    }
+                },
+                Source::Polling { provider, filter_id, polled } => {
+                    if std::mem::replace(polled, true) {
+                        tokio::time::sleep(self.subscriber.poll_interval).await;
+                    }
+                    let (provider, filter_id) = (provider.clone(), *filter_id);
+                    match self.poll(&provider, filter_id).await {
+                        Ok(()) => {}
+                        Err(WatchError::Provider(ProviderError::Transport(e))) => {
+                            log_warn(&format!("Polling {} failed: {}", self.subscriber.url, e));
+                            self.reconnect().await;
+                        }
+                        Err(e) => self.pending.push_back(Err(e)),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Queues a live notification, unless the backfill after a reconnect delivered it already.
+    fn deliver(&mut self, value: Value) {
+        let notification = self.kind.parse(value);
+        if let Ok(notification) = &notification {
+            if let Some((head, delivered)) = &self.backfilled {
+                let removed = matches!(notification, Notification::Log(log) if log.removed == Some(true));
+                if notification.block().is_some_and(|block| block > *head) {
+                    self.backfilled = None;
+                } else if !removed && notification.identity().is_some_and(|identity| delivered.contains(&identity)) {
+                    return;
+                }
+            }
+            if let Some(block) = notification.block() {
+                self.last_block = self.last_block.max(block);
+            }
+        }
+        self.pending.push_back(notification);
+    }
+
+    async fn poll(&mut self, provider: &Provider<SubscriptionTransport>, filter_id: Option<U256>) -> Result<(), WatchError> {
+        if let Some(filter_id) = filter_id {
+            let hashes: Vec<H256> = provider
+                .request("eth_getFilterChanges", vec![json!(filter_id)])
+                .await
+                .map_err(WatchError::Provider)?;
+            self.pending.extend(hashes.into_iter().map(|hash| Ok(Notification::PendingTransaction(hash))));
+            return Ok(());
+        }
+        let head = provider.block_number().await.map_err(WatchError::Provider)?;
+        self.backfill(provider, head).await
+    }
+
+    /// Queues the headers or logs of the blocks after the last delivered one, up to `head`.
+    async fn backfill(&mut self, provider: &Provider<SubscriptionTransport>, head: U64) -> Result<(), WatchError> {
+        if head <= self.last_block {
+            return Ok(());
+        }
+        match &self.kind {
+            SubscriptionKind::NewHeads => {
+                for number in self.last_block.as_u64() + 1..=head.as_u64() {
+                    let params = vec![json!(BlockNumber::Number(number.into())), json!(false)];
+                    let header: Option<BlockHeader> = provider.request("eth_getBlockByNumber", params).await.map_err(WatchError::Provider)?;
+                    self.pending.extend(header.map(|header| Ok(Notification::NewHead(Box::new(header)))));
+                    self.last_block = number.into();
+                }
+            }
+            SubscriptionKind::Logs(filter) => {
+                let filter = filter.to_filter(BlockNumber::Number(self.last_block + 1), BlockNumber::Number(head));
+                let logs = provider.logs(filter).await.map_err(WatchError::Provider)?;
+                self.pending.extend(logs.into_iter().map(|log| Ok(Notification::Log(Box::new(log)))));
+                self.last_block = head;
+            }
+            SubscriptionKind::NewPendingTransactions => {}
+        }
+        Ok(())
+    }
+
+    /// Reconnects with exponential backoff, and closes the subscription with `ConnectionLost`
+    /// once every attempt failed.
+    async fn reconnect(&mut self) {
+        self.unsubscribe();
+        self.source = Source::Closed;
+        let mut delay = self.subscriber.reconnect_delay;
+        let mut last_error = None;
+        for attempt in 1..=self.subscriber.max_reconnect_attempts {
+            tokio::time::sleep(delay).await;
+            match self.resubscribe().await {
+                Ok(()) => {
+                    log_info(&format!("Resubscribed at {} after {} attempt(s)", self.subscriber.url, attempt));
+                    return;
+                }
+                Err(e) => {
+                    log_warn(&format!("Reconnect attempt {} to {} failed: {:?}", attempt, self.subscriber.url, e));
+                    last_error = Some(e);
+                }
+            }
+            delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+        }
+        self.pending.push_back(Err(WatchError::ConnectionLost(format!("{}: {:?}", self.subscriber.url, last_error))));
+    }
+
+    async fn resubscribe(&mut self) -> Result<(), WatchError> {
+        let (source, head) = connect(&self.subscriber.url, &self.kind).await?;
+        if let Source::Live { provider, .. } = &source {
+            let provider = provider.clone();
+            self.backfill(&provider, head).await?;
+            let delivered = self.pending.iter().filter_map(|notification| notification.as_ref().ok()?.identity()).collect();
+            self.backfilled = Some((head, delivered));
+        }
+        self.polling.store(matches!(source, Source::Polling { .. }), Ordering::Relaxed);
+        self.source = source;
+        Ok(())
+    }
+
+    /// Cancels the live subscription on the node, if the connection is still up.
+    fn unsubscribe(&self) {
+        if let (Source::Live { provider, id, .. }, Ok(runtime)) = (&self.source, tokio::runtime::Handle::try_current()) {
+            let (provider, id) = (provider.clone(), id.clone());
+            runtime.spawn(async move {
+                let _ = provider.request::<bool>("eth_unsubscribe", vec![json!(id)]).await;
+            });
+        }
+    }
+}
+
+impl Drop for SubscriptionState {
+    fn drop(&mut self) {
+        self.unsubscribe();
     }
 }
 
@@ -47,23 +1118,342 @@ pub async fn watch_contract_events(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::contracts::abi::{parse_abi, parse_human_readable_abi};
+    use crate::testing::MockNode;
     use std::time::Duration;
+    use web3::types::{Bytes, U256};
+
+    const CONTRACT: &str = "0x1234567890abcdef1234567890abcdef12345678";
+
+    fn test_abi() -> Abi {
+        parse_abi(r#"[
+            { "type": "event", "name": "TestEvent", "anonymous": false, "inputs": [{ "name": "x", "type": "uint256", "indexed": false }] }
+        ]"#).unwrap()
+    }
+
+    fn offline_provider() -> Provider {
+        Provider::http("http://127.0.0.1:1").unwrap()
+    }
 
     #[tokio::test]
     async fn test_invalid_contract_address() {
-        let result = watch_contract_events("invalid", "TestEvent", Duration::from_secs(5)).await;
+        let provider = offline_provider();
+        let result = watch_contract_events(&provider, "invalid", &test_abi(), "TestEvent", &[], Duration::from_secs(5), None).await;
         assert!(matches!(result, Err(WatchError::InvalidAddress)));
     }
 
     #[tokio::test]
-    async fn test_successful_event_watch() {
-        let result = watch_contract_events("0x1234567890abcdef1234567890abcdef12345678", "TestEvent", Duration::from_secs(5)).await;
-        assert!(matches!(result, Ok(())));
+    async fn test_unknown_event() {
+        let provider = offline_provider();
+        let result = watch_contract_events(&provider, CONTRACT, &test_abi(), "Approval", &[], Duration::from_secs(5), None).await;
+        assert!(matches!(result, Err(WatchError::UnknownEvent(ResolveError::NotFound(_)))));
+        let result = EventFilter::new(CONTRACT, &test_abi(), "TestEvent", &[("x", AbiValue::Uint(U256::one()))]);
+        assert!(matches!(result, Err(WatchError::InvalidFilter(_))));
     }
 
     #[tokio::test]
     async fn test_event_listening_failure() {
-        let result = watch_contract_events("0x1234567890abcdef1234567890abcdef12345678", "MissingEvent", Duration::from_secs(5)).await;
-        assert!(matches!(result, Err(WatchError::EventListeningFailed)));
+        let provider = offline_provider();
+        let result = watch_contract_events(&provider, CONTRACT, &test_abi(), "TestEvent", &[], Duration::from_secs(5), None).await;
+        assert!(matches!(result, Err(WatchError::Provider(ProviderError::Transport(_)))));
+    }
+
+    #[test]
+    fn test_filter_and_decode() {
+        let abi = parse_human_readable_abi(&["event Named(address indexed owner, string indexed label, uint256 value, string note)"]).unwrap();
+        let owner = Address::repeat_byte(0xaa);
+        let other = Address::repeat_byte(0xbb);
+        let indexed = [("owner", AbiValue::Address(owner)), ("owner", AbiValue::Address(other))];
+        let filter = EventFilter::new(CONTRACT, &abi, "Named", &indexed).unwrap();
+
+        let json = serde_json::to_value(filter.to_filter(BlockNumber::Number(1.into()), BlockNumber::Latest)).unwrap();
+        let owner_topic = H256::from(owner);
+        assert_eq!(json["topics"][1], serde_json::json!([owner_topic, H256::from(other)]));
+        assert_eq!(json["topics"].as_array().unwrap().len(), 2);
+
+        let label_hash = H256(keccak256(b"savings"));
+        let log = Log {
+            address: CONTRACT.parse().unwrap(),
+            topics: vec![abi.event("Named").unwrap().topic().unwrap(), owner_topic, label_hash],
+            data: Bytes(encode(&[AbiValue::Uint(U256::from(7)), AbiValue::String("hi".to_string())])),
+            block_hash: None,
+            block_number: Some(9.into()),
+            transaction_hash: Some(H256::repeat_byte(0x11)),
+            transaction_index: Some(0.into()),
+            log_index: Some(2.into()),
+            transaction_log_index: None,
+            log_type: None,
+            removed: None,
+        };
+        let event = filter.decode(&log).unwrap();
+        assert_eq!(event.name, "Named");
+        assert_eq!((event.block, event.log_index, event.tx_hash), (9, 2, H256::repeat_byte(0x11)));
+        assert_eq!(event.arg("owner"), Some(&AbiValue::Address(owner)));
+        assert_eq!(event.arg("label"), Some(&AbiValue::FixedBytes(label_hash.as_bytes().to_vec())));
+        assert_eq!(event.arg("value"), Some(&AbiValue::Uint(U256::from(7))));
+        assert_eq!(event.args[3], ("note".to_string(), AbiValue::String("hi".to_string())));
+
+        // A log with a missing topic does not decode
+        let truncated = Log { topics: log.topics[..2].to_vec(), ..log };
+        assert!(matches!(filter.decode(&truncated), Err(WatchError::InvalidLog(_))));
+    }
+    fn test_event_log(x: u64) -> Log {
+        Log {
+            address: CONTRACT.parse().unwrap(),
+            topics: vec![test_abi().event("TestEvent").unwrap().topic().unwrap()],
+            data: Bytes(encode(&[AbiValue::Uint(U256::from(x))])),
+            block_hash: None,
+            block_number: None,
+            transaction_hash: Some(H256::repeat_byte(0x11)),
+            transaction_index: Some(0.into()),
+            log_index: None,
+            transaction_log_index: None,
+            log_type: None,
+            removed: None,
+        }
+    }
+
+    async fn next_block(subscription: &mut Subscription) -> u64 {
+        subscription.next().await.unwrap().unwrap().block().unwrap().as_u64()
+    }
+
+    #[test]
+    fn test_is_result_limit() {
+        let rpc = |code: i64, message: &str| ProviderError::Rpc { code, message: message.to_string(), data: None };
+        assert!(is_result_limit(&rpc(-32005, "query returned more than 10000 results")));
+        assert!(is_result_limit(&rpc(-32602, "Log response size exceeded.")));
+        assert!(is_result_limit(&rpc(-32000, "exceed maximum block range: 5000")));
+        assert!(!is_result_limit(&rpc(-32000, "header not found")));
+        assert!(!is_result_limit(&rpc(429, "Too Many Requests")));
+        assert!(!is_result_limit(&ProviderError::Transport("more than enough".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_backfill_with_checkpoint() {
+        let node = MockNode::start().await;
+        for block in 1..=10 {
+            node.mine_block(if [2, 5, 9].contains(&block) { vec![test_event_log(block)] } else { vec![] });
+        }
+        node.fail_once("eth_getLogs", -32005, "query returned more than 10000 results", None);
+        let path = std::env::temp_dir().join(format!("wasmify-checkpoint-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let backfill = Backfill::from_block(1).with_chunk_size(4).with_confirmations(2).with_checkpoint(&path);
+        let provider = node.provider();
+        let watch = |backfill: Backfill| {
+            let provider = &provider;
+            async move {
+                let events = watch_contract_events(provider, CONTRACT, &test_abi(), "TestEvent", &[], Duration::from_millis(10), Some(backfill)).await;
+                Box::pin(events.unwrap())
+            }
+        };
+
+        // The rejected chunk of 4 blocks is retried as 2
+        let mut events = watch(backfill.clone()).await;
+        assert_eq!(events.next().await.unwrap().unwrap().block, 2);
+        assert_eq!(events.next().await.unwrap().unwrap().block, 5);
+        let ranges: Vec<(Value, Value)> =
+            node.requests_for("eth_getLogs").iter().map(|params| (params[0]["fromBlock"].clone(), params[0]["toBlock"].clone())).collect();
+        assert_eq!(ranges, vec![(json!("0x1"), json!("0x4")), (json!("0x1"), json!("0x2")), (json!("0x3"), json!("0x5"))]);
+
+        // Only the chunk whose events were all consumed is saved, so block 5 is delivered again
+        drop(events);
+        assert_eq!(Checkpoint::load(&path).unwrap().unwrap().next_block, 3);
+        let mut events = watch(backfill.clone()).await;
+        assert_eq!(events.next().await.unwrap().unwrap().block, 5);
+        assert_eq!(events.next().await.unwrap().unwrap().block, 9);
+
+        // New blocks are only scanned once they have two confirmations
+        node.mine_block(vec![test_event_log(11)]);
+        node.mine_block(vec![test_event_log(12)]);
+        assert_eq!(events.next().await.unwrap().unwrap().block, 11);
+        assert_eq!(Checkpoint::load(&path).unwrap().unwrap().next_block, 10);
+        let last = node.requests_for("eth_getLogs").last().unwrap()[0].clone();
+        assert_eq!(last["toBlock"], json!("0xb"));
+
+        // A checkpoint of another event is not resumed from
+        let other = parse_human_readable_abi(&["event TestEvent(address x)"]).unwrap();
+        let result = watch_contract_events(&provider, CONTRACT, &other, "TestEvent", &[], Duration::from_millis(10), Some(backfill)).await;
+        assert!(matches!(result, Err(WatchError::Checkpoint(_))));
+        fs::remove_file(&path).unwrap();
+    }
+
+    fn value_of(update: &EventUpdate) -> (bool, u64, U256) {
+        let (added, event) = match update {
+            EventUpdate::Added(event) => (true, event),
+            EventUpdate::Removed(event) => (false, event),
+        };
+        match event.arg("x") {
+            Some(AbiValue::Uint(x)) => (added, event.block, *x),
+            other => panic!("unexpected argument {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_chain_tracker() {
+        let filter = EventFilter::new(CONTRACT, &test_abi(), "TestEvent", &[]).unwrap();
+        let block = |number: u64| TrackedBlock {
+            number,
+            hash: H256::from_low_u64_be(number),
+            events: vec![filter.decode(&Log { block_number: Some(number.into()), ..test_event_log(number) }).unwrap()],
+            delivered: false,
+        };
+        let mut tracker = ChainTracker::new(1, 2, 2);
+        (1..=3).for_each(|number| tracker.push(block(number)));
+
+        // Blocks 1 and 2 have two confirmations at head 3
+        let added: Vec<u64> = tracker.confirm(3).iter().map(|update| value_of(update).1).collect();
+        assert_eq!(added, vec![1, 2]);
+        assert_eq!(tracker.blocks.len(), 2);
+        assert!(tracker.pruned);
+
+        // The undelivered tip is dropped silently, the delivered block below it is removed
+        assert!(tracker.pop().is_empty());
+        assert!(matches!(tracker.pop().as_slice(), [EventUpdate::Removed(event)] if event.block == 2));
+        assert_eq!(tracker.next, 2);
+
+        (2..=5).for_each(|number| tracker.push(block(number)));
+        assert_eq!(tracker.confirm(5).len(), 3);
+        assert_eq!(tracker.blocks.iter().map(|block| block.number).collect::<Vec<_>>(), vec![4, 5]);
+    }
+
+    #[tokio::test]
+    async fn test_event_watcher_reorg() {
+        let node = MockNode::start().await;
+        let provider = node.provider();
+        let filter = EventFilter::new(CONTRACT, &test_abi(), "TestEvent", &[]).unwrap();
+        let watcher = EventWatcher::new(&provider, filter).with_poll_interval(Duration::from_millis(10));
+        let mut updates = Box::pin(watcher.watch().await.unwrap());
+
+        node.mine_block(vec![test_event_log(7)]);
+        assert_eq!(value_of(&updates.next().await.unwrap().unwrap()), (true, 1, U256::from(7)));
+
+        // The transaction is dropped with its block and included again in a later one
+        node.reorg(1);
+        node.mine_block(vec![test_event_log(7)]);
+        assert_eq!(value_of(&updates.next().await.unwrap().unwrap()), (false, 1, U256::from(7)));
+        assert_eq!(value_of(&updates.next().await.unwrap().unwrap()), (true, 2, U256::from(7)));
+    }
+
+    #[tokio::test]
+    async fn test_event_watcher_confirmations() {
+        let node = MockNode::start().await;
+        let provider = node.provider();
+        let filter = EventFilter::new(CONTRACT, &test_abi(), "TestEvent", &[]).unwrap();
+        let watcher = EventWatcher::new(&provider, filter).with_confirmations(3).with_poll_interval(Duration::from_millis(10));
+        let mut updates = Box::pin(watcher.watch().await.unwrap());
+
+        // A reorganization shallower than the confirmation depth never surfaces
+        node.mine_block(vec![test_event_log(1)]);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        node.reorg(1);
+        node.mine_block(vec![test_event_log(2)]);
+        node.mine_blocks(2);
+        assert_eq!(value_of(&updates.next().await.unwrap().unwrap()), (true, 2, U256::from(2)));
+
+        // A reorganization deeper than the history cannot be traced back
+        let watcher = EventWatcher::new(&provider, EventFilter::new(CONTRACT, &test_abi(), "TestEvent", &[]).unwrap())
+            .with_poll_interval(Duration::from_millis(10))
+            .with_history(1);
+        let mut updates = Box::pin(watcher.watch().await.unwrap());
+        node.mine_block(vec![test_event_log(3)]);
+        node.mine_block(vec![test_event_log(4)]);
+        assert_eq!(value_of(&updates.next().await.unwrap().unwrap()), (true, 5, U256::from(3)));
+        assert_eq!(value_of(&updates.next().await.unwrap().unwrap()), (true, 6, U256::from(4)));
+        node.reorg(2);
+        assert_eq!(value_of(&updates.next().await.unwrap().unwrap()), (false, 6, U256::from(4)));
+        assert!(matches!(updates.next().await, Some(Err(WatchError::ReorgTooDeep(1)))));
+    }
+
+    #[tokio::test]
+    async fn test_subscription_reconnects_and_backfills() {
+        let node = MockNode::start().await;
+        let subscriber = Subscriber::new(&node.ws_url()).with_reconnect_delay(Duration::from_millis(10));
+        let mut heads = subscriber.subscribe(SubscriptionKind::NewHeads).await.unwrap();
+        assert!(!heads.is_polling());
+        node.mine_blocks(1);
+        assert_eq!(next_block(&mut heads).await, 1);
+
+        // Blocks mined while disconnected are fetched after resubscribing, then live heads resume
+        node.drop_connections();
+        node.mine_blocks(2);
+        assert_eq!(next_block(&mut heads).await, 2);
+        assert_eq!(next_block(&mut heads).await, 3);
+
+        // A replacement of a backfilled block is not mistaken for a repeat
+        node.reorg(1);
+        let replacement = heads.next().await.unwrap().unwrap();
+        assert!(matches!(replacement, Notification::NewHead(header) if header.hash == node.block_hash(3)));
+        node.mine_blocks(1);
+        assert_eq!(next_block(&mut heads).await, 4);
+        assert_eq!(node.requests_for("eth_getBlockByNumber").len(), 2);
+        assert_eq!(node.requests_for("eth_subscribe").len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_watch_events_over_subscription() {
+        let node = MockNode::start().await;
+        let filter = EventFilter::new(CONTRACT, &test_abi(), "TestEvent", &[]).unwrap();
+        let subscriber = Subscriber::new(&node.ws_url()).with_reconnect_delay(Duration::from_millis(10));
+        let mut events = Box::pin(subscriber.watch_events(filter).await.unwrap());
+        assert_eq!(node.subscription_count(), 1);
+
+        node.mine_block(vec![test_event_log(1)]);
+        let event = events.next().await.unwrap().unwrap();
+        assert_eq!((event.block, event.arg("x")), (1, Some(&AbiValue::Uint(U256::from(1)))));
+
+        node.drop_connections();
+        node.mine_block(vec![test_event_log(2)]);
+        assert_eq!(events.next().await.unwrap().unwrap().block, 2);
+        assert_eq!(node.requests_for("eth_getLogs").len(), 1);
+
+        // Dropping the stream cancels the subscription on the node
+        drop(events);
+        for _ in 0..50 {
+            if node.subscription_count() == 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(node.subscription_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_polling_fallback() {
+        let node = MockNode::start().await;
+        node.fail("eth_subscribe", -32601, "the method eth_subscribe does not exist/is not available", None);
+        let filter = EventFilter::new(CONTRACT, &test_abi(), "TestEvent", &[]).unwrap();
+        let subscriber = Subscriber::new(&node.ws_url()).with_poll_interval(Duration::from_millis(10));
+        let mut logs = subscriber.subscribe(SubscriptionKind::Logs(filter)).await.unwrap();
+        assert!(logs.is_polling());
+        node.mine_block(vec![test_event_log(1)]);
+        assert!(matches!(logs.next().await, Some(Ok(Notification::Log(log))) if log.block_number == Some(1.into())));
+
+        let subscriber = Subscriber::new(&node.http_url()).with_poll_interval(Duration::from_millis(10));
+        let mut heads = subscriber.subscribe(SubscriptionKind::NewHeads).await.unwrap();
+        assert!(heads.is_polling());
+        node.mine_blocks(1);
+        assert_eq!(next_block(&mut heads).await, 2);
+
+        let hash = H256::repeat_byte(0x22);
+        node.respond("eth_newPendingTransactionFilter", serde_json::json!("0x1"));
+        node.respond("eth_getFilterChanges", serde_json::json!([]));
+        node.respond_once("eth_getFilterChanges", serde_json::json!([hash]));
+        let mut pending = subscriber.subscribe(SubscriptionKind::NewPendingTransactions).await.unwrap();
+        assert_eq!(pending.next().await.unwrap().unwrap(), Notification::PendingTransaction(hash));
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_gives_up() {
+        let node = MockNode::start().await;
+        let subscriber = Subscriber::new(&node.ws_url())
+            .with_reconnect_delay(Duration::from_millis(1))
+            .with_max_reconnect_attempts(2);
+        let mut heads = subscriber.subscribe(SubscriptionKind::NewHeads).await.unwrap();
+
+        node.fail("eth_blockNumber", -32000, "unavailable", None);
+        node.drop_connections();
+        assert!(matches!(heads.next().await, Some(Err(WatchError::ConnectionLost(_)))));
+        assert!(heads.next().await.is_none());
+        assert_eq!(node.requests_for("eth_subscribe").len(), 3);
     }
 }
@@ -0,0 +1,830 @@
+//! Static gas analysis of EVM bytecode.
+//!
+//! Code is disassembled and split into basic blocks. Starting from the first instruction the
+//! blocks are executed abstractly, tracking which stack items are constants, so that jump targets
+//! pushed before a `JUMP`, including the return addresses of internal function calls, can be
+//! resolved. Walking the resulting control-flow graph gives lower and upper gas bounds for the
+//! whole code and for every function found in the selector dispatcher, without a node.
+
+use super::interpreter::{intrinsic_gas, Message};
+use super::opcodes::*;
+use std::collections::{BinaryHeap, HashMap};
+use std::cmp::Reverse;
+use std::fmt;
+use web3::types::{Address, U256};
+
+/// The most abstract states explored before the analysis gives up on the rest of the code.
+const MAX_STATES: usize = 20_000;
+
+/// The protocol upgrade whose gas schedule the analysis uses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Hardfork {
+    Istanbul,
+    /// Cold and warm account and storage access (EIP-2929).
+    Berlin,
+    /// Adds `BASEFEE`.
+    London,
+    /// Adds `PUSH0`.
+    Shanghai,
+    /// Adds transient storage, `MCOPY` and the blob opcodes; the rules of the embedded interpreter.
+    #[default]
+    Cancun,
+}
+
+impl Hardfork {
+    /// Returns whether an opcode exists under this hardfork.
+    pub fn supports(self, opcode: u8) -> bool {
+        let introduced = match opcode {
+            BASEFEE => Hardfork::London,
+            PUSH0 => Hardfork::Shanghai,
+            TLOAD | TSTORE | MCOPY | BLOBHASH | BLOBBASEFEE => Hardfork::Cancun,
+            _ => Hardfork::Istanbul,
+        };
+        self >= introduced && opcode_info(opcode).is_some()
+    }
+}
+
+/// The static gas cost of an opcode.
+///
+/// # Fields
+/// - `min`: The cost with every access warm and no state change.
+/// - `max`: The cost with cold access, value transfer, account creation and fresh storage writes.
+/// - `dynamic`: Whether the opcode also pays costs that depend on its operands, such as memory
+///   expansion, copied or hashed bytes, or the gas forwarded to a call, which are not in `max`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpcodeGas {
+    pub min: u64,
+    pub max: u64,
+    pub dynamic: bool,
+}
+
+/// Returns the static gas cost of an opcode, or `None` if it does not exist under `hardfork`.
+///
+/// # Arguments
+/// * `opcode` - The opcode byte.
+/// * `hardfork` - The gas schedule to use.
+pub fn opcode_gas(opcode: u8, hardfork: Hardfork) -> Option<OpcodeGas> {
+    if !hardfork.supports(opcode) {
+        return None;
+    }
+    let info = opcode_info(opcode)?;
+    let berlin = hardfork >= Hardfork::Berlin;
+    // Warm and cold account access; a flat 700 before Berlin
+    let (warm, cold) = if berlin { (100, 2600) } else { (700, 700) };
+    let (min, max) = match opcode {
+        SLOAD if berlin => (100, 2100),
+        SLOAD => (800, 800),
+        SSTORE if berlin => (100, 22_100),
+        SSTORE => (800, 20_000),
+        BALANCE | EXTCODESIZE | EXTCODEHASH | EXTCODECOPY | DELEGATECALL | STATICCALL => (warm, cold),
+        // Transferring value costs 9000 more, and 25000 more if it creates the recipient
+        CALL => (warm, cold + 9000 + 25_000),
+        CALLCODE => (warm, cold + 9000),
+        SELFDESTRUCT => (5000, 5000 + if berlin { 2600 } else { 0 } + 25_000),
+        // 50 per byte of the exponent
+        EXP => (10, 10 + 50 * 32),
+        _ => (info.gas, info.gas),
+    };
+    let dynamic = matches!(
+        opcode,
+        MLOAD | MSTORE | MSTORE8 | KECCAK256 | CALLDATACOPY | CODECOPY | EXTCODECOPY | RETURNDATACOPY | MCOPY
+            | LOG0..=LOG4 | CREATE | CREATE2 | CALL | CALLCODE | DELEGATECALL | STATICCALL | RETURN | REVERT
+    );
+    Some(OpcodeGas { min, max, dynamic })
+}
+
+/// A disassembled instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    /// The offset of the opcode in the code.
+    pub pc: usize,
+    pub opcode: u8,
+    /// The bytes pushed by `PUSH1`..`PUSH32`, zero-padded if the code ends early.
+    pub immediate: Vec<u8>,
+}
+
+impl Instruction {
+    /// Returns the mnemonic, or `INVALID` for undefined opcodes.
+    pub fn name(&self) -> &'static str {
+        opcode_info(self.opcode).map_or("INVALID", |info| info.name)
+    }
+
+    /// Returns the value a `PUSH` instruction pushes.
+    pub fn push_value(&self) -> Option<U256> {
+        match self.opcode {
+            PUSH0..=PUSH32 => Some(U256::from_big_endian(&self.immediate)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#06x}: {}", self.pc, self.name())?;
+        if !self.immediate.is_empty() {
+            write!(f, " 0x")?;
+            for byte in &self.immediate {
+                write!(f, "{:02x}", byte)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Splits code into instructions.
+pub fn disassemble(code: &[u8]) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+    let mut pc = 0;
+    while pc < code.len() {
+        let opcode = code[pc];
+        let size = opcode_info(opcode).map_or(0, |info| info.immediate);
+        let mut immediate = code[(pc + 1).min(code.len())..(pc + 1 + size).min(code.len())].to_vec();
+        immediate.resize(size, 0);
+        instructions.push(Instruction { pc, opcode, immediate });
+        pc += 1 + size;
+    }
+    instructions
+}
+
+/// How control leaves a basic block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockExit {
+    /// Execution continues with the next block, which starts with a `JUMPDEST`.
+    Fallthrough,
+    Jump,
+    /// A `JUMPI`, continuing with the jump target or the next block.
+    ConditionalJump,
+    /// `STOP`, or the end of the code.
+    Stop,
+    Return,
+    Revert,
+    /// `INVALID`, or an opcode that does not exist under the analysed hardfork.
+    Invalid,
+    SelfDestruct,
+}
+
+/// A straight-line run of instructions with a single entry and a single exit.
+///
+/// # Fields
+/// - `start`: The offset of the first instruction.
+/// - `instructions`: The instructions of the block.
+/// - `min_gas`, `max_gas`: The sum of the static costs of the instructions.
+/// - `dynamic`: Whether an instruction also pays operand-dependent costs not included in `max_gas`.
+/// - `exit`: How the block ends.
+/// - `successors`: The start offsets of the blocks control can continue with, where known.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    pub start: usize,
+    pub instructions: Vec<Instruction>,
+    pub min_gas: u64,
+    pub max_gas: u64,
+    pub dynamic: bool,
+    pub exit: BlockExit,
+    pub successors: Vec<usize>,
+}
+
+/// Gas bounds of the paths through some code that end in `STOP`, `RETURN` or `SELFDESTRUCT`.
+///
+/// # Fields
+/// - `min`: The cheapest successful path.
+/// - `max`: The most expensive successful path, or `None` if it cannot be bounded because of a
+///   loop or a jump whose target is not a constant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GasBounds {
+    pub min: u64,
+    pub max: Option<u64>,
+}
+
+/// The gas bounds of one function of the selector dispatcher.
+///
+/// # Fields
+/// - `selector`: The 4-byte function selector.
+/// - `entry`: The offset the dispatcher jumps to.
+/// - `gas`: The bounds of a call from the first instruction, dispatcher included; `None` if no path
+///   through the function succeeds.
+/// - `dynamic`: Whether a reachable instruction pays operand-dependent costs not included in the bounds.
+/// - `unbounded_loop`: Whether the function can run a loop whose iterations are not statically known.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionGas {
+    pub selector: [u8; 4],
+    pub entry: usize,
+    pub gas: Option<GasBounds>,
+    pub dynamic: bool,
+    pub unbounded_loop: bool,
+}
+
+/// The result of analysing a piece of code.
+///
+/// # Fields
+/// - `hardfork`: The gas schedule used.
+/// - `blocks`: The basic blocks, in code order.
+/// - `gas`: The bounds of executing the code from the first instruction.
+/// - `functions`: The functions found in the selector dispatcher, in code order.
+/// - `loops`: The start offsets of the blocks that begin a loop.
+/// - `complete`: Whether every reachable path was explored; if not, the bounds only cover part of the code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GasAnalysis {
+    pub hardfork: Hardfork,
+    pub blocks: Vec<BasicBlock>,
+    pub gas: Option<GasBounds>,
+    pub functions: Vec<FunctionGas>,
+    pub loops: Vec<usize>,
+    pub complete: bool,
+}
+
+impl GasAnalysis {
+    /// Returns the function with a selector.
+    pub fn function(&self, selector: [u8; 4]) -> Option<&FunctionGas> {
+        self.functions.iter().find(|function| function.selector == selector)
+    }
+
+    /// Estimates the gas of a transaction calling the analysed code offline: the intrinsic gas of
+    /// the calldata plus the execution bounds of the selected function.
+    ///
+    /// # Arguments
+    /// * `calldata` - The transaction data, starting with the function selector.
+    ///
+    /// # Returns
+    /// Option<GasBounds> - The bounds, or `None` if the dispatcher has no such function or it cannot succeed.
+    pub fn estimate_call(&self, calldata: &[u8]) -> Option<GasBounds> {
+        let selector: [u8; 4] = calldata.get(..4)?.try_into().ok()?;
+        let execution = self.function(selector)?.gas?;
+        let intrinsic = intrinsic_gas(&Message { to: Some(Address::zero()), data: calldata.to_vec(), ..Default::default() });
+        Some(GasBounds { min: intrinsic + execution.min, max: execution.max.map(|max| intrinsic + max) })
+    }
+}
+
+/// The analysis of deployment bytecode and of the runtime code it deploys.
+///
+/// # Fields
+/// - `constructor`: The analysis of the init code itself.
+/// - `runtime`: The analysis of the runtime code, if the init code copies it from a constant range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeploymentAnalysis {
+    pub constructor: GasAnalysis,
+    pub runtime: Option<GasAnalysis>,
+}
+
+/// Analyses runtime code.
+///
+/// # Arguments
+/// * `code` - The bytecode to analyse.
+/// * `hardfork` - The gas schedule to use.
+pub fn analyze(code: &[u8], hardfork: Hardfork) -> GasAnalysis {
+    Analyzer::new(code, hardfork).run().0
+}
+
+/// Analyses deployment bytecode and the runtime code it returns.
+///
+/// The runtime code is taken from the largest `CODECOPY` of a constant range of the init code,
+/// which is how compilers deploy it.
+///
+/// # Arguments
+/// * `init_code` - The deployment bytecode, constructor arguments included.
+/// * `hardfork` - The gas schedule to use.
+pub fn analyze_deployment(init_code: &[u8], hardfork: Hardfork) -> DeploymentAnalysis {
+    let (constructor, copies) = Analyzer::new(init_code, hardfork).run();
+    let runtime = copies
+        .into_iter()
+        .filter(|(offset, size)| *size > 0 && offset.saturating_add(*size) <= init_code.len())
+        .max_by_key(|(_, size)| *size)
+        .map(|(offset, size)| analyze(&init_code[offset..offset + size], hardfork));
+    DeploymentAnalysis { constructor, runtime }
+}
+
+/// A stack item: a known constant, or anything.
+type Item = Option<u64>;
+
+/// How a path through the abstract state graph ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Continue,
+    Success,
+    Failure,
+    /// A jump to a target that is not a constant, or a state beyond `MAX_STATES`.
+    Unknown,
+}
+
+/// A block entered with a particular abstract stack.
+#[derive(Debug)]
+struct State {
+    block: usize,
+    successors: Vec<usize>,
+    outcome: Outcome,
+}
+
+/// The longest path from a state, over paths that succeed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Longest {
+    NoPath,
+    Bounded(u64),
+    Unbounded,
+}
+
+impl Longest {
+    /// Returns the longer of two alternatives.
+    fn or(self, other: Longest) -> Longest {
+        match (self, other) {
+            (Longest::Unbounded, _) | (_, Longest::Unbounded) => Longest::Unbounded,
+            (Longest::NoPath, other) | (other, Longest::NoPath) => other,
+            (Longest::Bounded(a), Longest::Bounded(b)) => Longest::Bounded(a.max(b)),
+        }
+    }
+}
+
+struct Analyzer<'a> {
+    hardfork: Hardfork,
+    blocks: Vec<BasicBlock>,
+    block_at: HashMap<usize, usize>,
+    jump_destinations: Vec<bool>,
+    code: &'a [u8],
+}
+
+impl<'a> Analyzer<'a> {
+    fn new(code: &'a [u8], hardfork: Hardfork) -> Self {
+        let mut analyzer = Analyzer { hardfork, blocks: Vec::new(), block_at: HashMap::new(), jump_destinations: jump_destinations(code), code };
+        analyzer.split_blocks();
+        analyzer
+    }
+
+    fn split_blocks(&mut self) {
+        let mut current: Vec<Instruction> = Vec::new();
+        for instruction in disassemble(self.code) {
+            if instruction.opcode == JUMPDEST && !current.is_empty() {
+                self.push_block(std::mem::take(&mut current), BlockExit::Fallthrough);
+            }
+            let opcode = instruction.opcode;
+            current.push(instruction);
+            let exit = match opcode {
+                _ if !self.hardfork.supports(opcode) => Some(BlockExit::Invalid),
+                JUMP => Some(BlockExit::Jump),
+                JUMPI => Some(BlockExit::ConditionalJump),
+                STOP => Some(BlockExit::Stop),
+                RETURN => Some(BlockExit::Return),
+                REVERT => Some(BlockExit::Revert),
+                SELFDESTRUCT => Some(BlockExit::SelfDestruct),
+                _ => None,
+            };
+            if let Some(exit) = exit {
+                self.push_block(std::mem::take(&mut current), exit);
+            }
+        }
+        if !current.is_empty() {
+            self.push_block(current, BlockExit::Stop);
+        }
+    }
+
+    fn push_block(&mut self, instructions: Vec<Instruction>, exit: BlockExit) {
+        let (mut min_gas, mut max_gas, mut dynamic) = (0, 0, false);
+        for gas in instructions.iter().filter_map(|instruction| opcode_gas(instruction.opcode, self.hardfork)) {
+            min_gas += gas.min;
+            max_gas += gas.max;
+            dynamic |= gas.dynamic;
+        }
+        let start = instructions[0].pc;
+        self.block_at.insert(start, self.blocks.len());
+        self.blocks.push(BasicBlock { start, instructions, min_gas, max_gas, dynamic, exit, successors: Vec::new() });
+    }
+
+    fn run(mut self) -> (GasAnalysis, Vec<(usize, usize)>) {
+        if self.blocks.is_empty() {
+            let analysis = GasAnalysis { hardfork: self.hardfork, blocks: Vec::new(), gas: Some(GasBounds { min: 0, max: Some(0) }), functions: Vec::new(), loops: Vec::new(), complete: true };
+            return (analysis, Vec::new());
+        }
+        let (states, copies, complete) = self.explore();
+        for state in &states {
+            for successor in &state.successors {
+                let start = self.blocks[states[*successor].block].start;
+                if !self.blocks[state.block].successors.contains(&start) {
+                    self.blocks[state.block].successors.push(start);
+                }
+            }
+        }
+        for block in &mut self.blocks {
+            block.successors.sort_unstable();
+        }
+
+        let min_costs: Vec<u64> = states.iter().map(|state| self.blocks[state.block].min_gas).collect();
+        let max_costs: Vec<u64> = states.iter().map(|state| self.blocks[state.block].max_gas).collect();
+        let mut predecessors = vec![Vec::new(); states.len()];
+        for (index, state) in states.iter().enumerate() {
+            for successor in &state.successors {
+                predecessors[*successor].push(index);
+            }
+        }
+        let forward: Vec<Vec<usize>> = states.iter().map(|state| state.successors.clone()).collect();
+
+        // Cheapest and most expensive way from each state to a successful end
+        let successes: Vec<usize> = (0..states.len()).filter(|index| states[*index].outcome == Outcome::Success).collect();
+        let min_to_end = shortest(&predecessors, &min_costs, &successes);
+        let base: Vec<Longest> = states
+            .iter()
+            .enumerate()
+            .map(|(index, state)| match state.outcome {
+                Outcome::Success => Longest::Bounded(max_costs[index]),
+                Outcome::Unknown => Longest::Unbounded,
+                _ => Longest::NoPath,
+            })
+            .collect();
+        let (max_to_end, loop_heads) = longest(&forward, &max_costs, &base);
+
+        // Cheapest and most expensive way from the first instruction to each state
+        let min_from_start = shortest(&forward, &min_costs, &[0]);
+        let mut start_base = vec![Longest::NoPath; states.len()];
+        start_base[0] = Longest::Bounded(max_costs[0]);
+        let (max_from_start, _) = longest(&predecessors, &max_costs, &start_base);
+
+        let through = |indices: &[usize]| -> Option<GasBounds> {
+            let min = indices
+                .iter()
+                .filter_map(|index| Some(min_from_start[*index]? + min_to_end[*index]? - min_costs[*index]))
+                .min()?;
+            let mut max = Some(0);
+            for index in indices {
+                max = match (max, max_from_start[*index], max_to_end[*index]) {
+                    (_, _, Longest::NoPath) | (_, Longest::NoPath, _) => max,
+                    (Some(max), Longest::Bounded(before), Longest::Bounded(after)) => Some(max.max(before + after - max_costs[*index])),
+                    _ => None,
+                };
+            }
+            Some(GasBounds { min, max })
+        };
+        let gas = through(&[0]);
+
+        let mut functions = Vec::new();
+        for (selector, entry) in self.dispatcher() {
+            let Some(&block) = self.block_at.get(&entry) else {
+                continue;
+            };
+            let entries: Vec<usize> = (0..states.len()).filter(|index| states[*index].block == block).collect();
+            let reachable = reachable(&forward, &entries);
+            functions.push(FunctionGas {
+                selector,
+                entry,
+                gas: through(&entries),
+                dynamic: reachable.iter().any(|index| self.blocks[states[*index].block].dynamic),
+                unbounded_loop: reachable.iter().any(|index| loop_heads.contains(index)),
+            });
+        }
+
+        let mut loops: Vec<usize> = loop_heads.iter().map(|index| self.blocks[states[*index].block].start).collect();
+        loops.sort_unstable();
+        loops.dedup();
+        let analysis = GasAnalysis { hardfork: self.hardfork, blocks: self.blocks, gas, functions, loops, complete };
+        (analysis, copies)
+    }
+
+    /// Builds the graph of blocks entered with distinct abstract stacks, starting at offset 0.
+    ///
+    /// # Returns
+    /// The states, the constant `(offset, size)` ranges copied by `CODECOPY`, and whether every
+    /// state was explored.
+    fn explore(&self) -> (Vec<State>, Vec<(usize, usize)>, bool) {
+        let mut states = vec![State { block: 0, successors: Vec::new(), outcome: Outcome::Continue }];
+        let mut stacks: Vec<Vec<Item>> = vec![Vec::new()];
+        let mut ids: HashMap<(usize, Vec<Item>), usize> = HashMap::from([((0, Vec::new()), 0)]);
+        let mut copies = Vec::new();
+        let mut complete = true;
+        let mut next = 0;
+
+        while next < states.len() {
+            let block = &self.blocks[states[next].block];
+            let mut stack = stacks[next].clone();
+            let mut jump = None;
+            let mut failed = false;
+            for instruction in &block.instructions {
+                if !self.hardfork.supports(instruction.opcode) {
+                    failed = true;
+                    break;
+                }
+                match instruction.opcode {
+                    JUMP | JUMPI => jump = top(&stack, 0),
+                    CODECOPY => {
+                        if let (Some(offset), Some(size)) = (top(&stack, 1), top(&stack, 2)) {
+                            copies.push((offset as usize, size as usize));
+                        }
+                    }
+                    _ => {}
+                }
+                if !step(&mut stack, instruction) {
+                    failed = true;
+                    break;
+                }
+            }
+
+            let mut targets = Vec::new();
+            let outcome = match block.exit {
+                _ if failed => Outcome::Failure,
+                BlockExit::Stop | BlockExit::Return | BlockExit::SelfDestruct => Outcome::Success,
+                BlockExit::Revert | BlockExit::Invalid => Outcome::Failure,
+                BlockExit::Fallthrough => {
+                    targets.push(block.instructions.last().map_or(0, |last| last.pc + 1 + last.immediate.len()));
+                    Outcome::Continue
+                }
+                BlockExit::Jump | BlockExit::ConditionalJump => {
+                    if block.exit == BlockExit::ConditionalJump {
+                        targets.push(block.instructions.last().map_or(0, |last| last.pc + 1));
+                    }
+                    match jump {
+                        Some(target) if self.jump_destinations.get(target as usize) == Some(&true) => {
+                            targets.push(target as usize);
+                            Outcome::Continue
+                        }
+                        // A constant that is not a JUMPDEST always fails
+                        Some(_) => Outcome::Continue,
+                        None => Outcome::Unknown,
+                    }
+                }
+            };
+
+            let mut successors = Vec::new();
+            for target in targets {
+                let Some(&block) = self.block_at.get(&target) else {
+                    continue;
+                };
+                let key = (block, stack.clone());
+                let id = match ids.get(&key) {
+                    Some(id) => *id,
+                    None if states.len() >= MAX_STATES => {
+                        complete = false;
+                        states[next].outcome = Outcome::Unknown;
+                        continue;
+                    }
+                    None => {
+                        states.push(State { block, successors: Vec::new(), outcome: Outcome::Continue });
+                        stacks.push(stack.clone());
+                        ids.insert(key, states.len() - 1);
+                        states.len() - 1
+                    }
+                };
+                successors.push(id);
+            }
+            if states[next].outcome == Outcome::Continue {
+                states[next].outcome = match (outcome, successors.is_empty()) {
+                    // A jump whose only target is invalid
+                    (Outcome::Continue, true) => Outcome::Failure,
+                    (outcome, _) => outcome,
+                };
+            }
+            states[next].successors = successors;
+            next += 1;
+        }
+        (states, copies, complete)
+    }
+
+    /// Finds the `PUSH4 <selector> EQ PUSH <entry> JUMPI` comparisons of a selector dispatcher.
+    ///
+    /// Solidity compares with `DUP1 PUSH4 <selector> EQ` or `PUSH4 <selector> DUP2 EQ`.
+    fn dispatcher(&self) -> Vec<([u8; 4], usize)> {
+        const PUSH4: u8 = PUSH1 + 3;
+        let mut functions: Vec<([u8; 4], usize)> = Vec::new();
+        for block in &self.blocks {
+            let instructions = &block.instructions;
+            let count = instructions.len();
+            if block.exit != BlockExit::ConditionalJump || count < 4 || instructions[count - 3].opcode != EQ {
+                continue;
+            }
+            let entry = match instructions[count - 2].push_value() {
+                Some(entry) if entry < U256::from(self.code.len()) && self.jump_destinations[entry.as_usize()] => entry.as_usize(),
+                _ => continue,
+            };
+            let push4 = instructions[count.saturating_sub(5)..count - 3].iter().find(|instruction| instruction.opcode == PUSH4);
+            if let Some(push4) = push4 {
+                let selector: [u8; 4] = push4.immediate[..].try_into().expect("PUSH4 pushes 4 bytes");
+                if !functions.iter().any(|(known, _)| *known == selector) {
+                    functions.push((selector, entry));
+                }
+            }
+        }
+        functions
+    }
+}
+
+/// Returns the stack item `depth` positions below the top, if it is a known constant.
+fn top(stack: &[Item], depth: usize) -> Item {
+    stack.len().checked_sub(depth + 1).and_then(|index| stack[index])
+}
+
+/// Applies an instruction to an abstract stack; returns `false` on a stack overflow.
+fn step(stack: &mut Vec<Item>, instruction: &Instruction) -> bool {
+    let info = opcode_info(instruction.opcode).expect("only defined opcodes are stepped");
+    match instruction.opcode {
+        PUSH0..=PUSH32 => {
+            let value = instruction.push_value().expect("PUSH has a value");
+            stack.push((value <= U256::from(u64::MAX)).then(|| value.as_u64()));
+        }
+        DUP1..=DUP16 => {
+            let depth = (instruction.opcode - DUP1) as usize;
+            stack.push(top(stack, depth));
+        }
+        SWAP1..=SWAP16 => {
+            let depth = (instruction.opcode - SWAP1) as usize + 1;
+            // Items below what the block knows about are unknown
+            while stack.len() <= depth {
+                stack.insert(0, None);
+            }
+            let len = stack.len();
+            stack.swap(len - 1, len - 1 - depth);
+        }
+        _ => {
+            stack.truncate(stack.len().saturating_sub(info.inputs));
+            stack.extend(std::iter::repeat_n(None, info.outputs));
+        }
+    }
+    stack.len() <= 1024
+}
+
+/// Returns the cheapest path cost from any source to every node, counting the cost of each node
+/// on the path, following `edges`.
+fn shortest(edges: &[Vec<usize>], costs: &[u64], sources: &[usize]) -> Vec<Option<u64>> {
+    let mut distance = vec![None; costs.len()];
+    let mut queue = BinaryHeap::new();
+    for source in sources {
+        distance[*source] = Some(costs[*source]);
+        queue.push(Reverse((costs[*source], *source)));
+    }
+    while let Some(Reverse((cost, node))) = queue.pop() {
+        if distance[node] != Some(cost) {
+            continue;
+        }
+        for next in &edges[node] {
+            let candidate = cost + costs[*next];
+            if distance[*next].is_none_or(|known| candidate < known) {
+                distance[*next] = Some(candidate);
+                queue.push(Reverse((candidate, *next)));
+            }
+        }
+    }
+    distance
+}
+
+/// Returns the most expensive path cost from every node to a node with a `base` value,
+/// following `edges`, and the nodes that close a cycle.
+///
+/// A node's value is the larger of its `base` and its cost plus the largest value of its
+/// successors. A path that can reach a cycle is unbounded.
+fn longest(edges: &[Vec<usize>], costs: &[u64], base: &[Longest]) -> (Vec<Longest>, Vec<usize>) {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Mark {
+        New,
+        Visiting,
+        Done,
+    }
+    let mut marks = vec![Mark::New; costs.len()];
+    let mut values = base.to_vec();
+    let mut cycle_heads = Vec::new();
+
+    for root in 0..costs.len() {
+        if marks[root] != Mark::New {
+            continue;
+        }
+        // Iterative depth-first search, so that long paths cannot overflow the call stack
+        let mut stack = vec![(root, 0)];
+        marks[root] = Mark::Visiting;
+        while let Some((node, edge)) = stack.pop() {
+            if let Some(&next) = edges[node].get(edge) {
+                stack.push((node, edge + 1));
+                match marks[next] {
+                    Mark::New => {
+                        marks[next] = Mark::Visiting;
+                        stack.push((next, 0));
+                    }
+                    Mark::Visiting => cycle_heads.push(next),
+                    Mark::Done => {}
+                }
+                continue;
+            }
+            let mut best = Longest::NoPath;
+            for next in &edges[node] {
+                let next_value = match marks[*next] {
+                    // A successor still being visited is part of a cycle through this node
+                    Mark::Visiting => Longest::Unbounded,
+                    _ => values[*next],
+                };
+                best = best.or(next_value);
+            }
+            let through_successors = match best {
+                Longest::Bounded(max) => Longest::Bounded(costs[node] + max),
+                other => other,
+            };
+            values[node] = base[node].or(through_successors);
+            marks[node] = Mark::Done;
+        }
+    }
+    cycle_heads.sort_unstable();
+    cycle_heads.dedup();
+    (values, cycle_heads)
+}
+
+/// Returns every node reachable from the given ones, themselves included.
+fn reachable(edges: &[Vec<usize>], from: &[usize]) -> Vec<usize> {
+    let mut seen = vec![false; edges.len()];
+    let mut pending = from.to_vec();
+    let mut found = Vec::new();
+    while let Some(node) = pending.pop() {
+        if std::mem::replace(&mut seen[node], true) {
+            continue;
+        }
+        found.push(node);
+        pending.extend(&edges[node]);
+    }
+    found
+}
+
+// Unit test example
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A dispatcher with `increment()` at 0xaabbccdd (offset 28) and a loop over the calldata at
+    /// 0x11223344 (offset 37).
+    const DISPATCHER: [u8; 50] = [
+        PUSH0, CALLDATALOAD, PUSH1, 0xe0, SHR,
+        DUP1, PUSH1 + 3, 0xaa, 0xbb, 0xcc, 0xdd, EQ, PUSH1, 28, JUMPI,
+        DUP1, PUSH1 + 3, 0x11, 0x22, 0x33, 0x44, EQ, PUSH1, 37, JUMPI,
+        PUSH0, DUP1, REVERT,
+        JUMPDEST, PUSH0, SLOAD, PUSH1, 1, ADD, PUSH0, SSTORE, STOP,
+        JUMPDEST, PUSH0,
+        JUMPDEST, PUSH1, 1, ADD, DUP1, CALLDATASIZE, GT, PUSH1, 39, JUMPI,
+        STOP,
+    ];
+
+    #[test]
+    fn test_disassemble_and_blocks() {
+        let instructions = disassemble(&[PUSH1 + 1, 0x01, 0x02, ADD, PUSH1 + 3, 0xff]);
+        assert_eq!(instructions.len(), 3);
+        assert_eq!(instructions[0].to_string(), "0x0000: PUSH2 0x0102");
+        assert_eq!(instructions[2].immediate, vec![0xff, 0, 0, 0]);
+
+        let analysis = analyze(&DISPATCHER, Hardfork::Cancun);
+        let starts: Vec<usize> = analysis.blocks.iter().map(|block| block.start).collect();
+        assert_eq!(starts, vec![0, 15, 25, 28, 37, 39, 49]);
+        assert_eq!(analysis.blocks[0].successors, vec![15, 28]);
+        assert_eq!(analysis.blocks[0].min_gas, 33);
+        assert_eq!((analysis.blocks[3].min_gas, analysis.blocks[3].max_gas), (211, 24_211));
+        assert_eq!(analysis.blocks[4].exit, BlockExit::Fallthrough);
+        assert_eq!(analysis.blocks[5].successors, vec![39, 49]);
+        assert_eq!(analysis.loops, vec![39]);
+        assert!(analysis.complete);
+    }
+
+    #[test]
+    fn test_function_bounds() {
+        let analysis = analyze(&DISPATCHER, Hardfork::Cancun);
+        assert_eq!(analysis.functions.len(), 2);
+
+        let increment = analysis.function([0xaa, 0xbb, 0xcc, 0xdd]).unwrap();
+        assert_eq!(increment.entry, 28);
+        assert_eq!(increment.gas, Some(GasBounds { min: 244, max: Some(24_244) }));
+        assert!(!increment.unbounded_loop);
+
+        let looping = analysis.function([0x11, 0x22, 0x33, 0x44]).unwrap();
+        assert_eq!(looping.gas, Some(GasBounds { min: 86, max: None }));
+        assert!(looping.unbounded_loop);
+
+        // 21000 plus 16 per non-zero calldata byte
+        let estimate = analysis.estimate_call(&[0xaa, 0xbb, 0xcc, 0xdd]).unwrap();
+        assert_eq!(estimate, GasBounds { min: 21_064 + 244, max: Some(21_064 + 24_244) });
+        assert!(analysis.estimate_call(&[0, 0, 0, 0]).is_none());
+
+        // The same code before Shanghai hits an undefined PUSH0 straight away
+        assert_eq!(analyze(&DISPATCHER, Hardfork::London).gas, None);
+    }
+
+    #[test]
+    fn test_deployment_and_internal_calls() {
+        // The counter of the interpreter tests, deployed by init code that copies and returns it
+        let runtime = [PUSH0, SLOAD, PUSH1, 1, ADD, DUP1, PUSH0, SSTORE, PUSH0, MSTORE, PUSH1, 32, PUSH0, RETURN];
+        let mut init_code = vec![PUSH1, runtime.len() as u8, PUSH1, 10, PUSH0, CODECOPY, PUSH1, runtime.len() as u8, PUSH0, RETURN];
+        init_code.extend_from_slice(&runtime);
+        let deployment = analyze_deployment(&init_code, Hardfork::Cancun);
+        let runtime = deployment.runtime.unwrap();
+        // The interpreter measures 22126 for the first call
+        assert_eq!(runtime.gas, Some(GasBounds { min: 223, max: Some(24_223) }));
+        assert!(runtime.blocks[0].dynamic);
+        assert_eq!(deployment.constructor.gas, Some(GasBounds { min: 16, max: Some(16) }));
+
+        // An internal function at 13 called twice returns to the address its caller pushed, which is no loop
+        let code = [
+            PUSH1, 5, PUSH1, 13, JUMP,
+            JUMPDEST, PUSH1, 11, PUSH1, 13, JUMP,
+            JUMPDEST, STOP,
+            JUMPDEST, JUMP,
+        ];
+        let analysis = analyze(&code, Hardfork::Cancun);
+        assert_eq!(analysis.blocks[3].successors, vec![5, 11]);
+        assert!(analysis.loops.is_empty());
+        assert_eq!(analysis.gas, Some(GasBounds { min: 48, max: Some(48) }));
+    }
+
+    #[test]
+    fn test_hardfork_costs() {
+        assert_eq!(opcode_gas(SLOAD, Hardfork::Istanbul), Some(OpcodeGas { min: 800, max: 800, dynamic: false }));
+        assert_eq!(opcode_gas(SLOAD, Hardfork::Berlin).unwrap().max, 2100);
+        assert_eq!(opcode_gas(CALL, Hardfork::Cancun), Some(OpcodeGas { min: 100, max: 36_600, dynamic: true }));
+        assert_eq!(opcode_gas(EXP, Hardfork::London).unwrap().max, 1610);
+        assert!(opcode_gas(BASEFEE, Hardfork::Berlin).is_none());
+        assert!(opcode_gas(TSTORE, Hardfork::Shanghai).is_none());
+        assert!(opcode_gas(0x0c, Hardfork::Cancun).is_none());
+    }
+}
@@ -0,0 +1,1227 @@
+use super::opcodes::*;
+use super::state::{create2_address, create_address, WorldState};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use web3::signing::{keccak256, recover};
+use web3::ethabi::ethereum_types::U512;
+use web3::types::{Address, H256, U256};
+
+/// Maximum size of deployed code (EIP-170).
+pub const MAX_CODE_SIZE: usize = 24_576;
+/// Maximum size of init code (EIP-3860).
+pub const MAX_INITCODE_SIZE: usize = 2 * MAX_CODE_SIZE;
+
+const MAX_STACK: usize = 1024;
+const MAX_DEPTH: usize = 1024;
+const CALL_STIPEND: u64 = 2300;
+const COLD_ACCOUNT_ACCESS: u64 = 2600;
+const COLD_SLOAD: u64 = 2100;
+const WARM_ACCESS: u64 = 100;
+
+/// The block a transaction executes in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockEnv {
+    pub number: u64,
+    pub timestamp: u64,
+    pub coinbase: Address,
+    pub gas_limit: u64,
+    pub base_fee: U256,
+    pub chain_id: u64,
+    pub prev_randao: H256,
+    /// Hashes of earlier blocks by number, used by `BLOCKHASH` for the last 256 blocks.
+    pub block_hashes: HashMap<u64, H256>,
+}
+
+/// A transaction to execute.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Message {
+    pub caller: Address,
+    /// The called account, or `None` to deploy `data` as init code.
+    pub to: Option<Address>,
+    pub value: U256,
+    pub data: Vec<u8>,
+    pub gas_limit: u64,
+    /// The effective gas price paid per unit of gas.
+    pub gas_price: U256,
+    /// The expected sender nonce; `None` uses the current one.
+    pub nonce: Option<u64>,
+    /// Accounts and storage slots pre-warmed by an EIP-2930 access list.
+    pub access_list: Vec<(Address, Vec<H256>)>,
+}
+
+/// A log emitted during execution.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogEntry {
+    pub address: Address,
+    pub topics: Vec<H256>,
+    pub data: Vec<u8>,
+}
+
+/// Why execution stopped abnormally, consuming all gas of the frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Halt {
+    OutOfGas,
+    StackUnderflow,
+    StackOverflow,
+    InvalidJump,
+    InvalidOpcode(u8),
+    /// A state-changing opcode ran inside a `STATICCALL`.
+    StaticStateChange,
+    ReturnDataOutOfBounds,
+    /// A contract was created at an address that already has code or a nonce.
+    CreateCollision,
+    /// The deployed code is larger than `MAX_CODE_SIZE` or starts with `0xEF`.
+    InvalidCode,
+    /// A call to a precompile that the interpreter does not implement.
+    UnsupportedPrecompile(u8),
+}
+
+/// How the outermost frame of a transaction ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitReason {
+    Success,
+    Revert,
+    Halt(Halt),
+}
+
+/// The outcome of an executed transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecutionResult {
+    pub exit: ExitReason,
+    /// The return data, or the revert data if the transaction reverted.
+    pub output: Vec<u8>,
+    /// The gas charged to the sender, after refunds.
+    pub gas_used: u64,
+    pub gas_refunded: u64,
+    pub logs: Vec<LogEntry>,
+    /// The address of the deployed contract, for successful deployments.
+    pub created_address: Option<Address>,
+}
+
+impl ExecutionResult {
+    pub fn is_success(&self) -> bool {
+        self.exit == ExitReason::Success
+    }
+}
+
+/// Reasons a transaction is rejected before execution; the state is left untouched.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvmError {
+    NonceTooLow { expected: u64, found: u64 },
+    NonceTooHigh { expected: u64, found: u64 },
+    InsufficientFunds { required: U256, available: U256 },
+    IntrinsicGasTooLow { required: u64, limit: u64 },
+    GasLimitExceedsBlock { limit: u64, block_limit: u64 },
+    GasPriceBelowBaseFee { gas_price: U256, base_fee: U256 },
+    InitCodeTooLarge(usize),
+}
+
+/// Returns the gas charged before execution: the base cost, calldata, contract creation and access list.
+pub fn intrinsic_gas(message: &Message) -> u64 {
+    let data_gas: u64 = message.data.iter().map(|byte| if *byte == 0 { 4 } else { 16 }).sum();
+    let create_gas = if message.to.is_none() { 32_000 + 2 * words(message.data.len()) } else { 0 };
+    let access_gas: u64 = message
+        .access_list
+        .iter()
+        .map(|(_, keys)| 2400 + 1900 * keys.len() as u64)
+        .sum();
+    21_000 + data_gas + create_gas + access_gas
+}
+
+/// Executes a transaction against `state`, committing its effects including the gas payment.
+///
+/// The interpreter follows the Cancun rules (warm/cold access, refunds capped at a fifth, transient
+/// storage, `PUSH0` and `MCOPY`). Of the precompiles only `ecrecover`, `sha256` and `identity`
+/// are available; calls to the others halt.
+///
+/// # Arguments
+/// * `state` - The world state to execute against.
+/// * `block` - The block the transaction is included in.
+/// * `message` - The transaction.
+///
+/// # Returns
+/// Result<ExecutionResult, EvmError> - The outcome, or the reason the transaction is invalid.
+pub fn transact(state: &mut WorldState, block: &BlockEnv, message: &Message) -> Result<ExecutionResult, EvmError> {
+    let caller = message.caller;
+    let nonce = state.nonce(&caller);
+    match message.nonce {
+        Some(found) if found < nonce => return Err(EvmError::NonceTooLow { expected: nonce, found }),
+        Some(found) if found > nonce => return Err(EvmError::NonceTooHigh { expected: nonce, found }),
+        _ => {}
+    }
+    if message.gas_price < block.base_fee {
+        return Err(EvmError::GasPriceBelowBaseFee { gas_price: message.gas_price, base_fee: block.base_fee });
+    }
+    if message.gas_limit > block.gas_limit {
+        return Err(EvmError::GasLimitExceedsBlock { limit: message.gas_limit, block_limit: block.gas_limit });
+    }
+    if message.to.is_none() && message.data.len() > MAX_INITCODE_SIZE {
+        return Err(EvmError::InitCodeTooLarge(message.data.len()));
+    }
+    let intrinsic = intrinsic_gas(message);
+    if intrinsic > message.gas_limit {
+        return Err(EvmError::IntrinsicGasTooLow { required: intrinsic, limit: message.gas_limit });
+    }
+    let available = state.balance(&caller);
+    // A cost beyond 256 bits is reported as the largest one, which no balance covers either
+    let gas_cost = U256::from(message.gas_limit).checked_mul(message.gas_price).unwrap_or(U256::MAX);
+    let required = gas_cost.checked_add(message.value).unwrap_or(U256::MAX);
+    if available < required || required == U256::MAX {
+        return Err(EvmError::InsufficientFunds { required, available });
+    }
+
+    state.account_mut(caller).balance -= gas_cost;
+    state.account_mut(caller).nonce += 1;
+
+    let mut evm = Evm::new(state, block, caller, message.gas_price);
+    evm.warm_addresses.extend([caller, block.coinbase]);
+    evm.warm_addresses.extend((1..=10u64).map(Address::from_low_u64_be));
+    for (address, keys) in &message.access_list {
+        evm.warm_addresses.insert(*address);
+        evm.warm_slots.extend(keys.iter().map(|key| (*address, U256::from_big_endian(key.as_bytes()))));
+    }
+
+    let gas = message.gas_limit - intrinsic;
+    let result = match message.to {
+        Some(to) => {
+            evm.warm_addresses.insert(to);
+            evm.call(CallFrame {
+                caller,
+                address: to,
+                code_address: to,
+                value: message.value,
+                transfer: true,
+                input: message.data.clone(),
+                gas,
+                is_static: false,
+                depth: 0,
+            })
+        }
+        None => evm.create(caller, message.value, message.data.clone(), gas, create_address(caller, nonce), 0),
+    };
+
+    let gas_used = message.gas_limit - result.gas_left;
+    let refunded = (evm.refund.max(0) as u64).min(gas_used / 5);
+    let gas_used = gas_used - refunded;
+    let logs = std::mem::take(&mut evm.logs);
+    let destructed = std::mem::take(&mut evm.destructed);
+
+    state.account_mut(caller).balance += U256::from(message.gas_limit - gas_used) * message.gas_price;
+    let tip = message.gas_price - block.base_fee;
+    if !tip.is_zero() {
+        state.account_mut(block.coinbase).balance += U256::from(gas_used) * tip;
+    }
+    for address in destructed {
+        state.remove(&address);
+    }
+
+    Ok(ExecutionResult {
+        exit: result.exit,
+        output: result.output,
+        gas_used,
+        gas_refunded: refunded,
+        logs,
+        created_address: if result.exit == ExitReason::Success { result.created } else { None },
+    })
+}
+
+struct CallFrame {
+    caller: Address,
+    /// The account whose storage and balance the code runs against.
+    address: Address,
+    /// The account the code is loaded from.
+    code_address: Address,
+    value: U256,
+    /// Whether `value` moves from the caller to `address` (false for `DELEGATECALL`).
+    transfer: bool,
+    input: Vec<u8>,
+    gas: u64,
+    is_static: bool,
+    depth: usize,
+}
+
+struct FrameResult {
+    exit: ExitReason,
+    output: Vec<u8>,
+    gas_left: u64,
+    created: Option<Address>,
+}
+
+impl FrameResult {
+    fn halt(halt: Halt) -> Self {
+        FrameResult { exit: ExitReason::Halt(halt), output: Vec::new(), gas_left: 0, created: None }
+    }
+}
+
+struct Checkpoint {
+    state: WorldState,
+    logs: usize,
+    warm_addresses: HashSet<Address>,
+    warm_slots: HashSet<(Address, U256)>,
+    transient: HashMap<(Address, U256), U256>,
+    refund: i64,
+    created: HashSet<Address>,
+    destructed: HashSet<Address>,
+}
+
+struct Evm<'a> {
+    state: &'a mut WorldState,
+    /// The state at the start of the transaction, for the SSTORE gas rules.
+    original: WorldState,
+    block: &'a BlockEnv,
+    origin: Address,
+    gas_price: U256,
+    logs: Vec<LogEntry>,
+    warm_addresses: HashSet<Address>,
+    warm_slots: HashSet<(Address, U256)>,
+    transient: HashMap<(Address, U256), U256>,
+    refund: i64,
+    created: HashSet<Address>,
+    destructed: HashSet<Address>,
+}
+
+/// The mutable machine state of one frame.
+struct Machine {
+    stack: Vec<U256>,
+    memory: Vec<u8>,
+    pc: usize,
+    gas_left: u64,
+    return_data: Vec<u8>,
+}
+
+impl Machine {
+    fn charge(&mut self, gas: u64) -> Result<(), Halt> {
+        self.gas_left = self.gas_left.checked_sub(gas).ok_or(Halt::OutOfGas)?;
+        Ok(())
+    }
+
+    fn pop(&mut self) -> U256 {
+        // Stack depth is validated against the opcode table before every instruction
+        self.stack.pop().expect("stack depth checked")
+    }
+
+    fn push(&mut self, value: U256) {
+        self.stack.push(value);
+    }
+
+    /// Charges for and performs memory expansion to cover `size` bytes at `offset`.
+    ///
+    /// # Returns
+    /// Result<usize, Halt> - The offset as a `usize`, or `Halt::OutOfGas` for unaffordable ranges.
+    fn expand(&mut self, offset: U256, size: U256) -> Result<usize, Halt> {
+        if size.is_zero() {
+            return Ok(0);
+        }
+        if offset > U256::from(u32::MAX) || size > U256::from(u32::MAX) {
+            return Err(Halt::OutOfGas);
+        }
+        let (offset, size) = (offset.as_usize(), size.as_usize());
+        let end = offset + size;
+        if end > self.memory.len() {
+            let new_words = words(end);
+            self.charge(memory_cost(new_words) - memory_cost(words(self.memory.len())))?;
+            self.memory.resize(new_words as usize * 32, 0);
+        }
+        Ok(offset)
+    }
+
+    fn read_memory(&mut self, offset: U256, size: U256) -> Result<Vec<u8>, Halt> {
+        let start = self.expand(offset, size)?;
+        Ok(if size.is_zero() { Vec::new() } else { self.memory[start..start + size.as_usize()].to_vec() })
+    }
+
+    /// Copies `size` bytes of `source` starting at `source_offset` to memory, zero-padding past its end.
+    fn copy_to_memory(&mut self, dest: U256, source: &[u8], source_offset: U256, size: U256) -> Result<(), Halt> {
+        self.charge(3 * words_u256(size)?)?;
+        let dest = self.expand(dest, size)?;
+        let size = size.as_usize();
+        for i in 0..size {
+            self.memory[dest + i] = index(source, source_offset, i);
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Evm<'a> {
+    fn new(state: &'a mut WorldState, block: &'a BlockEnv, origin: Address, gas_price: U256) -> Self {
+        let original = state.clone();
+        Evm {
+            state,
+            original,
+            block,
+            origin,
+            gas_price,
+            logs: Vec::new(),
+            warm_addresses: HashSet::new(),
+            warm_slots: HashSet::new(),
+            transient: HashMap::new(),
+            refund: 0,
+            created: HashSet::new(),
+            destructed: HashSet::new(),
+        }
+    }
+
+    fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            state: self.state.clone(),
+            logs: self.logs.len(),
+            warm_addresses: self.warm_addresses.clone(),
+            warm_slots: self.warm_slots.clone(),
+            transient: self.transient.clone(),
+            refund: self.refund,
+            created: self.created.clone(),
+            destructed: self.destructed.clone(),
+        }
+    }
+
+    fn restore(&mut self, checkpoint: Checkpoint) {
+        *self.state = checkpoint.state;
+        self.logs.truncate(checkpoint.logs);
+        self.warm_addresses = checkpoint.warm_addresses;
+        self.warm_slots = checkpoint.warm_slots;
+        self.transient = checkpoint.transient;
+        self.refund = checkpoint.refund;
+        self.created = checkpoint.created;
+        self.destructed = checkpoint.destructed;
+    }
+
+    /// Returns the cost of accessing an account, warming it.
+    fn access_address(&mut self, address: Address) -> u64 {
+        if self.warm_addresses.insert(address) {
+            COLD_ACCOUNT_ACCESS
+        } else {
+            WARM_ACCESS
+        }
+    }
+
+    fn call(&mut self, frame: CallFrame) -> FrameResult {
+        let checkpoint = self.checkpoint();
+        if frame.transfer && !self.state.transfer(frame.caller, frame.address, frame.value) {
+            return FrameResult { exit: ExitReason::Revert, output: Vec::new(), gas_left: frame.gas, created: None };
+        }
+        // Touch the callee so that empty accounts receiving zero value still exist for this transaction
+        self.state.account_mut(frame.address);
+
+        let result = match precompile(frame.code_address, &frame.input, frame.gas) {
+            Some(result) => result,
+            None => {
+                let code = self.state.code(&frame.code_address).to_vec();
+                if code.is_empty() {
+                    FrameResult { exit: ExitReason::Success, output: Vec::new(), gas_left: frame.gas, created: None }
+                } else {
+                    self.run(&frame, &code)
+                }
+            }
+        };
+
+        if result.exit != ExitReason::Success {
+            self.restore(checkpoint);
+        }
+        result
+    }
+
+    fn create(&mut self, caller: Address, value: U256, init_code: Vec<u8>, gas: u64, address: Address, depth: usize) -> FrameResult {
+        self.warm_addresses.insert(address);
+        if self.state.account(&address).is_some_and(|account| account.nonce != 0 || !account.code.is_empty()) {
+            return FrameResult::halt(Halt::CreateCollision);
+        }
+
+        let checkpoint = self.checkpoint();
+        self.state.account_mut(address).nonce = 1;
+        if !self.state.transfer(caller, address, value) {
+            self.restore(checkpoint);
+            return FrameResult { exit: ExitReason::Revert, output: Vec::new(), gas_left: gas, created: None };
+        }
+        self.created.insert(address);
+
+        let frame = CallFrame {
+            caller,
+            address,
+            code_address: address,
+            value,
+            transfer: false,
+            input: Vec::new(),
+            gas,
+            is_static: false,
+            depth,
+        };
+        let mut result = self.run(&frame, &init_code);
+
+        if result.exit == ExitReason::Success {
+            let deposit = 200 * result.output.len() as u64;
+            if result.output.len() > MAX_CODE_SIZE || result.output.first() == Some(&0xef) {
+                result = FrameResult::halt(Halt::InvalidCode);
+            } else if deposit > result.gas_left {
+                result = FrameResult::halt(Halt::OutOfGas);
+            } else {
+                result.gas_left -= deposit;
+                self.state.account_mut(address).code = std::mem::take(&mut result.output);
+                result.created = Some(address);
+            }
+        }
+
+        if result.exit != ExitReason::Success {
+            self.restore(checkpoint);
+        }
+        result
+    }
+
+    fn run(&mut self, frame: &CallFrame, code: &[u8]) -> FrameResult {
+        let jump_destinations = jump_destinations(code);
+        let mut machine = Machine { stack: Vec::new(), memory: Vec::new(), pc: 0, gas_left: frame.gas, return_data: Vec::new() };
+        loop {
+            match self.step(frame, code, &jump_destinations, &mut machine) {
+                Ok(None) => {}
+                Ok(Some((exit, output))) => {
+                    return FrameResult { exit, output, gas_left: machine.gas_left, created: None };
+                }
+                Err(halt) => return FrameResult::halt(halt),
+            }
+        }
+    }
+
+    /// Executes one instruction, returning the exit reason and output once the frame stops.
+    fn step(
+        &mut self,
+        frame: &CallFrame,
+        code: &[u8],
+        jump_destinations: &[bool],
+        m: &mut Machine,
+    ) -> Result<Option<(ExitReason, Vec<u8>)>, Halt> {
+        let opcode = code.get(m.pc).copied().unwrap_or(STOP);
+        let info = match opcode_info(opcode) {
+            Some(info) if opcode != INVALID => info,
+            _ => return Err(Halt::InvalidOpcode(opcode)),
+        };
+        if m.stack.len() < info.inputs {
+            return Err(Halt::StackUnderflow);
+        }
+        if m.stack.len() - info.inputs + info.outputs > MAX_STACK {
+            return Err(Halt::StackOverflow);
+        }
+        m.charge(info.gas)?;
+        m.pc += 1;
+
+        match opcode {
+            STOP => return Ok(Some((ExitReason::Success, Vec::new()))),
+            ADD => binary(m, |a, b| a.overflowing_add(b).0),
+            MUL => binary(m, |a, b| a.overflowing_mul(b).0),
+            SUB => binary(m, |a, b| a.overflowing_sub(b).0),
+            DIV => binary(m, |a, b| if b.is_zero() { U256::zero() } else { a / b }),
+            SDIV => binary(m, signed_div),
+            MOD => binary(m, |a, b| if b.is_zero() { U256::zero() } else { a % b }),
+            SMOD => binary(m, signed_mod),
+            ADDMOD | MULMOD => {
+                let (a, b, n) = (m.pop(), m.pop(), m.pop());
+                let result = if n.is_zero() {
+                    U256::zero()
+                } else if opcode == ADDMOD {
+                    narrow((U512::from(a) + U512::from(b)) % U512::from(n))
+                } else {
+                    narrow(a.full_mul(b) % U512::from(n))
+                };
+                m.push(result);
+            }
+            EXP => {
+                let (base, exponent) = (m.pop(), m.pop());
+                m.charge(50 * (exponent.bits() as u64).div_ceil(8))?;
+                m.push(base.overflowing_pow(exponent).0);
+            }
+            SIGNEXTEND => binary(m, sign_extend),
+            LT => binary(m, |a, b| bool_word(a < b)),
+            GT => binary(m, |a, b| bool_word(a > b)),
+            SLT => binary(m, |a, b| bool_word(flip_sign(a) < flip_sign(b))),
+            SGT => binary(m, |a, b| bool_word(flip_sign(a) > flip_sign(b))),
+            EQ => binary(m, |a, b| bool_word(a == b)),
+            ISZERO => {
+                let a = m.pop();
+                m.push(bool_word(a.is_zero()));
+            }
+            AND => binary(m, |a, b| a & b),
+            OR => binary(m, |a, b| a | b),
+            XOR => binary(m, |a, b| a ^ b),
+            NOT => {
+                let a = m.pop();
+                m.push(!a);
+            }
+            BYTE => binary(m, |i, x| if i >= U256::from(32) { U256::zero() } else { U256::from(x.byte(31 - i.as_usize())) }),
+            SHL => binary(m, |shift, value| if shift >= U256::from(256) { U256::zero() } else { value << shift.as_usize() }),
+            SHR => binary(m, |shift, value| if shift >= U256::from(256) { U256::zero() } else { value >> shift.as_usize() }),
+            SAR => binary(m, arithmetic_shift_right),
+            KECCAK256 => {
+                let (offset, size) = (m.pop(), m.pop());
+                m.charge(6 * words_u256(size)?)?;
+                let data = m.read_memory(offset, size)?;
+                m.push(U256::from_big_endian(&keccak256(&data)));
+            }
+            ADDRESS => m.push(address_word(frame.address)),
+            BALANCE => {
+                let address = word_address(m.pop());
+                m.charge(self.access_address(address))?;
+                m.push(self.state.balance(&address));
+            }
+            ORIGIN => m.push(address_word(self.origin)),
+            CALLER => m.push(address_word(frame.caller)),
+            CALLVALUE => m.push(frame.value),
+            CALLDATALOAD => {
+                let offset = m.pop();
+                let word: Vec<u8> = (0..32).map(|i| index(&frame.input, offset, i)).collect();
+                m.push(U256::from_big_endian(&word));
+            }
+            CALLDATASIZE => m.push(U256::from(frame.input.len())),
+            CALLDATACOPY => {
+                let (dest, offset, size) = (m.pop(), m.pop(), m.pop());
+                m.copy_to_memory(dest, &frame.input, offset, size)?;
+            }
+            CODESIZE => m.push(U256::from(code.len())),
+            CODECOPY => {
+                let (dest, offset, size) = (m.pop(), m.pop(), m.pop());
+                m.copy_to_memory(dest, code, offset, size)?;
+            }
+            GASPRICE => m.push(self.gas_price),
+            EXTCODESIZE => {
+                let address = word_address(m.pop());
+                m.charge(self.access_address(address))?;
+                m.push(U256::from(self.state.code(&address).len()));
+            }
+            EXTCODECOPY => {
+                let (address, dest, offset, size) = (word_address(m.pop()), m.pop(), m.pop(), m.pop());
+                m.charge(self.access_address(address))?;
+                let external = self.state.code(&address).to_vec();
+                m.copy_to_memory(dest, &external, offset, size)?;
+            }
+            RETURNDATASIZE => m.push(U256::from(m.return_data.len())),
+            RETURNDATACOPY => {
+                let (dest, offset, size) = (m.pop(), m.pop(), m.pop());
+                let end = offset.checked_add(size).ok_or(Halt::ReturnDataOutOfBounds)?;
+                if end > U256::from(m.return_data.len()) {
+                    return Err(Halt::ReturnDataOutOfBounds);
+                }
+                let return_data = std::mem::take(&mut m.return_data);
+                m.copy_to_memory(dest, &return_data, offset, size)?;
+                m.return_data = return_data;
+            }
+            EXTCODEHASH => {
+                let address = word_address(m.pop());
+                m.charge(self.access_address(address))?;
+                let hash = match self.state.account(&address) {
+                    Some(account) if !account.is_empty() => U256::from_big_endian(account.code_hash().as_bytes()),
+                    _ => U256::zero(),
+                };
+                m.push(hash);
+            }
+            BLOCKHASH => {
+                let number = m.pop();
+                let current = self.block.number;
+                let hash = if number < U256::from(current) && number + 256 >= U256::from(current) {
+                    self.block.block_hashes.get(&number.as_u64()).copied().unwrap_or_default()
+                } else {
+                    H256::zero()
+                };
+                m.push(U256::from_big_endian(hash.as_bytes()));
+            }
+            COINBASE => m.push(address_word(self.block.coinbase)),
+            TIMESTAMP => m.push(U256::from(self.block.timestamp)),
+            NUMBER => m.push(U256::from(self.block.number)),
+            PREVRANDAO => m.push(U256::from_big_endian(self.block.prev_randao.as_bytes())),
+            GASLIMIT => m.push(U256::from(self.block.gas_limit)),
+            CHAINID => m.push(U256::from(self.block.chain_id)),
+            SELFBALANCE => m.push(self.state.balance(&frame.address)),
+            BASEFEE => m.push(self.block.base_fee),
+            // Blob transactions are not supported, so there are never blob hashes and the blob base fee is the minimum
+            BLOBHASH => {
+                m.pop();
+                m.push(U256::zero());
+            }
+            BLOBBASEFEE => m.push(U256::one()),
+            POP => {
+                m.pop();
+            }
+            MLOAD => {
+                let offset = m.pop();
+                let offset = m.expand(offset, U256::from(32))?;
+                let word = U256::from_big_endian(&m.memory[offset..offset + 32]);
+                m.push(word);
+            }
+            MSTORE => {
+                let (offset, value) = (m.pop(), m.pop());
+                let offset = m.expand(offset, U256::from(32))?;
+                value.to_big_endian(&mut m.memory[offset..offset + 32]);
+            }
+            MSTORE8 => {
+                let (offset, value) = (m.pop(), m.pop());
+                let offset = m.expand(offset, U256::one())?;
+                m.memory[offset] = value.byte(0);
+            }
+            SLOAD => {
+                let key = m.pop();
+                let cost = if self.warm_slots.insert((frame.address, key)) { COLD_SLOAD } else { WARM_ACCESS };
+                m.charge(cost)?;
+                m.push(self.state.storage(&frame.address, key));
+            }
+            SSTORE => {
+                if frame.is_static {
+                    return Err(Halt::StaticStateChange);
+                }
+                // EIP-2200: SSTORE must not be able to run on the call stipend alone
+                if m.gas_left <= CALL_STIPEND {
+                    return Err(Halt::OutOfGas);
+                }
+                let (key, value) = (m.pop(), m.pop());
+                let cost = self.sstore_cost(frame.address, key, value);
+                m.charge(cost)?;
+                self.state.set_storage(frame.address, key, value);
+            }
+            JUMP => {
+                let dest = m.pop();
+                m.pc = jump_target(dest, jump_destinations)?;
+            }
+            JUMPI => {
+                let (dest, condition) = (m.pop(), m.pop());
+                if !condition.is_zero() {
+                    m.pc = jump_target(dest, jump_destinations)?;
+                }
+            }
+            PC => m.push(U256::from(m.pc - 1)),
+            MSIZE => m.push(U256::from(m.memory.len())),
+            GAS => m.push(U256::from(m.gas_left)),
+            JUMPDEST => {}
+            TLOAD => {
+                let key = m.pop();
+                m.push(self.transient.get(&(frame.address, key)).copied().unwrap_or_default());
+            }
+            TSTORE => {
+                if frame.is_static {
+                    return Err(Halt::StaticStateChange);
+                }
+                let (key, value) = (m.pop(), m.pop());
+                self.transient.insert((frame.address, key), value);
+            }
+            MCOPY => {
+                let (dest, source, size) = (m.pop(), m.pop(), m.pop());
+                m.charge(3 * words_u256(size)?)?;
+                m.expand(source.max(dest), size)?;
+                if !size.is_zero() {
+                    let (dest, source, size) = (dest.as_usize(), source.as_usize(), size.as_usize());
+                    m.memory.copy_within(source..source + size, dest);
+                }
+            }
+            PUSH0 => m.push(U256::zero()),
+            PUSH1..=PUSH32 => {
+                let size = info.immediate;
+                let mut word = [0u8; 32];
+                for (i, byte) in word[32 - size..].iter_mut().enumerate() {
+                    *byte = code.get(m.pc + i).copied().unwrap_or(0);
+                }
+                m.push(U256::from_big_endian(&word));
+                m.pc += size;
+            }
+            DUP1..=DUP16 => {
+                let value = m.stack[m.stack.len() - info.inputs];
+                m.push(value);
+            }
+            SWAP1..=SWAP16 => {
+                let top = m.stack.len() - 1;
+                m.stack.swap(top, top + 1 - info.inputs);
+            }
+            LOG0..=LOG4 => {
+                if frame.is_static {
+                    return Err(Halt::StaticStateChange);
+                }
+                let (offset, size) = (m.pop(), m.pop());
+                let topics = (0..info.inputs - 2).map(|_| H256(m.pop().into())).collect();
+                if size > U256::from(u32::MAX) {
+                    return Err(Halt::OutOfGas);
+                }
+                m.charge(8 * size.as_u64())?;
+                let data = m.read_memory(offset, size)?;
+                self.logs.push(LogEntry { address: frame.address, topics, data });
+            }
+            CREATE | CREATE2 => {
+                if frame.is_static {
+                    return Err(Halt::StaticStateChange);
+                }
+                let (value, offset, size) = (m.pop(), m.pop(), m.pop());
+                let salt = if opcode == CREATE2 { Some(H256(m.pop().into())) } else { None };
+                if size > U256::from(MAX_INITCODE_SIZE) {
+                    return Err(Halt::OutOfGas);
+                }
+                let hashing = if salt.is_some() { 6 } else { 0 };
+                m.charge((2 + hashing) * words_u256(size)?)?;
+                let init_code = m.read_memory(offset, size)?;
+                m.return_data.clear();
+
+                let nonce = self.state.nonce(&frame.address);
+                if frame.depth + 1 >= MAX_DEPTH || self.state.balance(&frame.address) < value || nonce == u64::MAX {
+                    m.push(U256::zero());
+                    return Ok(None);
+                }
+                self.state.account_mut(frame.address).nonce += 1;
+                let address = match salt {
+                    Some(salt) => create2_address(frame.address, salt, H256(keccak256(&init_code))),
+                    None => create_address(frame.address, nonce),
+                };
+
+                let gas = all_but_one_64th(m.gas_left);
+                m.charge(gas)?;
+                let result = self.create(frame.address, value, init_code, gas, address, frame.depth + 1);
+                m.gas_left += result.gas_left;
+                match result.created {
+                    Some(address) => m.push(address_word(address)),
+                    None => {
+                        if result.exit == ExitReason::Revert {
+                            m.return_data = result.output;
+                        }
+                        m.push(U256::zero());
+                    }
+                }
+            }
+            CALL | CALLCODE | DELEGATECALL | STATICCALL => return self.call_opcode(opcode, frame, m).map(|_| None),
+            RETURN | REVERT => {
+                let (offset, size) = (m.pop(), m.pop());
+                let output = m.read_memory(offset, size)?;
+                let exit = if opcode == RETURN { ExitReason::Success } else { ExitReason::Revert };
+                return Ok(Some((exit, output)));
+            }
+            SELFDESTRUCT => {
+                if frame.is_static {
+                    return Err(Halt::StaticStateChange);
+                }
+                let beneficiary = word_address(m.pop());
+                if self.warm_addresses.insert(beneficiary) {
+                    m.charge(COLD_ACCOUNT_ACCESS)?;
+                }
+                let balance = self.state.balance(&frame.address);
+                if !balance.is_zero() && !self.state.exists(&beneficiary) {
+                    m.charge(25_000)?;
+                }
+                // EIP-6780: the account is only deleted when it was created in the same transaction
+                self.state.account_mut(frame.address).balance = U256::zero();
+                if beneficiary != frame.address || !self.created.contains(&frame.address) {
+                    self.state.account_mut(beneficiary).balance += balance;
+                }
+                if self.created.contains(&frame.address) {
+                    self.destructed.insert(frame.address);
+                }
+                return Ok(Some((ExitReason::Success, Vec::new())));
+            }
+            _ => return Err(Halt::InvalidOpcode(opcode)),
+        }
+        Ok(None)
+    }
+
+    fn call_opcode(&mut self, opcode: u8, frame: &CallFrame, m: &mut Machine) -> Result<(), Halt> {
+        let requested = m.pop();
+        let target = word_address(m.pop());
+        let value = if matches!(opcode, CALL | CALLCODE) { m.pop() } else { U256::zero() };
+        let (args_offset, args_size, ret_offset, ret_size) = (m.pop(), m.pop(), m.pop(), m.pop());
+
+        if opcode == CALL && frame.is_static && !value.is_zero() {
+            return Err(Halt::StaticStateChange);
+        }
+
+        let mut cost = self.access_address(target);
+        if !value.is_zero() {
+            cost += 9000;
+            if opcode == CALL && !self.state.exists(&target) {
+                cost += 25_000;
+            }
+        }
+        m.charge(cost)?;
+        let input = m.read_memory(args_offset, args_size)?;
+        let ret_offset = m.expand(ret_offset, ret_size)?;
+
+        let gas = if requested > U256::from(u64::MAX) { u64::MAX } else { requested.as_u64() };
+        let gas = gas.min(all_but_one_64th(m.gas_left));
+        m.charge(gas)?;
+        let stipend = if value.is_zero() { 0 } else { CALL_STIPEND };
+        m.return_data.clear();
+
+        if frame.depth + 1 >= MAX_DEPTH || (matches!(opcode, CALL | CALLCODE) && self.state.balance(&frame.address) < value) {
+            m.gas_left += gas;
+            m.push(U256::zero());
+            return Ok(());
+        }
+
+        let (caller, address, call_value, transfer) = match opcode {
+            CALL => (frame.address, target, value, true),
+            CALLCODE => (frame.address, frame.address, value, true),
+            DELEGATECALL => (frame.caller, frame.address, frame.value, false),
+            _ => (frame.address, target, U256::zero(), false),
+        };
+        let result = self.call(CallFrame {
+            caller,
+            address,
+            code_address: target,
+            value: call_value,
+            transfer,
+            input,
+            gas: gas + stipend,
+            is_static: frame.is_static || opcode == STATICCALL,
+            depth: frame.depth + 1,
+        });
+
+        m.gas_left += result.gas_left;
+        let copied = result.output.len().min(ret_size.as_usize());
+        m.memory[ret_offset..ret_offset + copied].copy_from_slice(&result.output[..copied]);
+        m.return_data = result.output;
+        m.push(bool_word(result.exit == ExitReason::Success));
+        Ok(())
+    }
+
+    /// Returns the gas cost of an SSTORE and updates the refund counter (EIP-2200, EIP-2929 and EIP-3529).
+    fn sstore_cost(&mut self, address: Address, key: U256, new: U256) -> u64 {
+        let cold = if self.warm_slots.insert((address, key)) { COLD_SLOAD } else { 0 };
+        let original = self.original.storage(&address, key);
+        let current = self.state.storage(&address, key);
+
+        if current == new {
+            return cold + WARM_ACCESS;
+        }
+        if original == current {
+            if original.is_zero() {
+                return cold + 20_000;
+            }
+            if new.is_zero() {
+                self.refund += 4800;
+            }
+            return cold + 2900;
+        }
+
+        if !original.is_zero() {
+            if current.is_zero() {
+                self.refund -= 4800;
+            } else if new.is_zero() {
+                self.refund += 4800;
+            }
+        }
+        if original == new {
+            self.refund += if original.is_zero() { 20_000 - 100 } else { 2900 - 100 };
+        }
+        cold + WARM_ACCESS
+    }
+}
+
+/// Runs a precompiled contract, or returns `None` if `address` is not a precompile.
+fn precompile(address: Address, input: &[u8], gas: u64) -> Option<FrameResult> {
+    if address > Address::from_low_u64_be(10) || address.is_zero() {
+        return None;
+    }
+    let id = address.to_low_u64_be() as u8;
+    let (cost, output) = match id {
+        1 => (3000, ecrecover(input)),
+        2 => (60 + 12 * words(input.len()), Sha256::digest(input).to_vec()),
+        4 => (15 + 3 * words(input.len()), input.to_vec()),
+        _ => return Some(FrameResult::halt(Halt::UnsupportedPrecompile(id))),
+    };
+    Some(match gas.checked_sub(cost) {
+        Some(gas_left) => FrameResult { exit: ExitReason::Success, output, gas_left, created: None },
+        None => FrameResult::halt(Halt::OutOfGas),
+    })
+}
+
+fn ecrecover(input: &[u8]) -> Vec<u8> {
+    let mut padded = [0u8; 128];
+    let len = input.len().min(128);
+    padded[..len].copy_from_slice(&input[..len]);
+    let v = U256::from_big_endian(&padded[32..64]);
+    if v != U256::from(27) && v != U256::from(28) {
+        return Vec::new();
+    }
+    match recover(&padded[..32], &padded[64..128], v.as_u32() as i32 - 27) {
+        Ok(address) => H256::from(address).as_bytes().to_vec(),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn binary<F: Fn(U256, U256) -> U256>(m: &mut Machine, op: F) {
+    let (a, b) = (m.pop(), m.pop());
+    m.push(op(a, b));
+}
+
+fn words(len: usize) -> u64 {
+    len.div_ceil(32) as u64
+}
+
+fn words_u256(size: U256) -> Result<u64, Halt> {
+    if size > U256::from(u32::MAX) {
+        return Err(Halt::OutOfGas);
+    }
+    Ok(words(size.as_usize()))
+}
+
+fn memory_cost(words: u64) -> u64 {
+    3 * words + words * words / 512
+}
+
+fn all_but_one_64th(gas: u64) -> u64 {
+    gas - gas / 64
+}
+
+/// Reads byte `i` of `source` past `offset`, returning zero beyond its end.
+fn index(source: &[u8], offset: U256, i: usize) -> u8 {
+    if offset >= U256::from(source.len()) {
+        return 0;
+    }
+    source.get(offset.as_usize() + i).copied().unwrap_or(0)
+}
+
+fn jump_target(dest: U256, jump_destinations: &[bool]) -> Result<usize, Halt> {
+    if dest >= U256::from(jump_destinations.len()) || !jump_destinations[dest.as_usize()] {
+        return Err(Halt::InvalidJump);
+    }
+    Ok(dest.as_usize())
+}
+
+fn narrow(value: U512) -> U256 {
+    U256::try_from(value).expect("a value reduced modulo a U256 fits in a U256")
+}
+
+fn bool_word(value: bool) -> U256 {
+    if value {
+        U256::one()
+    } else {
+        U256::zero()
+    }
+}
+
+fn address_word(address: Address) -> U256 {
+    U256::from_big_endian(address.as_bytes())
+}
+
+fn word_address(word: U256) -> Address {
+    Address::from_slice(&<[u8; 32]>::from(word)[12..])
+}
+
+fn is_negative(value: U256) -> bool {
+    value.bit(255)
+}
+
+fn negate(value: U256) -> U256 {
+    (!value).overflowing_add(U256::one()).0
+}
+
+fn abs(value: U256) -> U256 {
+    if is_negative(value) {
+        negate(value)
+    } else {
+        value
+    }
+}
+
+/// Maps two's complement values onto an unsigned order by flipping the sign bit.
+fn flip_sign(value: U256) -> U256 {
+    value ^ (U256::one() << 255)
+}
+
+fn signed_div(a: U256, b: U256) -> U256 {
+    if b.is_zero() {
+        return U256::zero();
+    }
+    let quotient = abs(a) / abs(b);
+    if is_negative(a) != is_negative(b) {
+        negate(quotient)
+    } else {
+        quotient
+    }
+}
+
+fn signed_mod(a: U256, b: U256) -> U256 {
+    if b.is_zero() {
+        return U256::zero();
+    }
+    let remainder = abs(a) % abs(b);
+    if is_negative(a) {
+        negate(remainder)
+    } else {
+        remainder
+    }
+}
+
+fn sign_extend(size: U256, value: U256) -> U256 {
+    if size >= U256::from(31) {
+        return value;
+    }
+    let bit = size.as_usize() * 8 + 7;
+    let mask = (U256::one() << bit) - 1;
+    if value.bit(bit) {
+        value | !mask
+    } else {
+        value & mask
+    }
+}
+
+fn arithmetic_shift_right(shift: U256, value: U256) -> U256 {
+    let negative = is_negative(value);
+    if shift >= U256::from(256) {
+        return if negative { U256::MAX } else { U256::zero() };
+    }
+    let shift = shift.as_usize();
+    if negative {
+        !((!value) >> shift)
+    } else {
+        value >> shift
+    }
+}
+
+// Unit test example
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SENDER: Address = Address::repeat_byte(0xaa);
+
+    /// Increments storage slot 0 and returns the new value.
+    const COUNTER: [u8; 14] = [
+        PUSH0, SLOAD, PUSH1, 1, ADD, DUP1, PUSH0, SSTORE, PUSH0, MSTORE, PUSH1, 32, PUSH0, RETURN,
+    ];
+
+    /// Wraps runtime code in init code that copies it to memory and returns it.
+    fn init_code(runtime: &[u8]) -> Vec<u8> {
+        let len = runtime.len() as u8;
+        let mut code = vec![PUSH1, len, PUSH1, 10, PUSH0, CODECOPY, PUSH1, len, PUSH0, RETURN];
+        code.extend_from_slice(runtime);
+        code
+    }
+
+    fn block() -> BlockEnv {
+        BlockEnv {
+            number: 1,
+            timestamp: 1_700_000_000,
+            coinbase: Address::zero(),
+            gas_limit: 30_000_000,
+            base_fee: U256::zero(),
+            chain_id: 1,
+            prev_randao: H256::zero(),
+            block_hashes: HashMap::new(),
+        }
+    }
+
+    fn state() -> WorldState {
+        let mut state = WorldState::default();
+        state.account_mut(SENDER).balance = U256::exp10(18);
+        state
+    }
+
+    fn message(to: Option<Address>, data: Vec<u8>) -> Message {
+        Message { caller: SENDER, to, data, gas_limit: 1_000_000, ..Default::default() }
+    }
+
+    #[test]
+    fn test_deploy_and_call_counter_with_exact_gas() {
+        let mut state = state();
+        let deployed = transact(&mut state, &block(), &message(None, init_code(&COUNTER))).unwrap();
+        let counter = deployed.created_address.unwrap();
+        assert_eq!(counter, create_address(SENDER, 0));
+        assert_eq!(state.code(&counter), &COUNTER[..]);
+        assert_eq!(state.nonce(&SENDER), 1);
+
+        // 21000 + cold SLOAD 2100 + fresh SSTORE 20000 + 26 for the remaining opcodes and memory
+        let first = transact(&mut state, &block(), &message(Some(counter), vec![])).unwrap();
+        assert!(first.is_success());
+        assert_eq!(U256::from_big_endian(&first.output), U256::one());
+        assert_eq!(first.gas_used, 43_126);
+
+        // Modifying an already non-zero slot costs 2900 instead of 20000
+        let second = transact(&mut state, &block(), &message(Some(counter), vec![])).unwrap();
+        assert_eq!(U256::from_big_endian(&second.output), U256::from(2));
+        assert_eq!(second.gas_used, 26_026);
+        assert_eq!(state.storage(&counter, U256::zero()), U256::from(2));
+    }
+
+    #[test]
+    fn test_revert_undoes_state_and_returns_data() {
+        let mut state = state();
+        let target = Address::repeat_byte(0xcc);
+        // SSTORE(0, 1) then REVERT with the word 42
+        state.account_mut(target).code =
+            vec![PUSH1, 1, PUSH0, SSTORE, PUSH1, 42, PUSH0, MSTORE, PUSH1, 32, PUSH0, REVERT];
+
+        let mut call = message(Some(target), vec![]);
+        call.value = U256::from(5);
+        call.gas_price = U256::from(10);
+        let result = transact(&mut state, &block(), &call).unwrap();
+
+        assert_eq!(result.exit, ExitReason::Revert);
+        assert_eq!(U256::from_big_endian(&result.output), U256::from(42));
+        assert!(state.storage(&target, U256::zero()).is_zero());
+        assert!(state.balance(&target).is_zero());
+        // Only the gas is paid, and the nonce is still consumed
+        assert_eq!(state.balance(&SENDER), U256::exp10(18) - U256::from(result.gas_used) * 10);
+        assert_eq!(state.nonce(&SENDER), 1);
+    }
+
+    #[test]
+    fn test_calls_logs_and_halts() {
+        let mut state = state();
+        let counter = Address::repeat_byte(0xcc);
+        state.account_mut(counter).code = COUNTER.to_vec();
+
+        // STATICCALL the counter (which writes storage), then CALL it, and log both results
+        let caller = Address::repeat_byte(0xdd);
+        let mut code = Vec::new();
+        for opcode in [STATICCALL, CALL] {
+            code.extend_from_slice(&[PUSH1, 32, PUSH0, PUSH0, PUSH0]);
+            if opcode == CALL {
+                code.push(PUSH0);
+            }
+            code.push(PUSH1 + 19);
+            code.extend_from_slice(counter.as_bytes());
+            // The failing static call consumes all gas it is given, so it only gets 10000
+            match opcode {
+                STATICCALL => code.extend_from_slice(&[PUSH1 + 1, 0x27, 0x10, opcode]),
+                _ => code.extend_from_slice(&[GAS, opcode]),
+            }
+        }
+        // Stack: [static ok, call ok]; memory[0..32] holds the counter's return value
+        code.extend_from_slice(&[PUSH1, 32, PUSH0, LOG0 + 2, STOP]);
+        state.account_mut(caller).code = code;
+
+        let result = transact(&mut state, &block(), &message(Some(caller), vec![])).unwrap();
+        assert!(result.is_success());
+        let log = &result.logs[0];
+        assert_eq!(log.address, caller);
+        assert_eq!(log.topics, vec![H256::from_low_u64_be(1), H256::zero()]);
+        assert_eq!(U256::from_big_endian(&log.data), U256::one());
+        assert_eq!(state.storage(&counter, U256::zero()), U256::one());
+
+        // A jump into PUSH data halts and consumes all gas
+        let bad = Address::repeat_byte(0xee);
+        state.account_mut(bad).code = vec![PUSH1, JUMPDEST, PUSH1, 1, JUMP];
+        let result = transact(&mut state, &block(), &message(Some(bad), vec![])).unwrap();
+        assert_eq!(result.exit, ExitReason::Halt(Halt::InvalidJump));
+        assert_eq!(result.gas_used, 1_000_000);
+    }
+
+    #[test]
+    fn test_invalid_transactions_leave_state_untouched() {
+        let mut state = state();
+        let before = state.clone();
+
+        let mut call = message(Some(Address::repeat_byte(0xcc)), vec![]);
+        call.nonce = Some(3);
+        assert_eq!(transact(&mut state, &block(), &call), Err(EvmError::NonceTooHigh { expected: 0, found: 3 }));
+
+        let mut call = message(Some(Address::repeat_byte(0xcc)), vec![1]);
+        call.gas_limit = 21_000;
+        assert_eq!(transact(&mut state, &block(), &call), Err(EvmError::IntrinsicGasTooLow { required: 21_016, limit: 21_000 }));
+
+        let mut call = message(Some(Address::repeat_byte(0xcc)), vec![]);
+        call.value = U256::exp10(18);
+        call.gas_price = U256::one();
+        assert!(matches!(transact(&mut state, &block(), &call), Err(EvmError::InsufficientFunds { .. })));
+        assert_eq!(state, before);
+    }
+
+    #[test]
+    fn test_signed_arithmetic() {
+        let minus = |value: u64| negate(U256::from(value));
+        assert_eq!(signed_div(minus(7), U256::from(2)), minus(3));
+        assert_eq!(signed_mod(minus(7), U256::from(2)), minus(1));
+        assert_eq!(sign_extend(U256::zero(), U256::from(0xff)), U256::MAX);
+        assert_eq!(sign_extend(U256::zero(), U256::from(0x7f)), U256::from(0x7f));
+        assert_eq!(arithmetic_shift_right(U256::from(4), minus(32)), minus(2));
+        assert!(flip_sign(minus(1)) < flip_sign(U256::one()));
+    }
+}
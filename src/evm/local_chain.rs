@@ -0,0 +1,954 @@
+use super::interpreter::{transact, BlockEnv, EvmError, ExecutionResult, ExitReason, LogEntry, Message};
+use super::state::WorldState;
+use crate::contracts::abi::{decode_revert, RevertReason};
+use crate::contracts::provider::Provider;
+use crate::framework::logging::log_debug;
+use crate::signing::{decode_signed_transaction, effective_gas_price, PrivateKeySigner};
+use jsonrpc_core::{Call, ErrorCode, Params};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use web3::futures::future::{ready, Ready};
+use web3::signing::{keccak256, Key, SecretKey, SecretKeyRef};
+use web3::types::{Address, Bytes, CallRequest, Log, H2048, H256, U256, U64};
+use web3::{RequestId, Transport};
+
+const GWEI: u64 = 1_000_000_000;
+
+/// Settings of a `LocalChain`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalChainConfig {
+    pub chain_id: u64,
+    /// The number of pre-funded development accounts.
+    pub accounts: usize,
+    /// The balance of every development account, in wei.
+    pub account_balance: U256,
+    pub block_gas_limit: u64,
+    /// The base fee of every block; it does not adjust to demand.
+    pub base_fee: U256,
+    /// The priority fee suggested by `eth_maxPriorityFeePerGas` and added to the base fee by `eth_gasPrice`.
+    pub priority_fee: U256,
+}
+
+impl Default for LocalChainConfig {
+    fn default() -> Self {
+        LocalChainConfig {
+            chain_id: 31337,
+            accounts: 10,
+            // 10,000 ether
+            account_balance: U256::exp10(22),
+            block_gas_limit: 30_000_000,
+            base_fee: U256::from(GWEI),
+            priority_fee: U256::from(GWEI),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct LocalBlock {
+    number: u64,
+    hash: H256,
+    parent_hash: H256,
+    timestamp: u64,
+    transactions: Vec<H256>,
+    gas_used: u64,
+    logs: Vec<Log>,
+    /// The state after the block, for calls against past blocks.
+    state: WorldState,
+}
+
+#[derive(Debug, Clone)]
+struct MinedTransaction {
+    hash: H256,
+    message: Message,
+    nonce: u64,
+    block_number: u64,
+    block_hash: H256,
+    result: ExecutionResult,
+    logs: Vec<Log>,
+}
+
+/// Everything a snapshot captures.
+#[derive(Debug, Clone)]
+struct ChainData {
+    state: WorldState,
+    blocks: Vec<LocalBlock>,
+    transactions: HashMap<H256, MinedTransaction>,
+}
+
+#[derive(Debug)]
+struct ChainState {
+    config: LocalChainConfig,
+    data: ChainData,
+    snapshots: Vec<(u64, ChainData)>,
+    next_snapshot: u64,
+    accounts: Vec<(Address, H256)>,
+}
+
+/// An in-process chain that executes transactions on the embedded EVM interpreter.
+///
+/// `LocalChain` implements `web3::Transport`, so it plugs into `Provider::new` like any node and
+/// every function taking a `Provider` runs against it unchanged. Each transaction is mined into
+/// its own block immediately. State can be snapshotted and reverted, either through
+/// `snapshot`/`revert` or the Hardhat-style `evm_snapshot`/`evm_revert` methods.
+///
+/// Queries always read the latest state, whatever block tag they ask for. Transactions are
+/// accepted both signed with `eth_sendRawTransaction` and unsigned with `eth_sendTransaction`,
+/// which sends from any address without checking a signature.
+#[derive(Debug, Clone)]
+pub struct LocalChain {
+    inner: Arc<Mutex<ChainState>>,
+    request_id: Arc<AtomicUsize>,
+}
+
+impl Default for LocalChain {
+    fn default() -> Self {
+        LocalChain::new()
+    }
+}
+
+impl LocalChain {
+    /// Creates a chain with the default configuration.
+    pub fn new() -> Self {
+        LocalChain::with_config(LocalChainConfig::default())
+    }
+
+    /// Creates a chain whose genesis block funds `config.accounts` development accounts.
+    pub fn with_config(config: LocalChainConfig) -> Self {
+        let accounts: Vec<(Address, H256)> = (0..config.accounts).map(development_account).collect();
+        let mut state = WorldState::default();
+        for (address, _) in &accounts {
+            state.account_mut(*address).balance = config.account_balance;
+        }
+
+        let genesis = LocalBlock {
+            number: 0,
+            hash: block_hash(0, H256::zero(), &[]),
+            parent_hash: H256::zero(),
+            timestamp: chrono::Utc::now().timestamp() as u64,
+            transactions: Vec::new(),
+            gas_used: 0,
+            logs: Vec::new(),
+            state: state.clone(),
+        };
+        let data = ChainData { state, blocks: vec![genesis], transactions: HashMap::new() };
+
+        LocalChain {
+            inner: Arc::new(Mutex::new(ChainState { config, data, snapshots: Vec::new(), next_snapshot: 1, accounts })),
+            request_id: Arc::new(AtomicUsize::new(1)),
+        }
+    }
+
+    /// Returns a provider backed by this chain that polls every millisecond.
+    pub fn provider(&self) -> Provider<LocalChain> {
+        Provider::new(self.clone()).with_poll_interval(Duration::from_millis(1))
+    }
+
+    /// Returns the pre-funded development accounts.
+    pub fn accounts(&self) -> Vec<Address> {
+        self.inner.lock().unwrap().accounts.iter().map(|(address, _)| *address).collect()
+    }
+
+    /// Returns the private key of a development account.
+    pub fn private_key(&self, address: &Address) -> Option<H256> {
+        let chain = self.inner.lock().unwrap();
+        chain.accounts.iter().find(|(account, _)| account == address).map(|(_, key)| *key)
+    }
+
+    /// Returns a signer holding the key of a development account.
+    pub fn signer(&self, address: &Address) -> Option<PrivateKeySigner> {
+        self.private_key(address).and_then(|key| PrivateKeySigner::from_bytes(key.as_bytes()).ok())
+    }
+
+    /// Returns the number of the latest block.
+    pub fn block_number(&self) -> u64 {
+        self.inner.lock().unwrap().head().number
+    }
+
+    pub fn balance(&self, address: &Address) -> U256 {
+        self.inner.lock().unwrap().data.state.balance(address)
+    }
+
+    pub fn set_balance(&self, address: Address, balance: U256) {
+        self.inner.lock().unwrap().data.state.account_mut(address).balance = balance;
+    }
+
+    pub fn code(&self, address: &Address) -> Vec<u8> {
+        self.inner.lock().unwrap().data.state.code(address).to_vec()
+    }
+
+    /// Installs runtime code at an address without a deployment transaction.
+    pub fn set_code(&self, address: Address, code: Vec<u8>) {
+        self.inner.lock().unwrap().data.state.account_mut(address).code = code;
+    }
+
+    pub fn storage(&self, address: &Address, key: U256) -> U256 {
+        self.inner.lock().unwrap().data.state.storage(address, key)
+    }
+
+    /// Mines an empty block.
+    pub fn mine(&self) -> u64 {
+        let mut chain = self.inner.lock().unwrap();
+        chain.mine(Vec::new(), 0, Vec::new())
+    }
+
+    /// Records the current state, blocks and transactions.
+    ///
+    /// # Returns
+    /// u64 - The snapshot id to pass to `revert`.
+    pub fn snapshot(&self) -> u64 {
+        let mut chain = self.inner.lock().unwrap();
+        let id = chain.next_snapshot;
+        chain.next_snapshot += 1;
+        let data = chain.data.clone();
+        chain.snapshots.push((id, data));
+        id
+    }
+
+    /// Restores the chain to a snapshot, discarding it and every snapshot taken after it.
+    ///
+    /// # Returns
+    /// bool - False if the snapshot does not exist (for example because it was already reverted to).
+    pub fn revert(&self, id: u64) -> bool {
+        let mut chain = self.inner.lock().unwrap();
+        match chain.snapshots.iter().position(|(snapshot, _)| *snapshot == id) {
+            Some(position) => {
+                let (_, data) = chain.snapshots.drain(position..).next().expect("position is in range");
+                chain.data = data;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn handle(&self, method: &str, params: &[Value]) -> web3::Result<Value> {
+        log_debug(&format!("LocalChain request: {} {:?}", method, params));
+        let param = |i: usize| params.get(i).cloned().unwrap_or(Value::Null);
+        let mut chain = self.inner.lock().unwrap();
+
+        match method {
+            "eth_chainId" => Ok(json!(U64::from(chain.config.chain_id))),
+            "net_version" => Ok(json!(chain.config.chain_id.to_string())),
+            "eth_blockNumber" => Ok(json!(U64::from(chain.head().number))),
+            "eth_gasPrice" => Ok(json!(chain.config.base_fee + chain.config.priority_fee)),
+            "eth_maxPriorityFeePerGas" => Ok(json!(chain.config.priority_fee)),
+            "eth_accounts" => Ok(json!(chain.accounts.iter().map(|(address, _)| *address).collect::<Vec<_>>())),
+            "eth_getBalance" => Ok(json!(chain.data.state.balance(&parse(&param(0))?))),
+            "eth_getTransactionCount" => Ok(json!(U256::from(chain.data.state.nonce(&parse(&param(0))?)))),
+            "eth_getCode" => Ok(json!(Bytes(chain.data.state.code(&parse(&param(0))?).to_vec()))),
+            "eth_getStorageAt" => {
+                let key: U256 = parse(&param(1))?;
+                let value = chain.data.state.storage(&parse(&param(0))?, key);
+                Ok(json!(H256(value.into())))
+            }
+            "eth_call" => {
+                let message = chain.call_message(parse(&param(0))?, None)?;
+                let number = chain.block_tag(&param(1))?;
+                let result = chain.simulate_at(&message, number)?;
+                result_output(result)
+            }
+            "eth_estimateGas" => {
+                let request: CallRequest = parse(&param(0))?;
+                let cap = request.gas.is_some();
+                let message = chain.call_message(request, None)?;
+                let cap = cap.then_some(message.gas_limit);
+                Ok(json!(U256::from(chain.estimate(message, cap)?)))
+            }
+            "eth_sendTransaction" => {
+                let nonce: Option<U256> = parse(&param(0)["nonce"]).ok();
+                let request: CallRequest = parse(&param(0))?;
+                let mut message = chain.call_message(request.clone(), nonce)?;
+                if request.gas_price.is_none() && request.max_fee_per_gas.is_none() {
+                    message.gas_price = chain.config.base_fee + chain.config.priority_fee;
+                }
+                if request.gas.is_none() {
+                    message.gas_limit = chain.estimate(message.clone(), None)?;
+                }
+                Ok(json!(chain.execute(message, None)?))
+            }
+            "eth_sendRawTransaction" => {
+                let raw: Bytes = parse(&param(0))?;
+                let decoded = decode_signed_transaction(&raw.0)
+                    .map_err(|e| rpc_error(-32000, &format!("invalid transaction: {:?}", e), None))?;
+                let transaction = decoded.transaction;
+                if transaction.chain_id != chain.config.chain_id {
+                    return Err(rpc_error(-32000, "invalid chain id for signer", None));
+                }
+                if transaction.gas > U256::from(u64::MAX) || transaction.nonce > U256::from(u64::MAX) {
+                    return Err(rpc_error(-32000, "gas or nonce out of range", None));
+                }
+                let message = Message {
+                    caller: decoded.from,
+                    to: transaction.to,
+                    value: transaction.value,
+                    data: transaction.data,
+                    gas_limit: transaction.gas.as_u64(),
+                    gas_price: effective_gas_price(&transaction.kind, chain.config.base_fee),
+                    nonce: Some(transaction.nonce.as_u64()),
+                    access_list: transaction.access_list.into_iter().map(|item| (item.address, item.storage_keys)).collect(),
+                };
+                Ok(json!(chain.execute(message, Some(decoded.hash))?))
+            }
+            "eth_getTransactionReceipt" => {
+                let hash: H256 = parse(&param(0))?;
+                Ok(chain.data.transactions.get(&hash).map(receipt_json).unwrap_or(Value::Null))
+            }
+            "eth_getTransactionByHash" => {
+                let hash: H256 = parse(&param(0))?;
+                Ok(chain.data.transactions.get(&hash).map(transaction_json).unwrap_or(Value::Null))
+            }
+            "eth_getBlockByNumber" => {
+                let number = chain.block_tag(&param(0))?;
+                Ok(chain.data.blocks.get(number as usize).map(|block| block_json(block, &chain.config)).unwrap_or(Value::Null))
+            }
+            "eth_getBlockByHash" => {
+                let hash: H256 = parse(&param(0))?;
+                Ok(chain.data.blocks.iter().find(|block| block.hash == hash).map(|block| block_json(block, &chain.config)).unwrap_or(Value::Null))
+            }
+            "eth_getLogs" => chain.logs(&param(0)),
+            "evm_mine" => Ok(json!(U64::from(chain.mine(Vec::new(), 0, Vec::new())))),
+            "evm_snapshot" | "evm_revert" => {
+                drop(chain);
+                if method == "evm_snapshot" {
+                    Ok(json!(U64::from(self.snapshot())))
+                } else {
+                    let id: U64 = parse(&param(0))?;
+                    Ok(json!(self.revert(id.as_u64())))
+                }
+            }
+            _ => Err(rpc_error(-32601, &format!("the method {} does not exist/is not available", method), None)),
+        }
+    }
+}
+
+impl Transport for LocalChain {
+    type Out = Ready<web3::Result<Value>>;
+
+    fn prepare(&self, method: &str, params: Vec<Value>) -> (RequestId, Call) {
+        let id = self.request_id.fetch_add(1, Ordering::SeqCst);
+        (id, web3::helpers::build_request(id, method, params))
+    }
+
+    fn send(&self, _id: RequestId, request: Call) -> Self::Out {
+        ready(match request {
+            Call::MethodCall(call) => {
+                let params = match call.params {
+                    Params::Array(params) => params,
+                    Params::Map(map) => vec![Value::Object(map)],
+                    Params::None => Vec::new(),
+                };
+                self.handle(&call.method, &params)
+            }
+            _ => Err(rpc_error(-32600, "invalid request", None)),
+        })
+    }
+}
+
+impl ChainState {
+    fn head(&self) -> &LocalBlock {
+        self.data.blocks.last().expect("the chain always has a genesis block")
+    }
+
+    /// Builds the environment of the next block.
+    fn pending_block(&self, base_fee: U256) -> BlockEnv {
+        self.next_block(self.head(), base_fee)
+    }
+
+    /// Returns the environment of the block following `parent`.
+    fn next_block(&self, parent: &LocalBlock, base_fee: U256) -> BlockEnv {
+        let block_hashes = self.data.blocks[..=parent.number as usize]
+            .iter()
+            .rev()
+            .take(256)
+            .map(|block| (block.number, block.hash))
+            .collect();
+        BlockEnv {
+            number: parent.number + 1,
+            timestamp: next_timestamp(parent.timestamp),
+            coinbase: Address::zero(),
+            gas_limit: self.config.block_gas_limit,
+            base_fee,
+            chain_id: self.config.chain_id,
+            prev_randao: H256(keccak256(parent.hash.as_bytes())),
+            block_hashes,
+        }
+    }
+
+    /// Builds the message of a call request, rejecting gas limits and nonces beyond `u64` like
+    /// `eth_sendRawTransaction` does.
+    fn call_message(&self, request: CallRequest, nonce: Option<U256>) -> web3::Result<Message> {
+        let max = U256::from(u64::MAX);
+        if request.gas.is_some_and(|gas| gas > max) || nonce.is_some_and(|nonce| nonce > max) {
+            return Err(rpc_error(-32000, "gas or nonce out of range", None));
+        }
+        let gas_price = match (request.gas_price, request.max_fee_per_gas) {
+            (Some(gas_price), _) => gas_price,
+            (None, Some(max_fee)) => {
+                let priority = request.max_priority_fee_per_gas.unwrap_or(self.config.priority_fee);
+                max_fee.min(self.config.base_fee.saturating_add(priority))
+            }
+            (None, None) => U256::zero(),
+        };
+        let access_list = request
+            .access_list
+            .unwrap_or_default()
+            .into_iter()
+            .map(|item| (item.address, item.storage_keys))
+            .collect();
+        Ok(Message {
+            caller: request.from.unwrap_or_default(),
+            to: request.to,
+            value: request.value.unwrap_or_default(),
+            data: request.data.map(|data| data.0).unwrap_or_default(),
+            gas_limit: request.gas.map(|gas| gas.as_u64()).unwrap_or(self.config.block_gas_limit),
+            gas_price,
+            nonce: nonce.map(|nonce| nonce.as_u64()),
+            access_list,
+        })
+    }
+
+    /// Runs a message against a copy of the latest state. Calls without a gas price skip the base fee check.
+    fn simulate(&self, message: &Message) -> web3::Result<ExecutionResult> {
+        self.simulate_at(message, self.head().number)
+    }
+
+    /// Runs a message against a copy of the state after the given block, as if it were included in
+    /// the next one.
+    fn simulate_at(&self, message: &Message, number: u64) -> web3::Result<ExecutionResult> {
+        let parent = self.data.blocks.get(number as usize).ok_or_else(|| rpc_error(-32000, "header not found", None))?;
+        // The latest state also holds changes made without a transaction, such as `set_code`
+        let mut state = if number == self.head().number { self.data.state.clone() } else { parent.state.clone() };
+        let base_fee = if message.gas_price.is_zero() { U256::zero() } else { self.config.base_fee };
+        let block = self.next_block(parent, base_fee);
+        transact(&mut state, &block, message).map_err(invalid_transaction)
+    }
+
+    /// Finds the lowest gas limit at which the message succeeds, by binary search up to `cap`.
+    fn estimate(&self, mut message: Message, cap: Option<u64>) -> web3::Result<u64> {
+        let mut high = cap.unwrap_or(self.config.block_gas_limit);
+        message.gas_limit = high;
+        // Only the gas limit is searched; a fee the sender cannot afford is ignored while estimating
+        message.gas_price = U256::zero();
+        let result = self.simulate(&message)?;
+        if !result.is_success() {
+            return Err(execution_error(&result));
+        }
+
+        let mut low = result.gas_used.saturating_sub(1);
+        while low + 1 < high {
+            let mid = low + (high - low) / 2;
+            message.gas_limit = mid;
+            match self.simulate(&message) {
+                Ok(result) if result.is_success() => high = mid,
+                _ => low = mid,
+            }
+        }
+        Ok(high)
+    }
+
+    /// Executes a transaction and mines it into a new block.
+    ///
+    /// Unsigned transactions have no raw encoding to hash, so they get a hash derived from the
+    /// sender, nonce, chain and block.
+    fn execute(&mut self, message: Message, hash: Option<H256>) -> web3::Result<H256> {
+        let nonce = self.data.state.nonce(&message.caller);
+        let block = self.pending_block(self.config.base_fee);
+        let result = transact(&mut self.data.state, &block, &message).map_err(invalid_transaction)?;
+
+        let hash = hash.unwrap_or_else(|| {
+            let mut preimage = message.caller.as_bytes().to_vec();
+            preimage.extend_from_slice(&nonce.to_be_bytes());
+            preimage.extend_from_slice(&self.config.chain_id.to_be_bytes());
+            preimage.extend_from_slice(&block.number.to_be_bytes());
+            H256(keccak256(&preimage))
+        });
+
+        let logs: Vec<Log> = result.logs.iter().enumerate().map(|(index, entry)| log(entry, hash, index)).collect();
+        let gas_used = result.gas_used;
+        let block_number = self.mine(vec![hash], gas_used, logs);
+        let block_hash = self.head().hash;
+        let logs = self.head().logs.clone();
+
+        self.data.transactions.insert(
+            hash,
+            MinedTransaction { hash, message, nonce, block_number, block_hash, result, logs },
+        );
+        Ok(hash)
+    }
+
+    fn mine(&mut self, transactions: Vec<H256>, gas_used: u64, mut logs: Vec<Log>) -> u64 {
+        let head = self.head();
+        let number = head.number + 1;
+        let parent_hash = head.hash;
+        let hash = block_hash(number, parent_hash, &transactions);
+        for log in &mut logs {
+            log.block_hash = Some(hash);
+            log.block_number = Some(U64::from(number));
+        }
+        let timestamp = next_timestamp(head.timestamp);
+        let state = self.data.state.clone();
+        self.data.blocks.push(LocalBlock { number, hash, parent_hash, timestamp, transactions, gas_used, logs, state });
+        number
+    }
+
+    fn block_tag(&self, tag: &Value) -> web3::Result<u64> {
+        match tag.as_str() {
+            None | Some("latest") | Some("pending") | Some("safe") | Some("finalized") => Ok(self.head().number),
+            Some("earliest") => Ok(0),
+            Some(_) => parse::<U64>(tag).map(|number| number.as_u64()),
+        }
+    }
+
+    fn logs(&self, filter: &Value) -> web3::Result<Value> {
+        let blocks: Vec<&LocalBlock> = match filter.get("blockHash") {
+            Some(hash) => {
+                let hash: H256 = parse(hash)?;
+                self.data.blocks.iter().filter(|block| block.hash == hash).collect()
+            }
+            None => {
+                let from = self.block_tag(&filter["fromBlock"])?;
+                let to = self.block_tag(&filter["toBlock"])?;
+                self.data.blocks.iter().filter(|block| block.number >= from && block.number <= to).collect()
+            }
+        };
+        Ok(json!(blocks
+            .iter()
+            .flat_map(|block| block.logs.iter())
+            .filter(|log| log_matches(filter, log))
+            .collect::<Vec<_>>()))
+    }
+}
+
+/// Checks a log against the `address` and `topics` of an `eth_getLogs` or `logs` subscription filter.
+pub(crate) fn log_matches(filter: &Value, log: &Log) -> bool {
+    let address_matches = match &filter["address"] {
+        Value::Null => true,
+        Value::Array(addresses) => addresses.iter().any(|address| parse(address).ok() == Some(log.address)),
+        address => parse(address).ok() == Some(log.address),
+    };
+
+    let topics = filter["topics"].as_array().cloned().unwrap_or_default();
+    let topics_match = topics.iter().enumerate().all(|(position, wanted)| {
+        let actual = log.topics.get(position).copied();
+        match wanted {
+            Value::Null => true,
+            Value::Array(options) => options.iter().any(|topic| parse(topic).ok().is_some_and(|topic: H256| Some(topic) == actual)),
+            topic => parse(topic).ok().is_some_and(|topic: H256| Some(topic) == actual),
+        }
+    });
+
+    address_matches && topics_match
+}
+
+/// Derives the private key and address of the development account with the given index.
+fn development_account(index: usize) -> (Address, H256) {
+    let key = H256(keccak256(format!("wasmify-rs local account {}", index).as_bytes()));
+    let secret = SecretKey::from_slice(key.as_bytes()).expect("a keccak hash is a valid secp256k1 key");
+    (SecretKeyRef::new(&secret).address(), key)
+}
+
+fn block_hash(number: u64, parent_hash: H256, transactions: &[H256]) -> H256 {
+    let mut preimage = number.to_be_bytes().to_vec();
+    preimage.extend_from_slice(parent_hash.as_bytes());
+    for transaction in transactions {
+        preimage.extend_from_slice(transaction.as_bytes());
+    }
+    H256(keccak256(&preimage))
+}
+
+/// Blocks follow the wall clock, but never share a timestamp with their parent.
+fn next_timestamp(parent: u64) -> u64 {
+    (chrono::Utc::now().timestamp() as u64).max(parent + 1)
+}
+
+fn log(entry: &LogEntry, transaction_hash: H256, index: usize) -> Log {
+    Log {
+        address: entry.address,
+        topics: entry.topics.clone(),
+        data: Bytes(entry.data.clone()),
+        block_hash: None,
+        block_number: None,
+        transaction_hash: Some(transaction_hash),
+        transaction_index: Some(0.into()),
+        log_index: Some(index.into()),
+        transaction_log_index: Some(index.into()),
+        log_type: None,
+        removed: Some(false),
+    }
+}
+
+/// Computes the 2048-bit bloom filter of a set of logs, as stored in receipts and block headers.
+fn logs_bloom(logs: &[Log]) -> H2048 {
+    let mut bloom = H2048::zero();
+    let inputs = logs
+        .iter()
+        .flat_map(|log| std::iter::once(log.address.as_bytes()).chain(log.topics.iter().map(|topic| topic.as_bytes())));
+    for input in inputs {
+        let hash = keccak256(input);
+        for i in 0..3 {
+            let bit = (((hash[2 * i] as usize) << 8) | hash[2 * i + 1] as usize) & 2047;
+            bloom.0[255 - bit / 8] |= 1 << (bit % 8);
+        }
+    }
+    bloom
+}
+
+fn receipt_json(transaction: &MinedTransaction) -> Value {
+    let result = &transaction.result;
+    json!({
+        "transactionHash": transaction.hash,
+        "transactionIndex": "0x0",
+        "blockHash": transaction.block_hash,
+        "blockNumber": U64::from(transaction.block_number),
+        "from": transaction.message.caller,
+        "to": transaction.message.to,
+        "cumulativeGasUsed": U256::from(result.gas_used),
+        "gasUsed": U256::from(result.gas_used),
+        "effectiveGasPrice": transaction.message.gas_price,
+        "contractAddress": result.created_address,
+        "logs": transaction.logs,
+        "logsBloom": logs_bloom(&transaction.logs),
+        "status": if result.is_success() { "0x1" } else { "0x0" },
+        "type": "0x2",
+    })
+}
+
+fn transaction_json(transaction: &MinedTransaction) -> Value {
+    let message = &transaction.message;
+    json!({
+        "hash": transaction.hash,
+        "nonce": U256::from(transaction.nonce),
+        "blockHash": transaction.block_hash,
+        "blockNumber": U64::from(transaction.block_number),
+        "transactionIndex": "0x0",
+        "from": message.caller,
+        "to": message.to,
+        "value": message.value,
+        "gas": U256::from(message.gas_limit),
+        "gasPrice": message.gas_price,
+        "input": Bytes(message.data.clone()),
+        "type": "0x2",
+    })
+}
+
+fn block_json(block: &LocalBlock, config: &LocalChainConfig) -> Value {
+    json!({
+        "number": U64::from(block.number),
+        "hash": block.hash,
+        "parentHash": block.parent_hash,
+        "sha3Uncles": H256::zero(),
+        "miner": Address::zero(),
+        "stateRoot": H256::zero(),
+        "transactionsRoot": H256::zero(),
+        "receiptsRoot": H256::zero(),
+        "logsBloom": logs_bloom(&block.logs),
+        "difficulty": "0x0",
+        "totalDifficulty": "0x0",
+        "gasLimit": U256::from(config.block_gas_limit),
+        "gasUsed": U256::from(block.gas_used),
+        "baseFeePerGas": config.base_fee,
+        "extraData": "0x",
+        "timestamp": U256::from(block.timestamp),
+        "mixHash": H256::zero(),
+        "nonce": "0x0000000000000000",
+        "size": "0x220",
+        "transactions": block.transactions,
+        "uncles": [],
+    })
+}
+
+/// Answers an `eth_call` with the return data, or a node-style error carrying the revert data.
+fn result_output(result: ExecutionResult) -> web3::Result<Value> {
+    if result.is_success() {
+        Ok(json!(Bytes(result.output)))
+    } else {
+        Err(execution_error(&result))
+    }
+}
+
+fn execution_error(result: &ExecutionResult) -> web3::Error {
+    match result.exit {
+        ExitReason::Revert => {
+            let message = match decode_revert(&result.output, None) {
+                RevertReason::Error(reason) => format!("execution reverted: {}", reason),
+                _ => "execution reverted".to_string(),
+            };
+            rpc_error(3, &message, Some(json!(Bytes(result.output.clone()))))
+        }
+        ExitReason::Halt(halt) => rpc_error(-32000, &format!("execution halted: {:?}", halt), None),
+        ExitReason::Success => rpc_error(-32603, "execution succeeded", None),
+    }
+}
+
+/// Maps a rejected transaction to the error messages geth uses.
+fn invalid_transaction(error: EvmError) -> web3::Error {
+    let message = match error {
+        EvmError::NonceTooLow { expected, found } => format!("nonce too low: next nonce {}, tx nonce {}", expected, found),
+        EvmError::NonceTooHigh { expected, found } => format!("nonce too high: next nonce {}, tx nonce {}", expected, found),
+        EvmError::InsufficientFunds { required, available } => {
+            format!("insufficient funds for gas * price + value: have {} want {}", available, required)
+        }
+        EvmError::IntrinsicGasTooLow { required, limit } => format!("intrinsic gas too low: have {}, want {}", limit, required),
+        EvmError::GasLimitExceedsBlock { limit, block_limit } => format!("exceeds block gas limit: {} > {}", limit, block_limit),
+        EvmError::GasPriceBelowBaseFee { gas_price, base_fee } => {
+            format!("max fee per gas less than block base fee: maxFeePerGas: {} baseFee: {}", gas_price, base_fee)
+        }
+        EvmError::InitCodeTooLarge(size) => format!("max initcode size exceeded: code size {}", size),
+    };
+    rpc_error(-32000, &message, None)
+}
+
+fn rpc_error(code: i64, message: &str, data: Option<Value>) -> web3::Error {
+    web3::Error::Rpc(jsonrpc_core::Error { code: ErrorCode::from(code), message: message.to_string(), data })
+}
+
+fn parse<T: DeserializeOwned>(value: &Value) -> web3::Result<T> {
+    serde_json::from_value(value.clone()).map_err(|e| rpc_error(-32602, &format!("invalid params: {}", e), None))
+}
+
+// Unit test example
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contracts::abi::{parse_human_readable_abi, AbiValue};
+    use crate::contracts::deploy::deploy_contract;
+    use crate::contracts::interaction::{call_contract_function, fetch_contract_data, InteractionError};
+    use crate::contracts::provider::ProviderError;
+    use crate::evm::opcodes::*;
+    use web3::types::TransactionRequest;
+
+    /// `count()`: increments storage slot 0 and returns the new value, whatever the calldata.
+    const COUNTER: [u8; 14] = [
+        PUSH0, SLOAD, PUSH1, 1, ADD, DUP1, PUSH0, SSTORE, PUSH0, MSTORE, PUSH1, 32, PUSH0, RETURN,
+    ];
+
+    /// Reverts with `Error("nope")`.
+    const REVERTER: [u8; 35] = [
+        PUSH1 + 3, 0x08, 0xc3, 0x79, 0xa0, PUSH1, 0xe0, SHL, PUSH0, MSTORE, // selector
+        PUSH1, 0x20, PUSH1, 0x04, MSTORE, // offset of the string
+        PUSH1, 0x04, PUSH1, 0x24, MSTORE, // length of the string
+        PUSH1 + 3, b'n', b'o', b'p', b'e', PUSH1, 0xe0, SHL, PUSH1, 0x44, MSTORE, // contents
+        PUSH1, 0x64, PUSH0, REVERT,
+    ];
+
+    fn init_code(runtime: &[u8]) -> Vec<u8> {
+        let len = runtime.len() as u8;
+        let mut code = vec![PUSH1, len, PUSH1, 10, PUSH0, CODECOPY, PUSH1, len, PUSH0, RETURN];
+        code.extend_from_slice(runtime);
+        code
+    }
+
+    #[tokio::test]
+    async fn test_deploy_call_and_receipts() {
+        let chain = LocalChain::new();
+        let provider = chain.provider();
+        let sender = chain.accounts()[0];
+        assert_eq!(provider.chain_id().await.unwrap(), U256::from(31337));
+
+        let result = deploy_contract(&provider, &init_code(&COUNTER), U256::from(200_000), &format!("{:?}", sender)).await;
+        assert!(result.is_ok());
+        let counter = super::super::state::create_address(sender, 0);
+        assert_eq!(provider.code(counter).await.unwrap().0, COUNTER.to_vec());
+        assert_eq!(provider.block_number().await.unwrap(), U64::from(1));
+
+        // eth_call simulates without changing state
+        let abi = parse_human_readable_abi(&["function count() returns (uint256)"]).unwrap();
+        let address = format!("{:?}", counter);
+        let value = fetch_contract_data(&provider, &address, &abi, "count", vec![]).await.unwrap();
+        assert_eq!(value, vec![AbiValue::Uint(U256::one())]);
+        assert!(chain.storage(&counter, U256::zero()).is_zero());
+
+        // Transactions without a gas limit are estimated, and the receipt records the gas used
+        let receipt = call_contract_function(&provider, &address, &abi, "count", vec![], &format!("{:?}", sender)).await.unwrap();
+        assert_eq!(chain.storage(&counter, U256::zero()), U256::one());
+        assert_eq!(receipt.gas_used, Some(U256::from(43_190)));
+        assert_eq!(receipt.effective_gas_price, Some(U256::from(2 * GWEI)));
+        assert_eq!(provider.transaction_count(sender, web3::types::BlockNumber::Latest).await.unwrap(), U256::from(2));
+
+        let estimate = provider.estimate_gas(CallRequest { from: Some(sender), to: Some(counter), ..Default::default() }).await.unwrap();
+        assert_eq!(estimate, U256::from(26_026));
+
+        // Calls against an earlier block see the state after that block
+        let call = CallRequest { from: Some(sender), to: Some(counter), ..Default::default() };
+        let latest = provider.call(call.clone()).await.unwrap();
+        let before = provider.call_at(call.clone(), web3::types::BlockNumber::Number(1.into())).await.unwrap();
+        assert_eq!((U256::from_big_endian(&latest.0), U256::from_big_endian(&before.0)), (U256::from(2), U256::one()));
+        let missing = provider.call_at(call, web3::types::BlockNumber::Number(9.into())).await;
+        assert!(matches!(missing, Err(ProviderError::Rpc { code: -32000, ref message, .. }) if message == "header not found"));
+    }
+
+    #[tokio::test]
+    async fn test_reverts_are_decoded() {
+        let chain = LocalChain::new();
+        let provider = chain.provider();
+        let reverter = Address::repeat_byte(0xcc);
+        chain.set_code(reverter, REVERTER.to_vec());
+
+        let abi = parse_human_readable_abi(&["function poke()"]).unwrap();
+        let address = format!("{:?}", reverter);
+        let sender = format!("{:?}", chain.accounts()[0]);
+        let result = call_contract_function(&provider, &address, &abi, "poke", vec![], &sender).await;
+        assert!(matches!(result, Err(InteractionError::Reverted(RevertReason::Error(ref message))) if message == "nope"));
+        let result = fetch_contract_data(&provider, &address, &abi, "poke", vec![]).await;
+        assert!(matches!(result, Err(InteractionError::Reverted(RevertReason::Error(_)))));
+
+        // With an explicit gas limit the transaction is mined with a failed status
+        let transaction = TransactionRequest { from: chain.accounts()[0], to: Some(reverter), gas: Some(100_000.into()), ..Default::default() };
+        let hash = provider.send_transaction(transaction).await.unwrap();
+        let receipt = provider.wait_for_receipt(hash).await.unwrap();
+        assert_eq!(receipt.status, Some(U64::zero()));
+
+        let broke = Address::repeat_byte(0x01);
+        let transaction = TransactionRequest { from: broke, to: Some(reverter), gas: Some(100_000.into()), gas_price: Some(GWEI.into()), ..Default::default() };
+        let result = provider.send_transaction(transaction).await;
+        assert!(matches!(result, Err(ProviderError::Rpc { code: -32000, ref message, .. }) if message.starts_with("insufficient funds")));
+    }
+
+    #[tokio::test]
+    async fn test_out_of_range_quantities() {
+        let chain = LocalChain::new();
+        let provider = chain.provider();
+        let sender = chain.accounts()[0];
+        let huge = U256::from(u64::MAX) + 1;
+        let out_of_range = |result: Result<_, ProviderError>| {
+            matches!(result, Err(ProviderError::Rpc { code: -32000, ref message, .. }) if message == "gas or nonce out of range")
+        };
+
+        let call = CallRequest { from: Some(sender), to: Some(sender), gas: Some(huge), ..Default::default() };
+        assert!(out_of_range(provider.call(call.clone()).await.map(|_| ())));
+        assert!(out_of_range(provider.estimate_gas(call).await.map(|_| ())));
+        let transaction = TransactionRequest { from: sender, to: Some(sender), nonce: Some(huge), ..Default::default() };
+        assert!(out_of_range(provider.send_transaction(transaction).await.map(|_| ())));
+
+        // The chain stays usable afterwards
+        assert_eq!(provider.block_number().await.unwrap(), U64::zero());
+    }
+
+    #[tokio::test]
+    async fn test_overflowing_costs() {
+        use crate::signing::{PrivateKeySigner, Signer};
+        let chain = LocalChain::new();
+        let signer = PrivateKeySigner::random();
+        let provider = chain.provider().with_signer(signer.clone());
+        let sender = chain.accounts()[0];
+        let insufficient_funds = |result: Result<_, ProviderError>| {
+            matches!(result, Err(ProviderError::Rpc { code: -32000, ref message, .. }) if message.starts_with("insufficient funds"))
+        };
+
+        let call = CallRequest { from: Some(sender), to: Some(sender), gas_price: Some(U256::MAX), ..Default::default() };
+        assert!(insufficient_funds(provider.call(call).await.map(|_| ())));
+        let call = CallRequest { from: Some(sender), to: Some(sender), gas_price: Some(GWEI.into()), value: Some(U256::MAX), ..Default::default() };
+        assert!(insufficient_funds(provider.call(call).await.map(|_| ())));
+        let transaction = TransactionRequest { from: sender, to: Some(sender), gas_price: Some(U256::MAX), ..Default::default() };
+        assert!(insufficient_funds(provider.send_transaction(transaction).await.map(|_| ())));
+        let signed = TransactionRequest {
+            from: signer.address(),
+            to: Some(sender),
+            gas: Some(21_000.into()),
+            max_fee_per_gas: Some(U256::MAX),
+            max_priority_fee_per_gas: Some(U256::MAX),
+            ..Default::default()
+        };
+        assert!(insufficient_funds(provider.send_transaction(signed).await.map(|_| ())));
+
+        // The chain stays usable afterwards
+        assert_eq!(provider.block_number().await.unwrap(), U64::zero());
+    }
+
+    #[tokio::test]
+    async fn test_signed_transactions() {
+        use crate::signing::{PrivateKeySigner, Signer};
+        let chain = LocalChain::new();
+        let signer = PrivateKeySigner::random();
+        chain.set_balance(signer.address(), U256::exp10(18));
+        let provider = chain.provider().with_signer(signer.clone());
+        let counter = Address::repeat_byte(0xcc);
+        chain.set_code(counter, COUNTER.to_vec());
+
+        // Legacy, EIP-2930 and EIP-1559 (the default on a chain with a base fee)
+        let legacy = TransactionRequest { from: signer.address(), to: Some(counter), gas_price: Some(GWEI.into()), ..Default::default() };
+        let access_list = TransactionRequest { access_list: Some(vec![]), ..legacy.clone() };
+        let eip1559 = TransactionRequest { gas_price: None, ..legacy.clone() };
+        for (i, transaction) in [legacy, access_list, eip1559].into_iter().enumerate() {
+            let hash = provider.send_transaction(transaction).await.unwrap();
+            let mined: Value = provider.request("eth_getTransactionByHash", vec![json!(hash)]).await.unwrap();
+            assert_eq!(mined["from"], json!(signer.address()));
+            assert_eq!(mined["nonce"], json!(U256::from(i)));
+        }
+        assert_eq!(chain.storage(&counter, U256::zero()), U256::from(3));
+
+        // A replayed transaction is rejected by its nonce, a foreign chain id by the chain
+        let transaction = provider.fill_transaction(&TransactionRequest { from: signer.address(), to: Some(counter), ..Default::default() }).await.unwrap();
+        let mut stale = transaction.clone();
+        stale.nonce = U256::zero();
+        let raw = signer.sign_transaction(&stale).unwrap().raw_transaction;
+        let result = provider.send_raw_transaction(raw).await;
+        assert!(matches!(result, Err(ProviderError::Rpc { ref message, .. }) if message.starts_with("nonce too low")));
+        let mut foreign = transaction;
+        foreign.chain_id = 1;
+        let raw = signer.sign_transaction(&foreign).unwrap().raw_transaction;
+        assert!(provider.send_raw_transaction(raw).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_and_revert() {
+        let chain = LocalChain::new();
+        let provider = chain.provider();
+        let counter = Address::repeat_byte(0xcc);
+        chain.set_code(counter, COUNTER.to_vec());
+        let sender = chain.accounts()[0];
+        let count = TransactionRequest { from: sender, to: Some(counter), ..Default::default() };
+
+        let snapshot = chain.snapshot();
+        let hash = provider.send_transaction(count.clone()).await.unwrap();
+        let nested: U64 = provider.request("evm_snapshot", vec![]).await.unwrap();
+        provider.send_transaction(count.clone()).await.unwrap();
+        assert_eq!(chain.storage(&counter, U256::zero()), U256::from(2));
+
+        assert!(provider.request::<bool>("evm_revert", vec![json!(nested)]).await.unwrap());
+        assert_eq!(chain.storage(&counter, U256::zero()), U256::one());
+        assert!(provider.transaction_receipt(hash).await.unwrap().is_some());
+
+        assert!(chain.revert(snapshot));
+        assert!(chain.storage(&counter, U256::zero()).is_zero());
+        assert_eq!(chain.block_number(), 0);
+        assert!(provider.transaction_receipt(hash).await.unwrap().is_none());
+        assert_eq!(chain.balance(&sender), U256::exp10(22));
+        // Reverting discards the snapshot and every later one
+        assert!(!chain.revert(snapshot));
+        assert!(!chain.revert(nested.as_u64()));
+    }
+
+    #[tokio::test]
+    async fn test_logs_and_blocks() {
+        let chain = LocalChain::new();
+        let provider = chain.provider();
+        let emitter = Address::repeat_byte(0xcc);
+        // LOG1 with topic 0x2a and no data
+        chain.set_code(emitter, vec![PUSH1, 0x2a, PUSH0, PUSH0, LOG0 + 1, STOP]);
+        let transaction = TransactionRequest { from: chain.accounts()[1], to: Some(emitter), ..Default::default() };
+        let hash = provider.send_transaction(transaction).await.unwrap();
+
+        let filter = web3::types::FilterBuilder::default()
+            .address(vec![emitter])
+            .topics(Some(vec![H256::from_low_u64_be(0x2a)]), None, None, None)
+            .from_block(web3::types::BlockNumber::Earliest)
+            .build();
+        let logs = provider.logs(filter).await.unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].transaction_hash, Some(hash));
+        assert_eq!(logs[0].block_number, Some(U64::one()));
+
+        let receipt = provider.transaction_receipt(hash).await.unwrap().unwrap();
+        assert_eq!(receipt.logs, logs);
+        assert_ne!(receipt.logs_bloom, H2048::zero());
+
+        let block: web3::types::Block<H256> = provider.request("eth_getBlockByNumber", vec![json!("latest"), json!(false)]).await.unwrap();
+        assert_eq!(block.transactions, vec![hash]);
+        assert_eq!(block.hash, receipt.block_hash);
+        assert_eq!(block.base_fee_per_gas, Some(U256::from(GWEI)));
+    }
+}
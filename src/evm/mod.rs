@@ -0,0 +1,17 @@
+//! An embedded EVM for executing contracts in-process.
+//!
+//! The interpreter runs bytecode against an in-memory `WorldState`, and `LocalChain` wraps it in a
+//! `web3::Transport` so that deployments, calls and gas measurements can run through a `Provider`
+//! without any node. Bytecode can also be analysed statically for gas bounds.
+
+// Module declarations
+pub mod analysis;
+pub mod interpreter;
+pub mod local_chain;
+pub mod opcodes;
+pub mod state;
+
+pub use analysis::{analyze, analyze_deployment, GasAnalysis, Hardfork};
+pub use interpreter::{transact, BlockEnv, EvmError, ExecutionResult, ExitReason, Halt, LogEntry, Message};
+pub use local_chain::{LocalChain, LocalChainConfig};
+pub use state::{Account, WorldState};
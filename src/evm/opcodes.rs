@@ -0,0 +1,268 @@
+//! EVM opcode constants and their static properties.
+
+pub const STOP: u8 = 0x00;
+pub const ADD: u8 = 0x01;
+pub const MUL: u8 = 0x02;
+pub const SUB: u8 = 0x03;
+pub const DIV: u8 = 0x04;
+pub const SDIV: u8 = 0x05;
+pub const MOD: u8 = 0x06;
+pub const SMOD: u8 = 0x07;
+pub const ADDMOD: u8 = 0x08;
+pub const MULMOD: u8 = 0x09;
+pub const EXP: u8 = 0x0a;
+pub const SIGNEXTEND: u8 = 0x0b;
+pub const LT: u8 = 0x10;
+pub const GT: u8 = 0x11;
+pub const SLT: u8 = 0x12;
+pub const SGT: u8 = 0x13;
+pub const EQ: u8 = 0x14;
+pub const ISZERO: u8 = 0x15;
+pub const AND: u8 = 0x16;
+pub const OR: u8 = 0x17;
+pub const XOR: u8 = 0x18;
+pub const NOT: u8 = 0x19;
+pub const BYTE: u8 = 0x1a;
+pub const SHL: u8 = 0x1b;
+pub const SHR: u8 = 0x1c;
+pub const SAR: u8 = 0x1d;
+pub const KECCAK256: u8 = 0x20;
+pub const ADDRESS: u8 = 0x30;
+pub const BALANCE: u8 = 0x31;
+pub const ORIGIN: u8 = 0x32;
+pub const CALLER: u8 = 0x33;
+pub const CALLVALUE: u8 = 0x34;
+pub const CALLDATALOAD: u8 = 0x35;
+pub const CALLDATASIZE: u8 = 0x36;
+pub const CALLDATACOPY: u8 = 0x37;
+pub const CODESIZE: u8 = 0x38;
+pub const CODECOPY: u8 = 0x39;
+pub const GASPRICE: u8 = 0x3a;
+pub const EXTCODESIZE: u8 = 0x3b;
+pub const EXTCODECOPY: u8 = 0x3c;
+pub const RETURNDATASIZE: u8 = 0x3d;
+pub const RETURNDATACOPY: u8 = 0x3e;
+pub const EXTCODEHASH: u8 = 0x3f;
+pub const BLOCKHASH: u8 = 0x40;
+pub const COINBASE: u8 = 0x41;
+pub const TIMESTAMP: u8 = 0x42;
+pub const NUMBER: u8 = 0x43;
+pub const PREVRANDAO: u8 = 0x44;
+pub const GASLIMIT: u8 = 0x45;
+pub const CHAINID: u8 = 0x46;
+pub const SELFBALANCE: u8 = 0x47;
+pub const BASEFEE: u8 = 0x48;
+pub const BLOBHASH: u8 = 0x49;
+pub const BLOBBASEFEE: u8 = 0x4a;
+pub const POP: u8 = 0x50;
+pub const MLOAD: u8 = 0x51;
+pub const MSTORE: u8 = 0x52;
+pub const MSTORE8: u8 = 0x53;
+pub const SLOAD: u8 = 0x54;
+pub const SSTORE: u8 = 0x55;
+pub const JUMP: u8 = 0x56;
+pub const JUMPI: u8 = 0x57;
+pub const PC: u8 = 0x58;
+pub const MSIZE: u8 = 0x59;
+pub const GAS: u8 = 0x5a;
+pub const JUMPDEST: u8 = 0x5b;
+pub const TLOAD: u8 = 0x5c;
+pub const TSTORE: u8 = 0x5d;
+pub const MCOPY: u8 = 0x5e;
+pub const PUSH0: u8 = 0x5f;
+pub const PUSH1: u8 = 0x60;
+pub const PUSH32: u8 = 0x7f;
+pub const DUP1: u8 = 0x80;
+pub const DUP16: u8 = 0x8f;
+pub const SWAP1: u8 = 0x90;
+pub const SWAP16: u8 = 0x9f;
+pub const LOG0: u8 = 0xa0;
+pub const LOG4: u8 = 0xa4;
+pub const CREATE: u8 = 0xf0;
+pub const CALL: u8 = 0xf1;
+pub const CALLCODE: u8 = 0xf2;
+pub const RETURN: u8 = 0xf3;
+pub const DELEGATECALL: u8 = 0xf4;
+pub const CREATE2: u8 = 0xf5;
+pub const STATICCALL: u8 = 0xfa;
+pub const REVERT: u8 = 0xfd;
+pub const INVALID: u8 = 0xfe;
+pub const SELFDESTRUCT: u8 = 0xff;
+
+/// Static properties of an opcode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpcodeInfo {
+    /// The mnemonic, e.g. `SSTORE`.
+    pub name: &'static str,
+    /// The number of stack items the opcode pops.
+    pub inputs: usize,
+    /// The number of stack items the opcode pushes.
+    pub outputs: usize,
+    /// The constant part of the gas cost; memory expansion, cold access and other dynamic costs come on top.
+    pub gas: u64,
+    /// The number of immediate bytes following the opcode (only non-zero for `PUSH1`..`PUSH32`).
+    pub immediate: usize,
+}
+
+const fn op(name: &'static str, inputs: usize, outputs: usize, gas: u64) -> Option<OpcodeInfo> {
+    Some(OpcodeInfo { name, inputs, outputs, gas, immediate: 0 })
+}
+
+const PUSH_NAMES: [&str; 32] = [
+    "PUSH1", "PUSH2", "PUSH3", "PUSH4", "PUSH5", "PUSH6", "PUSH7", "PUSH8", "PUSH9", "PUSH10", "PUSH11", "PUSH12",
+    "PUSH13", "PUSH14", "PUSH15", "PUSH16", "PUSH17", "PUSH18", "PUSH19", "PUSH20", "PUSH21", "PUSH22", "PUSH23",
+    "PUSH24", "PUSH25", "PUSH26", "PUSH27", "PUSH28", "PUSH29", "PUSH30", "PUSH31", "PUSH32",
+];
+const DUP_NAMES: [&str; 16] = [
+    "DUP1", "DUP2", "DUP3", "DUP4", "DUP5", "DUP6", "DUP7", "DUP8", "DUP9", "DUP10", "DUP11", "DUP12", "DUP13", "DUP14",
+    "DUP15", "DUP16",
+];
+const SWAP_NAMES: [&str; 16] = [
+    "SWAP1", "SWAP2", "SWAP3", "SWAP4", "SWAP5", "SWAP6", "SWAP7", "SWAP8", "SWAP9", "SWAP10", "SWAP11", "SWAP12",
+    "SWAP13", "SWAP14", "SWAP15", "SWAP16",
+];
+const LOG_NAMES: [&str; 5] = ["LOG0", "LOG1", "LOG2", "LOG3", "LOG4"];
+
+/// Returns the static properties of an opcode under the Cancun rules, or `None` for undefined opcodes.
+///
+/// # Arguments
+/// * `opcode` - The opcode byte.
+pub fn opcode_info(opcode: u8) -> Option<OpcodeInfo> {
+    match opcode {
+        STOP => op("STOP", 0, 0, 0),
+        ADD => op("ADD", 2, 1, 3),
+        MUL => op("MUL", 2, 1, 5),
+        SUB => op("SUB", 2, 1, 3),
+        DIV => op("DIV", 2, 1, 5),
+        SDIV => op("SDIV", 2, 1, 5),
+        MOD => op("MOD", 2, 1, 5),
+        SMOD => op("SMOD", 2, 1, 5),
+        ADDMOD => op("ADDMOD", 3, 1, 8),
+        MULMOD => op("MULMOD", 3, 1, 8),
+        EXP => op("EXP", 2, 1, 10),
+        SIGNEXTEND => op("SIGNEXTEND", 2, 1, 5),
+        LT => op("LT", 2, 1, 3),
+        GT => op("GT", 2, 1, 3),
+        SLT => op("SLT", 2, 1, 3),
+        SGT => op("SGT", 2, 1, 3),
+        EQ => op("EQ", 2, 1, 3),
+        ISZERO => op("ISZERO", 1, 1, 3),
+        AND => op("AND", 2, 1, 3),
+        OR => op("OR", 2, 1, 3),
+        XOR => op("XOR", 2, 1, 3),
+        NOT => op("NOT", 1, 1, 3),
+        BYTE => op("BYTE", 2, 1, 3),
+        SHL => op("SHL", 2, 1, 3),
+        SHR => op("SHR", 2, 1, 3),
+        SAR => op("SAR", 2, 1, 3),
+        KECCAK256 => op("KECCAK256", 2, 1, 30),
+        ADDRESS => op("ADDRESS", 0, 1, 2),
+        BALANCE => op("BALANCE", 1, 1, 0),
+        ORIGIN => op("ORIGIN", 0, 1, 2),
+        CALLER => op("CALLER", 0, 1, 2),
+        CALLVALUE => op("CALLVALUE", 0, 1, 2),
+        CALLDATALOAD => op("CALLDATALOAD", 1, 1, 3),
+        CALLDATASIZE => op("CALLDATASIZE", 0, 1, 2),
+        CALLDATACOPY => op("CALLDATACOPY", 3, 0, 3),
+        CODESIZE => op("CODESIZE", 0, 1, 2),
+        CODECOPY => op("CODECOPY", 3, 0, 3),
+        GASPRICE => op("GASPRICE", 0, 1, 2),
+        EXTCODESIZE => op("EXTCODESIZE", 1, 1, 0),
+        EXTCODECOPY => op("EXTCODECOPY", 4, 0, 0),
+        RETURNDATASIZE => op("RETURNDATASIZE", 0, 1, 2),
+        RETURNDATACOPY => op("RETURNDATACOPY", 3, 0, 3),
+        EXTCODEHASH => op("EXTCODEHASH", 1, 1, 0),
+        BLOCKHASH => op("BLOCKHASH", 1, 1, 20),
+        COINBASE => op("COINBASE", 0, 1, 2),
+        TIMESTAMP => op("TIMESTAMP", 0, 1, 2),
+        NUMBER => op("NUMBER", 0, 1, 2),
+        PREVRANDAO => op("PREVRANDAO", 0, 1, 2),
+        GASLIMIT => op("GASLIMIT", 0, 1, 2),
+        CHAINID => op("CHAINID", 0, 1, 2),
+        SELFBALANCE => op("SELFBALANCE", 0, 1, 5),
+        BASEFEE => op("BASEFEE", 0, 1, 2),
+        BLOBHASH => op("BLOBHASH", 1, 1, 3),
+        BLOBBASEFEE => op("BLOBBASEFEE", 0, 1, 2),
+        POP => op("POP", 1, 0, 2),
+        MLOAD => op("MLOAD", 1, 1, 3),
+        MSTORE => op("MSTORE", 2, 0, 3),
+        MSTORE8 => op("MSTORE8", 2, 0, 3),
+        SLOAD => op("SLOAD", 1, 1, 0),
+        SSTORE => op("SSTORE", 2, 0, 0),
+        JUMP => op("JUMP", 1, 0, 8),
+        JUMPI => op("JUMPI", 2, 0, 10),
+        PC => op("PC", 0, 1, 2),
+        MSIZE => op("MSIZE", 0, 1, 2),
+        GAS => op("GAS", 0, 1, 2),
+        JUMPDEST => op("JUMPDEST", 0, 0, 1),
+        TLOAD => op("TLOAD", 1, 1, 100),
+        TSTORE => op("TSTORE", 2, 0, 100),
+        MCOPY => op("MCOPY", 3, 0, 3),
+        PUSH0 => op("PUSH0", 0, 1, 2),
+        PUSH1..=PUSH32 => {
+            let size = (opcode - PUSH1) as usize;
+            Some(OpcodeInfo { name: PUSH_NAMES[size], inputs: 0, outputs: 1, gas: 3, immediate: size + 1 })
+        }
+        DUP1..=DUP16 => {
+            let n = (opcode - DUP1) as usize;
+            op(DUP_NAMES[n], n + 1, n + 2, 3)
+        }
+        SWAP1..=SWAP16 => {
+            let n = (opcode - SWAP1) as usize;
+            op(SWAP_NAMES[n], n + 2, n + 2, 3)
+        }
+        LOG0..=LOG4 => {
+            let topics = (opcode - LOG0) as usize;
+            op(LOG_NAMES[topics], topics + 2, 0, 375 * (topics as u64 + 1))
+        }
+        CREATE => op("CREATE", 3, 1, 32000),
+        CALL => op("CALL", 7, 1, 0),
+        CALLCODE => op("CALLCODE", 7, 1, 0),
+        RETURN => op("RETURN", 2, 0, 0),
+        DELEGATECALL => op("DELEGATECALL", 6, 1, 0),
+        CREATE2 => op("CREATE2", 4, 1, 32000),
+        STATICCALL => op("STATICCALL", 6, 1, 0),
+        REVERT => op("REVERT", 2, 0, 0),
+        INVALID => op("INVALID", 0, 0, 0),
+        SELFDESTRUCT => op("SELFDESTRUCT", 1, 0, 5000),
+        _ => None,
+    }
+}
+
+/// Returns the offsets of every valid `JUMPDEST` in `code`, skipping `PUSH` immediates.
+pub fn jump_destinations(code: &[u8]) -> Vec<bool> {
+    let mut valid = vec![false; code.len()];
+    let mut pc = 0;
+    while pc < code.len() {
+        match code[pc] {
+            JUMPDEST => valid[pc] = true,
+            PUSH1..=PUSH32 => pc += (code[pc] - PUSH1) as usize + 1,
+            _ => {}
+        }
+        pc += 1;
+    }
+    valid
+}
+
+// Unit test example
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opcode_info() {
+        assert_eq!(opcode_info(ADD).unwrap().name, "ADD");
+        assert_eq!(opcode_info(0x7f).unwrap().immediate, 32);
+        assert_eq!(opcode_info(0x8f).unwrap(), OpcodeInfo { name: "DUP16", inputs: 16, outputs: 17, gas: 3, immediate: 0 });
+        assert_eq!(opcode_info(0xa2).unwrap().gas, 1125);
+        assert!(opcode_info(0x0c).is_none());
+        assert_eq!((0..=255u8).filter(|byte| opcode_info(*byte).is_some()).count(), 149);
+    }
+
+    #[test]
+    fn test_jump_destinations_skip_push_data() {
+        // PUSH2 0x5b5b JUMPDEST
+        let valid = jump_destinations(&[PUSH1 + 1, JUMPDEST, JUMPDEST, JUMPDEST]);
+        assert_eq!(valid, vec![false, false, false, true]);
+    }
+}
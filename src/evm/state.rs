@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use web3::signing::keccak256;
+use web3::types::{Address, H256, U256};
+
+/// Keccak-256 of the empty string, the code hash of accounts without code.
+pub const EMPTY_CODE_HASH: H256 = H256([
+    0xc5, 0xd2, 0x46, 0x01, 0x86, 0xf7, 0x23, 0x3c, 0x92, 0x7e, 0x7d, 0xb2, 0xdc, 0xc7, 0x03, 0xc0, 0xe5, 0x00, 0xb6, 0x53,
+    0xca, 0x82, 0x27, 0x3b, 0x7b, 0xfa, 0xd8, 0x04, 0x5d, 0x85, 0xa4, 0x70,
+]);
+
+/// An account of the simulated world state.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Account {
+    pub nonce: u64,
+    pub balance: U256,
+    pub code: Vec<u8>,
+    pub storage: HashMap<U256, U256>,
+}
+
+impl Account {
+    /// Returns true if the account has no nonce, balance or code (EIP-161).
+    pub fn is_empty(&self) -> bool {
+        self.nonce == 0 && self.balance.is_zero() && self.code.is_empty()
+    }
+
+    /// Returns the Keccak-256 hash of the account code.
+    pub fn code_hash(&self) -> H256 {
+        if self.code.is_empty() {
+            EMPTY_CODE_HASH
+        } else {
+            H256(keccak256(&self.code))
+        }
+    }
+}
+
+/// The accounts of the simulated chain.
+///
+/// The state is a plain map so that snapshots are cheap to reason about: taking one is a clone,
+/// and reverting to it is an assignment.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WorldState {
+    accounts: HashMap<Address, Account>,
+}
+
+impl WorldState {
+    /// Returns the account at `address`, if it exists.
+    pub fn account(&self, address: &Address) -> Option<&Account> {
+        self.accounts.get(address)
+    }
+
+    /// Returns the account at `address`, creating an empty one if needed.
+    pub fn account_mut(&mut self, address: Address) -> &mut Account {
+        self.accounts.entry(address).or_default()
+    }
+
+    /// Returns true if an account exists at `address` and is not empty.
+    pub fn exists(&self, address: &Address) -> bool {
+        self.account(address).is_some_and(|account| !account.is_empty())
+    }
+
+    /// Removes the account at `address`.
+    pub fn remove(&mut self, address: &Address) {
+        self.accounts.remove(address);
+    }
+
+    pub fn balance(&self, address: &Address) -> U256 {
+        self.account(address).map(|account| account.balance).unwrap_or_default()
+    }
+
+    pub fn nonce(&self, address: &Address) -> u64 {
+        self.account(address).map(|account| account.nonce).unwrap_or_default()
+    }
+
+    pub fn code(&self, address: &Address) -> &[u8] {
+        self.account(address).map(|account| account.code.as_slice()).unwrap_or_default()
+    }
+
+    pub fn storage(&self, address: &Address, key: U256) -> U256 {
+        self.account(address)
+            .and_then(|account| account.storage.get(&key).copied())
+            .unwrap_or_default()
+    }
+
+    /// Writes a storage slot; writing zero clears it.
+    pub fn set_storage(&mut self, address: Address, key: U256, value: U256) {
+        let storage = &mut self.account_mut(address).storage;
+        if value.is_zero() {
+            storage.remove(&key);
+        } else {
+            storage.insert(key, value);
+        }
+    }
+
+    /// Moves `value` wei between two accounts.
+    ///
+    /// # Returns
+    /// bool - False, leaving the state untouched, if the sender cannot cover the value.
+    pub fn transfer(&mut self, from: Address, to: Address, value: U256) -> bool {
+        if self.balance(&from) < value {
+            return false;
+        }
+        if !value.is_zero() {
+            self.account_mut(from).balance -= value;
+            self.account_mut(to).balance += value;
+        }
+        true
+    }
+}
+
+/// Computes the address of a contract created with `CREATE`: `keccak256(rlp([sender, nonce]))[12..]`.
+pub fn create_address(sender: Address, nonce: u64) -> Address {
+    let mut stream = rlp::RlpStream::new_list(2);
+    stream.append(&sender);
+    stream.append(&nonce);
+    Address::from_slice(&keccak256(&stream.out())[12..])
+}
+
+/// Computes the address of a contract created with `CREATE2`:
+/// `keccak256(0xff ++ sender ++ salt ++ keccak256(init_code))[12..]`.
+pub fn create2_address(sender: Address, salt: H256, init_code_hash: H256) -> Address {
+    let mut preimage = Vec::with_capacity(85);
+    preimage.push(0xff);
+    preimage.extend_from_slice(sender.as_bytes());
+    preimage.extend_from_slice(salt.as_bytes());
+    preimage.extend_from_slice(init_code_hash.as_bytes());
+    Address::from_slice(&keccak256(&preimage)[12..])
+}
+
+// Unit test example
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_addresses() {
+        // Vectors from EIP-1014 and the well-known first contract of the zero address
+        let sender: Address = "0x6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0".parse().unwrap();
+        assert_eq!(create_address(sender, 0), "0xcd234a471b72ba2f1ccf0a70fcaba648a5eecd8d".parse().unwrap());
+        assert_eq!(create_address(sender, 1), "0x343c43a37d37dff08ae8c4a11544c718abb4fcf8".parse().unwrap());
+
+        let deployer: Address = "0xdeadbeef00000000000000000000000000000000".parse().unwrap();
+        let salt = H256::zero();
+        let init_code_hash = H256(keccak256(&[0x00]));
+        assert_eq!(
+            create2_address(deployer, salt, init_code_hash),
+            "0xB928f69Bb1D91Cd65274e3c79d8986362984fDA3".parse().unwrap()
+        );
+        assert_eq!(H256(keccak256(&[])), EMPTY_CODE_HASH);
+    }
+
+    #[test]
+    fn test_transfer_and_storage() {
+        let (alice, bob) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let mut state = WorldState::default();
+        state.account_mut(alice).balance = U256::from(10);
+
+        assert!(!state.transfer(alice, bob, U256::from(11)));
+        assert!(state.transfer(alice, bob, U256::from(4)));
+        assert_eq!((state.balance(&alice), state.balance(&bob)), (U256::from(6), U256::from(4)));
+
+        state.set_storage(bob, U256::one(), U256::from(7));
+        assert_eq!(state.storage(&bob, U256::one()), U256::from(7));
+        state.set_storage(bob, U256::one(), U256::zero());
+        assert!(state.account(&bob).unwrap().storage.is_empty());
+        assert!(!state.exists(&Address::repeat_byte(3)));
+    }
+}
@@ -1,4 +1,6 @@
+use crate::contracts::gas_history::{GasHistory, GasMetric};
 use crate::framework::logging::log_info;
+use std::time::Duration;
 use web3::types::U256;
 
 /// Optimizes the gas usage for a smart contract based on historical gas prices.
@@ -11,7 +13,7 @@ use web3::types::U256;
 /// U256 - The optimized gas limit.
 pub fn optimize_gas_usage(historical_gas_prices: Vec<U256>, current_gas_limit: U256) -> U256 {
     let average_gas_price = if !historical_gas_prices.is_empty() {
-        let sum: U256 = historical_gas_prices.iter().sum();
+        let sum = historical_gas_prices.iter().fold(U256::zero(), |a, b| a + b);
         sum / U256::from(historical_gas_prices.len())
     } else {
         U256::from(0)
@@ -29,6 +31,19 @@ pub fn optimize_gas_usage(historical_gas_prices: Vec<U256>, current_gas_limit: U
     optimized_gas_limit
 }
 
+/// Optimizes the gas usage for a smart contract based on the gas prices recorded in a history.
+///
+/// # Arguments
+/// * `history` - The recorded gas prices, e.g. filled by a `GasHistoryCollector`.
+/// * `window` - How far back from the newest sample to look.
+/// * `current_gas_limit` - The current gas limit for the contract.
+///
+/// # Returns
+/// U256 - The optimized gas limit.
+pub fn optimize_gas_usage_from_history(history: &GasHistory, window: Duration, current_gas_limit: U256) -> U256 {
+    optimize_gas_usage(history.values(GasMetric::GasPrice, window), current_gas_limit)
+}
+
 // Unit test example
 #[cfg(test)]
 mod tests {
@@ -48,4 +63,22 @@ mod tests {
         let optimized_gas = optimize_gas_usage(historical_gas_prices, U256::from(10000));
         assert!(optimized_gas > U256::from(10000));
     }
+
+    #[test]
+    fn test_gas_optimization_from_history() {
+        let path = std::env::temp_dir().join(format!("wasmify-optimize-{}.jsonl", std::process::id()));
+        let mut history = GasHistory::open(&path, Duration::from_secs(3600)).unwrap();
+        let sample = |block_number: u64, price: u64| crate::contracts::gas_history::GasSample {
+            block_number,
+            timestamp: block_number * 12,
+            base_fee_per_gas: U256::from(price),
+            priority_fee_per_gas: U256::zero(),
+            gas_used_ratio: 0.5,
+        };
+        history.record(&[sample(1, 500), sample(2, 80), sample(3, 90)]).unwrap();
+        // The expensive first block falls outside the window
+        let optimized_gas = optimize_gas_usage_from_history(&history, Duration::from_secs(12), U256::from(10000));
+        assert_eq!(optimized_gas, U256::from(11000));
+        std::fs::remove_file(&path).unwrap();
+    }
 }
@@ -8,12 +8,25 @@ pub mod contracts;
 /// Framework modules for optimization and asynchronous operations.
 pub mod framework;
 
+/// An embedded EVM and the in-process `LocalChain` backend built on it.
+pub mod evm;
+
+/// Local transaction signing with private keys and encrypted keystores.
+pub mod signing;
+
+/// A scriptable mock Ethereum node for testing against without a live chain.
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
 // Exported functions and modules for external use.
-pub use contracts::deploy::deploy_contract;
-pub use contracts::abi::parse_abi;
+pub use contracts::deploy::{deploy_contract, deploy_contract_create2, deploy_contract_with_args, predict_create2_address, Deployment};
+pub use contracts::abi::{parse_abi, parse_human_readable_abi, Abi, AbiValue};
 pub use contracts::gas::{estimate_gas, check_gas_limit, optimize_gas_dynamically};
 pub use contracts::interaction::{call_contract_function, fetch_contract_data};
 pub use contracts::watch::watch_contract_events;
+pub use contracts::provider::{Provider, ProviderError};
+pub use evm::LocalChain;
+pub use signing::{HdWallet, Mnemonic, PrivateKeySigner, Signer};
 pub use contracts::contract_update::update_contract;
 pub use contracts::monitor::monitor_contract_activity;
 pub use crate::framework::async_operations::perform_optimized_operations;
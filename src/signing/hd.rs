@@ -0,0 +1,320 @@
+use super::mnemonic::Mnemonic;
+use super::{PrivateKeySigner, SignerError};
+use hmac::{Hmac, Mac};
+use ripemd::Ripemd160;
+use secp256k1::{PublicKey, Scalar, Secp256k1, SecretKey, SignOnly};
+use sha2::{Digest, Sha256, Sha512};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// Child numbers at or above this offset are hardened.
+pub const HARDENED: u32 = 0x8000_0000;
+
+/// The BIP-44 path prefix of Ethereum accounts; the account index is appended.
+pub const ETHEREUM_PATH_PREFIX: &str = "m/44'/60'/0'/0";
+
+/// The version bytes of a mainnet extended private key (`xprv`).
+const XPRV_VERSION: [u8; 4] = [0x04, 0x88, 0xad, 0xe4];
+
+fn secp() -> &'static Secp256k1<SignOnly> {
+    static SECP: OnceLock<Secp256k1<SignOnly>> = OnceLock::new();
+    SECP.get_or_init(Secp256k1::signing_only)
+}
+
+fn hmac_sha512(key: &[u8], data: &[u8]) -> [u8; 64] {
+    let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
+
+fn base58check(payload: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+    let checksum = Sha256::digest(Sha256::digest(payload));
+    let bytes = [payload, &checksum[..4]].concat();
+
+    // Repeated division of the big-endian number by 58, least significant digit first
+    let mut digits: Vec<u8> = Vec::new();
+    for &byte in &bytes {
+        let mut carry = byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let zeros = bytes.iter().take_while(|&&byte| byte == 0).count();
+    std::iter::repeat_n(b'1', zeros)
+        .chain(digits.iter().rev().map(|&digit| ALPHABET[digit as usize]))
+        .map(char::from)
+        .collect()
+}
+
+/// A BIP-32 derivation path such as `m/44'/60'/0'/0/0`.
+///
+/// Hardened components are written with a trailing `'`, `h` or `H`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DerivationPath(Vec<u32>);
+
+impl DerivationPath {
+    /// Returns the BIP-44 path of the Ethereum account with the given index, `m/44'/60'/0'/0/<index>`.
+    pub fn ethereum(index: u32) -> Self {
+        DerivationPath(vec![44 | HARDENED, 60 | HARDENED, HARDENED, 0, index])
+    }
+
+    /// Returns the child numbers, with hardened ones offset by `HARDENED`.
+    pub fn components(&self) -> &[u32] {
+        &self.0
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = SignerError;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        let invalid = || SignerError::InvalidDerivationPath(path.to_string());
+        let mut components = path.trim().split('/');
+        if components.next() != Some("m") {
+            return Err(invalid());
+        }
+        components
+            .map(|component| {
+                let (number, hardened) = match component.strip_suffix(['\'', 'h', 'H']) {
+                    Some(number) => (number, HARDENED),
+                    None => (component, 0),
+                };
+                match number.parse::<u32>() {
+                    Ok(number) if number < HARDENED => Ok(number | hardened),
+                    _ => Err(invalid()),
+                }
+            })
+            .collect::<Result<_, _>>()
+            .map(DerivationPath)
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "m")?;
+        for &component in &self.0 {
+            match component >= HARDENED {
+                true => write!(f, "/{}'", component - HARDENED)?,
+                false => write!(f, "/{}", component)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A BIP-32 extended private key: a secp256k1 key plus the chain code its children are derived with.
+#[derive(Clone)]
+pub struct ExtendedPrivateKey {
+    key: SecretKey,
+    chain_code: [u8; 32],
+    depth: u8,
+    parent_fingerprint: [u8; 4],
+    child_number: u32,
+}
+
+impl fmt::Debug for ExtendedPrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print the key or chain code
+        f.debug_struct("ExtendedPrivateKey")
+            .field("depth", &self.depth)
+            .field("child_number", &self.child_number)
+            .finish()
+    }
+}
+
+impl ExtendedPrivateKey {
+    /// Derives the master key from a seed.
+    ///
+    /// # Arguments
+    /// * `seed` - 16 to 64 bytes, usually the output of `Mnemonic::to_seed`.
+    pub fn master(seed: &[u8]) -> Result<Self, SignerError> {
+        if seed.len() < 16 || seed.len() > 64 {
+            return Err(SignerError::InvalidKey);
+        }
+        let output = hmac_sha512(b"Bitcoin seed", seed);
+        let key = SecretKey::from_slice(&output[..32]).map_err(|_| SignerError::InvalidKey)?;
+        let mut chain_code = [0u8; 32];
+        chain_code.copy_from_slice(&output[32..]);
+        Ok(ExtendedPrivateKey { key, chain_code, depth: 0, parent_fingerprint: [0; 4], child_number: 0 })
+    }
+
+    /// Derives a child key; `index` at or above `HARDENED` derives a hardened child.
+    ///
+    /// Fails with `SignerError::InvalidKey` in the astronomically unlikely case that the
+    /// derived key is out of range, in which case BIP-32 says to use the next index.
+    pub fn derive_child(&self, index: u32) -> Result<Self, SignerError> {
+        let public_key = self.public_key();
+        let mut data = Vec::with_capacity(37);
+        if index >= HARDENED {
+            data.push(0);
+            data.extend_from_slice(&self.key.secret_bytes());
+        } else {
+            data.extend_from_slice(&public_key);
+        }
+        data.extend_from_slice(&index.to_be_bytes());
+
+        let output = hmac_sha512(&self.chain_code, &data);
+        let mut tweak = [0u8; 32];
+        tweak.copy_from_slice(&output[..32]);
+        let tweak = Scalar::from_be_bytes(tweak).map_err(|_| SignerError::InvalidKey)?;
+        let key = self.key.add_tweak(&tweak).map_err(|_| SignerError::InvalidKey)?;
+        let mut chain_code = [0u8; 32];
+        chain_code.copy_from_slice(&output[32..]);
+
+        let identifier = Ripemd160::digest(Sha256::digest(public_key));
+        let mut parent_fingerprint = [0u8; 4];
+        parent_fingerprint.copy_from_slice(&identifier[..4]);
+        Ok(ExtendedPrivateKey { key, chain_code, depth: self.depth.saturating_add(1), parent_fingerprint, child_number: index })
+    }
+
+    /// Derives the key at a path relative to this key.
+    pub fn derive(&self, path: &DerivationPath) -> Result<Self, SignerError> {
+        path.components().iter().try_fold(self.clone(), |key, &index| key.derive_child(index))
+    }
+
+    /// Returns the compressed (33-byte) public key.
+    pub fn public_key(&self) -> [u8; 33] {
+        PublicKey::from_secret_key(secp(), &self.key).serialize()
+    }
+
+    /// Returns the chain code.
+    pub fn chain_code(&self) -> [u8; 32] {
+        self.chain_code
+    }
+
+    /// Serializes the key in the Base58Check `xprv` format.
+    pub fn to_xprv(&self) -> String {
+        let mut payload = Vec::with_capacity(78);
+        payload.extend_from_slice(&XPRV_VERSION);
+        payload.push(self.depth);
+        payload.extend_from_slice(&self.parent_fingerprint);
+        payload.extend_from_slice(&self.child_number.to_be_bytes());
+        payload.extend_from_slice(&self.chain_code);
+        payload.push(0);
+        payload.extend_from_slice(&self.key.secret_bytes());
+        base58check(&payload)
+    }
+
+    /// Returns a signer for this key.
+    pub fn signer(&self) -> PrivateKeySigner {
+        PrivateKeySigner::from_bytes(&self.key.secret_bytes()).expect("an extended key holds a valid private key")
+    }
+}
+
+/// A hierarchical deterministic wallet deriving Ethereum signers from one seed.
+///
+/// The signers it hands out are ordinary `PrivateKeySigner`s, so they work anywhere a
+/// `Signer` is accepted, e.g. `Provider::with_signer`.
+#[derive(Clone)]
+pub struct HdWallet {
+    master: ExtendedPrivateKey,
+}
+
+impl fmt::Debug for HdWallet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HdWallet").finish_non_exhaustive()
+    }
+}
+
+impl HdWallet {
+    /// Creates a wallet from a BIP-39 phrase.
+    ///
+    /// # Arguments
+    /// * `phrase` - The mnemonic phrase; its checksum is validated.
+    /// * `passphrase` - The optional BIP-39 passphrase; use `""` for none.
+    pub fn from_phrase(phrase: &str, passphrase: &str) -> Result<Self, SignerError> {
+        HdWallet::from_mnemonic(&Mnemonic::from_phrase(phrase)?, passphrase)
+    }
+
+    /// Creates a wallet from a parsed mnemonic and an optional passphrase.
+    pub fn from_mnemonic(mnemonic: &Mnemonic, passphrase: &str) -> Result<Self, SignerError> {
+        HdWallet::from_seed(&mnemonic.to_seed(passphrase))
+    }
+
+    /// Creates a wallet from a raw BIP-32 seed.
+    pub fn from_seed(seed: &[u8]) -> Result<Self, SignerError> {
+        Ok(HdWallet { master: ExtendedPrivateKey::master(seed)? })
+    }
+
+    /// Returns the signer at an arbitrary derivation path.
+    pub fn derive(&self, path: &DerivationPath) -> Result<PrivateKeySigner, SignerError> {
+        Ok(self.master.derive(path)?.signer())
+    }
+
+    /// Returns the signer of the Ethereum account at `m/44'/60'/0'/0/<index>`.
+    pub fn signer(&self, index: u32) -> Result<PrivateKeySigner, SignerError> {
+        self.derive(&DerivationPath::ethereum(index))
+    }
+
+    /// Returns the signers of the first `count` Ethereum accounts.
+    pub fn signers(&self, count: u32) -> Result<Vec<PrivateKeySigner>, SignerError> {
+        // Derive the shared parent once rather than per account
+        let parent = self.master.derive(&ETHEREUM_PATH_PREFIX.parse()?)?;
+        (0..count).map(|index| Ok(parent.derive_child(index)?.signer())).collect()
+    }
+}
+
+// Unit test example
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contracts::provider::decode_hex;
+    use crate::signing::Signer;
+    use web3::types::Address;
+
+    #[test]
+    fn test_bip32_vector_1() {
+        let master = ExtendedPrivateKey::master(&decode_hex("000102030405060708090a0b0c0d0e0f").unwrap()).unwrap();
+        let vectors = [
+            ("m", "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi"),
+            ("m/0H", "xprv9uHRZZhk6KAJC1avXpDAp4MDc3sQKNxDiPvvkX8Br5ngLNv1TxvUxt4cV1rGL5hj6KCesnDYUhd7oWgT11eZG7XnxHrnYeSvkzY7d2bhkJ7"),
+            ("m/0H/1", "xprv9wTYmMFdV23N2TdNG573QoEsfRrWKQgWeibmLntzniatZvR9BmLnvSxqu53Kw1UmYPxLgboyZQaXwTCg8MSY3H2EU4pWcQDnRnrVA1xe8fs"),
+            ("m/0H/1/2H", "xprv9z4pot5VBttmtdRTWfWQmoH1taj2axGVzFqSb8C9xaxKymcFzXBDptWmT7FwuEzG3ryjH4ktypQSAewRiNMjANTtpgP4mLTj34bhnZX7UiM"),
+            ("m/0H/1/2H/2", "xprvA2JDeKCSNNZky6uBCviVfJSKyQ1mDYahRjijr5idH2WwLsEd4Hsb2Tyh8RfQMuPh7f7RtyzTtdrbdqqsunu5Mm3wDvUAKRHSC34sJ7in334"),
+            (
+                "m/0H/1/2H/2/1000000000",
+                "xprvA41z7zogVVwxVSgdKUHDy1SKmdb533PjDz7J6N6mV6uS3ze1ai8FHa8kmHScGpWmj4WggLyQjgPie1rFSruoUihUZREPSL39UNdE3BBDu76",
+            ),
+        ];
+        for (path, xprv) in vectors {
+            assert_eq!(master.derive(&path.parse().unwrap()).unwrap().to_xprv(), xprv, "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_derivation_paths() {
+        let path: DerivationPath = "m/44'/60'/0'/0/7".parse().unwrap();
+        assert_eq!(path, DerivationPath::ethereum(7));
+        assert_eq!(path.to_string(), "m/44'/60'/0'/0/7");
+        assert_eq!("m/44h/60H/0'/0/7".parse::<DerivationPath>().unwrap(), path);
+        assert_eq!("m".parse::<DerivationPath>().unwrap().components(), &[] as &[u32]);
+        for invalid in ["", "44'/60'", "m/", "m/x", "m/2147483648", "m/1''"] {
+            assert!(matches!(invalid.parse::<DerivationPath>(), Err(SignerError::InvalidDerivationPath(_))), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_ethereum_accounts() {
+        let wallet = HdWallet::from_phrase("test test test test test test test test test test test junk", "").unwrap();
+        let expected: [Address; 2] = [
+            "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap(),
+            "0x70997970C51812dc3A010C7d01b50e0d17dc79C8".parse().unwrap(),
+        ];
+        assert_eq!(wallet.signer(0).unwrap().address(), expected[0]);
+        let signers = wallet.signers(2).unwrap();
+        assert_eq!(signers.iter().map(|signer| signer.address()).collect::<Vec<_>>(), expected);
+
+        // A passphrase yields an unrelated set of accounts
+        let protected = HdWallet::from_phrase("test test test test test test test test test test test junk", "secret").unwrap();
+        assert_ne!(protected.signer(0).unwrap().address(), expected[0]);
+        assert!(matches!(HdWallet::from_phrase("test test junk", ""), Err(SignerError::InvalidMnemonic(_))));
+    }
+}
@@ -0,0 +1,259 @@
+use crate::contracts::provider::decode_hex;
+use aes::cipher::{KeyIvInit, StreamCipher};
+use serde_json::{json, Value};
+use web3::signing::keccak256;
+
+type Aes128Ctr = ctr::Ctr128BE<aes::Aes128>;
+
+/// The largest scrypt cost accepted from a keystore, 2^20; geth writes 2^18 by default.
+const MAX_SCRYPT_LOG_N: u32 = 20;
+
+/// Errors that can occur while decrypting or encrypting a keystore.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeystoreError {
+    /// The keystore JSON is malformed or misses a field.
+    InvalidKeystore(String),
+    /// The keystore uses a cipher, key derivation function or version other than v3 with aes-128-ctr.
+    Unsupported(String),
+    /// The MAC does not match: the password is wrong or the keystore was tampered with.
+    WrongPassword,
+}
+
+/// The key derivation function protecting a keystore.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kdf {
+    /// scrypt with cost `2^log_n`, block size `r` and parallelism `p`.
+    Scrypt { log_n: u8, r: u32, p: u32 },
+    /// PBKDF2-HMAC-SHA256 with `c` iterations.
+    Pbkdf2 { c: u32 },
+}
+
+impl Default for Kdf {
+    /// The parameters geth uses for new accounts: scrypt with n = 2^18, r = 8, p = 1.
+    fn default() -> Self {
+        Kdf::Scrypt { log_n: 18, r: 8, p: 1 }
+    }
+}
+
+fn field<'a>(value: &'a Value, path: &[&str]) -> Result<&'a Value, KeystoreError> {
+    path.iter()
+        .try_fold(value, |value, key| value.get(*key))
+        .ok_or_else(|| KeystoreError::InvalidKeystore(format!("missing field {}", path.join("."))))
+}
+
+fn hex_field(value: &Value, path: &[&str]) -> Result<Vec<u8>, KeystoreError> {
+    field(value, path)?
+        .as_str()
+        .and_then(decode_hex)
+        .ok_or_else(|| KeystoreError::InvalidKeystore(format!("{} is not hex", path.join("."))))
+}
+
+fn number_field(value: &Value, path: &[&str]) -> Result<u64, KeystoreError> {
+    field(value, path)?
+        .as_u64()
+        .ok_or_else(|| KeystoreError::InvalidKeystore(format!("{} is not a number", path.join("."))))
+}
+
+fn u32_field(value: &Value, path: &[&str]) -> Result<u32, KeystoreError> {
+    let number = number_field(value, path)?;
+    u32::try_from(number).map_err(|_| KeystoreError::InvalidKeystore(format!("{} {} is out of range", path.join("."), number)))
+}
+
+fn derive_key(kdf: Kdf, password: &str, salt: &[u8], dklen: usize) -> Result<Vec<u8>, KeystoreError> {
+    let mut key = vec![0u8; dklen];
+    match kdf {
+        Kdf::Scrypt { log_n, r, p } => {
+            let params = scrypt::Params::new(log_n, r, p, dklen)
+                .map_err(|e| KeystoreError::Unsupported(format!("scrypt parameters: {}", e)))?;
+            scrypt::scrypt(password.as_bytes(), salt, &params, &mut key)
+                .map_err(|e| KeystoreError::Unsupported(format!("scrypt key length: {}", e)))?;
+        }
+        Kdf::Pbkdf2 { c } => pbkdf2::pbkdf2_hmac::<sha2::Sha256>(password.as_bytes(), salt, c, &mut key),
+    }
+    Ok(key)
+}
+
+fn mac(derived_key: &[u8], ciphertext: &[u8]) -> [u8; 32] {
+    keccak256(&[&derived_key[16..32], ciphertext].concat())
+}
+
+/// Decrypts a Web3 Secret Storage (version 3) keystore.
+///
+/// # Arguments
+/// * `keystore` - The keystore JSON, as written by geth, Clef, MetaMask or `encrypt_keystore`.
+/// * `password` - The password the keystore was encrypted with.
+///
+/// # Returns
+/// Result<Vec<u8>, KeystoreError> - The decrypted private key.
+pub fn decrypt_keystore(keystore: &str, password: &str) -> Result<Vec<u8>, KeystoreError> {
+    let keystore: Value = serde_json::from_str(keystore).map_err(|e| KeystoreError::InvalidKeystore(e.to_string()))?;
+    if number_field(&keystore, &["version"])? != 3 {
+        return Err(KeystoreError::Unsupported("only version 3 keystores are supported".to_string()));
+    }
+    // Some writers capitalise the `crypto` key
+    let crypto = field(&keystore, &["crypto"]).or_else(|_| field(&keystore, &["Crypto"]))?;
+    let cipher = field(crypto, &["cipher"])?.as_str().unwrap_or_default();
+    if cipher != "aes-128-ctr" {
+        return Err(KeystoreError::Unsupported(format!("cipher {}", cipher)));
+    }
+
+    // Only the first 32 bytes are ever used, and the length must not size an allocation
+    let dklen = number_field(crypto, &["kdfparams", "dklen"])?;
+    if dklen != 32 {
+        return Err(KeystoreError::Unsupported(format!("derived key length {}", dklen)));
+    }
+    let salt = hex_field(crypto, &["kdfparams", "salt"])?;
+    let kdf = match field(crypto, &["kdf"])?.as_str().unwrap_or_default() {
+        "scrypt" => {
+            let n = number_field(crypto, &["kdfparams", "n"])?;
+            if !n.is_power_of_two() || n < 2 {
+                return Err(KeystoreError::InvalidKeystore(format!("scrypt n {} is not a power of two", n)));
+            }
+            if n.trailing_zeros() > MAX_SCRYPT_LOG_N {
+                return Err(KeystoreError::Unsupported(format!("scrypt n {} is above 2^{}", n, MAX_SCRYPT_LOG_N)));
+            }
+            let r = u32_field(crypto, &["kdfparams", "r"])?;
+            let p = u32_field(crypto, &["kdfparams", "p"])?;
+            Kdf::Scrypt { log_n: n.trailing_zeros() as u8, r, p }
+        }
+        "pbkdf2" => {
+            let prf = field(crypto, &["kdfparams", "prf"])?.as_str().unwrap_or_default();
+            if prf != "hmac-sha256" {
+                return Err(KeystoreError::Unsupported(format!("pbkdf2 prf {}", prf)));
+            }
+            Kdf::Pbkdf2 { c: u32_field(crypto, &["kdfparams", "c"])? }
+        }
+        other => return Err(KeystoreError::Unsupported(format!("kdf {}", other))),
+    };
+
+    let derived_key = derive_key(kdf, password, &salt, 32)?;
+    let ciphertext = hex_field(crypto, &["ciphertext"])?;
+    if mac(&derived_key, &ciphertext).as_slice() != hex_field(crypto, &["mac"])?.as_slice() {
+        return Err(KeystoreError::WrongPassword);
+    }
+
+    let iv = hex_field(crypto, &["cipherparams", "iv"])?;
+    let mut cipher = Aes128Ctr::new_from_slices(&derived_key[..16], &iv)
+        .map_err(|_| KeystoreError::InvalidKeystore(format!("iv of {} bytes", iv.len())))?;
+    let mut key = ciphertext;
+    cipher.apply_keystream(&mut key);
+    Ok(key)
+}
+
+/// Encrypts a private key into a Web3 Secret Storage (version 3) keystore.
+///
+/// # Arguments
+/// * `private_key` - The key to encrypt.
+/// * `password` - The password protecting the keystore.
+/// * `kdf` - The key derivation function; `Kdf::default()` matches geth.
+///
+/// # Returns
+/// Result<String, KeystoreError> - The keystore JSON, without an `address` field.
+pub fn encrypt_keystore(private_key: &[u8], password: &str, kdf: Kdf) -> Result<String, KeystoreError> {
+    let salt: [u8; 32] = rand::random();
+    let iv: [u8; 16] = rand::random();
+    let derived_key = derive_key(kdf, password, &salt, 32)?;
+
+    let mut ciphertext = private_key.to_vec();
+    let mut cipher = Aes128Ctr::new_from_slices(&derived_key[..16], &iv).expect("the key and iv have fixed lengths");
+    cipher.apply_keystream(&mut ciphertext);
+
+    let hex = |bytes: &[u8]| bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+    let (kdf_name, kdfparams) = match kdf {
+        Kdf::Scrypt { log_n, r, p } => ("scrypt", json!({ "dklen": 32, "n": 1u64 << log_n, "r": r, "p": p, "salt": hex(&salt) })),
+        Kdf::Pbkdf2 { c } => ("pbkdf2", json!({ "dklen": 32, "c": c, "prf": "hmac-sha256", "salt": hex(&salt) })),
+    };
+
+    // A random (version 4) UUID
+    let mut id: [u8; 16] = rand::random();
+    id[6] = (id[6] & 0x0f) | 0x40;
+    id[8] = (id[8] & 0x3f) | 0x80;
+    let id = hex(&id);
+    let id = format!("{}-{}-{}-{}-{}", &id[..8], &id[8..12], &id[12..16], &id[16..20], &id[20..]);
+
+    let keystore = json!({
+        "crypto": {
+            "cipher": "aes-128-ctr",
+            "cipherparams": { "iv": hex(&iv) },
+            "ciphertext": hex(&ciphertext),
+            "kdf": kdf_name,
+            "kdfparams": kdfparams,
+            "mac": hex(&mac(&derived_key, &ciphertext)),
+        },
+        "id": id,
+        "version": 3,
+    });
+    Ok(keystore.to_string())
+}
+
+// Unit test example
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The PBKDF2 test vector of the Web3 Secret Storage definition.
+    fn pbkdf2_vector() -> Value {
+        json!({
+            "crypto": {
+                "cipher": "aes-128-ctr",
+                "cipherparams": { "iv": "6087dab2f9fdbbfaddc31a909735c1e6" },
+                "ciphertext": "5318b4d5bcd28de64ee5559e671353e16f075ecae9f99c7a79a38af5f869aa46",
+                "kdf": "pbkdf2",
+                "kdfparams": { "c": 262144, "dklen": 32, "prf": "hmac-sha256", "salt": "ae3cd4e7013836a3df6bd7241b12db061dbe2c6785853cce422d148a624ce0bd" },
+                "mac": "517ead924a9d0dc3124507e3393d175ce3ff7c1e96529c6c555ce9e51205e9b2"
+            },
+            "id": "3198bc9c-6672-5ab3-d995-4942343ae5b6",
+            "version": 3
+        })
+    }
+
+    #[test]
+    fn test_decrypt_spec_vector() {
+        let keystore = pbkdf2_vector().to_string();
+        let key = decrypt_keystore(&keystore, "testpassword").unwrap();
+        assert_eq!(key, decode_hex("7a28b5ba57c53603b0b07b56bba752f7784bf506fa95edc395f5cf6c7514fe9d").unwrap());
+        assert_eq!(decrypt_keystore(&keystore, "wrong"), Err(KeystoreError::WrongPassword));
+
+        let mut keystore = pbkdf2_vector();
+        keystore["crypto"]["cipher"] = json!("aes-256-cbc");
+        assert!(matches!(decrypt_keystore(&keystore.to_string(), "testpassword"), Err(KeystoreError::Unsupported(_))));
+        assert!(matches!(decrypt_keystore("{}", "testpassword"), Err(KeystoreError::InvalidKeystore(_))));
+    }
+
+    #[test]
+    fn test_untrusted_kdf_parameters() {
+        let decrypt = |path: [&str; 2], value: Value, kdf: Value| {
+            let mut keystore = pbkdf2_vector();
+            keystore["crypto"]["kdf"] = kdf;
+            keystore["crypto"]["kdfparams"]["n"] = json!(1024);
+            keystore["crypto"]["kdfparams"]["r"] = json!(8);
+            keystore["crypto"]["kdfparams"]["p"] = json!(1);
+            keystore["crypto"][path[0]][path[1]] = value;
+            decrypt_keystore(&keystore.to_string(), "testpassword")
+        };
+        let (scrypt, pbkdf2) = (json!("scrypt"), json!("pbkdf2"));
+        let too_large = json!(u64::from(u32::MAX) + 1);
+
+        assert!(matches!(decrypt(["kdfparams", "dklen"], json!(1u64 << 40), pbkdf2.clone()), Err(KeystoreError::Unsupported(_))));
+        assert!(matches!(decrypt(["kdfparams", "dklen"], json!(64), pbkdf2.clone()), Err(KeystoreError::Unsupported(_))));
+        assert!(matches!(decrypt(["kdfparams", "c"], too_large.clone(), pbkdf2), Err(KeystoreError::InvalidKeystore(_))));
+        assert!(matches!(decrypt(["kdfparams", "r"], too_large.clone(), scrypt.clone()), Err(KeystoreError::InvalidKeystore(_))));
+        assert!(matches!(decrypt(["kdfparams", "p"], too_large, scrypt.clone()), Err(KeystoreError::InvalidKeystore(_))));
+        assert!(matches!(decrypt(["kdfparams", "n"], json!(1000), scrypt.clone()), Err(KeystoreError::InvalidKeystore(_))));
+        assert!(matches!(decrypt(["kdfparams", "n"], json!(1u64 << 40), scrypt.clone()), Err(KeystoreError::Unsupported(_))));
+        // Valid parameters get as far as the MAC check
+        assert_eq!(decrypt(["kdfparams", "n"], json!(1024), scrypt), Err(KeystoreError::WrongPassword));
+    }
+
+    #[test]
+    fn test_encrypt_roundtrip() {
+        let key = [0x42u8; 32];
+        for kdf in [Kdf::Scrypt { log_n: 10, r: 8, p: 1 }, Kdf::Pbkdf2 { c: 1000 }] {
+            let keystore = encrypt_keystore(&key, "secret", kdf).unwrap();
+            assert_eq!(decrypt_keystore(&keystore, "secret").unwrap(), key.to_vec());
+            assert_eq!(decrypt_keystore(&keystore, "Secret"), Err(KeystoreError::WrongPassword));
+        }
+        let keystore: Value = serde_json::from_str(&encrypt_keystore(&key, "secret", Kdf::Pbkdf2 { c: 1 }).unwrap()).unwrap();
+        assert_eq!(keystore["id"].as_str().unwrap().as_bytes()[14], b'4');
+    }
+}
@@ -0,0 +1,191 @@
+use super::SignerError;
+use sha2::{Digest, Sha256, Sha512};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use unicode_normalization::UnicodeNormalization;
+
+/// The BIP-39 English wordlist.
+const ENGLISH: &str = include_str!("english.txt");
+
+fn wordlist() -> &'static [&'static str] {
+    static WORDS: OnceLock<Vec<&'static str>> = OnceLock::new();
+    WORDS.get_or_init(|| ENGLISH.lines().collect())
+}
+
+/// A BIP-39 mnemonic phrase of 12, 15, 18, 21 or 24 English words.
+///
+/// The phrase encodes 128 to 256 bits of entropy plus a checksum. `to_seed` stretches it,
+/// together with an optional passphrase, into the 64-byte seed an `HdWallet` derives keys from.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Mnemonic {
+    /// Indices of the words in the wordlist.
+    indices: Vec<u16>,
+}
+
+impl fmt::Debug for Mnemonic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The phrase is as secret as a private key
+        f.debug_struct("Mnemonic").field("words", &self.indices.len()).finish()
+    }
+}
+
+impl Mnemonic {
+    /// Encodes entropy as a mnemonic.
+    ///
+    /// # Arguments
+    /// * `entropy` - 16, 20, 24, 28 or 32 bytes of entropy.
+    pub fn from_entropy(entropy: &[u8]) -> Result<Self, SignerError> {
+        if entropy.len() < 16 || entropy.len() > 32 || !entropy.len().is_multiple_of(4) {
+            return Err(SignerError::InvalidMnemonic(format!("entropy of {} bytes", entropy.len())));
+        }
+        // The checksum is the first `bits / 32` bits of the entropy's SHA-256 hash
+        let checksum_bits = entropy.len() / 4;
+        let mut bits: Vec<bool> = entropy.iter().flat_map(|byte| (0..8).rev().map(move |i| byte >> i & 1 == 1)).collect();
+        let checksum = Sha256::digest(entropy);
+        bits.extend((0..checksum_bits).map(|i| checksum[i / 8] >> (7 - i % 8) & 1 == 1));
+
+        let indices = bits.chunks(11).map(|chunk| chunk.iter().fold(0u16, |index, &bit| index << 1 | bit as u16)).collect();
+        Ok(Mnemonic { indices })
+    }
+
+    /// Parses and validates a phrase.
+    ///
+    /// Words may be separated by any whitespace and are matched case-insensitively. Fails if a
+    /// word is not in the English wordlist, the word count is not a multiple of three between
+    /// 12 and 24, or the checksum does not match.
+    pub fn from_phrase(phrase: &str) -> Result<Self, SignerError> {
+        let words = wordlist();
+        let indices = phrase
+            .split_whitespace()
+            .map(|word| {
+                let word = word.to_lowercase();
+                words
+                    .binary_search(&word.as_str())
+                    .map(|index| index as u16)
+                    .map_err(|_| SignerError::InvalidMnemonic(format!("unknown word {}", word)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if indices.len() < 12 || indices.len() > 24 || !indices.len().is_multiple_of(3) {
+            return Err(SignerError::InvalidMnemonic(format!("{} words", indices.len())));
+        }
+
+        let mnemonic = Mnemonic { indices };
+        let expected = Mnemonic::from_entropy(&mnemonic.entropy())?;
+        if expected != mnemonic {
+            return Err(SignerError::InvalidMnemonic("checksum mismatch".to_string()));
+        }
+        Ok(mnemonic)
+    }
+
+    /// Creates a mnemonic from fresh random entropy.
+    ///
+    /// # Arguments
+    /// * `word_count` - 12, 15, 18, 21 or 24.
+    pub fn generate(word_count: usize) -> Result<Self, SignerError> {
+        if !word_count.is_multiple_of(3) {
+            return Err(SignerError::InvalidMnemonic(format!("{} words", word_count)));
+        }
+        let entropy: Vec<u8> = (0..word_count / 3 * 4).map(|_| rand::random()).collect();
+        Mnemonic::from_entropy(&entropy)
+    }
+
+    /// Returns the words separated by single spaces.
+    pub fn phrase(&self) -> String {
+        let words = wordlist();
+        self.indices.iter().map(|&index| words[index as usize]).collect::<Vec<_>>().join(" ")
+    }
+
+    /// Returns the entropy the phrase encodes, without the checksum.
+    pub fn entropy(&self) -> Vec<u8> {
+        let bits: Vec<bool> = self.indices.iter().flat_map(|&index| (0..11).rev().map(move |i| index >> i & 1 == 1)).collect();
+        // 11 bits per word, of which one in 33 is checksum
+        let entropy_bits = bits.len() * 32 / 33;
+        bits[..entropy_bits].chunks(8).map(|chunk| chunk.iter().fold(0u8, |byte, &bit| byte << 1 | bit as u8)).collect()
+    }
+
+    /// Derives the 64-byte BIP-39 seed.
+    ///
+    /// # Arguments
+    /// * `passphrase` - The optional extra passphrase ("25th word"); use `""` for none.
+    ///
+    /// # Returns
+    /// [u8; 64] - PBKDF2-HMAC-SHA512 of the phrase, salted with `"mnemonic" + passphrase`.
+    pub fn to_seed(&self, passphrase: &str) -> [u8; 64] {
+        let salt: String = format!("mnemonic{}", passphrase).nfkd().collect();
+        let mut seed = [0u8; 64];
+        pbkdf2::pbkdf2_hmac::<Sha512>(self.phrase().as_bytes(), salt.as_bytes(), 2048, &mut seed);
+        seed
+    }
+}
+
+impl FromStr for Mnemonic {
+    type Err = SignerError;
+
+    fn from_str(phrase: &str) -> Result<Self, Self::Err> {
+        Mnemonic::from_phrase(phrase)
+    }
+}
+
+// Unit test example
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contracts::provider::decode_hex;
+
+    /// Vectors from the reference implementation (trezor/python-mnemonic), all with the passphrase "TREZOR".
+    const VECTORS: [(&str, &str, &str); 4] = [
+        (
+            "00000000000000000000000000000000",
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+            "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04",
+        ),
+        (
+            "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+            "legal winner thank year wave sausage worth useful legal winner thank yellow",
+            "2e8905819b8723fe2c1d161860e5ee1830318dbf49a83bd451cfb8440c28bd6fa457fe1296106559a3c80937a1c1069be3a3a5bd381ee6260e8d9739fce1f607",
+        ),
+        (
+            "80808080808080808080808080808080",
+            "letter advice cage absurd amount doctor acoustic avoid letter advice cage above",
+            "d71de856f81a8acc65e6fc851a38d4d7ec216fd0796d0a6827a3ad6ed5511a30fa280f12eb2e47ed2ac03b5c462a0358d18d69fe4f985ec81778c1b370b652a8",
+        ),
+        (
+            "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo vote",
+            "dd48c104698c30cfe2b6142103248622fb7bb0ff692eebb00089b32d22484e1613912f0a5b694407be899ffd31ed3992c456cdf60f5d4564b8ba3f05a69890ad",
+        ),
+    ];
+
+    #[test]
+    fn test_bip39_vectors() {
+        assert_eq!(wordlist().len(), 2048);
+        for (entropy, phrase, seed) in VECTORS {
+            let entropy = decode_hex(entropy).unwrap();
+            let mnemonic = Mnemonic::from_entropy(&entropy).unwrap();
+            assert_eq!(mnemonic.phrase(), phrase);
+            assert_eq!(Mnemonic::from_phrase(phrase).unwrap(), mnemonic);
+            assert_eq!(mnemonic.entropy(), entropy);
+            assert_eq!(mnemonic.to_seed("TREZOR").to_vec(), decode_hex(seed).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_invalid_phrases() {
+        let checksum = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon";
+        assert_eq!(Mnemonic::from_phrase(checksum), Err(SignerError::InvalidMnemonic("checksum mismatch".to_string())));
+        assert!(matches!(Mnemonic::from_phrase("abandon abandon about"), Err(SignerError::InvalidMnemonic(_))));
+        assert!(matches!(Mnemonic::from_phrase(&checksum.replace("abandon", "bitcoins")), Err(SignerError::InvalidMnemonic(_))));
+        assert!(Mnemonic::from_phrase(" Abandon abandon abandon abandon abandon abandon\nabandon abandon abandon abandon abandon ABOUT ").is_ok());
+        assert!(matches!(Mnemonic::from_entropy(&[0u8; 15]), Err(SignerError::InvalidMnemonic(_))));
+    }
+
+    #[test]
+    fn test_generate() {
+        let mnemonic = Mnemonic::generate(24).unwrap();
+        assert_eq!(mnemonic.phrase().split(' ').count(), 24);
+        assert_eq!(mnemonic.phrase().parse::<Mnemonic>().unwrap(), mnemonic);
+        assert!(!format!("{:?}", mnemonic).contains(&mnemonic.phrase()[..8]));
+        assert!(Mnemonic::generate(13).is_err());
+    }
+}
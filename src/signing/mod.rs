@@ -0,0 +1,230 @@
+//! Local transaction signing: the `Signer` trait, private key, keystore and HD wallet signers,
+//! and the encoding of legacy (EIP-155), EIP-2930 and EIP-1559 transactions.
+
+pub mod hd;
+pub mod keystore;
+pub mod mnemonic;
+pub mod transaction;
+
+pub use hd::{DerivationPath, ExtendedPrivateKey, HdWallet};
+pub use keystore::{decrypt_keystore, encrypt_keystore, Kdf, KeystoreError};
+pub use mnemonic::Mnemonic;
+pub use transaction::{decode_signed_transaction, DecodedTransaction, TransactionKind, UnsignedTransaction};
+
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use web3::signing::{hash_message, keccak256, Key, SecretKey, SecretKeyRef};
+use web3::types::{Address, Bytes, SignedTransaction, H256, U256};
+
+/// Errors that can occur while creating a signer or signing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignerError {
+    /// The bytes are not a valid secp256k1 private key.
+    InvalidKey,
+    Keystore(KeystoreError),
+    /// A keystore file could not be read.
+    Io(String),
+    /// A raw transaction could not be decoded or its sender recovered.
+    InvalidTransaction(String),
+    /// The signer failed to produce a signature.
+    Signing(String),
+    /// A BIP-39 phrase has an unknown word, a bad word count or a wrong checksum.
+    InvalidMnemonic(String),
+    /// A BIP-32 derivation path could not be parsed.
+    InvalidDerivationPath(String),
+}
+
+impl From<KeystoreError> for SignerError {
+    fn from(error: KeystoreError) -> Self {
+        SignerError::Keystore(error)
+    }
+}
+
+/// A secp256k1 signature with its recovery id (0 or 1).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Signature {
+    pub r: H256,
+    pub s: H256,
+    pub recovery_id: u8,
+}
+
+impl Signature {
+    /// Returns the 65-byte `r ++ s ++ v` encoding with `v` = 27 + recovery id, as used by `eth_sign` and `ecrecover`.
+    pub fn to_bytes(&self) -> [u8; 65] {
+        let mut bytes = [0u8; 65];
+        bytes[..32].copy_from_slice(self.r.as_bytes());
+        bytes[32..64].copy_from_slice(self.s.as_bytes());
+        bytes[64] = 27 + self.recovery_id;
+        bytes
+    }
+}
+
+/// Something that holds a key and signs with it.
+///
+/// Implementors only sign hashes; transaction and message signing are built on top. A signer
+/// attached to a `Provider` with `Provider::with_signer` signs every transaction sent from its
+/// address, so the key never has to be imported into a node.
+pub trait Signer: fmt::Debug + Send + Sync {
+    /// Returns the address of the signing key.
+    fn address(&self) -> Address;
+
+    /// Signs a 32-byte hash.
+    fn sign_hash(&self, hash: H256) -> Result<Signature, SignerError>;
+
+    /// Signs a transaction and returns its raw encoding and hash.
+    fn sign_transaction(&self, transaction: &UnsignedTransaction) -> Result<SignedTransaction, SignerError> {
+        let message_hash = transaction.signing_hash();
+        let signature = self.sign_hash(message_hash)?;
+        let raw = transaction.encode(Some(&signature));
+        let v = match transaction.kind {
+            TransactionKind::Legacy { .. } => signature.recovery_id as u64 + 35 + 2 * transaction.chain_id,
+            _ => signature.recovery_id as u64,
+        };
+        Ok(SignedTransaction {
+            message_hash,
+            v,
+            r: signature.r,
+            s: signature.s,
+            transaction_hash: H256(keccak256(&raw)),
+            raw_transaction: Bytes(raw),
+        })
+    }
+
+    /// Signs a message with the EIP-191 `personal_sign` prefix.
+    fn sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        self.sign_hash(hash_message(message))
+    }
+}
+
+/// A signer holding a raw private key in memory.
+#[derive(Clone)]
+pub struct PrivateKeySigner {
+    key: SecretKey,
+    address: Address,
+}
+
+impl fmt::Debug for PrivateKeySigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print the key itself
+        f.debug_struct("PrivateKeySigner").field("address", &self.address).finish()
+    }
+}
+
+impl PrivateKeySigner {
+    /// Creates a signer from a 32-byte private key.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SignerError> {
+        let key = SecretKey::from_slice(bytes).map_err(|_| SignerError::InvalidKey)?;
+        let address = SecretKeyRef::new(&key).address();
+        Ok(PrivateKeySigner { key, address })
+    }
+
+    /// Creates a signer with a new random key.
+    pub fn random() -> Self {
+        loop {
+            let bytes: [u8; 32] = rand::random();
+            // Fewer than one in 2^127 random values is not a valid key
+            if let Ok(signer) = PrivateKeySigner::from_bytes(&bytes) {
+                return signer;
+            }
+        }
+    }
+
+    /// Decrypts a Web3 Secret Storage (version 3) keystore.
+    ///
+    /// # Arguments
+    /// * `keystore` - The keystore JSON.
+    /// * `password` - The password the keystore was encrypted with.
+    pub fn from_keystore(keystore: &str, password: &str) -> Result<Self, SignerError> {
+        PrivateKeySigner::from_bytes(&decrypt_keystore(keystore, password)?)
+    }
+
+    /// Reads and decrypts a keystore file, e.g. from geth's `keystore` directory.
+    pub fn from_keystore_file(path: &Path, password: &str) -> Result<Self, SignerError> {
+        let keystore = std::fs::read_to_string(path).map_err(|e| SignerError::Io(format!("{}: {}", path.display(), e)))?;
+        PrivateKeySigner::from_keystore(&keystore, password)
+    }
+
+    /// Encrypts the key into a keystore that `from_keystore` can read back.
+    pub fn to_keystore(&self, password: &str, kdf: Kdf) -> Result<String, SignerError> {
+        Ok(encrypt_keystore(&self.key.secret_bytes(), password, kdf)?)
+    }
+}
+
+impl FromStr for PrivateKeySigner {
+    type Err = SignerError;
+
+    /// Parses a hex private key, with or without a `0x` prefix.
+    fn from_str(key: &str) -> Result<Self, Self::Err> {
+        let bytes = crate::contracts::provider::decode_hex(key.trim()).ok_or(SignerError::InvalidKey)?;
+        PrivateKeySigner::from_bytes(&bytes)
+    }
+}
+
+impl Signer for PrivateKeySigner {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn sign_hash(&self, hash: H256) -> Result<Signature, SignerError> {
+        let signature = SecretKeyRef::new(&self.key)
+            .sign_message(hash.as_bytes())
+            .map_err(|e| SignerError::Signing(e.to_string()))?;
+        Ok(Signature { r: signature.r, s: signature.s, recovery_id: signature.v as u8 })
+    }
+}
+
+/// Returns the price per gas a transaction pays in a block with the given base fee.
+pub(crate) fn effective_gas_price(kind: &TransactionKind, base_fee: U256) -> U256 {
+    match *kind {
+        TransactionKind::Legacy { gas_price } | TransactionKind::AccessList { gas_price } => gas_price,
+        TransactionKind::Eip1559 { max_fee_per_gas, max_priority_fee_per_gas } => {
+            max_fee_per_gas.min(base_fee.saturating_add(max_priority_fee_per_gas))
+        }
+    }
+}
+
+// Unit test example
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use web3::signing::recover;
+
+    const KEY: &str = "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+
+    #[test]
+    fn test_private_key_signer() {
+        let signer: PrivateKeySigner = KEY.parse().unwrap();
+        assert_eq!(signer.address(), "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23".parse().unwrap());
+        assert!(!format!("{:?}", signer).contains("4c0883a6"));
+
+        let hash = H256::repeat_byte(0x11);
+        let signature = signer.sign_hash(hash).unwrap();
+        let recovered = recover(hash.as_bytes(), &signature.to_bytes()[..64], signature.recovery_id as i32).unwrap();
+        assert_eq!(recovered, signer.address());
+
+        assert_eq!("0x1234".parse::<PrivateKeySigner>().unwrap_err(), SignerError::InvalidKey);
+        assert_eq!(PrivateKeySigner::from_bytes(&[0u8; 32]).unwrap_err(), SignerError::InvalidKey);
+    }
+
+    #[test]
+    fn test_keystore_signer() {
+        let signer = PrivateKeySigner::random();
+        let keystore = signer.to_keystore("hunter2", Kdf::Scrypt { log_n: 10, r: 8, p: 1 }).unwrap();
+        assert_eq!(PrivateKeySigner::from_keystore(&keystore, "hunter2").unwrap().address(), signer.address());
+        assert_eq!(
+            PrivateKeySigner::from_keystore(&keystore, "hunter3").unwrap_err(),
+            SignerError::Keystore(KeystoreError::WrongPassword)
+        );
+        let missing = PrivateKeySigner::from_keystore_file(Path::new("/nonexistent/keystore.json"), "hunter2");
+        assert!(matches!(missing, Err(SignerError::Io(_))));
+    }
+
+    #[test]
+    fn test_effective_gas_price() {
+        let kind = TransactionKind::Eip1559 { max_fee_per_gas: U256::from(100), max_priority_fee_per_gas: U256::from(2) };
+        assert_eq!(effective_gas_price(&kind, U256::from(10)), U256::from(12));
+        assert_eq!(effective_gas_price(&kind, U256::from(99)), U256::from(100));
+        assert_eq!(effective_gas_price(&TransactionKind::Legacy { gas_price: U256::from(5) }, U256::from(99)), U256::from(5));
+    }
+}
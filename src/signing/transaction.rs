@@ -0,0 +1,334 @@
+use super::{Signature, SignerError};
+use rlp::{Rlp, RlpStream};
+use web3::signing::{keccak256, recover};
+use web3::types::{AccessList, AccessListItem, Address, H256, U256};
+
+/// The fee fields of a transaction, which also select its envelope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionKind {
+    /// A pre-EIP-2718 transaction, signed with EIP-155 replay protection.
+    Legacy { gas_price: U256 },
+    /// An EIP-2930 (type 1) transaction carrying an access list.
+    AccessList { gas_price: U256 },
+    /// An EIP-1559 (type 2) transaction.
+    Eip1559 { max_fee_per_gas: U256, max_priority_fee_per_gas: U256 },
+}
+
+impl TransactionKind {
+    /// Returns the EIP-2718 type byte: 0, 1 or 2.
+    pub fn type_id(&self) -> u8 {
+        match self {
+            TransactionKind::Legacy { .. } => 0,
+            TransactionKind::AccessList { .. } => 1,
+            TransactionKind::Eip1559 { .. } => 2,
+        }
+    }
+
+    /// Returns the price per gas the sender pays at most: the gas price, or the max fee per gas.
+    pub fn max_gas_price(&self) -> U256 {
+        match *self {
+            TransactionKind::Legacy { gas_price } | TransactionKind::AccessList { gas_price } => gas_price,
+            TransactionKind::Eip1559 { max_fee_per_gas, .. } => max_fee_per_gas,
+        }
+    }
+}
+
+/// A transaction with every field filled in, ready to be signed.
+///
+/// The access list is ignored by legacy transactions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnsignedTransaction {
+    pub kind: TransactionKind,
+    pub chain_id: u64,
+    pub nonce: U256,
+    pub to: Option<Address>,
+    pub value: U256,
+    pub data: Vec<u8>,
+    pub gas: U256,
+    pub access_list: AccessList,
+}
+
+/// A signed transaction decoded from its raw encoding.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedTransaction {
+    pub transaction: UnsignedTransaction,
+    /// The sender recovered from the signature.
+    pub from: Address,
+    /// The transaction hash, the Keccak-256 hash of the raw encoding.
+    pub hash: H256,
+}
+
+impl UnsignedTransaction {
+    /// Returns the hash the sender signs.
+    pub fn signing_hash(&self) -> H256 {
+        H256(keccak256(&self.encode(None)))
+    }
+
+    /// Encodes the transaction, signed if a signature is given, in its EIP-2718 envelope.
+    ///
+    /// # Arguments
+    /// * `signature` - The signature over `signing_hash`, or `None` for the signing payload.
+    pub fn encode(&self, signature: Option<&Signature>) -> Vec<u8> {
+        let mut stream = RlpStream::new();
+        match self.kind {
+            TransactionKind::Legacy { gas_price } => {
+                stream.begin_list(9);
+                self.append_common(&mut stream, gas_price);
+                match signature {
+                    // EIP-155: v = recovery id + 35 + 2 * chain id
+                    Some(signature) => {
+                        stream.append(&(signature.recovery_id as u64 + 35 + 2 * self.chain_id));
+                        append_signature_values(&mut stream, signature);
+                    }
+                    None => {
+                        stream.append(&self.chain_id);
+                        stream.append(&0u8);
+                        stream.append(&0u8);
+                    }
+                }
+                return stream.out().to_vec();
+            }
+            TransactionKind::AccessList { gas_price } => {
+                stream.begin_list(if signature.is_some() { 11 } else { 8 });
+                stream.append(&self.chain_id);
+                self.append_common(&mut stream, gas_price);
+            }
+            TransactionKind::Eip1559 { max_fee_per_gas, max_priority_fee_per_gas } => {
+                stream.begin_list(if signature.is_some() { 12 } else { 9 });
+                stream.append(&self.chain_id);
+                stream.append(&self.nonce);
+                stream.append(&max_priority_fee_per_gas);
+                stream.append(&max_fee_per_gas);
+                stream.append(&self.gas);
+                append_to(&mut stream, self.to);
+                stream.append(&self.value);
+                stream.append(&self.data);
+            }
+        }
+        append_access_list(&mut stream, &self.access_list);
+        if let Some(signature) = signature {
+            stream.append(&signature.recovery_id);
+            append_signature_values(&mut stream, signature);
+        }
+        [&[self.kind.type_id()], stream.as_raw()].concat()
+    }
+
+    /// Appends nonce, gas price, gas, to, value and data, the fields legacy and EIP-2930 transactions share.
+    fn append_common(&self, stream: &mut RlpStream, gas_price: U256) {
+        stream.append(&self.nonce);
+        stream.append(&gas_price);
+        stream.append(&self.gas);
+        append_to(stream, self.to);
+        stream.append(&self.value);
+        stream.append(&self.data);
+    }
+}
+
+fn append_to(stream: &mut RlpStream, to: Option<Address>) {
+    match to {
+        Some(to) => stream.append(&to),
+        None => stream.append_empty_data(),
+    };
+}
+
+fn append_access_list(stream: &mut RlpStream, access_list: &AccessList) {
+    stream.begin_list(access_list.len());
+    for item in access_list {
+        stream.begin_list(2);
+        stream.append(&item.address);
+        stream.append_list(&item.storage_keys);
+    }
+}
+
+fn append_signature_values(stream: &mut RlpStream, signature: &Signature) {
+    stream.append(&U256::from_big_endian(signature.r.as_bytes()));
+    stream.append(&U256::from_big_endian(signature.s.as_bytes()));
+}
+
+fn invalid(error: rlp::DecoderError) -> SignerError {
+    SignerError::InvalidTransaction(error.to_string())
+}
+
+fn decode_to(rlp: &Rlp) -> Result<Option<Address>, rlp::DecoderError> {
+    if rlp.is_empty() {
+        Ok(None)
+    } else {
+        rlp.as_val().map(Some)
+    }
+}
+
+fn decode_access_list(rlp: &Rlp) -> Result<AccessList, rlp::DecoderError> {
+    rlp.iter()
+        .map(|item| Ok(AccessListItem { address: item.val_at(0)?, storage_keys: item.list_at(1)? }))
+        .collect()
+}
+
+fn decode_signature(rlp: &Rlp, index: usize, recovery_id: u64) -> Result<Signature, SignerError> {
+    let r: U256 = rlp.val_at(index).map_err(invalid)?;
+    let s: U256 = rlp.val_at(index + 1).map_err(invalid)?;
+    if recovery_id > 1 {
+        return Err(SignerError::InvalidTransaction(format!("invalid signature recovery id {}", recovery_id)));
+    }
+    Ok(Signature { r: H256(r.into()), s: H256(s.into()), recovery_id: recovery_id as u8 })
+}
+
+/// Decodes a signed legacy, EIP-2930 or EIP-1559 transaction and recovers its sender.
+///
+/// Legacy transactions without EIP-155 replay protection are decoded with a chain id of 0.
+///
+/// # Arguments
+/// * `raw` - The raw transaction as sent with `eth_sendRawTransaction`.
+///
+/// # Returns
+/// Result<DecodedTransaction, SignerError> - The transaction with its sender and hash.
+pub fn decode_signed_transaction(raw: &[u8]) -> Result<DecodedTransaction, SignerError> {
+    let (type_id, payload) = match raw.first() {
+        Some(&byte) if byte <= 0x7f => (byte, &raw[1..]),
+        Some(_) => (0, raw),
+        None => return Err(SignerError::InvalidTransaction("empty transaction".to_string())),
+    };
+    let rlp = Rlp::new(payload);
+    let count = rlp.item_count().map_err(invalid)?;
+    let expected = match type_id {
+        0 => 9,
+        1 => 11,
+        2 => 12,
+        _ => return Err(SignerError::InvalidTransaction(format!("unsupported transaction type {}", type_id))),
+    };
+    if count != expected {
+        return Err(SignerError::InvalidTransaction(format!("expected {} fields, found {}", expected, count)));
+    }
+
+    let (transaction, signature) = match type_id {
+        0 => {
+            let v: u64 = rlp.val_at(6).map_err(invalid)?;
+            let (chain_id, recovery_id) = match v {
+                27 | 28 => (0, v - 27),
+                v if v >= 35 => ((v - 35) / 2, (v - 35) % 2),
+                v => return Err(SignerError::InvalidTransaction(format!("invalid signature v {}", v))),
+            };
+            let transaction = UnsignedTransaction {
+                kind: TransactionKind::Legacy { gas_price: rlp.val_at(1).map_err(invalid)? },
+                chain_id,
+                nonce: rlp.val_at(0).map_err(invalid)?,
+                gas: rlp.val_at(2).map_err(invalid)?,
+                to: decode_to(&rlp.at(3).map_err(invalid)?).map_err(invalid)?,
+                value: rlp.val_at(4).map_err(invalid)?,
+                data: rlp.val_at(5).map_err(invalid)?,
+                access_list: Vec::new(),
+            };
+            (transaction, decode_signature(&rlp, 7, recovery_id)?)
+        }
+        1 => {
+            let transaction = UnsignedTransaction {
+                kind: TransactionKind::AccessList { gas_price: rlp.val_at(2).map_err(invalid)? },
+                chain_id: rlp.val_at(0).map_err(invalid)?,
+                nonce: rlp.val_at(1).map_err(invalid)?,
+                gas: rlp.val_at(3).map_err(invalid)?,
+                to: decode_to(&rlp.at(4).map_err(invalid)?).map_err(invalid)?,
+                value: rlp.val_at(5).map_err(invalid)?,
+                data: rlp.val_at(6).map_err(invalid)?,
+                access_list: decode_access_list(&rlp.at(7).map_err(invalid)?).map_err(invalid)?,
+            };
+            (transaction, decode_signature(&rlp, 9, rlp.val_at(8).map_err(invalid)?)?)
+        }
+        _ => {
+            let transaction = UnsignedTransaction {
+                kind: TransactionKind::Eip1559 {
+                    max_priority_fee_per_gas: rlp.val_at(2).map_err(invalid)?,
+                    max_fee_per_gas: rlp.val_at(3).map_err(invalid)?,
+                },
+                chain_id: rlp.val_at(0).map_err(invalid)?,
+                nonce: rlp.val_at(1).map_err(invalid)?,
+                gas: rlp.val_at(4).map_err(invalid)?,
+                to: decode_to(&rlp.at(5).map_err(invalid)?).map_err(invalid)?,
+                value: rlp.val_at(6).map_err(invalid)?,
+                data: rlp.val_at(7).map_err(invalid)?,
+                access_list: decode_access_list(&rlp.at(8).map_err(invalid)?).map_err(invalid)?,
+            };
+            (transaction, decode_signature(&rlp, 10, rlp.val_at(9).map_err(invalid)?)?)
+        }
+    };
+
+    // Pre-EIP-155 legacy transactions sign the six transaction fields alone
+    let signing_hash = if type_id == 0 && transaction.chain_id == 0 {
+        let mut stream = RlpStream::new_list(6);
+        for index in 0..6 {
+            stream.append_raw(rlp.at(index).map_err(invalid)?.as_raw(), 1);
+        }
+        H256(keccak256(&stream.out()))
+    } else {
+        transaction.signing_hash()
+    };
+    let compact = [signature.r.as_bytes(), signature.s.as_bytes()].concat();
+    let from = recover(signing_hash.as_bytes(), &compact, signature.recovery_id as i32)
+        .map_err(|e| SignerError::InvalidTransaction(format!("cannot recover the sender: {:?}", e)))?;
+    Ok(DecodedTransaction { transaction, from, hash: H256(keccak256(raw)) })
+}
+
+// Unit test example
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::{PrivateKeySigner, Signer};
+
+    fn transaction(kind: TransactionKind) -> UnsignedTransaction {
+        UnsignedTransaction {
+            kind,
+            chain_id: 1,
+            nonce: U256::from(9),
+            to: Some("0x3535353535353535353535353535353535353535".parse().unwrap()),
+            value: U256::exp10(18),
+            data: Vec::new(),
+            gas: U256::from(21_000),
+            access_list: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_eip155_example() {
+        // The example transaction of EIP-155
+        let signer: PrivateKeySigner = "0x4646464646464646464646464646464646464646464646464646464646464646".parse().unwrap();
+        let transaction = transaction(TransactionKind::Legacy { gas_price: U256::from(20_000_000_000u64) });
+        assert_eq!(
+            transaction.signing_hash(),
+            "0xdaf5a779ae972f972197303d7b574746c7ef83eadac0f2791ad23db92e4c8e53".parse().unwrap()
+        );
+
+        let signed = signer.sign_transaction(&transaction).unwrap();
+        let expected = "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83";
+        let hex: String = signed.raw_transaction.0.iter().map(|byte| format!("{:02x}", byte)).collect();
+        assert_eq!(hex, expected);
+        assert_eq!(signed.v, 37);
+
+        let decoded = decode_signed_transaction(&signed.raw_transaction.0).unwrap();
+        assert_eq!(decoded.transaction, transaction);
+        assert_eq!(decoded.from, signer.address());
+        assert_eq!(decoded.hash, signed.transaction_hash);
+    }
+
+    #[test]
+    fn test_typed_transactions_roundtrip() {
+        let signer = PrivateKeySigner::random();
+        let access_list = vec![AccessListItem { address: Address::repeat_byte(1), storage_keys: vec![H256::repeat_byte(2)] }];
+        let kinds = [
+            TransactionKind::AccessList { gas_price: U256::from(7) },
+            TransactionKind::Eip1559 { max_fee_per_gas: U256::from(100), max_priority_fee_per_gas: U256::from(2) },
+        ];
+        for kind in kinds {
+            let mut transaction = transaction(kind);
+            transaction.to = None;
+            transaction.data = vec![0x60, 0x80];
+            transaction.access_list = access_list.clone();
+
+            let signed = signer.sign_transaction(&transaction).unwrap();
+            assert_eq!(signed.raw_transaction.0[0], kind.type_id());
+            let decoded = decode_signed_transaction(&signed.raw_transaction.0).unwrap();
+            assert_eq!(decoded.transaction, transaction);
+            assert_eq!(decoded.from, signer.address());
+        }
+
+        assert!(matches!(decode_signed_transaction(&[0x03, 0xc0]), Err(SignerError::InvalidTransaction(_))));
+        assert!(matches!(decode_signed_transaction(&[0x02, 0xc0]), Err(SignerError::InvalidTransaction(_))));
+    }
+}
@@ -0,0 +1,805 @@
+//! A scriptable mock Ethereum node for offline tests.
+//!
+//! `MockNode` serves JSON-RPC over HTTP and WebSocket on local ports, records every request it
+//! receives, answers with canned or scripted responses per method, and can simulate errors,
+//! latency, new blocks and chain reorganisations. Methods that are not scripted fall back to a
+//! small simulated chain that answers `eth_chainId`, `eth_blockNumber`, `eth_getBlockByNumber`,
+//! `eth_getBlockByHash`, `eth_getLogs` and, over WebSocket, `eth_subscribe`/`eth_unsubscribe`.
+
+use crate::contracts::provider::Provider;
+use crate::evm::local_chain::log_matches;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use soketto::handshake::server::Response as Handshake;
+use soketto::handshake::Server as WsServer;
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio_util::compat::TokioAsyncReadCompatExt;
+use web3::signing::keccak256;
+use web3::types::{Address, Log, H256, U64};
+
+/// Timestamp of the simulated genesis block; every following block is 12 seconds later.
+const GENESIS_TIMESTAMP: u64 = 1_600_000_000;
+
+/// Methods whose successful result is a transaction hash announced to `newPendingTransactions` subscribers.
+const SEND_METHODS: [&str; 2] = ["eth_sendTransaction", "eth_sendRawTransaction"];
+
+/// A JSON-RPC result, or the JSON-RPC error object to answer with.
+type Reply = Result<Value, Value>;
+
+/// A scripted handler computing the reply from the request parameters.
+type Handler = Arc<dyn Fn(&Value) -> Reply + Send + Sync>;
+
+type SharedState = Arc<Mutex<NodeState>>;
+
+/// A request received by the mock node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub params: Value,
+    /// Whether the request arrived over the WebSocket endpoint.
+    pub websocket: bool,
+}
+
+enum Outgoing {
+    Text(String),
+    Close,
+}
+
+enum SubscriptionKind {
+    NewHeads,
+    Logs(Value),
+    PendingTransactions,
+}
+
+struct Subscription {
+    connection: u64,
+    kind: SubscriptionKind,
+}
+
+struct MockBlock {
+    number: u64,
+    hash: H256,
+    parent_hash: H256,
+    logs: Vec<Log>,
+}
+
+struct NodeState {
+    requests: Vec<RecordedRequest>,
+    once: HashMap<String, VecDeque<Reply>>,
+    canned: HashMap<String, Reply>,
+    handlers: HashMap<String, Handler>,
+    latency: Duration,
+    chain_id: u64,
+    blocks: Vec<MockBlock>,
+    forks: u64,
+    connections: HashMap<u64, UnboundedSender<Outgoing>>,
+    subscriptions: HashMap<String, Subscription>,
+    next_id: u64,
+}
+
+/// A mock Ethereum node listening on local HTTP and WebSocket ports.
+///
+/// Cloning a `MockNode` yields another handle to the same node, so it can be scripted from
+/// inside `respond_with` handlers or spawned tasks.
+#[derive(Clone)]
+pub struct MockNode {
+    state: SharedState,
+    http_addr: SocketAddr,
+    ws_addr: SocketAddr,
+}
+
+impl MockNode {
+    /// Starts a node with a genesis block, listening on random local ports.
+    ///
+    /// Both servers run on the current Tokio runtime and stop with it.
+    pub async fn start() -> MockNode {
+        let state = Arc::new(Mutex::new(NodeState::new()));
+        let http_addr = serve_http(state.clone());
+        let ws_addr = serve_ws(state.clone()).await;
+        MockNode { state, http_addr, ws_addr }
+    }
+
+    /// Returns the URL of the HTTP endpoint.
+    pub fn http_url(&self) -> String {
+        format!("http://{}", self.http_addr)
+    }
+
+    /// Returns the URL of the WebSocket endpoint.
+    pub fn ws_url(&self) -> String {
+        format!("ws://{}", self.ws_addr)
+    }
+
+    /// Returns an HTTP provider connected to the node that polls every 10 milliseconds.
+    pub fn provider(&self) -> Provider {
+        Provider::http(&self.http_url())
+            .expect("the mock node URL is valid")
+            .with_poll_interval(Duration::from_millis(10))
+    }
+
+    /// Sets the chain id reported by `eth_chainId` and `net_version`.
+    pub fn set_chain_id(&self, chain_id: u64) {
+        self.state.lock().unwrap().chain_id = chain_id;
+    }
+
+    /// Answers every call to `method` with `result` until scripted otherwise.
+    pub fn respond(&self, method: &str, result: Value) {
+        self.state.lock().unwrap().canned.insert(method.to_string(), Ok(result));
+    }
+
+    /// Answers the next call to `method` with `result`; queued replies are used in order before
+    /// any other response configured for the method.
+    pub fn respond_once(&self, method: &str, result: Value) {
+        self.queue(method, Ok(result));
+    }
+
+    /// Answers every call to `method` by running `handler` on the request parameters.
+    ///
+    /// # Arguments
+    /// * `method` - The JSON-RPC method to script.
+    /// * `handler` - Returns either the result or a JSON-RPC error object such as `{"code": 3, "message": "..."}`.
+    pub fn respond_with<F>(&self, method: &str, handler: F)
+    where
+        F: Fn(&Value) -> Result<Value, Value> + Send + Sync + 'static,
+    {
+        self.state.lock().unwrap().handlers.insert(method.to_string(), Arc::new(handler));
+    }
+
+    /// Fails every call to `method` with a JSON-RPC error until scripted otherwise.
+    ///
+    /// # Arguments
+    /// * `method` - The JSON-RPC method to fail.
+    /// * `code` - The JSON-RPC error code, e.g. `3` for reverted calls.
+    /// * `message` - The error message.
+    /// * `data` - Optional error data, e.g. hex-encoded revert data.
+    pub fn fail(&self, method: &str, code: i64, message: &str, data: Option<Value>) {
+        self.state.lock().unwrap().canned.insert(method.to_string(), Err(rpc_error(code, message, data)));
+    }
+
+    /// Fails only the next call to `method` with a JSON-RPC error.
+    pub fn fail_once(&self, method: &str, code: i64, message: &str, data: Option<Value>) {
+        self.queue(method, Err(rpc_error(code, message, data)));
+    }
+
+    /// Removes every canned, queued and scripted response for `method`.
+    pub fn reset(&self, method: &str) {
+        let mut node = self.state.lock().unwrap();
+        node.canned.remove(method);
+        node.once.remove(method);
+        node.handlers.remove(method);
+    }
+
+    /// Delays every response by `latency`.
+    pub fn set_latency(&self, latency: Duration) {
+        self.state.lock().unwrap().latency = latency;
+    }
+
+    /// Returns every request received so far, in order.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Returns the parameters of every request made to `method`, in order.
+    pub fn requests_for(&self, method: &str) -> Vec<Value> {
+        let node = self.state.lock().unwrap();
+        node.requests
+            .iter()
+            .filter(|request| request.method == method)
+            .map(|request| request.params.clone())
+            .collect()
+    }
+
+    /// Forgets the requests received so far.
+    pub fn clear_requests(&self) {
+        self.state.lock().unwrap().requests.clear();
+    }
+
+    /// Returns the number of the current head block.
+    pub fn block_number(&self) -> U64 {
+        U64::from(self.state.lock().unwrap().head())
+    }
+
+    /// Returns the hash of the canonical block with the given number, if it exists.
+    pub fn block_hash(&self, number: u64) -> Option<H256> {
+        self.state.lock().unwrap().blocks.get(number as usize).map(|block| block.hash)
+    }
+
+    /// Mines a block containing `logs` and notifies `newHeads` and `logs` subscribers.
+    ///
+    /// The block hash, block number and log index of every log are filled in.
+    ///
+    /// # Returns
+    /// U64 - The number of the new block.
+    pub fn mine_block(&self, logs: Vec<Log>) -> U64 {
+        U64::from(self.state.lock().unwrap().mine(logs))
+    }
+
+    /// Mines `count` empty blocks.
+    pub fn mine_blocks(&self, count: usize) -> U64 {
+        let mut node = self.state.lock().unwrap();
+        for _ in 0..count {
+            node.mine(Vec::new());
+        }
+        U64::from(node.head())
+    }
+
+    /// Replaces the last `depth` blocks with empty blocks of the same height but different hashes.
+    ///
+    /// `logs` subscribers are sent every log of the dropped blocks again with `removed: true`,
+    /// followed by `newHeads` notifications for the replacement blocks. The genesis block is never
+    /// replaced.
+    ///
+    /// # Returns
+    /// Vec<Log> - The logs that were removed from the canonical chain.
+    pub fn reorg(&self, depth: usize) -> Vec<Log> {
+        let mut node = self.state.lock().unwrap();
+        let depth = depth.min(node.blocks.len() - 1);
+        let keep = node.blocks.len() - depth;
+        node.forks += 1;
+
+        let removed: Vec<Log> = node
+            .blocks
+            .split_off(keep)
+            .into_iter()
+            .flat_map(|block| block.logs)
+            .map(|mut log| {
+                log.removed = Some(true);
+                log
+            })
+            .collect();
+        node.notify_logs(&removed);
+
+        for _ in 0..depth {
+            node.mine(Vec::new());
+        }
+        removed
+    }
+
+    /// Builds a receipt for a transaction included in the current head block.
+    ///
+    /// # Arguments
+    /// * `transaction_hash` - The hash of the transaction.
+    /// * `contract_address` - The address of the created contract, for deployments.
+    /// * `success` - Whether the transaction succeeded (`status` 1) or reverted (`status` 0).
+    pub fn receipt(&self, transaction_hash: H256, contract_address: Option<Address>, success: bool) -> Value {
+        let node = self.state.lock().unwrap();
+        let head = node.blocks.last().expect("the chain always has a genesis block");
+        json!({
+            "transactionHash": transaction_hash,
+            "transactionIndex": "0x0",
+            "blockHash": head.hash,
+            "blockNumber": hex(head.number),
+            "from": Address::zero(),
+            "to": null,
+            "cumulativeGasUsed": "0x5208",
+            "gasUsed": "0x5208",
+            "effectiveGasPrice": "0x3b9aca00",
+            "contractAddress": contract_address,
+            "logs": [],
+            "status": if success { "0x1" } else { "0x0" },
+            "logsBloom": format!("0x{}", "00".repeat(256)),
+        })
+    }
+
+    /// Returns the number of active WebSocket subscriptions.
+    pub fn subscription_count(&self) -> usize {
+        self.state.lock().unwrap().subscriptions.len()
+    }
+
+    /// Closes every WebSocket connection and drops its subscriptions, as a restarting node would.
+    pub fn drop_connections(&self) {
+        let mut node = self.state.lock().unwrap();
+        for (_, connection) in node.connections.drain() {
+            let _ = connection.send(Outgoing::Close);
+        }
+        node.subscriptions.clear();
+    }
+
+    fn queue(&self, method: &str, reply: Reply) {
+        self.state.lock().unwrap().once.entry(method.to_string()).or_default().push_back(reply);
+    }
+}
+
+impl NodeState {
+    fn new() -> Self {
+        let mut node = NodeState {
+            requests: Vec::new(),
+            once: HashMap::new(),
+            canned: HashMap::new(),
+            handlers: HashMap::new(),
+            latency: Duration::ZERO,
+            chain_id: 31337,
+            blocks: Vec::new(),
+            forks: 0,
+            connections: HashMap::new(),
+            subscriptions: HashMap::new(),
+            next_id: 0,
+        };
+        node.mine(Vec::new());
+        node
+    }
+
+    fn head(&self) -> u64 {
+        self.blocks.len() as u64 - 1
+    }
+
+    fn mine(&mut self, logs: Vec<Log>) -> u64 {
+        let number = self.blocks.len() as u64;
+        let parent_hash = self.blocks.last().map(|block| block.hash).unwrap_or_default();
+        let hash = H256(keccak256(&[number.to_be_bytes(), self.forks.to_be_bytes()].concat()));
+        let logs: Vec<Log> = logs
+            .into_iter()
+            .enumerate()
+            .map(|(index, mut log)| {
+                log.block_hash = Some(hash);
+                log.block_number = Some(U64::from(number));
+                log.log_index = Some(index.into());
+                log.removed = Some(false);
+                log
+            })
+            .collect();
+
+        let block = MockBlock { number, hash, parent_hash, logs };
+        let header = block_json(&block);
+        self.notify(|kind| matches!(kind, SubscriptionKind::NewHeads).then(|| vec![header.clone()]));
+        self.notify_logs(&block.logs);
+        self.blocks.push(block);
+        number
+    }
+
+    fn notify_logs(&self, logs: &[Log]) {
+        self.notify(|kind| match kind {
+            SubscriptionKind::Logs(filter) => Some(
+                logs.iter()
+                    .filter(|log| log_matches(filter, log))
+                    .map(|log| json!(log))
+                    .collect(),
+            ),
+            _ => None,
+        });
+    }
+
+    /// Sends `eth_subscription` notifications with the results `select` picks for each subscription.
+    fn notify<F: Fn(&SubscriptionKind) -> Option<Vec<Value>>>(&self, select: F) {
+        for (id, subscription) in &self.subscriptions {
+            let connection = match self.connections.get(&subscription.connection) {
+                Some(connection) => connection,
+                None => continue,
+            };
+            for result in select(&subscription.kind).unwrap_or_default() {
+                let notification = json!({
+                    "jsonrpc": "2.0",
+                    "method": "eth_subscription",
+                    "params": { "subscription": id, "result": result },
+                });
+                let _ = connection.send(Outgoing::Text(notification.to_string()));
+            }
+        }
+    }
+
+    fn disconnect(&mut self, connection: u64) {
+        self.connections.remove(&connection);
+        self.subscriptions.retain(|_, subscription| subscription.connection != connection);
+    }
+
+    /// Answers the methods of the simulated chain.
+    fn builtin(&mut self, connection: Option<u64>, method: &str, params: &Value) -> Reply {
+        match method {
+            "eth_chainId" => Ok(json!(hex(self.chain_id))),
+            "net_version" => Ok(json!(self.chain_id.to_string())),
+            "eth_blockNumber" => Ok(json!(hex(self.head()))),
+            "eth_getBlockByNumber" => {
+                let number = self.block_tag(&params[0])?;
+                Ok(self.blocks.get(number as usize).map(block_json).unwrap_or(Value::Null))
+            }
+            "eth_getBlockByHash" => {
+                let hash: H256 = parse(&params[0]).ok_or_else(|| invalid_params("invalid block hash"))?;
+                Ok(self.blocks.iter().find(|block| block.hash == hash).map(block_json).unwrap_or(Value::Null))
+            }
+            "eth_getLogs" => self.logs(&params[0]),
+            "eth_subscribe" => self.subscribe(connection, params),
+            "eth_unsubscribe" => {
+                let id = params[0].as_str().unwrap_or_default();
+                Ok(json!(self.subscriptions.remove(id).is_some()))
+            }
+            _ => Err(rpc_error(-32601, &format!("the method {} does not exist/is not available", method), None)),
+        }
+    }
+
+    /// Resolves a block number or tag; a missing tag means the head.
+    fn block_tag(&self, tag: &Value) -> Result<u64, Value> {
+        match tag.as_str() {
+            None | Some("latest") | Some("pending") | Some("safe") | Some("finalized") => Ok(self.head()),
+            Some("earliest") => Ok(0),
+            Some(number) => u64::from_str_radix(number.trim_start_matches("0x"), 16)
+                .map_err(|_| invalid_params(&format!("invalid block number {}", number))),
+        }
+    }
+
+    fn logs(&self, filter: &Value) -> Reply {
+        let blocks: Vec<&MockBlock> = match filter.get("blockHash") {
+            Some(hash) => {
+                let hash: H256 = parse(hash).ok_or_else(|| invalid_params("invalid block hash"))?;
+                self.blocks.iter().filter(|block| block.hash == hash).collect()
+            }
+            None => {
+                let from = self.block_tag(&filter["fromBlock"])?;
+                let to = self.block_tag(&filter["toBlock"])?;
+                self.blocks.iter().filter(|block| block.number >= from && block.number <= to).collect()
+            }
+        };
+
+        Ok(blocks
+            .iter()
+            .flat_map(|block| block.logs.iter())
+            .filter(|log| log_matches(filter, log))
+            .map(|log| json!(log))
+            .collect())
+    }
+
+    fn subscribe(&mut self, connection: Option<u64>, params: &Value) -> Reply {
+        let connection = connection.ok_or_else(|| rpc_error(-32601, "notifications not supported", None))?;
+        let kind = match params[0].as_str() {
+            Some("newHeads") => SubscriptionKind::NewHeads,
+            Some("logs") => SubscriptionKind::Logs(params[1].clone()),
+            Some("newPendingTransactions") => SubscriptionKind::PendingTransactions,
+            _ => return Err(invalid_params(&format!("unsupported subscription {}", params[0]))),
+        };
+
+        self.next_id += 1;
+        let id = hex(self.next_id);
+        self.subscriptions.insert(id.clone(), Subscription { connection, kind });
+        Ok(json!(id))
+    }
+}
+
+/// Answers a JSON-RPC message, which may be a single call or a batch.
+async fn handle_message(state: &SharedState, connection: Option<u64>, body: &[u8]) -> String {
+    let message: Value = match serde_json::from_slice(body) {
+        Ok(message) => message,
+        Err(e) => {
+            return json!({ "jsonrpc": "2.0", "id": null, "error": rpc_error(-32700, &e.to_string(), None) }).to_string();
+        }
+    };
+
+    let latency = state.lock().unwrap().latency;
+    if !latency.is_zero() {
+        tokio::time::sleep(latency).await;
+    }
+
+    let response = match message {
+        Value::Array(calls) => Value::Array(calls.iter().map(|call| handle_call(state, connection, call)).collect()),
+        call => handle_call(state, connection, &call),
+    };
+    response.to_string()
+}
+
+fn handle_call(state: &SharedState, connection: Option<u64>, call: &Value) -> Value {
+    let method = call["method"].as_str().unwrap_or_default();
+    let params = if call["params"].is_null() { json!([]) } else { call["params"].clone() };
+    let reply = reply(state, connection, method, &params);
+
+    if let (true, Ok(hash)) = (SEND_METHODS.contains(&method), &reply) {
+        let node = state.lock().unwrap();
+        node.notify(|kind| matches!(kind, SubscriptionKind::PendingTransactions).then(|| vec![hash.clone()]));
+    }
+
+    match reply {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": call["id"], "result": result }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": call["id"], "error": error }),
+    }
+}
+
+/// Records a call and picks its reply: queued replies first, then a scripted handler, then the
+/// canned response, and finally the simulated chain.
+fn reply(state: &SharedState, connection: Option<u64>, method: &str, params: &Value) -> Reply {
+    let handler = {
+        let mut node = state.lock().unwrap();
+        node.requests.push(RecordedRequest {
+            method: method.to_string(),
+            params: params.clone(),
+            websocket: connection.is_some(),
+        });
+        if let Some(reply) = node.once.get_mut(method).and_then(VecDeque::pop_front) {
+            return reply;
+        }
+        node.handlers.get(method).cloned()
+    };
+
+    // The handler runs without the lock held so that it may script the node itself
+    if let Some(handler) = handler {
+        return handler(params);
+    }
+
+    let mut node = state.lock().unwrap();
+    match node.canned.get(method) {
+        Some(reply) => reply.clone(),
+        None => node.builtin(connection, method, params),
+    }
+}
+
+fn serve_http(state: SharedState) -> SocketAddr {
+    let make_service = make_service_fn(move |_| {
+        let state = state.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                let state = state.clone();
+                async move {
+                    let body = hyper::body::to_bytes(request.into_body()).await.unwrap_or_default();
+                    let response = handle_message(&state, None, &body).await;
+                    Ok::<_, Infallible>(Response::new(Body::from(response)))
+                }
+            }))
+        }
+    });
+
+    let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service);
+    let addr = server.local_addr();
+    tokio::spawn(server);
+    addr
+}
+
+async fn serve_ws(state: SharedState) -> SocketAddr {
+    let listener = TcpListener::bind(("127.0.0.1", 0))
+        .await
+        .expect("failed to bind the mock WebSocket endpoint");
+    let addr = listener.local_addr().expect("the listener has a local address");
+    tokio::spawn(async move {
+        while let Ok((socket, _)) = listener.accept().await {
+            tokio::spawn(serve_ws_connection(state.clone(), socket));
+        }
+    });
+    addr
+}
+
+async fn serve_ws_connection(state: SharedState, socket: TcpStream) {
+    let mut server = WsServer::new(socket.compat());
+    let key = match server.receive_request().await {
+        Ok(request) => request.key(),
+        Err(_) => return,
+    };
+    if server.send_response(&Handshake::Accept { key, protocol: None }).await.is_err() {
+        return;
+    }
+    let (mut sender, mut receiver) = server.into_builder().finish();
+
+    let (outgoing, mut queue) = unbounded_channel();
+    let connection = {
+        let mut node = state.lock().unwrap();
+        node.next_id += 1;
+        let connection = node.next_id;
+        node.connections.insert(connection, outgoing.clone());
+        connection
+    };
+
+    let reader_state = state.clone();
+    let reader = tokio::spawn(async move {
+        let mut message = Vec::new();
+        while receiver.receive_data(&mut message).await.is_ok() {
+            let response = handle_message(&reader_state, Some(connection), &message).await;
+            message.clear();
+            if outgoing.send(Outgoing::Text(response)).is_err() {
+                break;
+            }
+        }
+        reader_state.lock().unwrap().disconnect(connection);
+    });
+
+    while let Some(message) = queue.recv().await {
+        match message {
+            Outgoing::Text(text) => {
+                if sender.send_text(text).await.is_err() || sender.flush().await.is_err() {
+                    break;
+                }
+            }
+            Outgoing::Close => {
+                let _ = sender.close().await;
+                break;
+            }
+        }
+    }
+    reader.abort();
+    state.lock().unwrap().disconnect(connection);
+}
+
+fn block_json(block: &MockBlock) -> Value {
+    json!({
+        "number": hex(block.number),
+        "hash": block.hash,
+        "parentHash": block.parent_hash,
+        "sha3Uncles": H256::zero(),
+        "miner": Address::zero(),
+        "stateRoot": H256::zero(),
+        "transactionsRoot": H256::zero(),
+        "receiptsRoot": H256::zero(),
+        "logsBloom": format!("0x{}", "00".repeat(256)),
+        "difficulty": "0x0",
+        "totalDifficulty": "0x0",
+        "gasLimit": "0x1c9c380",
+        "gasUsed": "0x0",
+        "baseFeePerGas": "0x3b9aca00",
+        "extraData": "0x",
+        "timestamp": hex(GENESIS_TIMESTAMP + block.number * 12),
+        "mixHash": H256::zero(),
+        "nonce": "0x0000000000000000",
+        "size": "0x220",
+        "transactions": [],
+        "uncles": [],
+    })
+}
+
+fn rpc_error(code: i64, message: &str, data: Option<Value>) -> Value {
+    match data {
+        Some(data) => json!({ "code": code, "message": message, "data": data }),
+        None => json!({ "code": code, "message": message }),
+    }
+}
+
+fn invalid_params(message: &str) -> Value {
+    rpc_error(-32602, message, None)
+}
+
+fn parse<T: DeserializeOwned>(value: &Value) -> Option<T> {
+    serde_json::from_value(value.clone()).ok()
+}
+
+fn hex(number: u64) -> String {
+    format!("{:#x}", number)
+}
+
+// Unit test example
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contracts::provider::ProviderError;
+    use std::time::Instant;
+    use web3::futures::StreamExt;
+    use web3::transports::WebSocket;
+    use web3::types::{BlockId, BlockNumber, FilterBuilder};
+    use web3::DuplexTransport;
+
+    const CONTRACT: &str = "0x1234567890abcdef1234567890abcdef12345678";
+
+    fn transfer_log(topic: H256) -> Log {
+        Log {
+            address: CONTRACT.parse().unwrap(),
+            topics: vec![topic],
+            data: Default::default(),
+            block_hash: None,
+            block_number: None,
+            transaction_hash: Some(H256::repeat_byte(0x11)),
+            transaction_index: Some(0.into()),
+            log_index: None,
+            transaction_log_index: None,
+            log_type: None,
+            removed: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scripted_responses_and_recording() {
+        let node = MockNode::start().await;
+        let provider = node.provider();
+
+        node.respond("eth_gasPrice", json!("0x64"));
+        node.fail_once("eth_gasPrice", -32000, "header not found", None);
+        node.respond_once("eth_gasPrice", json!("0x1"));
+
+        assert!(matches!(provider.gas_price().await, Err(ProviderError::Rpc { code: -32000, .. })));
+        assert_eq!(provider.gas_price().await.unwrap(), 1.into());
+        assert_eq!(provider.gas_price().await.unwrap(), 100.into());
+
+        node.respond_with("eth_getCode", |params| match params[0].as_str() {
+            Some(CONTRACT) => Ok(json!("0x6080")),
+            _ => Err(json!({ "code": -32602, "message": "unknown account" })),
+        });
+        assert_eq!(provider.code(CONTRACT.parse().unwrap()).await.unwrap().0, vec![0x60, 0x80]);
+        assert!(provider.code(Address::zero()).await.is_err());
+
+        assert_eq!(provider.chain_id().await.unwrap(), 31337.into());
+        assert!(matches!(provider.estimate_gas(Default::default()).await, Err(ProviderError::Rpc { code: -32601, .. })));
+
+        assert_eq!(node.requests_for("eth_gasPrice").len(), 3);
+        assert_eq!(node.requests_for("eth_getCode")[0][0], json!(CONTRACT));
+        assert!(!node.requests()[0].websocket);
+        node.clear_requests();
+        assert!(node.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_simulated_chain_and_reorg() {
+        let node = MockNode::start().await;
+        let provider = node.provider();
+        let wanted = H256::repeat_byte(0xaa);
+
+        node.mine_blocks(2);
+        node.mine_block(vec![transfer_log(wanted), transfer_log(H256::repeat_byte(0xbb))]);
+        assert_eq!(provider.block_number().await.unwrap(), U64::from(3));
+
+        let filter = FilterBuilder::default()
+            .address(vec![CONTRACT.parse().unwrap()])
+            .topics(Some(vec![wanted]), None, None, None)
+            .from_block(BlockNumber::Earliest)
+            .build();
+        let logs = provider.logs(filter.clone()).await.unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].block_number, Some(U64::from(3)));
+        assert_eq!(logs[0].block_hash, node.block_hash(3));
+
+        let old_hash = node.block_hash(3).unwrap();
+        let removed = node.reorg(2);
+        assert_eq!(removed.len(), 2);
+        assert!(removed.iter().all(|log| log.removed == Some(true)));
+        assert_eq!(provider.block_number().await.unwrap(), U64::from(3));
+        assert_ne!(node.block_hash(3), Some(old_hash));
+        assert!(provider.logs(filter).await.unwrap().is_empty());
+
+        let block: Option<web3::types::Block<H256>> = provider
+            .request("eth_getBlockByHash", vec![json!(node.block_hash(2)), json!(false)])
+            .await
+            .unwrap();
+        assert_eq!(block.unwrap().parent_hash, node.block_hash(1).unwrap());
+        let missing: Option<web3::types::Block<H256>> = provider
+            .request("eth_getBlockByNumber", vec![json!(BlockId::Number(BlockNumber::Number(9.into()))), json!(false)])
+            .await
+            .unwrap();
+        assert!(missing.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_websocket_subscriptions() {
+        let node = MockNode::start().await;
+        let topic = H256::repeat_byte(0xaa);
+        let transport = WebSocket::new(&node.ws_url()).await.unwrap();
+        let ws = Provider::new(transport.clone());
+
+        let heads: String = ws.request("eth_subscribe", vec![json!("newHeads")]).await.unwrap();
+        let logs: String = ws
+            .request("eth_subscribe", vec![json!("logs"), json!({ "topics": [topic] })])
+            .await
+            .unwrap();
+        assert_eq!(node.subscription_count(), 2);
+        let mut heads = transport.subscribe(heads.into()).unwrap();
+        let mut logs = transport.subscribe(logs.into()).unwrap();
+
+        node.mine_block(vec![transfer_log(topic)]);
+        assert_eq!(heads.next().await.unwrap()["number"], json!("0x1"));
+        assert_eq!(logs.next().await.unwrap()["removed"], json!(false));
+
+        node.reorg(1);
+        assert_eq!(logs.next().await.unwrap()["removed"], json!(true));
+        let replacement = heads.next().await.unwrap();
+        assert_eq!(replacement["number"], json!("0x1"));
+        assert_eq!(replacement["hash"], json!(node.block_hash(1)));
+        assert!(node.requests().iter().all(|request| request.websocket));
+
+        node.drop_connections();
+        assert_eq!(node.subscription_count(), 0);
+        assert!(ws.block_number().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_latency_and_pending_transactions() {
+        let node = MockNode::start().await;
+        let transport = WebSocket::new(&node.ws_url()).await.unwrap();
+        let ws = Provider::new(transport.clone());
+        let pending: String = ws.request("eth_subscribe", vec![json!("newPendingTransactions")]).await.unwrap();
+        let mut pending = transport.subscribe(pending.into()).unwrap();
+
+        let hash = H256::repeat_byte(0x22);
+        node.respond("eth_sendRawTransaction", json!(hash));
+        node.set_latency(Duration::from_millis(100));
+
+        let started = Instant::now();
+        assert_eq!(node.provider().send_raw_transaction(vec![0x01].into()).await.unwrap(), hash);
+        assert!(started.elapsed() >= Duration::from_millis(100));
+        assert_eq!(pending.next().await.unwrap(), json!(hash));
+    }
+}